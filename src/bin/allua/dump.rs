@@ -0,0 +1,170 @@
+//! printing lexemes, modules & errors
+
+use std::fmt::Write;
+
+use allua::error::{ErrorMulti, RuntimeError};
+use allua::lex::tokenize;
+use allua::parse::token::{Module, Token};
+use allua::span::line_col;
+
+/// print every lexeme as `from..to kind "text"`
+pub fn lexemes(src: &str) -> std::process::ExitCode {
+    let mut pos = 0;
+    for lex in tokenize(src) {
+        let text = &src[pos..pos + lex.len];
+        println!("{pos}..{} {:?} {text:?}", pos + lex.len, lex.kind);
+        pos += lex.len;
+    }
+    std::process::ExitCode::SUCCESS
+}
+
+/// print every token, indented under the token owning it
+pub fn tree(module: &Module) {
+    tree_in(module, 0, module.len(), 0);
+}
+
+fn tree_in(module: &Module, from: usize, to: usize, depth: usize) {
+    let mut pos = from;
+    while pos < to {
+        let end = module.end(pos).max(pos + 1);
+        println!(
+            "{pos:>4} {:indent$}{:?}",
+            "",
+            module.items()[pos],
+            indent = depth * 2
+        );
+        tree_in(module, pos + 1, end, depth + 1);
+        pos = end;
+    }
+}
+
+/// print every warning as `path:line:col: warning: message`, then every
+/// error as `path:line:col: error: message`
+pub fn errors(path: &str, src: &str, errors: &ErrorMulti) {
+    for warning in &errors.warnings {
+        let (line, col) = line_col(src, warning.span().from);
+        eprintln!("{path}:{line}:{col}: warning: {warning}");
+    }
+    for err in errors.iter() {
+        match err.span() {
+            Some(span) => {
+                let (line, col) = line_col(src, span.from);
+                eprintln!("{path}:{line}:{col}: error: {err}");
+            }
+            None => eprintln!("{path}: error: {err}"),
+        }
+    }
+}
+
+/// print a runtime error as `path:line:col: error: message`, at the call of
+/// the host fn raising it, or as `path: error: message` if it has no span
+pub fn runtime_error(path: &str, src: &str, err: &RuntimeError) {
+    match err {
+        RuntimeError::Host(Some(span), name, err) => {
+            let (line, col) = line_col(src, span.from);
+            eprintln!("{path}:{line}:{col}: error: in `{name}`: {err}");
+        }
+        err => eprintln!("{path}: error: {err}"),
+    }
+}
+
+/// The module as a json tree of tokens, & its errors
+///
+/// Each token has its `pos`, its `kind`, its debug repr as `token` & the
+/// tokens it owns as `children`.
+pub fn json(module: &Module, errors: &ErrorMulti) -> String {
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "{{\"module\":{},\"items\":",
+        string(module.name().as_str())
+    );
+    json_in(&mut out, module, 0, module.len());
+    out.push_str(",\"errors\":[");
+    for (i, err) in errors.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        let span = err.span().unwrap_or_default();
+        let message = string(&err.to_string());
+        let _ = write!(
+            out,
+            "{{\"from\":{},\"to\":{},\"message\":{message}}}",
+            span.from, span.to
+        );
+    }
+    out.push_str("]}");
+    out
+}
+
+fn json_in(out: &mut String, module: &Module, from: usize, to: usize) {
+    out.push('[');
+    let mut pos = from;
+    while pos < to {
+        if pos > from {
+            out.push(',');
+        }
+        let token = module.items()[pos];
+        let end = module.end(pos).max(pos + 1);
+        let _ = write!(
+            out,
+            "{{\"pos\":{pos},\"kind\":\"{}\",\"token\":{},\"children\":",
+            kind(token),
+            string(&format!("{token:?}"))
+        );
+        json_in(out, module, pos + 1, end);
+        out.push('}');
+        pos = end;
+    }
+    out.push(']');
+}
+
+const fn kind(token: Token) -> &'static str {
+    match token {
+        Token::FnDef(_) => "FnDef",
+        Token::Decl(_) => "Decl",
+        Token::Expr(_) => "Expr",
+        Token::Flow(_) => "Flow",
+        Token::Return(_) => "Return",
+        Token::Tail(_) => "Tail",
+        Token::Assign(_) => "Assign",
+        Token::Import(_) => "Import",
+        Token::Block(_) => "Block",
+        Token::Tagged(_) => "Tagged",
+        Token::Break(_) => "Break",
+        Token::FnDefParam(_) => "FnDefParam",
+        Token::Struct(_) => "Struct",
+        Token::Enum(_) => "Enum",
+        Token::Variant(_) => "Variant",
+        Token::Field(_) => "Field",
+        Token::Impl(_) => "Impl",
+        Token::Like(_) => "Like",
+        Token::FnSig(_) => "FnSig",
+        Token::Match(_) => "Match",
+        Token::Arm(_) => "Arm",
+        Token::Pat(_) => "Pat",
+        Token::Type(_) => "Type",
+        Token::Error(_) => "Error",
+        Token::Dummy => "Dummy",
+    }
+}
+
+/// a json string literal
+fn string(text: &str) -> String {
+    let mut out = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
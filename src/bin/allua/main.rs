@@ -0,0 +1,184 @@
+//! the allua command line
+//!
+//! Exit codes: 0 on success, 1 if the input has errors or fails at runtime, 2
+//! for bad usage or unreadable input.
+
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use allua::check;
+use allua::error::ErrorMulti;
+use allua::lint::{Level, Lints};
+use allua::parse::token::Module;
+use allua::parse::{LuaReader, Reader};
+use allua::runtime::{Engine, Value};
+
+mod dump;
+mod repl;
+
+const USAGE: &str = "\
+usage: allua [command] [args]
+
+commands:
+  repl                        start an interactive prompt, the default
+  run <file> [args...]        run a file, with `args` as its `...`
+  check [file]                report the errors of a file
+  lex [file]                  print the lexemes of a file
+  parse [--json] [file]       print the parsed module of a file
+  lsp                         serve the language server protocol over stdio
+
+A missing file or `-` reads stdin. Files ending in `.lua` are read as lua 5.4.";
+
+/// The lints allowed in lua modules, lua has no predefs & names in camel case
+const LUA_ALLOWED: [&str; 2] = ["const_predef", "snake_case"];
+
+/// The input has errors or failed at runtime
+const FAILED: u8 = 1;
+/// Bad usage or unreadable input
+const USAGE_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let out = match args.as_slice() {
+        [] | ["repl"] => return repl::run(),
+        ["run", path, args @ ..] => read(path).map(|src| run(path, &src, args)),
+        ["check"] => read("-").map(|src| check("-", &src)),
+        ["check", path] => read(path).map(|src| check(path, &src)),
+        ["lex"] => read("-").map(|src| dump::lexemes(&src)),
+        ["lex", path] => read(path).map(|src| dump::lexemes(&src)),
+        ["parse", rest @ ..] => parse(rest),
+        ["lsp"] => return lsp(),
+        ["help" | "-h" | "--help"] => {
+            println!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        }
+        _ => Err(USAGE.to_owned()),
+    };
+    out.unwrap_or_else(|err| {
+        eprintln!("{err}");
+        ExitCode::from(USAGE_ERROR)
+    })
+}
+
+/// read a file, or stdin for `-`
+fn read(path: &str) -> Result<String, String> {
+    let mut src = String::new();
+    let out = match path {
+        "-" => io::stdin().read_to_string(&mut src).map(|_| src),
+        path => std::fs::read_to_string(path),
+    };
+    out.map_err(|err| format!("cannot read {path}: {err}"))
+}
+
+/// parse a file, as lua if its name ends in `.lua`
+fn module(path: &str, src: &str) -> (Module, ErrorMulti) {
+    match path.ends_with(".lua") {
+        true => LuaReader::new(src).module(path),
+        false => Reader::new(src).module(path),
+    }
+}
+
+fn run(path: &str, src: &str, args: &[&str]) -> ExitCode {
+    let (module, mut errors) = module(path, src);
+    diagnose(path, src, &module, &mut errors);
+    dump::errors(path, src, &errors);
+    if !errors.is_empty() {
+        return ExitCode::from(FAILED);
+    }
+    let mut engine = match path.ends_with(".lua") {
+        true => Engine::lua(),
+        false => Engine::new(),
+    };
+    register_builtins(&mut engine);
+    let args = args.iter().map(|&arg| Value::from(arg)).collect();
+    match engine.run_with_args(module, args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            dump::runtime_error(path, src, &err);
+            ExitCode::from(FAILED)
+        }
+    }
+}
+
+fn check(path: &str, src: &str) -> ExitCode {
+    let (module, mut errors) = module(path, src);
+    diagnose(path, src, &module, &mut errors);
+    dump::errors(path, src, &errors);
+    match errors.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(FAILED),
+    }
+}
+
+/// add the findings of name resolution & of the lints to `errors`
+///
+/// Names resolve against the globals of [`register_builtins`], lua modules
+/// are left out as lua globals are created at runtime, as are the lints of
+/// what lua doesn't have or name otherwise. The lint levels may be set in
+/// `src`.
+fn diagnose(path: &str, src: &str, module: &Module, errors: &mut ErrorMulti) {
+    let lua = path.ends_with(".lua");
+    if !lua {
+        let mut engine = Engine::new();
+        register_builtins(&mut engine);
+        let globals: Vec<_> = engine.globals().collect();
+        let resolution = check::resolve(module, &globals);
+        for err in resolution.errors {
+            errors.push(err);
+        }
+        for warning in resolution.warnings {
+            errors.warn(warning);
+        }
+    }
+    let mut lints = Lints::default();
+    if lua {
+        for name in LUA_ALLOWED {
+            lints.set_level(name, Level::Allow);
+        }
+    }
+    lints.configure(src);
+    lints.report(module, errors);
+}
+
+fn parse(args: &[&str]) -> Result<ExitCode, String> {
+    let (json, path) = match args {
+        ["--json"] => (true, "-"),
+        ["--json", path] | [path, "--json"] => (true, *path),
+        [] => (false, "-"),
+        [path] => (false, *path),
+        _ => return Err(USAGE.to_owned()),
+    };
+    let src = read(path)?;
+    let (module, errors) = module(path, &src);
+    match json {
+        true => println!("{}", dump::json(&module, &errors)),
+        false => {
+            dump::tree(&module);
+            dump::errors(path, &src, &errors);
+        }
+    }
+    Ok(match errors.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(FAILED),
+    })
+}
+
+fn lsp() -> ExitCode {
+    match allua::lsp::serve(io::stdin().lock(), io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("lsp: {err}");
+            ExitCode::from(FAILED)
+        }
+    }
+}
+
+/// fns every script run from the command line can use
+pub fn register_builtins(engine: &mut Engine) {
+    engine.register_raw_fn("print", |_, args| {
+        let args: Vec<_> = args.iter().map(Value::to_string).collect();
+        println!("{}", args.join("\t"));
+        Ok(Vec::new())
+    });
+}
@@ -0,0 +1,183 @@
+//! an interactive prompt
+//!
+//! Input is read with a line editor a line at a time until every block is
+//! closed, then run with an engine shared by every entry. An entry that is an
+//! expr list prints its values.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use allua::parse::token::{Module, Token};
+use allua::parse::Reader;
+use allua::runtime::{Engine, Value};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+use crate::dump;
+
+const HELP: &str = "\
+:tokens <src>  print the lexemes of <src>
+:ast <src>     print the module parsed from <src>
+:history       print the previous entries
+:help          print this message
+:quit          exit, as does ctrl-d
+
+ctrl-r searches the history, ctrl-c drops the current entry";
+
+pub fn run() -> ExitCode {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("can't start the line editor: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let history = history_path();
+    if let Some(path) = &history {
+        // history is best effort
+        let _ = editor.load_history(path);
+    }
+    let mut repl = Repl {
+        engine: {
+            let mut engine = Engine::new();
+            crate::register_builtins(&mut engine);
+            engine
+        },
+        editor,
+        history,
+    };
+    let mut entry = String::new();
+    loop {
+        let prompt = if entry.is_empty() { "> " } else { ". " };
+        let line = match repl.editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                entry.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => return ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        if !entry.is_empty() {
+            entry.push('\n');
+        }
+        entry.push_str(&line);
+        if !entry.starts_with(':') && incomplete(&entry) {
+            continue;
+        }
+        let entry = std::mem::take(&mut entry);
+        if entry.trim().is_empty() {
+            continue;
+        }
+        repl.push_history(&entry);
+        if !repl.entry(&entry) {
+            return ExitCode::SUCCESS;
+        }
+    }
+}
+
+struct Repl {
+    engine: Engine,
+    editor: DefaultEditor,
+    /// the file the history is kept in, if there's a data dir
+    history: Option<PathBuf>,
+}
+
+impl Repl {
+    /// handle a complete entry, false to exit
+    fn entry(&mut self, entry: &str) -> bool {
+        let (command, src) = match entry.strip_prefix(':') {
+            Some(rest) => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+            None => return self.eval(entry),
+        };
+        match command {
+            "tokens" => {
+                dump::lexemes(src);
+            }
+            "ast" => print_ast(src),
+            "history" => {
+                for (i, entry) in self.editor.history().iter().enumerate() {
+                    println!("{i:>4} {entry}");
+                }
+            }
+            "help" => println!("{HELP}"),
+            "quit" | "q" => return false,
+            _ => println!("unknown command `:{command}`, see `:help`"),
+        }
+        true
+    }
+
+    fn eval(&mut self, src: &str) -> bool {
+        let module = match as_return(src) {
+            Some(module) => module,
+            None => {
+                let (module, errors) = Reader::new(src).module("repl");
+                if !errors.is_empty() {
+                    dump::errors("repl", src, &errors);
+                    return true;
+                }
+                module
+            }
+        };
+        match self.engine.run(module) {
+            Ok(values) if values.is_empty() => {}
+            Ok(values) => {
+                let values: Vec<_> = values.iter().map(Value::to_string).collect();
+                println!("{}", values.join("\t"));
+            }
+            Err(err) => println!("error: {err}"),
+        }
+        true
+    }
+
+    fn push_history(&mut self, entry: &str) {
+        let entry = entry.replace('\n', " ");
+        // history is best effort
+        let _ = self.editor.add_history_entry(entry);
+        if let Some(path) = &self.history {
+            let _ = self.editor.append_history(path);
+        }
+    }
+}
+
+/// `$XDG_DATA_HOME/allua/history`, else under `~/.local/share`, or
+/// `%APPDATA%` on windows
+fn history_path() -> Option<PathBuf> {
+    let env = |name| std::env::var_os(name).filter(|dir| !dir.is_empty());
+    let dir = env("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .or_else(|| env("APPDATA").map(PathBuf::from))?
+        .join("allua");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir.join("history"))
+}
+
+/// parse an expr list as `return <src>`, so that its values are printed
+fn as_return(src: &str) -> Option<Module> {
+    let (module, errors) = Reader::new(&format!("return {src}")).module("repl");
+    let lone =
+        matches!(module.items().first(), Some(Token::Return(_))) && module.end(0) == module.len();
+    (errors.is_empty() && lone).then_some(module)
+}
+
+/// true if more lines are needed, either as statements or as an expr list
+fn incomplete(src: &str) -> bool {
+    as_return(src).is_none()
+        && (Reader::new(src).incomplete() || Reader::new(&format!("return {src}")).incomplete())
+}
+
+/// print the module as it would be run
+fn print_ast(src: &str) {
+    match as_return(src) {
+        Some(module) => dump::tree(&module),
+        None => {
+            let (module, errors) = Reader::new(src).module("repl");
+            dump::tree(&module);
+            dump::errors("repl", src, &errors);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! semantic passes over a parsed [`Module`]
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::util::Symbol;
+
+pub use capture::{captures, uses, Captures};
+pub use comp::comp;
+pub use label::labels;
+pub use like::likes;
+pub use null::nulls;
+pub use num::{numbers, Num};
+pub use pattern::patterns;
+pub use resolve::{resolve, Resolution, Scope, ScopeKind};
+pub use string::strings;
+pub use ty::types;
+
+pub mod capture;
+pub mod comp;
+pub mod label;
+pub mod like;
+pub mod null;
+pub mod num;
+pub mod pattern;
+pub mod resolve;
+pub mod string;
+#[cfg(test)]
+mod test;
+pub mod ty;
+
+/// Run the passes every parse ends with, in order, filling in comps
+pub fn passes(module: &mut Module) -> Vec<SemanticError> {
+    let mut errors = labels(module);
+    errors.extend(types(module));
+    errors.extend(likes(module));
+    errors.extend(patterns(module));
+    errors.extend(comp(module));
+    errors.extend(numbers(module));
+    errors.extend(strings(module));
+    errors.extend(nulls(module));
+    errors
+}
+
+/// The name bound by the token at `pos`
+///
+/// `None` if the token doesn't bind a name
+#[must_use]
+pub fn binding_name(module: &Module, pos: usize) -> Option<Symbol> {
+    match module.get(pos)? {
+        Token::Decl(decl) => Some(decl.name),
+        Token::FnDefParam(param) => Some(param.name),
+        Token::FnDef(def) => Some(def.name),
+        Token::Pat(Pat {
+            kind: PatKind::Bind(name),
+            ..
+        }) => Some(name),
+        _ => None,
+    }
+}
+
+/// The pos of the fn `name` declared at the top level, maybe after its use
+fn global_fn(module: &Module, name: Symbol) -> Option<usize> {
+    let mut pos = 0;
+    while pos < module.len() {
+        if matches!(module.items()[pos], Token::FnDef(def) if def.name == name) {
+            return Some(pos);
+        }
+        pos = module.end(pos);
+    }
+    None
+}
@@ -0,0 +1,308 @@
+//! upvalue capture analysis
+//!
+//! Bindings declared at the top level of a module are globals and are never
+//! captured. Every other binding belongs to the fn it is declared in, or to
+//! the module body when it is declared in a top level block.
+
+use std::collections::BTreeMap;
+
+use crate::parse::token::*;
+use crate::span::TSpan;
+use crate::util::Symbol;
+
+use super::binding_name;
+
+/// The outer bindings captured by each fn & closure
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Captures {
+    /// fn pos -> sorted positions of the captured bindings
+    map: BTreeMap<usize, Vec<usize>>,
+}
+
+impl Captures {
+    /// The positions of the bindings captured by the fn at `pos`
+    ///
+    /// A binding is a [`Decl`], [`FnDefParam`], [`FnDef`] or [`Pat`].
+    #[must_use]
+    pub fn get(&self, pos: usize) -> &[usize] {
+        self.map.get(&pos).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every fn that captures at least one binding
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &[usize])> {
+        self.map
+            .iter()
+            .map(|(&pos, bindings)| (pos, bindings.as_slice()))
+    }
+
+    fn insert(&mut self, func: usize, binding: usize) {
+        let bindings = self.map.entry(func).or_default();
+        if let Err(i) = bindings.binary_search(&binding) {
+            bindings.insert(i, binding);
+        }
+    }
+}
+
+/// Find the captured bindings of every fn & closure in the module
+#[must_use]
+pub fn captures(module: &Module) -> Captures {
+    walk(module).captures
+}
+
+/// The binding each [`ExprKind::Var`] & [`ExprKind::FnCall`] refers to
+///
+/// Maps the pos of the expr to the pos of the binding. Uses of globals
+/// declared later in the module & of undeclared names are missing.
+#[must_use]
+pub fn uses(module: &Module) -> BTreeMap<usize, usize> {
+    walk(module).uses
+}
+
+fn walk(module: &Module) -> Walker<'_> {
+    let mut walker = Walker {
+        module,
+        scopes: vec![Scope {
+            func: None,
+            boundary: false,
+            bindings: Vec::new(),
+        }],
+        captures: Captures::default(),
+        uses: BTreeMap::new(),
+    };
+    walker.block(0, module.len());
+    walker
+}
+
+#[derive(Debug)]
+struct Scope {
+    /// the fn owning this scope, `None` for the module body
+    func: Option<usize>,
+    /// true if this is the outermost scope of `func`
+    boundary: bool,
+    /// (name, pos, visible from)
+    bindings: Vec<(Symbol, usize, usize)>,
+}
+
+#[derive(Debug)]
+struct Walker<'a> {
+    module: &'a Module,
+    scopes: Vec<Scope>,
+    captures: Captures,
+    uses: BTreeMap<usize, usize>,
+}
+
+impl Walker<'_> {
+    fn block(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            self.stmt(pos);
+            pos = self.module.end(pos);
+        }
+    }
+
+    fn scoped(&mut self, span: TSpan) {
+        let func = self.func();
+        self.scopes.push(Scope {
+            func,
+            boundary: false,
+            bindings: Vec::new(),
+        });
+        self.block(span.from, span.to);
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, pos: usize) {
+        let end = self.module.end(pos);
+        match self.module.items()[pos] {
+            Token::Decl(decl) => {
+                let at = decl.value_pos(pos);
+                match self.module.get(at) {
+                    Some(Token::Tagged(_)) if decl.value => self.stmt(at),
+                    _ => self.exprs(at, end),
+                }
+                self.bind(decl.name, pos, end);
+            }
+            Token::FnDef(def) => {
+                self.bind(def.name, pos, pos);
+                self.func_body(pos, def.params, def.tokens);
+            }
+            Token::Impl(def) => {
+                let mut method = def.methods.from;
+                while method < def.methods.to {
+                    if let Token::FnDef(def) = self.module.items()[method] {
+                        self.func_body(method, def.params, def.tokens);
+                    }
+                    method = self.module.end(method);
+                }
+            }
+            Token::Block(block) => self.scoped(block.tokens),
+            Token::Tagged(tagged) => self.scoped(tagged.tokens),
+            Token::Flow(Flow::If(span, el)) => {
+                self.exprs(pos + 1, span.from);
+                self.scoped(span);
+                if let Some(el) = el {
+                    self.scoped(el);
+                }
+            }
+            Token::Flow(Flow::While(body)) => {
+                self.exprs(pos + 1, body.from);
+                self.scoped(body);
+            }
+            Token::Flow(Flow::Repeat(body)) => self.loop_scope(|this| {
+                this.block(body.from, body.to);
+                this.exprs(body.to, end);
+            }),
+            Token::Flow(Flow::For(def)) => {
+                self.exprs(pos + 1, def.vars.from);
+                self.loop_scope(|this| {
+                    for var in def.vars.from..def.vars.to {
+                        if let Some(name) = binding_name(this.module, var) {
+                            this.bind(name, var, def.vars.to);
+                        }
+                    }
+                    this.block(def.body.from, def.body.to);
+                });
+            }
+            Token::Match(def) => {
+                self.exprs(pos + 1, def.arms.from);
+                let mut arm = def.arms.from;
+                while arm < def.arms.to {
+                    if let Token::Arm(token) = self.module.items()[arm] {
+                        self.arm(token);
+                    }
+                    arm = self.module.end(arm);
+                }
+            }
+            Token::Expr(_) => self.exprs(pos, end),
+            Token::Return(_) | Token::Tail(_) | Token::Assign(_) | Token::Break(_) => {
+                self.exprs(pos + 1, end);
+            }
+            Token::FnDefParam(_)
+            | Token::Flow(Flow::Goto(..) | Flow::Label(..))
+            | Token::Import(_)
+            | Token::Struct(_)
+            | Token::Enum(_)
+            | Token::Variant(_)
+            | Token::Field(_)
+            | Token::Like(_)
+            | Token::FnSig(_)
+            | Token::Arm(_)
+            | Token::Pat(_)
+            | Token::Type(_)
+            | Token::Error(_)
+            | Token::Dummy => (),
+        }
+    }
+
+    /// a scope of a loop, holding more than a plain block
+    fn loop_scope(&mut self, walk: impl FnOnce(&mut Self)) {
+        let func = self.func();
+        self.scopes.push(Scope {
+            func,
+            boundary: false,
+            bindings: Vec::new(),
+        });
+        walk(self);
+        self.scopes.pop();
+    }
+
+    /// the bindings of the pats of an arm are in a scope of their own
+    fn arm(&mut self, arm: Arm) {
+        let func = self.func();
+        self.scopes.push(Scope {
+            func,
+            boundary: false,
+            bindings: Vec::new(),
+        });
+        for pos in arm.pats.from..arm.pats.to {
+            if let Some(name) = binding_name(self.module, pos) {
+                self.bind(name, pos, arm.pats.to);
+            }
+        }
+        self.exprs(arm.pats.to, arm.body.from);
+        self.block(arm.body.from, arm.body.to);
+        self.scopes.pop();
+    }
+
+    /// walk every expr token in `from..to`
+    fn exprs(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            match self.module.items()[pos] {
+                Token::Expr(Expr {
+                    kind: ExprKind::Var(name) | ExprKind::FnCall(FnCall { name }),
+                    ..
+                }) => self.use_name(name, pos),
+                Token::Expr(Expr {
+                    kind: ExprKind::Closure(closure),
+                    end,
+                    ..
+                }) => {
+                    self.func_body(pos, closure.params, closure.tokens);
+                    pos = end;
+                    continue;
+                }
+                Token::Block(_) | Token::Flow(_) | Token::Match(_) => {
+                    self.stmt(pos);
+                    pos = self.module.end(pos);
+                    continue;
+                }
+                _ => (),
+            }
+            pos += 1;
+        }
+    }
+
+    fn func_body(&mut self, func: usize, params: TSpan, tokens: TSpan) {
+        self.scopes.push(Scope {
+            func: Some(func),
+            boundary: true,
+            bindings: Vec::new(),
+        });
+        let mut pos = params.from;
+        while pos < params.to {
+            let end = self.module.end(pos);
+            if let Token::FnDefParam(param) = self.module.items()[pos] {
+                self.exprs(param.value_pos(pos), end);
+                self.bind(param.name, pos, end);
+            }
+            pos = end;
+        }
+        self.block(tokens.from, tokens.to);
+        self.scopes.pop();
+    }
+
+    fn func(&self) -> Option<usize> {
+        self.scopes.last().and_then(|scope| scope.func)
+    }
+
+    fn bind(&mut self, name: Symbol, pos: usize, visible: usize) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.bindings.push((name, pos, visible));
+        }
+    }
+
+    /// record a capture for every fn between the use & the binding
+    fn use_name(&mut self, name: Symbol, pos: usize) {
+        let found = self.scopes.iter().enumerate().rev().find_map(|(i, scope)| {
+            let binding = scope
+                .bindings
+                .iter()
+                .rev()
+                .find(|&&(other, _, visible)| other == name && visible <= pos)?;
+            Some((i, binding.1))
+        });
+        if let Some((_, binding)) = found {
+            self.uses.insert(pos, binding);
+        }
+        // top level bindings are globals
+        let Some((i @ 1.., binding)) = found else {
+            return;
+        };
+        for scope in &self.scopes[i + 1..] {
+            if let (true, Some(func)) = (scope.boundary, scope.func) {
+                self.captures.insert(func, binding);
+            }
+        }
+    }
+}
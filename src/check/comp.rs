@@ -0,0 +1,177 @@
+//! compile time evaluation of `comp` exprs
+//!
+//! A comp context is a [`ExprKind::Comp`], including the values of `pre`
+//! decls, or the body of a `prefn`. Only predefs & bindings declared inside
+//! the context may be used in one, as well as the numeric conversions.
+//!
+//! Each outermost `comp` is run by an [`Engine`] of its own over a module
+//! made of every `prefn` & the top level `pre` decls before it. Comps in a
+//! `prefn` are left to run with the fn. Each may make at most [`MAX_STEPS`]
+//! calls.
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::runtime::Engine;
+use crate::span::TSpan;
+use crate::util::Symbol;
+
+use super::{uses, Num};
+
+/// The calls a `comp` may make before it's stopped
+pub const MAX_STEPS: usize = 100_000;
+
+/// Check every comp context & fill in the value of each `comp` that can be
+/// evaluated
+pub fn comp(module: &mut Module) -> Vec<SemanticError> {
+    // each comp is evaluated afresh, as its context may have changed
+    for token in &mut module.items {
+        if let Token::Expr(Expr {
+            kind: kind @ ExprKind::Comp(_),
+            ..
+        }) = token
+        {
+            *kind = ExprKind::Comp(None);
+        }
+    }
+    let uses = uses(module);
+    let mut errors = Vec::new();
+    for (context, is_comp) in contexts(module) {
+        let before = errors.len();
+        for pos in context.from..context.to {
+            let Token::Expr(Expr {
+                kind: ExprKind::Var(name) | ExprKind::FnCall(FnCall { name }),
+                span,
+                ..
+            }) = module.items[pos]
+            else {
+                continue;
+            };
+            let allowed = match uses.get(&pos) {
+                Some(&binding) => context.contains(binding) || is_pre(module, binding),
+                None => {
+                    matches!(name.as_str(), "true" | "false" | "nil")
+                        || Num::conversion(name.as_str()).is_some()
+                        || global(module, name)
+                }
+            };
+            if !allowed {
+                errors.push(SemanticError::NotPredef(span, name));
+            }
+        }
+        if is_comp && errors.len() == before {
+            if let Err(err) = eval(module, context.from) {
+                errors.push(err);
+            }
+        }
+    }
+    errors
+}
+
+/// The outermost comp contexts, true for a `comp` & false for a `prefn`
+fn contexts(module: &Module) -> Vec<(TSpan, bool)> {
+    let mut contexts = Vec::new();
+    let mut pos = 0;
+    while pos < module.len() {
+        let context = match module.items[pos] {
+            Token::Expr(Expr {
+                kind: ExprKind::Comp(_),
+                end,
+                ..
+            }) => (TSpan { from: pos, to: end }, true),
+            Token::FnDef(def) if def.pre => (
+                TSpan {
+                    from: pos,
+                    to: def.tokens.to,
+                },
+                false,
+            ),
+            _ => {
+                pos += 1;
+                continue;
+            }
+        };
+        contexts.push(context);
+        pos = context.0.to;
+    }
+    contexts
+}
+
+/// true if the binding at `pos` is a predef
+fn is_pre(module: &Module, pos: usize) -> bool {
+    match module.items[pos] {
+        Token::Decl(decl) => decl.pre,
+        Token::FnDef(def) => def.pre,
+        _ => false,
+    }
+}
+
+/// true if `name` is a predef declared at the top level
+fn global(module: &Module, name: Symbol) -> bool {
+    top_level(module).any(|pos| match module.items[pos] {
+        Token::Decl(decl) => decl.pre && decl.name == name,
+        Token::FnDef(def) => def.pre && def.name == name,
+        _ => false,
+    })
+}
+
+fn top_level(module: &Module) -> impl Iterator<Item = usize> + '_ {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        (pos < module.len()).then(|| {
+            let at = pos;
+            pos = module.end(pos);
+            at
+        })
+    })
+}
+
+/// run the `comp` at `pos` & store its value
+fn eval(module: &mut Module, pos: usize) -> Result<(), SemanticError> {
+    let Token::Expr(expr) = module.items[pos] else {
+        unreachable!("non expr at {pos}");
+    };
+
+    // every prefn first, so each may call the others
+    let prefns =
+        top_level(module).filter(|&at| matches!(module.items[at], Token::FnDef(def) if def.pre));
+    let decls = top_level(module).filter(|&at| {
+        matches!(module.items[at], Token::Decl(decl) if decl.pre) && module.end(at) <= pos
+    });
+    let mut tokens = Vec::new();
+    for at in prefns.chain(decls) {
+        copy(module, &mut tokens, at, module.end(at));
+    }
+    let values = TSpan {
+        from: tokens.len() + 1,
+        to: tokens.len() + expr.end - pos,
+    };
+    tokens.push(Token::Return(values));
+    copy(module, &mut tokens, pos + 1, expr.end);
+
+    let mut engine = Engine::new();
+    engine.set_fuel(Some(MAX_STEPS));
+    let value = engine
+        .run(Module::new(module.name().as_str(), tokens))
+        .map_err(|err| SemanticError::Comp(expr.span, err))?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let value = value
+        .to_const()
+        .ok_or_else(|| SemanticError::NotConst(expr.span, value.type_name()))?;
+    if let Token::Expr(expr) = &mut module.items[pos] {
+        expr.kind = ExprKind::Comp(Some(value));
+    }
+    Ok(())
+}
+
+/// copy the tokens in `from..to` to the end of `tokens`
+#[allow(clippy::cast_possible_wrap)]
+fn copy(module: &Module, tokens: &mut Vec<Token>, from: usize, to: usize) {
+    let moved = tokens.len() as isize - from as isize;
+    tokens.extend(module.items[from..to].iter().map(|&token| {
+        let mut token = token;
+        token.offset(moved, 0);
+        token
+    }));
+}
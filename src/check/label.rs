@@ -0,0 +1,45 @@
+//! resolving the labels of tagged blocks & breaks
+//!
+//! A `break` may only name a tagged block enclosing it in the same fn, fns
+//! & closures start without any labels.
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::util::Symbol;
+
+/// The unknown & shadowed labels of the module, in order
+#[must_use]
+pub fn labels(module: &Module) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    // (label, end), `None` for the start of a fn
+    let mut open: Vec<(Option<Symbol>, usize)> = Vec::new();
+    for (pos, &token) in module.items().iter().enumerate() {
+        while open.last().is_some_and(|&(_, end)| end <= pos) {
+            open.pop();
+        }
+        let visible = |label| {
+            open.iter()
+                .rev()
+                .map_while(|&(label, _)| label)
+                .any(|open| open == label)
+        };
+        match token {
+            Token::Tagged(tagged) => {
+                if visible(tagged.label) {
+                    errors.push(SemanticError::ShadowedLabel(tagged.span, tagged.label));
+                }
+                open.push((Some(tagged.label), tagged.tokens.to));
+            }
+            Token::Break(brk) if !visible(brk.label) => {
+                errors.push(SemanticError::NoLabel(brk.span, brk.label));
+            }
+            Token::FnDef(def) => open.push((None, def.tokens.to)),
+            Token::Expr(Expr {
+                kind: ExprKind::Closure(closure),
+                ..
+            }) => open.push((None, closure.tokens.to)),
+            _ => (),
+        }
+    }
+    errors
+}
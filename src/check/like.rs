@@ -0,0 +1,111 @@
+//! checking that every `impl` is of a type & satisfies the like it claims
+//!
+//! A method satisfies a sig when it has the same name, the same params &
+//! return type, written the same way, & is variadic if the sig is. Likes & types may be declared after
+//! the impls using them, a type is a struct, an enum or a builtin type.
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::runtime::Value;
+use crate::span::TSpan;
+use crate::util::Symbol;
+
+/// The errors of every `impl` in the module, in order
+#[must_use]
+pub fn likes(module: &Module) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    let mut methods: Vec<(Symbol, Symbol)> = Vec::new();
+    let mut pos = 0;
+    while pos < module.len() {
+        if let Token::Impl(def) = module.items()[pos] {
+            if !is_type(module, def.ty) {
+                errors.push(SemanticError::NoType(def.span, def.ty));
+            }
+            for method in fns(module, def.methods) {
+                if methods.contains(&(def.ty, method.name)) {
+                    errors.push(SemanticError::DupeMethod(method.span, def.ty, method.name));
+                }
+                methods.push((def.ty, method.name));
+            }
+            if let Some(like) = def.like {
+                check(module, def, like, &mut errors);
+            }
+        }
+        pos = module.end(pos);
+    }
+    errors
+}
+
+fn check(module: &Module, def: Impl, like: Symbol, errors: &mut Vec<SemanticError>) {
+    let found = module.items().iter().find_map(|token| match token {
+        Token::Like(other) if other.name == like => Some(*other),
+        _ => None,
+    });
+    let Some(found) = found else {
+        errors.push(SemanticError::NoLike(def.span, like));
+        return;
+    };
+
+    let ret = |ty: Option<TSpan>| ty.map(|ty| module.type_str(ty.from));
+    let mut pos = found.sigs.from;
+    while pos < found.sigs.to {
+        if let Token::FnSig(sig) = module.items()[pos] {
+            let method = fns(module, def.methods).find(|method| method.name == sig.name);
+            match method {
+                None => errors.push(SemanticError::MissingMethod {
+                    span: def.span,
+                    ty: def.ty,
+                    like,
+                    name: sig.name,
+                }),
+                Some(method)
+                    if method.variadic != sig.variadic
+                        || params(module, method.params) != params(module, sig.params)
+                        || ret(method.ty) != ret(sig.ty) =>
+                {
+                    errors.push(SemanticError::MethodSig(method.span, like, sig.name));
+                }
+                Some(_) => (),
+            }
+        }
+        pos = module.end(pos);
+    }
+}
+
+/// true if `ty` is builtin or a struct or enum of the module
+pub(super) fn is_type(module: &Module, ty: Symbol) -> bool {
+    Value::TYPE_NAMES.contains(&ty.as_str())
+        || module.items().iter().any(|token| match token {
+            Token::Struct(def) => def.name == ty,
+            Token::Enum(def) => def.name == ty,
+            _ => false,
+        })
+}
+
+/// The fns in `span`
+fn fns(module: &Module, span: TSpan) -> impl Iterator<Item = FnDef> + '_ {
+    let mut pos = span.from;
+    std::iter::from_fn(move || {
+        while pos < span.to {
+            let token = module.items()[pos];
+            pos = module.end(pos);
+            if let Token::FnDef(def) = token {
+                return Some(def);
+            }
+        }
+        None
+    })
+}
+
+/// The types of the params in `span`, `None` for untyped ones
+fn params(module: &Module, span: TSpan) -> Vec<Option<String>> {
+    let mut types = Vec::new();
+    let mut pos = span.from;
+    while pos < span.to {
+        if let Token::FnDefParam(param) = module.items()[pos] {
+            types.push(param.ty.map(|ty| module.type_str(ty.from)));
+        }
+        pos = module.end(pos);
+    }
+    types
+}
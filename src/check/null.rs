@@ -0,0 +1,531 @@
+//! nullability checking
+//!
+//! A binding declared with an optional type, `int? x`, may be `nil`, as may
+//! an untyped binding of a value that may be. Such a binding can't be used
+//! as an operand, callee, receiver or for a binding of a non optional type
+//! until a test rules `nil` out:
+//! - `if x { .. }` & `if x != nil { .. }` narrow `x` inside the block,
+//!   `if !x { .. } else { .. }` & `if x == nil { .. } else { .. }` in the else
+//! - `x && ..` & `!x || ..` narrow `x` on the right
+//! - a branch that returns or breaks leaves the narrowing of the other one
+//!   in place after the `if`
+//! - assigning a value that can't be `nil` narrows `x` until it's reassigned
+//!
+//! Fns & closures start without any narrowing, they may run at any time.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::span::TSpan;
+use crate::util::Symbol;
+
+use super::{global_fn, uses};
+
+/// The uses of possibly `nil` values of the module, in order
+#[must_use]
+pub fn nulls(module: &Module) -> Vec<SemanticError> {
+    let mut checker = Checker {
+        module,
+        uses: uses(module),
+        optional: HashSet::new(),
+        typed: HashMap::new(),
+        narrowed: HashSet::new(),
+        breaks: Vec::new(),
+        tail: false,
+        errors: Vec::new(),
+    };
+    checker.block(0, module.len());
+    checker.errors
+}
+
+/// The narrowing an expr gives when tested
+#[derive(Default)]
+struct Test {
+    /// the value may be `nil`
+    maybe: bool,
+    /// the bindings known not to be `nil` if the value is truthy
+    then: Vec<usize>,
+    /// the bindings known not to be `nil` if it isn't
+    els: Vec<usize>,
+}
+
+struct Checker<'a> {
+    module: &'a Module,
+    uses: BTreeMap<usize, usize>,
+    /// the bindings that may be `nil`, by pos
+    optional: HashSet<usize>,
+    /// the bindings of a non optional type, with their type
+    typed: HashMap<usize, TSpan>,
+    /// the optional bindings known not to be `nil` here
+    narrowed: HashSet<usize>,
+    /// the narrowing at the breaks out of each open tagged block, & if a
+    /// value it's broken out with may be `nil`
+    breaks: Vec<(Symbol, Option<HashSet<usize>>, bool)>,
+    /// a tail expr of the block used as a value may be `nil`
+    tail: bool,
+    errors: Vec<SemanticError>,
+}
+
+impl Checker<'_> {
+    /// true if the block always returns or breaks
+    fn block(&mut self, from: usize, to: usize) -> bool {
+        let mut exits = false;
+        let mut pos = from;
+        while pos < to {
+            let next = self.module.end(pos);
+            // only the last stmt can be the value of the block
+            let tail = (next < to).then_some(self.tail);
+            // the rest is unreachable but still checked
+            exits |= self.stmt(pos);
+            if let Some(tail) = tail {
+                self.tail = tail;
+            }
+            pos = next;
+        }
+        exits
+    }
+
+    /// true if the stmt always returns or breaks
+    fn stmt(&mut self, pos: usize) -> bool {
+        match self.module.items()[pos] {
+            Token::Decl(decl) => {
+                let at = decl.value_pos(pos);
+                let optional = self.module.optional(decl.ty);
+                let maybe = match decl.ty {
+                    _ if !decl.value => false,
+                    Some(ty) if !optional => {
+                        self.typed(at, ty);
+                        false
+                    }
+                    _ => self.value(at),
+                };
+                self.bind(pos, decl.ty, maybe);
+                if optional && decl.value && !maybe {
+                    self.narrowed.insert(pos);
+                }
+            }
+            Token::FnDef(def) => self.func(def.params, def.tokens),
+            Token::Impl(def) => {
+                let mut method = def.methods.from;
+                while method < def.methods.to {
+                    if let Token::FnDef(def) = self.module.items()[method] {
+                        self.func(def.params, def.tokens);
+                    }
+                    method = self.module.end(method);
+                }
+            }
+            Token::Block(block) => return self.block(block.tokens.from, block.tokens.to),
+            Token::Tagged(tagged) => {
+                self.breaks.push((tagged.label, None, false));
+                let exits = self.block(tagged.tokens.from, tagged.tokens.to);
+                let (_, broke, maybe) = self.breaks.pop().unwrap_or_default();
+                self.tail |= maybe;
+                match (exits, broke) {
+                    (exits, None) => return exits,
+                    (true, Some(broke)) => self.narrowed = broke,
+                    (false, Some(broke)) => self.narrowed.retain(|b| broke.contains(b)),
+                }
+            }
+            Token::Flow(Flow::If(span, el)) => return self.if_else(pos, span, el),
+            Token::Flow(Flow::While(_) | Flow::Repeat(_) | Flow::For(_)) => self.looped(pos),
+            Token::Match(def) => self.match_arms(pos, def),
+            Token::Expr(_) => {
+                self.value(pos);
+            }
+            Token::Tail(values) => {
+                self.tail |= self.value(values.from);
+            }
+            Token::Return(values) => {
+                self.values(values.from, values.to);
+                return true;
+            }
+            Token::Break(brk) => {
+                // a break without a value gives `nil`
+                let maybe = brk.values.from == brk.values.to || self.value(brk.values.from);
+                if brk.values.from < brk.values.to {
+                    self.values(self.module.end(brk.values.from), brk.values.to);
+                }
+                let narrowed = &self.narrowed;
+                if let Some((_, broke, nil)) =
+                    self.breaks.iter_mut().rev().find(|(l, ..)| *l == brk.label)
+                {
+                    *nil |= maybe;
+                    match broke {
+                        Some(broke) => broke.retain(|b| narrowed.contains(b)),
+                        None => *broke = Some(narrowed.clone()),
+                    }
+                }
+                return true;
+            }
+            Token::Assign(assign) => self.assign(assign),
+            Token::FnDefParam(_)
+            | Token::Flow(Flow::Goto(..) | Flow::Label(..))
+            | Token::Import(_)
+            | Token::Struct(_)
+            | Token::Enum(_)
+            | Token::Variant(_)
+            | Token::Field(_)
+            | Token::Like(_)
+            | Token::FnSig(_)
+            | Token::Arm(_)
+            | Token::Pat(_)
+            | Token::Type(_)
+            | Token::Error(_)
+            | Token::Dummy => (),
+        }
+        false
+    }
+
+    /// true if both branches always return or break
+    fn if_else(&mut self, pos: usize, span: TSpan, el: Option<TSpan>) -> bool {
+        let test = self.test(pos + 1);
+        let before = self.narrowed.clone();
+        self.narrowed.extend(test.then);
+        let then_exits = self.block(span.from, span.to);
+        let then = std::mem::replace(&mut self.narrowed, before);
+        self.narrowed.extend(test.els);
+        let else_exits = el.is_some_and(|el| self.block(el.from, el.to));
+        match (then_exits, else_exits) {
+            (true, true) => return true,
+            (true, false) => (),
+            (false, true) => self.narrowed = then,
+            (false, false) => self.narrowed.retain(|b| then.contains(b)),
+        }
+        false
+    }
+
+    /// a loop may run any number of times, so no narrowing crosses it
+    fn looped(&mut self, pos: usize) {
+        self.narrowed.clear();
+        self.breaks.push((Symbol::from(""), None, false));
+        self.block(pos + 1, self.module.end(pos));
+        self.breaks.pop();
+        self.narrowed.clear();
+    }
+
+    /// each arm starts with the narrowing before the `match`, none of theirs
+    /// is kept after it
+    fn match_arms(&mut self, pos: usize, def: Match) {
+        self.values(pos + 1, def.arms.from);
+        let before = self.narrowed.clone();
+        let mut arm = def.arms.from;
+        while arm < def.arms.to {
+            if let Token::Arm(token) = self.module.items()[arm] {
+                if token.pats.to < token.body.from {
+                    self.value(token.pats.to);
+                }
+                self.block(token.body.from, token.body.to);
+                self.narrowed.clone_from(&before);
+            }
+            arm = self.module.end(arm);
+        }
+    }
+
+    /// record the binding at `pos`, untyped bindings of a possibly `nil`
+    /// value may be `nil` too
+    fn bind(&mut self, pos: usize, ty: Option<TSpan>, maybe: bool) {
+        match ty {
+            _ if self.module.optional(ty) => {
+                self.optional.insert(pos);
+            }
+            Some(ty) => {
+                self.typed.insert(pos, ty);
+            }
+            None if maybe => {
+                self.optional.insert(pos);
+            }
+            None => (),
+        }
+    }
+
+    fn func(&mut self, params: TSpan, tokens: TSpan) {
+        let outer = std::mem::take(&mut self.narrowed);
+        let mut pos = params.from;
+        while pos < params.to {
+            if let Token::FnDefParam(param) = self.module.items()[pos] {
+                if param.value {
+                    self.value(param.value_pos(pos));
+                }
+                self.bind(pos, param.ty, false);
+            }
+            pos = self.module.end(pos);
+        }
+        let tail = self.tail;
+        self.block(tokens.from, tokens.to);
+        self.tail = tail;
+        self.narrowed = outer;
+    }
+
+    /// a var target of an optional binding is narrowed by a value that can't
+    /// be `nil`, one of a non optional type can't take a value that may
+    fn assign(&mut self, assign: Assign) {
+        let mut targets = Vec::new();
+        let mut pos = assign.targets.from;
+        while pos < assign.targets.to {
+            let var = matches!(
+                self.module.items()[pos],
+                Token::Expr(Expr {
+                    kind: ExprKind::Var(_),
+                    ..
+                })
+            );
+            if var {
+                if assign.op.is_some() {
+                    self.used(pos);
+                }
+                targets.push(self.uses.get(&pos).copied());
+            } else {
+                self.value(pos);
+                targets.push(None);
+            }
+            pos = self.module.end(pos);
+        }
+        let mut pos = assign.values.from;
+        let mut targets = targets.into_iter();
+        while pos < assign.values.to {
+            let binding = targets.next().flatten();
+            match binding {
+                Some(binding) if self.typed.contains_key(&binding) => {
+                    self.typed(pos, self.typed[&binding]);
+                }
+                Some(binding) if self.optional.contains(&binding) => {
+                    if self.value(pos) {
+                        self.narrowed.remove(&binding);
+                    } else {
+                        self.narrowed.insert(binding);
+                    }
+                }
+                _ => {
+                    self.value(pos);
+                }
+            }
+            pos = self.module.end(pos);
+        }
+    }
+
+    fn values(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            self.value(pos);
+            pos = self.module.end(pos);
+        }
+    }
+
+    /// check the expr at `pos`, true if it may be `nil`
+    fn value(&mut self, pos: usize) -> bool {
+        self.test(pos).maybe
+    }
+
+    /// check the expr at `pos`, which must not be `nil`
+    fn used(&mut self, pos: usize) {
+        if self.value(pos) {
+            if let Some((_, expr)) = self.var(pos) {
+                if let ExprKind::Var(name) = expr.kind {
+                    self.errors.push(SemanticError::MaybeNil(expr.span, name));
+                }
+            }
+        }
+    }
+
+    /// check the expr at `pos` for a binding or param of type `ty`
+    fn typed(&mut self, pos: usize, ty: TSpan) {
+        match self.var(pos) {
+            Some((_, expr)) if self.is_nil(pos) => {
+                let ty = self.module.type_str(ty.from);
+                self.errors.push(SemanticError::NilAssigned(expr.span, ty));
+            }
+            _ => self.used(pos),
+        }
+    }
+
+    /// the var at `pos` & its pos, through parens
+    fn var(&self, pos: usize) -> Option<(usize, Expr)> {
+        match self.module.get(pos)? {
+            Token::Expr(
+                expr @ Expr {
+                    kind: ExprKind::Var(_),
+                    ..
+                },
+            ) => Some((pos, expr)),
+            Token::Expr(Expr {
+                kind: ExprKind::Paren,
+                ..
+            }) => self.var(pos + 1),
+            _ => None,
+        }
+    }
+
+    /// the optional binding the var at `pos` refers to
+    fn optional(&self, pos: usize) -> Option<usize> {
+        let (pos, _) = self.var(pos)?;
+        self.uses
+            .get(&pos)
+            .copied()
+            .filter(|binding| self.optional.contains(binding))
+    }
+
+    /// check the expr at `pos`, the narrowing it gives when tested
+    fn test(&mut self, pos: usize) -> Test {
+        let expr = match self.module.get(pos) {
+            Some(Token::Expr(expr)) => expr,
+            // a block used as a value may be `nil` if a tail of it may, or
+            // a value a tagged block is broken out with
+            Some(Token::Block(_) | Token::Tagged(_) | Token::Flow(_) | Token::Match(_)) => {
+                let outer = std::mem::take(&mut self.tail);
+                self.stmt(pos);
+                return Test {
+                    maybe: std::mem::replace(&mut self.tail, outer),
+                    ..Test::default()
+                };
+            }
+            _ => return Test::default(),
+        };
+        match expr.kind {
+            ExprKind::Var(name) => match self.uses.get(&pos) {
+                Some(binding) if self.optional.contains(binding) => Test {
+                    maybe: !self.narrowed.contains(binding),
+                    then: vec![*binding],
+                    els: Vec::new(),
+                },
+                Some(_) => Test::default(),
+                None => Test {
+                    maybe: name.as_str() == "nil",
+                    ..Test::default()
+                },
+            },
+            ExprKind::Paren => self.test(pos + 1),
+            ExprKind::Comp(value) => {
+                let inner = self.value(pos + 1);
+                Test {
+                    maybe: value.map_or(inner, |value| value == Const::Nil),
+                    ..Test::default()
+                }
+            }
+            ExprKind::Unary(UnOp::Not) => {
+                let test = self.test(pos + 1);
+                Test {
+                    maybe: false,
+                    then: test.els,
+                    els: test.then,
+                }
+            }
+            ExprKind::Unary(_) => {
+                self.used(pos + 1);
+                Test::default()
+            }
+            ExprKind::Binary(op) => self.binary(op, pos),
+            ExprKind::FnCall(FnCall { name }) => {
+                self.call(pos, expr, name);
+                Test::default()
+            }
+            ExprKind::Call | ExprKind::MethodCall(_) | ExprKind::Field(_) | ExprKind::Index => {
+                self.used(pos + 1);
+                self.values(self.module.end(pos + 1), expr.end);
+                Test::default()
+            }
+            ExprKind::Closure(closure) => {
+                self.func(closure.params, closure.tokens);
+                Test::default()
+            }
+            ExprKind::Value(_) | ExprKind::Varargs => Test::default(),
+            ExprKind::Construct(_) | ExprKind::FieldInit(_) | ExprKind::Table | ExprKind::Entry => {
+                self.values(pos + 1, expr.end);
+                Test::default()
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinOp, pos: usize) -> Test {
+        let rhs = self.module.end(pos + 1);
+        match op {
+            BinOp::And => {
+                let lhs = self.test(pos + 1);
+                let before = self.narrowed.clone();
+                self.narrowed.extend(lhs.then.iter().copied());
+                let rhs = self.test(rhs);
+                self.narrowed = before;
+                Test {
+                    maybe: lhs.maybe || rhs.maybe,
+                    then: [lhs.then, rhs.then].concat(),
+                    els: Vec::new(),
+                }
+            }
+            BinOp::Or => {
+                let lhs = self.test(pos + 1);
+                let before = self.narrowed.clone();
+                self.narrowed.extend(lhs.els.iter().copied());
+                let rhs = self.test(rhs);
+                self.narrowed = before;
+                Test {
+                    maybe: rhs.maybe,
+                    then: Vec::new(),
+                    els: [lhs.els, rhs.els].concat(),
+                }
+            }
+            BinOp::Eq | BinOp::Ne => {
+                self.value(pos + 1);
+                self.value(rhs);
+                let binding = match (self.is_nil(pos + 1), self.is_nil(rhs)) {
+                    (false, true) => self.optional(pos + 1),
+                    (true, false) => self.optional(rhs),
+                    _ => None,
+                };
+                let narrowed = binding.into_iter().collect();
+                match op {
+                    BinOp::Ne => Test {
+                        then: narrowed,
+                        ..Test::default()
+                    },
+                    _ => Test {
+                        els: narrowed,
+                        ..Test::default()
+                    },
+                }
+            }
+            _ => {
+                self.used(pos + 1);
+                self.used(rhs);
+                Test::default()
+            }
+        }
+    }
+
+    /// args for params of a non optional type must not be `nil`
+    fn call(&mut self, pos: usize, expr: Expr, name: Symbol) {
+        let params = self
+            .uses
+            .get(&pos)
+            .copied()
+            .or_else(|| global_fn(self.module, name))
+            .and_then(|def| match self.module.get(def)? {
+                Token::FnDef(def) => Some(def.params),
+                _ => None,
+            });
+        let (mut param, to) = params.map_or((0, 0), |params| (params.from, params.to));
+        let mut arg = pos + 1;
+        while arg < expr.end {
+            match self.module.items().get(param).filter(|_| param < to) {
+                Some(Token::FnDefParam(FnDefParam { ty: Some(ty), .. }))
+                    if !self.module.optional(Some(*ty)) =>
+                {
+                    self.typed(arg, *ty);
+                }
+                _ => {
+                    self.value(arg);
+                }
+            }
+            if param < to {
+                param = self.module.end(param);
+            }
+            arg = self.module.end(arg);
+        }
+    }
+
+    /// true if the expr at `pos` is the literal `nil`
+    fn is_nil(&self, pos: usize) -> bool {
+        self.var(pos).is_some_and(|(pos, expr)| {
+            expr.kind == ExprKind::Var("nil".into()) && !self.uses.contains_key(&pos)
+        })
+    }
+}
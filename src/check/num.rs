@@ -0,0 +1,523 @@
+//! numeric type checking
+//!
+//! Numbers are `signed`, `unsigned` or `decimal`, or `int`, `uint` & `float`
+//! for short. A binding has the type it's declared with, or else the type of
+//! its value. Types never mix implicitly, the builtin fns `signed`,
+//! `unsigned` & `decimal` convert between them. An int literal fits any of
+//! them, unless it is negated & the type is `unsigned`. `unsigned`s can't be
+//! negated or subtracted, as either may underflow. Values of unknown
+//! type, such as untyped params, are never errors.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::SemanticError;
+use crate::lex::LiteralKind;
+use crate::parse::token::*;
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+use super::{global_fn, uses};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Num {
+    Signed,
+    Unsigned,
+    Decimal,
+}
+
+impl Num {
+    /// The numeric type named `name`
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "signed" | "int" => Some(Self::Signed),
+            "unsigned" | "uint" => Some(Self::Unsigned),
+            "decimal" | "float" => Some(Self::Decimal),
+            _ => None,
+        }
+    }
+
+    /// The type the builtin fn `name` converts to
+    #[must_use]
+    pub fn conversion(name: &str) -> Option<Self> {
+        match name {
+            "signed" => Some(Self::Signed),
+            "unsigned" => Some(Self::Unsigned),
+            "decimal" => Some(Self::Decimal),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Signed => "signed",
+            Self::Unsigned => "unsigned",
+            Self::Decimal => "decimal",
+        }
+    }
+}
+
+/// The type of an expr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ty {
+    Num(Num),
+    /// an int literal, which fits any type
+    Lit,
+    /// a negated int literal, which fits any type but `unsigned`
+    NegLit,
+}
+
+impl Ty {
+    /// a binding of an int literal is `signed`
+    const fn num(self) -> Num {
+        match self {
+            Self::Num(num) => num,
+            Self::Lit | Self::NegLit => Num::Signed,
+        }
+    }
+
+    const fn is_lit(self) -> bool {
+        matches!(self, Self::Lit | Self::NegLit)
+    }
+}
+
+/// The type of a value & the pos of the expr it comes from
+type Typed = (usize, Ty);
+
+/// The numeric type errors of the module, in order
+#[must_use]
+pub fn numbers(module: &Module) -> Vec<SemanticError> {
+    let mut checker = Checker {
+        module,
+        uses: uses(module),
+        types: HashMap::new(),
+        returns: Vec::new(),
+        breaks: Vec::new(),
+        errors: Vec::new(),
+    };
+    checker.block(0, module.len());
+    checker.errors
+}
+
+struct Checker<'a> {
+    module: &'a Module,
+    uses: BTreeMap<usize, usize>,
+    /// the type of each numeric binding, by pos
+    types: HashMap<usize, Num>,
+    /// the declared return type of each fn being checked
+    returns: Vec<Option<Num>>,
+    /// the values broken out of each open tagged block with
+    breaks: Vec<(Symbol, Vec<Option<Typed>>)>,
+    errors: Vec<SemanticError>,
+}
+
+impl Checker<'_> {
+    fn block(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            self.stmt(pos);
+            pos = self.module.end(pos);
+        }
+    }
+
+    fn stmt(&mut self, pos: usize) {
+        match self.module.items()[pos] {
+            Token::Decl(decl) => {
+                let at = decl.value_pos(pos);
+                let ty = decl.value.then(|| self.value(at)).flatten();
+                self.bind(pos, decl.ty, ty);
+            }
+            Token::FnDef(def) => self.func(def.ty, def.params, def.tokens),
+            Token::Impl(def) => {
+                let mut method = def.methods.from;
+                while method < def.methods.to {
+                    if let Token::FnDef(def) = self.module.items()[method] {
+                        self.func(def.ty, def.params, def.tokens);
+                    }
+                    method = self.module.end(method);
+                }
+            }
+            Token::Block(block) => self.block(block.tokens.from, block.tokens.to),
+            Token::Tagged(tagged) => {
+                self.tagged(tagged);
+            }
+            Token::Flow(Flow::If(span, el)) => {
+                self.expr(pos + 1);
+                self.block(span.from, span.to);
+                if let Some(el) = el {
+                    self.block(el.from, el.to);
+                }
+            }
+            Token::Flow(Flow::While(_) | Flow::Repeat(_) | Flow::For(_)) => {
+                self.block(pos + 1, self.module.end(pos));
+            }
+            Token::Match(_) => {
+                self.value(pos);
+            }
+            Token::Expr(_) => {
+                self.expr(pos);
+            }
+            Token::Tail(values) => {
+                self.expr(values.from);
+            }
+            Token::Return(values) => {
+                let found = self.exprs(values.from, values.to);
+                if let (Some(&(pos, Some(found))), Some(&Some(expected))) =
+                    (found.first(), self.returns.last())
+                {
+                    self.expect(expected, found, pos);
+                }
+            }
+            Token::Break(brk) => {
+                let found = self.exprs(brk.values.from, brk.values.to);
+                let value = found.first().and_then(|&(pos, ty)| Some((pos, ty?)));
+                if let Some((_, values)) =
+                    self.breaks.iter_mut().rev().find(|(l, _)| *l == brk.label)
+                {
+                    values.push(value);
+                }
+            }
+            Token::Assign(assign) => self.assign(assign),
+            Token::FnDefParam(_)
+            | Token::Flow(Flow::Goto(..) | Flow::Label(..))
+            | Token::Import(_)
+            | Token::Struct(_)
+            | Token::Enum(_)
+            | Token::Variant(_)
+            | Token::Field(_)
+            | Token::Like(_)
+            | Token::FnSig(_)
+            | Token::Arm(_)
+            | Token::Pat(_)
+            | Token::Type(_)
+            | Token::Error(_)
+            | Token::Dummy => (),
+        }
+    }
+
+    /// the binding at `pos` is of its declared type, or of its value's, the
+    /// type of the expr at the given pos
+    fn bind(&mut self, pos: usize, ty: Option<TSpan>, value: Option<(usize, Ty)>) {
+        match (self.declared(ty), value) {
+            (Some(num), value) => {
+                if let Some((at, value)) = value {
+                    self.expect(num, value, at);
+                }
+                self.types.insert(pos, num);
+            }
+            (None, Some((_, value))) => {
+                self.types.insert(pos, value.num());
+            }
+            (None, None) => (),
+        }
+    }
+
+    /// the number type named by `ty`, if any
+    fn declared(&self, ty: Option<TSpan>) -> Option<Num> {
+        self.module
+            .type_name(ty)
+            .and_then(|name| Num::from_name(name.as_str()))
+    }
+
+    fn func(&mut self, ty: Option<TSpan>, params: TSpan, tokens: TSpan) {
+        let mut pos = params.from;
+        while pos < params.to {
+            if let Token::FnDefParam(param) = self.module.items()[pos] {
+                let value = param
+                    .value
+                    .then(|| self.value(param.value_pos(pos)))
+                    .flatten();
+                self.bind(pos, param.ty, value);
+            }
+            pos = self.module.end(pos);
+        }
+        self.returns.push(self.declared(ty));
+        self.block(tokens.from, tokens.to);
+        self.returns.pop();
+    }
+
+    /// each value must be of its target's type
+    fn assign(&mut self, assign: Assign) {
+        let values = self.exprs(assign.values.from, assign.values.to);
+        let targets = self.exprs(assign.targets.from, assign.targets.to);
+        for ((target, expected), &(value, found)) in targets.into_iter().zip(&values) {
+            match (assign.op, expected, found) {
+                (Some(op), Some(expected), Some(found)) => {
+                    self.arith(op, expected, found, self.span(target));
+                }
+                (None, Some(Ty::Num(expected)), Some(found)) => {
+                    self.expect(expected, found, value);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// the type of the value at `pos` & the pos of the expr it comes from, a
+    /// block, `if` or `match` used as a value has the type of its tail exprs
+    fn value(&mut self, pos: usize) -> Option<(usize, Ty)> {
+        match self.module.items()[pos] {
+            Token::Block(block) => self.tail(block.tokens.from, block.tokens.to),
+            Token::Tagged(tagged) => self.tagged(tagged),
+            Token::Flow(Flow::If(span, el)) => {
+                self.expr(pos + 1);
+                let then = self.tail(span.from, span.to);
+                let el = el.and_then(|el| self.tail(el.from, el.to));
+                let ((then_pos, then), (el_pos, el)) = (then?, el?);
+                let ty = self.unify(then, el, self.span(el_pos))?;
+                Some(if then.is_lit() {
+                    (el_pos, ty)
+                } else {
+                    (then_pos, ty)
+                })
+            }
+            Token::Match(def) => {
+                self.exprs(pos + 1, def.arms.from);
+                let mut tails = Vec::new();
+                let mut arm = def.arms.from;
+                while arm < def.arms.to {
+                    if let Token::Arm(token) = self.module.items()[arm] {
+                        if token.pats.to < token.body.from {
+                            self.expr(token.pats.to);
+                        }
+                        tails.push(self.tail(token.body.from, token.body.to));
+                    }
+                    arm = self.module.end(arm);
+                }
+                self.join(tails)
+            }
+            // a loop has no value
+            Token::Flow(_) => {
+                self.stmt(pos);
+                None
+            }
+            _ => self.expr(pos).map(|ty| (pos, ty)),
+        }
+    }
+
+    /// the type of a tagged block, of its tail & the values it's broken out
+    /// with
+    fn tagged(&mut self, tagged: Tagged) -> Option<(usize, Ty)> {
+        self.breaks.push((tagged.label, Vec::new()));
+        let tail = self.tail(tagged.tokens.from, tagged.tokens.to);
+        let (_, mut values) = self.breaks.pop()?;
+        if self.falls_through(tagged.tokens) {
+            values.push(tail);
+        }
+        self.join(values)
+    }
+
+    /// false if the block always ends in a `return` or `break`
+    fn falls_through(&self, tokens: TSpan) -> bool {
+        let mut last = None;
+        let mut pos = tokens.from;
+        while pos < tokens.to {
+            last = Some(pos);
+            pos = self.module.end(pos);
+        }
+        !matches!(
+            last.map(|pos| self.module.items()[pos]),
+            Some(Token::Return(_) | Token::Break(_))
+        )
+    }
+
+    /// the type the values of the tails of arms or a tagged block unify to,
+    /// `None` if any is unknown
+    fn join(&mut self, tails: Vec<Option<(usize, Ty)>>) -> Option<(usize, Ty)> {
+        let mut tails = tails.into_iter();
+        let first = tails.next()??;
+        tails.try_fold(first, |(pos, ty), tail| {
+            let (tail_pos, tail) = tail?;
+            let unified = self.unify(ty, tail, self.span(tail_pos))?;
+            Some(if ty.is_lit() {
+                (tail_pos, unified)
+            } else {
+                (pos, unified)
+            })
+        })
+    }
+
+    /// check the block in `from..to`, the type of its tail expr
+    fn tail(&mut self, from: usize, to: usize) -> Option<(usize, Ty)> {
+        let mut tail = None;
+        let mut pos = from;
+        while pos < to {
+            tail = match self.module.items()[pos] {
+                Token::Tail(values) => self.value(values.from),
+                // the `if` of an `else if`, or a block, `if` or `match` with
+                // tails of its own
+                Token::Block(_) | Token::Tagged(_) | Token::Flow(_) | Token::Match(_) => {
+                    self.value(pos)
+                }
+                _ => {
+                    self.stmt(pos);
+                    None
+                }
+            };
+            pos = self.module.end(pos);
+        }
+        tail
+    }
+
+    /// check every expr in `from..to`, their positions & types
+    fn exprs(&mut self, from: usize, to: usize) -> Vec<(usize, Option<Ty>)> {
+        let mut types = Vec::new();
+        let mut pos = from;
+        while pos < to {
+            types.push((pos, self.expr(pos)));
+            pos = self.module.end(pos);
+        }
+        types
+    }
+
+    /// the numeric type of the expr at `pos`, `None` if unknown
+    fn expr(&mut self, pos: usize) -> Option<Ty> {
+        let expr = match self.module.get(pos) {
+            Some(Token::Expr(expr)) => expr,
+            Some(Token::Block(_) | Token::Flow(_) | Token::Match(_)) => {
+                return self.value(pos).map(|(_, ty)| ty);
+            }
+            _ => return None,
+        };
+        match expr.kind {
+            ExprKind::Value(lit) => match lit.kind {
+                LiteralKind::Int { .. } => Some(Ty::Lit),
+                LiteralKind::Float { .. } => Some(Ty::Num(Num::Decimal)),
+                _ => None,
+            },
+            ExprKind::Var(_) => {
+                let binding = self.uses.get(&pos)?;
+                self.types.get(binding).copied().map(Ty::Num)
+            }
+            ExprKind::Paren | ExprKind::Comp(None) => self.expr(pos + 1),
+            ExprKind::Comp(Some(Const::Int(i))) if i < 0 => Some(Ty::NegLit),
+            ExprKind::Comp(Some(Const::Int(_))) => Some(Ty::Lit),
+            ExprKind::Comp(Some(Const::Float(_))) => Some(Ty::Num(Num::Decimal)),
+            ExprKind::Binary(op) => {
+                let lhs = self.expr(pos + 1);
+                let rhs = self.expr(self.module.end(pos + 1));
+                let (lhs, rhs) = (lhs?, rhs?);
+                match op {
+                    BinOp::Shl | BinOp::Shr => Some(lhs),
+                    BinOp::And | BinOp::Or => None,
+                    BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                        self.unify(lhs, rhs, expr.span);
+                        None
+                    }
+                    _ => self.arith(op, lhs, rhs, expr.span),
+                }
+            }
+            ExprKind::Unary(op) => {
+                let value = self.expr(pos + 1)?;
+                match op {
+                    UnOp::Neg if value == Ty::Num(Num::Unsigned) => {
+                        self.errors.push(SemanticError::NegUnsigned(expr.span));
+                        None
+                    }
+                    UnOp::Neg if value == Ty::Lit => Some(Ty::NegLit),
+                    UnOp::Neg if value == Ty::NegLit => Some(Ty::Lit),
+                    UnOp::Neg | UnOp::BitNot => Some(value),
+                    UnOp::Not | UnOp::Len => None,
+                }
+            }
+            ExprKind::FnCall(FnCall { name }) => self.call(pos, expr, name),
+            ExprKind::Closure(closure) => {
+                self.func(None, closure.params, closure.tokens);
+                None
+            }
+            _ => {
+                self.exprs(pos + 1, expr.end);
+                None
+            }
+        }
+    }
+
+    /// check the args of a call against the params of the fn, the type it
+    /// returns
+    fn call(&mut self, pos: usize, expr: Expr, name: Symbol) -> Option<Ty> {
+        let def = self
+            .uses
+            .get(&pos)
+            .copied()
+            .or_else(|| global_fn(self.module, name))
+            .and_then(|def| match self.module.get(def)? {
+                Token::FnDef(def) => Some(def),
+                _ => None,
+            });
+        let args = self.exprs(pos + 1, expr.end);
+        if let Some(def) = def {
+            let mut param = def.params.from;
+            for (arg, found) in args {
+                if param >= def.params.to {
+                    break;
+                }
+                if let (Token::FnDefParam(p), Some(found)) = (self.module.items()[param], found) {
+                    if let Some(expected) = self.declared(p.ty) {
+                        self.expect(expected, found, arg);
+                    }
+                }
+                param = self.module.end(param);
+            }
+        }
+        match def {
+            Some(def) => self.declared(def.ty).map(Ty::Num),
+            None if !self.uses.contains_key(&pos) => Num::conversion(name.as_str()).map(Ty::Num),
+            None => None,
+        }
+    }
+
+    /// the type of an arithmetic op, an error for a subtraction of
+    /// `unsigned`s
+    fn arith(&mut self, op: BinOp, lhs: Ty, rhs: Ty, span: BSpan) -> Option<Ty> {
+        let ty = self.unify(lhs, rhs, span)?;
+        if op == BinOp::Sub && ty == Ty::Num(Num::Unsigned) {
+            self.errors.push(SemanticError::SubUnsigned(span));
+        }
+        Some(ty)
+    }
+
+    /// the type both operands share, an error if they don't
+    fn unify(&mut self, lhs: Ty, rhs: Ty, span: BSpan) -> Option<Ty> {
+        match (lhs, rhs) {
+            (Ty::NegLit, Ty::Num(Num::Unsigned)) | (Ty::Num(Num::Unsigned), Ty::NegLit) => {
+                self.errors.push(SemanticError::NegUnsigned(span));
+                None
+            }
+            (Ty::Lit, other) | (other, Ty::Lit) => Some(other),
+            (Ty::NegLit, Ty::Num(num)) | (Ty::Num(num), Ty::NegLit) => Some(Ty::Num(num)),
+            (Ty::NegLit, Ty::NegLit) => Some(Ty::NegLit),
+            (Ty::Num(lhs), Ty::Num(rhs)) if lhs == rhs => Some(Ty::Num(lhs)),
+            (Ty::Num(lhs), Ty::Num(rhs)) => {
+                self.errors.push(SemanticError::MixedNum {
+                    span,
+                    lhs: lhs.name(),
+                    rhs: rhs.name(),
+                });
+                None
+            }
+        }
+    }
+
+    /// the expr at `pos` must be of the type `expected`
+    fn expect(&mut self, expected: Num, found: Ty, pos: usize) {
+        if found == Ty::NegLit && expected == Num::Unsigned {
+            self.errors.push(SemanticError::NegUnsigned(self.span(pos)));
+        } else if let Ty::Num(found) = found {
+            if found != expected {
+                self.errors.push(SemanticError::NumType {
+                    span: self.span(pos),
+                    expected: expected.name(),
+                    found: found.name(),
+                });
+            }
+        }
+    }
+
+    fn span(&self, pos: usize) -> BSpan {
+        match self.module.get(pos) {
+            Some(Token::Expr(expr)) => expr.span,
+            _ => BSpan::default(),
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! exhaustiveness & reachability of `match` arms
+//!
+//! Values are dynamically typed, so the values a pat may be checked against
+//! are guessed from the pats beside it: the variants of one enum, one
+//! struct, `true` & `false`, or tuples of one size. Any other value, such
+//! as a number or a string, is only covered by `_` or a binding. An arm with
+//! a guard covers nothing, it may not match.
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::runtime;
+use crate::span::BSpan;
+use crate::util::Symbol;
+
+/// The unreachable arms & pats, the non-exhaustive matches & the names not
+/// bound by every alternative pat of the module, in order
+#[must_use]
+pub fn patterns(module: &Module) -> Vec<SemanticError> {
+    let mut checker = Checker {
+        module,
+        errors: Vec::new(),
+    };
+    for token in module.items() {
+        if let Token::Match(def) = *token {
+            checker.check(def);
+        }
+    }
+    checker.errors
+}
+
+/// A pat as far as coverage is concerned, bindings are wildcards
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cover {
+    Wild,
+    Ctor(Ctor, Vec<Self>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ctor {
+    Const(Const),
+    Tuple(usize),
+    Data(Construct),
+}
+
+/// rows of pats, one column for each value left to check
+type Matrix = Vec<Vec<Cover>>;
+
+struct Checker<'a> {
+    module: &'a Module,
+    errors: Vec<SemanticError>,
+}
+
+impl Checker<'_> {
+    fn check(&mut self, def: Match) {
+        let mut matrix = Matrix::new();
+        let mut pos = def.arms.from;
+        while pos < def.arms.to {
+            if let Token::Arm(arm) = self.module.items()[pos] {
+                self.arm(arm, &mut matrix);
+            }
+            pos = self.module.end(pos);
+        }
+        if let Some(missing) = self.missing(&matrix, 1) {
+            let missing = missing.into_iter().next().unwrap_or_default();
+            self.errors
+                .push(SemanticError::NonExhaustive(def.span, missing));
+        }
+    }
+
+    /// check the arm against the pats before it, adding its pats if it has
+    /// no guard
+    fn arm(&mut self, arm: Arm, matrix: &mut Matrix) {
+        let guard = arm.pats.to < arm.body.from;
+        let mut reachable = false;
+        let mut unreachable = Vec::new();
+        let mut pos = arm.pats.from;
+        while pos < arm.pats.to {
+            let row = vec![self.cover(pos)];
+            if self.useful(matrix, &row) {
+                reachable = true;
+                if !guard {
+                    matrix.push(row);
+                }
+            } else {
+                unreachable.push(self.span(pos));
+            }
+            pos = self.module.end(pos);
+        }
+        if reachable {
+            let unreachable = unreachable.into_iter().map(SemanticError::UnreachablePat);
+            self.errors.extend(unreachable);
+        } else {
+            self.errors.push(SemanticError::UnreachableArm(arm.span));
+        }
+        self.bindings(arm);
+    }
+
+    /// every alternative pat of the arm must bind the same names
+    fn bindings(&mut self, arm: Arm) {
+        let mut alts = Vec::new();
+        let mut pos = arm.pats.from;
+        while pos < arm.pats.to {
+            let end = self.module.end(pos);
+            let names: Vec<Symbol> = self.module.items()[pos..end]
+                .iter()
+                .filter_map(|token| match token {
+                    Token::Pat(Pat {
+                        kind: PatKind::Bind(name),
+                        ..
+                    }) => Some(*name),
+                    _ => None,
+                })
+                .collect();
+            alts.push((self.span(pos), names));
+            pos = end;
+        }
+        let mut all: Vec<Symbol> = Vec::new();
+        for name in alts.iter().flat_map(|(_, names)| names) {
+            if !all.contains(name) {
+                all.push(*name);
+            }
+        }
+        for (span, names) in &alts {
+            for name in all.iter().filter(|name| !names.contains(name)) {
+                self.errors.push(SemanticError::AltBindings(*span, *name));
+            }
+        }
+    }
+
+    fn span(&self, pos: usize) -> BSpan {
+        match self.module.items()[pos] {
+            Token::Pat(pat) => pat.span,
+            _ => BSpan::default(),
+        }
+    }
+
+    /// the coverage of the pat at `pos`
+    fn cover(&self, pos: usize) -> Cover {
+        let Some(Token::Pat(pat)) = self.module.get(pos) else {
+            return Cover::Wild;
+        };
+        match pat.kind {
+            PatKind::Wild | PatKind::Bind(_) => Cover::Wild,
+            PatKind::Field(_) => self.cover(pos + 1),
+            PatKind::Const(value) => Cover::Ctor(Ctor::Const(value), Vec::new()),
+            PatKind::Tuple => {
+                let subs: Vec<Cover> = self
+                    .subs(pos + 1, pat.end)
+                    .map(|at| self.cover(at))
+                    .collect();
+                Cover::Ctor(Ctor::Tuple(subs.len()), subs)
+            }
+            PatKind::Data(construct) => self.cover_data(pos, pat.end, construct),
+        }
+    }
+
+    /// a struct or variant, with a sub pat for each of its declared fields
+    fn cover_data(&self, pos: usize, end: usize, construct: Construct) -> Cover {
+        let ctor = Ctor::Data(construct);
+        // an undeclared one is already an error
+        let Some(def) = self.def(construct) else {
+            return Cover::Ctor(ctor, Vec::new());
+        };
+        let fields = self.module.fields(def);
+        let subs = match construct.kind {
+            VariantKind::Unit => Vec::new(),
+            VariantKind::Tuple => {
+                let mut subs: Vec<Cover> =
+                    self.subs(pos + 1, end).map(|at| self.cover(at)).collect();
+                subs.resize(fields.len(), Cover::Wild);
+                subs
+            }
+            VariantKind::Struct => fields
+                .iter()
+                .map(|field| {
+                    self.subs(pos + 1, end)
+                        .find(|&at| {
+                            matches!(self.module.items()[at],
+                                Token::Pat(Pat { kind: PatKind::Field(other), .. })
+                                if Some(other) == field.name)
+                        })
+                        .map_or(Cover::Wild, |at| self.cover(at))
+                })
+                .collect(),
+        };
+        Cover::Ctor(ctor, subs)
+    }
+
+    /// the positions of the sub pats in `from..to`
+    fn subs(&self, from: usize, to: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut pos = from;
+        std::iter::from_fn(move || {
+            let at = pos;
+            (at < to).then(|| {
+                pos = self.module.end(at);
+                at
+            })
+        })
+    }
+
+    /// the pos of the struct or variant declaration
+    fn def(&self, construct: Construct) -> Option<usize> {
+        let def = self.module.data(construct.ty)?;
+        construct
+            .variant
+            .map_or(Some(def), |variant| self.module.variant(def, variant))
+    }
+
+    fn arity(&self, ctor: Ctor) -> usize {
+        match ctor {
+            Ctor::Const(_) => 0,
+            Ctor::Tuple(len) => len,
+            Ctor::Data(construct) => self
+                .def(construct)
+                .map_or(0, |def| self.module.fields(def).len()),
+        }
+    }
+
+    /// every ctor of the values the ctors of a column are guessed to be,
+    /// `None` if there are too many to list
+    fn signature(&self, heads: &[Ctor]) -> Option<Vec<Ctor>> {
+        let all = match *heads.first()? {
+            Ctor::Const(Const::Bool(_)) => vec![
+                Ctor::Const(Const::Bool(true)),
+                Ctor::Const(Const::Bool(false)),
+            ],
+            Ctor::Const(_) => return None,
+            ctor @ Ctor::Tuple(_) => vec![ctor],
+            Ctor::Data(construct) if construct.variant.is_none() => {
+                self.def(construct)?;
+                vec![Ctor::Data(construct)]
+            }
+            Ctor::Data(construct) => {
+                let Some(Token::Enum(def)) = self.module.get(self.module.data(construct.ty)?)
+                else {
+                    return None;
+                };
+                self.subs(def.variants.from, def.variants.to)
+                    .filter_map(|pos| match self.module.items()[pos] {
+                        Token::Variant(variant) => Some(Ctor::Data(Construct {
+                            ty: def.name,
+                            variant: Some(variant.name),
+                            kind: variant.kind,
+                        })),
+                        _ => None,
+                    })
+                    .collect()
+            }
+        };
+        heads.iter().all(|head| all.contains(head)).then_some(all)
+    }
+
+    /// the ctors of the first column
+    fn heads(matrix: &Matrix) -> Vec<Ctor> {
+        let mut heads = Vec::new();
+        for row in matrix {
+            if let Some(Cover::Ctor(ctor, _)) = row.first() {
+                if !heads.contains(ctor) {
+                    heads.push(*ctor);
+                }
+            }
+        }
+        heads
+    }
+
+    /// the rows that may match a value of `ctor`, its fields in place of
+    /// their first column
+    fn specialize(&self, matrix: &Matrix, ctor: Ctor) -> Matrix {
+        matrix
+            .iter()
+            .filter_map(|row| {
+                let (head, rest) = row.split_first()?;
+                let mut out = match head {
+                    Cover::Wild => vec![Cover::Wild; self.arity(ctor)],
+                    Cover::Ctor(other, subs) if *other == ctor => subs.clone(),
+                    Cover::Ctor(..) => return None,
+                };
+                out.extend_from_slice(rest);
+                Some(out)
+            })
+            .collect()
+    }
+
+    /// the rows whose first column matches anything, without it
+    fn default(matrix: &Matrix) -> Matrix {
+        matrix
+            .iter()
+            .filter(|row| row.first() == Some(&Cover::Wild))
+            .map(|row| row[1..].to_vec())
+            .collect()
+    }
+
+    /// true if `row` matches a value none of the rows of `matrix` do
+    fn useful(&self, matrix: &Matrix, row: &[Cover]) -> bool {
+        let Some((head, rest)) = row.split_first() else {
+            return matrix.is_empty();
+        };
+        match head {
+            Cover::Ctor(ctor, subs) => {
+                let row = [subs.as_slice(), rest].concat();
+                self.useful(&self.specialize(matrix, *ctor), &row)
+            }
+            Cover::Wild => {
+                let Some(all) = self.signature(&Self::heads(matrix)) else {
+                    return self.useful(&Self::default(matrix), rest);
+                };
+                all.into_iter().any(|ctor| {
+                    let row = [vec![Cover::Wild; self.arity(ctor)].as_slice(), rest].concat();
+                    self.useful(&self.specialize(matrix, ctor), &row)
+                })
+            }
+        }
+    }
+
+    /// values for the `len` columns that no row of `matrix` matches
+    fn missing(&self, matrix: &Matrix, len: usize) -> Option<Vec<String>> {
+        if len == 0 {
+            return matrix.is_empty().then(Vec::new);
+        }
+        let Some(all) = self.signature(&Self::heads(matrix)) else {
+            let mut missing = self.missing(&Self::default(matrix), len - 1)?;
+            missing.insert(0, "_".to_owned());
+            return Some(missing);
+        };
+        all.into_iter().find_map(|ctor| {
+            let arity = self.arity(ctor);
+            let mut missing = self.missing(&self.specialize(matrix, ctor), arity + len - 1)?;
+            let fields: Vec<String> = missing.drain(..arity).collect();
+            missing.insert(0, self.show(ctor, &fields));
+            Some(missing)
+        })
+    }
+
+    /// a value of `ctor`, written as a pat
+    fn show(&self, ctor: Ctor, fields: &[String]) -> String {
+        let construct = match ctor {
+            Ctor::Const(value) => return runtime::Value::from(value).to_string(),
+            Ctor::Tuple(_) => return format!("({})", fields.join(", ")),
+            Ctor::Data(construct) => construct,
+        };
+        let mut out = construct.ty.to_string();
+        if let Some(variant) = construct.variant {
+            out = format!("{out}.{variant}");
+        }
+        match construct.kind {
+            VariantKind::Unit => out,
+            VariantKind::Tuple => format!("{out}({})", fields.join(", ")),
+            VariantKind::Struct => {
+                let names = self
+                    .def(construct)
+                    .map_or_else(Vec::new, |def| self.module.fields(def));
+                let fields: Vec<String> = names
+                    .iter()
+                    .zip(fields)
+                    .map(|(field, value)| {
+                        field
+                            .name
+                            .map_or_else(|| value.clone(), |name| format!("{name} = {value}"))
+                    })
+                    .collect();
+                format!("{out} {{ {} }}", fields.join(", "))
+            }
+        }
+    }
+}
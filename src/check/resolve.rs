@@ -0,0 +1,458 @@
+//! name resolution
+//!
+//! Builds the tree of scopes of a module, the module body, fns & closures,
+//! blocks, the branches of an `if` & the arms of a `match`, & binds each use
+//! of a name to the [`Decl`], [`FnDefParam`], [`FnDef`] or [`Pat`] it
+//! refers to.
+//!
+//! A name is defined if a binding of it is visible, if the host defines it,
+//! or if an assignment creates it as a global. Inside a fn the top level
+//! bindings declared after it & the globals assigned anywhere are defined
+//! too, outside of one only the globals assigned before the use are.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+use super::binding_name;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    Module,
+    /// the fn or closure at the pos, its params are in this scope
+    Fn(usize),
+    Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    /// the index of the enclosing scope, `None` for the module body
+    pub parent: Option<usize>,
+    pub span: TSpan,
+    /// the positions of the bindings declared directly in this scope
+    pub bindings: Vec<usize>,
+}
+
+/// The scope tree of a module & the binding of each use
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolution {
+    /// every scope, parents before their children, the module body first
+    pub scopes: Vec<Scope>,
+    /// the pos of each [`ExprKind::Var`] & [`ExprKind::FnCall`] -> the pos
+    /// of its binding, host globals & created globals have none
+    pub uses: BTreeMap<usize, usize>,
+    /// undefined names & duplicate params
+    pub errors: Vec<SemanticError>,
+    /// bindings that shadow a binding of an enclosing scope
+    pub warnings: Vec<SemanticError>,
+}
+
+/// Resolve every name of the module, `globals` are the names the host
+/// defines
+#[must_use]
+pub fn resolve(module: &Module, globals: &[Symbol]) -> Resolution {
+    let mut resolver = Resolver {
+        module,
+        top: HashMap::new(),
+        globals: globals.iter().copied().collect(),
+        assigned: HashSet::new(),
+        created: HashSet::new(),
+        open: Vec::new(),
+        out: Resolution::default(),
+    };
+    resolver.globals(module);
+    resolver.scoped(
+        ScopeKind::Module,
+        TSpan {
+            from: 0,
+            to: module.len(),
+        },
+        |this| {
+            this.block(0, module.len());
+        },
+    );
+    resolver.out
+}
+
+/// (name, pos, visible from)
+type Visible = (Symbol, usize, usize);
+
+struct Resolver<'a> {
+    module: &'a Module,
+    /// the bindings declared at the top level
+    top: HashMap<Symbol, usize>,
+    /// host globals
+    globals: HashSet<Symbol>,
+    /// the names assigned without a binding anywhere
+    assigned: HashSet<Symbol>,
+    /// the names assigned without a binding so far, outside of fns
+    created: HashSet<Symbol>,
+    /// the index & visible bindings of each open scope
+    open: Vec<(usize, Vec<Visible>)>,
+    out: Resolution,
+}
+
+impl Resolver<'_> {
+    /// the top level bindings & the globals created by assignments
+    fn globals(&mut self, module: &Module) {
+        let mut pos = 0;
+        while pos < module.len() {
+            if let Some(name) = binding_name(module, pos) {
+                self.top.entry(name).or_insert(pos);
+            }
+            pos = module.end(pos);
+        }
+        for token in module.items() {
+            let Token::Assign(assign) = *token else {
+                continue;
+            };
+            let mut target = assign.targets.from;
+            while target < assign.targets.to {
+                if let Token::Expr(Expr {
+                    kind: ExprKind::Var(name),
+                    ..
+                }) = module.items()[target]
+                {
+                    self.assigned.insert(name);
+                }
+                target = module.end(target);
+            }
+        }
+    }
+
+    fn scoped(&mut self, kind: ScopeKind, span: TSpan, walk: impl FnOnce(&mut Self)) {
+        let index = self.out.scopes.len();
+        self.out.scopes.push(Scope {
+            kind,
+            parent: self.open.last().map(|&(scope, _)| scope),
+            span,
+            bindings: Vec::new(),
+        });
+        self.open.push((index, Vec::new()));
+        walk(self);
+        self.open.pop();
+    }
+
+    fn block(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            self.stmt(pos);
+            pos = self.module.end(pos);
+        }
+    }
+
+    fn stmt(&mut self, pos: usize) {
+        let end = self.module.end(pos);
+        match self.module.items()[pos] {
+            Token::Decl(decl) => {
+                let at = decl.value_pos(pos);
+                match self.module.get(at) {
+                    Some(Token::Tagged(_)) if decl.value => self.stmt(at),
+                    _ => self.exprs(at, end),
+                }
+                self.bind(decl.name, pos, end);
+            }
+            Token::FnDef(def) => {
+                self.bind(def.name, pos, pos);
+                self.func(pos, def.params, def.tokens);
+            }
+            Token::Impl(def) => {
+                let mut method = def.methods.from;
+                while method < def.methods.to {
+                    if let Token::FnDef(def) = self.module.items()[method] {
+                        self.func(method, def.params, def.tokens);
+                    }
+                    method = self.module.end(method);
+                }
+            }
+            Token::Block(block) => self.scoped_block(block.tokens),
+            Token::Tagged(tagged) => self.scoped_block(tagged.tokens),
+            Token::Flow(Flow::If(span, el)) => {
+                self.exprs(pos + 1, span.from);
+                self.scoped_block(span);
+                if let Some(el) = el {
+                    self.scoped_block(el);
+                }
+            }
+            Token::Flow(Flow::While(body)) => {
+                self.exprs(pos + 1, body.from);
+                self.scoped_block(body);
+            }
+            Token::Flow(Flow::Repeat(body)) => {
+                self.scoped(ScopeKind::Block, body.to(end), |this| {
+                    this.block(body.from, body.to);
+                    this.exprs(body.to, end);
+                });
+            }
+            Token::Flow(Flow::For(def)) => {
+                self.exprs(pos + 1, def.vars.from);
+                self.scoped(ScopeKind::Block, def.vars.to(def.body.to), |this| {
+                    for var in def.vars.from..def.vars.to {
+                        if let Some(name) = binding_name(this.module, var) {
+                            this.bind(name, var, def.vars.to);
+                        }
+                    }
+                    this.block(def.body.from, def.body.to);
+                });
+            }
+            Token::Match(def) => {
+                self.exprs(pos + 1, def.arms.from);
+                let mut arm = def.arms.from;
+                while arm < def.arms.to {
+                    if let Token::Arm(token) = self.module.items()[arm] {
+                        self.arm(arm, token);
+                    }
+                    arm = self.module.end(arm);
+                }
+            }
+            Token::Expr(_) => self.exprs(pos, end),
+            Token::Assign(assign) => self.assign(assign),
+            Token::Return(_) | Token::Tail(_) | Token::Break(_) => {
+                self.exprs(pos + 1, end);
+            }
+            Token::FnDefParam(_)
+            | Token::Flow(Flow::Goto(..) | Flow::Label(..))
+            | Token::Import(_)
+            | Token::Struct(_)
+            | Token::Enum(_)
+            | Token::Variant(_)
+            | Token::Field(_)
+            | Token::Like(_)
+            | Token::FnSig(_)
+            | Token::Arm(_)
+            | Token::Pat(_)
+            | Token::Type(_)
+            | Token::Error(_)
+            | Token::Dummy => (),
+        }
+    }
+
+    /// the values are read before a var target without a binding is
+    /// created
+    fn assign(&mut self, assign: Assign) {
+        self.exprs(assign.values.from, assign.values.to);
+        let mut target = assign.targets.from;
+        while target < assign.targets.to {
+            let end = self.module.end(target);
+            match self.module.items()[target] {
+                Token::Expr(Expr {
+                    kind: ExprKind::Var(name),
+                    ..
+                }) if assign.op.is_none() => match self.lookup(name, target) {
+                    Some(binding) => {
+                        self.out.uses.insert(target, binding);
+                    }
+                    None if !self.in_fn() => {
+                        self.created.insert(name);
+                    }
+                    None => (),
+                },
+                _ => self.exprs(target, end),
+            }
+            target = end;
+        }
+    }
+
+    fn scoped_block(&mut self, span: TSpan) {
+        self.scoped(ScopeKind::Block, span, |this| {
+            this.block(span.from, span.to);
+        });
+    }
+
+    /// the bindings of the pats of the arm at `pos` are in a block scope
+    /// with its guard & body
+    fn arm(&mut self, pos: usize, arm: Arm) {
+        let span = TSpan {
+            from: pos + 1,
+            to: arm.body.to,
+        };
+        self.scoped(ScopeKind::Block, span, |this| {
+            for pat in arm.pats.from..arm.pats.to {
+                if let Some(name) = binding_name(this.module, pat) {
+                    this.bind(name, pat, arm.pats.to);
+                }
+            }
+            this.exprs(arm.pats.to, arm.body.from);
+            this.block(arm.body.from, arm.body.to);
+        });
+    }
+
+    /// resolve every name in `from..to`
+    fn exprs(&mut self, from: usize, to: usize) {
+        let mut pos = from;
+        while pos < to {
+            match self.module.items()[pos] {
+                Token::Expr(Expr {
+                    kind: ExprKind::Var(name) | ExprKind::FnCall(FnCall { name }),
+                    ..
+                }) => self.use_name(name, pos),
+                Token::Expr(Expr {
+                    kind: ExprKind::Closure(closure),
+                    end,
+                    ..
+                }) => {
+                    self.func(pos, closure.params, closure.tokens);
+                    pos = end;
+                    continue;
+                }
+                Token::Block(_) | Token::Flow(_) | Token::Match(_) => {
+                    self.stmt(pos);
+                    pos = self.module.end(pos);
+                    continue;
+                }
+                _ => (),
+            }
+            pos += 1;
+        }
+    }
+
+    fn func(&mut self, func: usize, params: TSpan, tokens: TSpan) {
+        let span = params.to(tokens.to);
+        self.scoped(ScopeKind::Fn(func), span, |this| {
+            let mut seen = HashSet::new();
+            let mut pos = params.from;
+            while pos < params.to {
+                let end = this.module.end(pos);
+                if let Token::FnDefParam(param) = this.module.items()[pos] {
+                    this.exprs(param.value_pos(pos), end);
+                    if !seen.insert(param.name) {
+                        let err = SemanticError::DupeParam(param.span, param.name);
+                        this.out.errors.push(err);
+                    }
+                    this.bind(param.name, pos, end);
+                }
+                pos = end;
+            }
+            this.block(tokens.from, tokens.to);
+        });
+    }
+
+    /// declare a binding in the innermost scope, a warning if it hides one
+    /// of an enclosing scope
+    fn bind(&mut self, name: Symbol, pos: usize, visible: usize) {
+        let outer = self.open.len().saturating_sub(1);
+        let shadows = self.open[..outer]
+            .iter()
+            .any(|(_, bindings)| bindings.iter().any(|&(other, ..)| other == name));
+        if shadows {
+            let span = self.span(pos);
+            self.out.warnings.push(SemanticError::Shadowed(span, name));
+        }
+        if let Some((scope, bindings)) = self.open.last_mut() {
+            bindings.push((name, pos, visible));
+            self.out.scopes[*scope].bindings.push(pos);
+        }
+    }
+
+    /// the binding a use of `name` at `pos` refers to
+    fn lookup(&self, name: Symbol, pos: usize) -> Option<usize> {
+        let found = self.open.iter().rev().find_map(|(_, bindings)| {
+            bindings
+                .iter()
+                .rev()
+                .find(|&&(other, _, visible)| other == name && visible <= pos)
+        });
+        if let Some(&(_, binding, _)) = found {
+            return Some(binding);
+        }
+        // a fn may run after a later top level binding is declared
+        self.top.get(&name).copied().filter(|_| self.in_fn())
+    }
+
+    fn in_fn(&self) -> bool {
+        self.open
+            .iter()
+            .any(|&(scope, _)| matches!(self.out.scopes[scope].kind, ScopeKind::Fn(_)))
+    }
+
+    fn use_name(&mut self, name: Symbol, pos: usize) {
+        if let Some(binding) = self.lookup(name, pos) {
+            self.out.uses.insert(pos, binding);
+            return;
+        }
+        let created = if self.in_fn() {
+            self.assigned.contains(&name)
+        } else {
+            self.created.contains(&name)
+        };
+        if created
+            || self.globals.contains(&name)
+            || matches!(name.as_str(), "true" | "false" | "nil")
+        {
+            return;
+        }
+        let span = self.span(pos);
+        if self.assigned.contains(&name) {
+            self.out.errors.push(SemanticError::Unassigned(span, name));
+            return;
+        }
+        let suggestion = self.suggest(name, pos);
+        self.out.errors.push(SemanticError::Undefined {
+            span,
+            name,
+            suggestion,
+        });
+    }
+
+    /// the span of the name of the binding or use at `pos`
+    fn span(&self, pos: usize) -> BSpan {
+        match self.module.items()[pos] {
+            Token::Decl(Decl { span, .. })
+            | Token::FnDefParam(FnDefParam { span, .. })
+            | Token::FnDef(FnDef { span, .. })
+            | Token::Pat(Pat { span, .. })
+            | Token::Expr(Expr { span, .. }) => span,
+            _ => BSpan::default(),
+        }
+    }
+
+    /// the defined name closest to `name`, if it's close enough to be a typo
+    fn suggest(&self, name: Symbol, pos: usize) -> Option<Symbol> {
+        let visible = self.open.iter().flat_map(|(_, bindings)| {
+            bindings
+                .iter()
+                .filter(move |&&(_, _, visible)| visible <= pos)
+                .map(|&(name, ..)| name)
+        });
+        let max = (name.len() / 3).max(1);
+        visible
+            .chain(self.top.keys().copied())
+            .chain(self.globals.iter().copied())
+            .chain(self.assigned.iter().copied())
+            .map(|other| (edit_distance(name.as_str(), other.as_str()), other))
+            .filter(|&(distance, _)| (1..=max).contains(&distance) && distance < name.len())
+            .min_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.as_str().cmp(b.1.as_str())))
+            .map(|(_, other)| other)
+    }
+}
+
+/// the edit distance between `a` & `b` in chars, where swapping two
+/// adjacent chars is one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // d[i][j] is the distance between a[..i] & b[..j]
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = best;
+        }
+    }
+    d[a.len()][b.len()]
+}
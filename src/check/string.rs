@@ -0,0 +1,280 @@
+//! validating string literals & checking string types
+//!
+//! `"..."` & `'c'` are `ustr`s, `b"..."` & `b'c'` are `bstr`s and `c"..."`
+//! is an `astr`, which can only hold ascii. A binding has the string type
+//! it's declared with, or else the type of its value. Types never mix
+//! implicitly, the builtin fns `astr`, `bstr` & `ustr` convert between them.
+//! Values of unknown type are never errors.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::SemanticError;
+use crate::lex::unescape::{unescape_mixed, MixedUnit, Mode};
+use crate::lex::LiteralKind;
+use crate::parse::token::*;
+use crate::runtime;
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+use super::{global_fn, uses};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Str {
+    A,
+    B,
+    U,
+}
+
+impl Str {
+    /// The string type named `name`, or that the builtin fn `name` converts to
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "astr" => Some(Self::A),
+            "bstr" => Some(Self::B),
+            "ustr" => Some(Self::U),
+            _ => None,
+        }
+    }
+
+    const fn of(kind: LiteralKind) -> Option<Self> {
+        use LiteralKind::*;
+        match kind {
+            Char { .. } | Str { .. } | RawStr { .. } => Some(Self::U),
+            Byte { .. } | ByteStr { .. } | RawByteStr { .. } => Some(Self::B),
+            CStr { .. } | RawCStr { .. } => Some(Self::A),
+            _ => None,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::A => "astr",
+            Self::B => "bstr",
+            Self::U => "ustr",
+        }
+    }
+}
+
+/// The malformed string literals, non ascii `astr`s & strings of the wrong
+/// type of the module, in order
+#[must_use]
+pub fn strings(module: &Module) -> Vec<SemanticError> {
+    let mut checker = Checker {
+        module,
+        uses: uses(module),
+        types: HashMap::new(),
+        returns: Vec::new(),
+        errors: Vec::new(),
+    };
+    for pos in 0..module.len() {
+        checker.token(pos);
+    }
+    checker.errors
+}
+
+struct Checker<'a> {
+    module: &'a Module,
+    uses: BTreeMap<usize, usize>,
+    /// the type of each string binding, by pos
+    types: HashMap<usize, Str>,
+    /// the end of each fn the token is in & its declared return type
+    returns: Vec<(usize, Option<Str>)>,
+    errors: Vec<SemanticError>,
+}
+
+impl Checker<'_> {
+    fn token(&mut self, pos: usize) {
+        while self.returns.last().is_some_and(|&(end, _)| pos >= end) {
+            self.returns.pop();
+        }
+        match self.module.items()[pos] {
+            Token::Expr(Expr {
+                kind: ExprKind::Value(lit),
+                span,
+                ..
+            }) => self.literal(lit, span),
+            Token::Expr(Expr {
+                kind: ExprKind::FnCall(FnCall { name }),
+                end,
+                ..
+            }) => self.call(pos, name, end),
+            Token::Expr(Expr {
+                kind: ExprKind::Closure(closure),
+                ..
+            }) => self.returns.push((closure.tokens.to, None)),
+            Token::Decl(decl) => {
+                let value = decl.value.then(|| decl.value_pos(pos));
+                self.bind(pos, decl.ty, value);
+            }
+            Token::FnDefParam(param) => {
+                let value = param.value.then(|| param.value_pos(pos));
+                self.bind(pos, param.ty, value);
+            }
+            Token::FnDef(def) => self.returns.push((def.tokens.to, self.declared(def.ty))),
+            Token::Return(values) if values.from < values.to => {
+                if let Some(&(_, Some(expected))) = self.returns.last() {
+                    self.expect(expected, values.from);
+                }
+            }
+            Token::Assign(Assign {
+                targets,
+                values,
+                op: None,
+                ..
+            }) => self.assign(targets, values),
+            _ => (),
+        }
+    }
+
+    fn literal(&mut self, lit: Value, span: BSpan) {
+        if !is_string(lit.kind) {
+            return;
+        }
+        if !is_ascii(lit) {
+            self.errors.push(SemanticError::NotAscii(span));
+        } else if runtime::Value::from_literal(lit).is_err() {
+            self.errors.push(SemanticError::BadString(span));
+        }
+    }
+
+    /// the binding at `pos` is of its declared type, or of its value's
+    fn bind(&mut self, pos: usize, ty: Option<TSpan>, value: Option<usize>) {
+        match self.declared(ty) {
+            Some(ty) => {
+                if let Some(value) = value {
+                    self.expect(ty, value);
+                }
+                self.types.insert(pos, ty);
+            }
+            None => {
+                if let Some(ty) = value.and_then(|value| self.ty(value)) {
+                    self.types.insert(pos, ty);
+                }
+            }
+        }
+    }
+
+    /// the string type named by `ty`, if any
+    fn declared(&self, ty: Option<TSpan>) -> Option<Str> {
+        self.module
+            .type_name(ty)
+            .and_then(|name| Str::from_name(name.as_str()))
+    }
+
+    /// each value must be of its target's type
+    fn assign(&mut self, targets: TSpan, values: TSpan) {
+        let (mut target, mut value) = (targets.from, values.from);
+        while target < targets.to && value < values.to {
+            let binding = self.uses.get(&target);
+            if let Some(&ty) = binding.and_then(|binding| self.types.get(binding)) {
+                self.expect(ty, value);
+            }
+            target = self.module.end(target);
+            value = self.module.end(value);
+        }
+    }
+
+    /// check the args of a call against the params of the fn
+    fn call(&mut self, pos: usize, name: Symbol, end: usize) {
+        let Some(def) = self.def(pos, name) else {
+            return;
+        };
+        let (mut arg, mut param) = (pos + 1, def.params.from);
+        while arg < end && param < def.params.to {
+            if let Token::FnDefParam(p) = self.module.items()[param] {
+                if let Some(ty) = self.declared(p.ty) {
+                    self.expect(ty, arg);
+                }
+            }
+            arg = self.module.end(arg);
+            param = self.module.end(param);
+        }
+    }
+
+    /// the fn called `name` at `pos`
+    fn def(&self, pos: usize, name: Symbol) -> Option<FnDef> {
+        let def = self
+            .uses
+            .get(&pos)
+            .copied()
+            .or_else(|| global_fn(self.module, name))?;
+        match self.module.get(def)? {
+            Token::FnDef(def) => Some(def),
+            _ => None,
+        }
+    }
+
+    /// the string type of the expr at `pos`, `None` if unknown
+    fn ty(&self, pos: usize) -> Option<Str> {
+        let Some(Token::Expr(expr)) = self.module.get(pos) else {
+            return None;
+        };
+        match expr.kind {
+            ExprKind::Value(lit) => Str::of(lit.kind),
+            ExprKind::Var(_) => self.types.get(self.uses.get(&pos)?).copied(),
+            ExprKind::Paren => self.ty(pos + 1),
+            ExprKind::Binary(BinOp::Add) => {
+                let lhs = self.ty(pos + 1)?;
+                (self.ty(self.module.end(pos + 1))? == lhs).then_some(lhs)
+            }
+            ExprKind::FnCall(FnCall { name }) => match self.def(pos, name) {
+                Some(def) => self.declared(def.ty),
+                None if !self.uses.contains_key(&pos) => Str::from_name(name.as_str()),
+                None => None,
+            },
+            ExprKind::Comp(Some(Const::Str(_))) => Some(Str::U),
+            ExprKind::Comp(Some(Const::AStr(_))) => Some(Str::A),
+            _ => None,
+        }
+    }
+
+    /// the expr at `pos` must be of the type `expected`
+    fn expect(&mut self, expected: Str, pos: usize) {
+        let Some(found) = self.ty(pos) else {
+            return;
+        };
+        if found != expected {
+            let Some(Token::Expr(expr)) = self.module.get(pos) else {
+                return;
+            };
+            self.errors.push(SemanticError::StrType {
+                span: expr.span,
+                expected: expected.name(),
+                found: found.name(),
+            });
+        }
+    }
+}
+
+const fn is_string(kind: LiteralKind) -> bool {
+    use LiteralKind::*;
+    matches!(
+        kind,
+        Char { .. }
+            | Byte { .. }
+            | Str { .. }
+            | ByteStr { .. }
+            | CStr { .. }
+            | RawStr { .. }
+            | RawByteStr { .. }
+            | RawCStr { .. }
+    )
+}
+
+/// false if an `astr` literal holds a non ascii char or byte
+fn is_ascii(lit: Value) -> bool {
+    let text = &lit.value.as_str()[..lit.suffix_start];
+    match lit.kind {
+        LiteralKind::CStr { terminated: true } => {
+            let mut ascii = true;
+            unescape_mixed(&text[2..text.len() - 1], Mode::CStr, &mut |_, c| match c {
+                Ok(MixedUnit::Char(c)) if c.is_ascii() => (),
+                Ok(_) => ascii = false,
+                Err(_) => (),
+            });
+            ascii
+        }
+        LiteralKind::CStr { .. } | LiteralKind::RawCStr { .. } => text.is_ascii(),
+        _ => true,
+    }
+}
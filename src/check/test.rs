@@ -0,0 +1,701 @@
+use crate::check::*;
+use crate::error::{ErrorMulti, RuntimeError, SemanticError};
+use crate::parse::token::{Const, Expr, ExprKind, Token};
+use crate::parse::Reader;
+use crate::span::BSpan;
+
+/// the names captured by each fn, in source order
+fn captured(src: &str) -> Vec<(usize, Vec<&'static str>)> {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    captures(&module)
+        .iter()
+        .map(|(pos, bindings)| {
+            let names = bindings
+                .iter()
+                .map(|&pos| binding_name(&module, pos).unwrap().as_str())
+                .collect();
+            (pos, names)
+        })
+        .collect()
+}
+
+fn names(src: &str) -> Vec<Vec<&'static str>> {
+    captured(src).into_iter().map(|(_, names)| names).collect()
+}
+
+#[test]
+fn no_captures() {
+    assert_eq!(names(""), Vec::<Vec<&str>>::new());
+    assert_eq!(names("fn a(int x) { return x }"), Vec::<Vec<&str>>::new());
+    // top level bindings are globals
+    assert_eq!(
+        names("let x = 1; fn a() { return x }"),
+        Vec::<Vec<&str>>::new()
+    );
+    assert_eq!(names("let x = 1; let f = || x"), Vec::<Vec<&str>>::new());
+}
+
+#[test]
+fn closure_captures() {
+    assert_eq!(names("fn a(int x) { let f = || x }"), [["x"]]);
+    assert_eq!(
+        names("fn a(x, y) { let z = 1; let f = |w| x + y + w + z }"),
+        [["x", "y", "z"]]
+    );
+    assert_eq!(names("{ let x = 1; let f = fn() { x = 2 } }"), [["x"]]);
+}
+
+#[test]
+fn nested_captures() {
+    // the middle closure must capture x to pass it on
+    assert_eq!(
+        captured("fn a(x) { let f = || || x }"),
+        [(3, vec!["x"]), (5, vec!["x"])]
+    );
+    // the inner fn declares its own y
+    assert_eq!(
+        names("fn a(x, y) { fn b(y) { let f = || x + y } }"),
+        [vec!["x"], vec!["x", "y"]]
+    );
+}
+
+#[test]
+fn capture_scopes() {
+    // the value of a decl can't see the decl itself
+    assert_eq!(names("fn a(x) { let x = || x }"), [["x"]]);
+    // bindings in a closed block are not visible
+    assert_eq!(
+        names("fn a() { { let x = 1 } let f = || x }"),
+        Vec::<Vec<&str>>::new()
+    );
+    // a local fn may capture itself
+    assert_eq!(names("fn a() { fn b() { b() } }"), [["b"]]);
+    assert_eq!(
+        names("fn a(x) { if x { let y = 1; let f = || y } else { let f = || x } }"),
+        [["y"], ["x"]]
+    );
+}
+
+/// (use, binding) names & positions
+fn resolved(src: &str) -> Vec<(usize, &'static str, usize)> {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    uses(&module)
+        .into_iter()
+        .map(|(pos, binding)| {
+            (
+                pos,
+                binding_name(&module, binding).unwrap().as_str(),
+                binding,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn resolve_uses() {
+    assert_eq!(resolved("let x = 1; let y = x"), [(3, "x", 0)]);
+    // shadowed in a block
+    assert_eq!(
+        resolved("let x = 1; { let x = 2; f(x) } f(x)"),
+        [(6, "x", 3), (8, "x", 0)]
+    );
+    // params & the fn itself
+    assert_eq!(
+        resolved("fn f(a) { return f(a) }"),
+        [(3, "f", 0), (4, "a", 1)]
+    );
+    // globals declared later are left out
+    assert_eq!(resolved("fn f() { return g() } fn g() {}"), []);
+}
+
+fn like_errors(src: &str) -> Vec<String> {
+    let (module, _) = Reader::new(src).module("test");
+    likes(&module).iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn impls_satisfy_likes() {
+    let show = "struct P {} like Show { fn show(self) fn join(self, other, ...) } ";
+    assert_eq!(
+        like_errors(&format!(
+            "{show}impl P like Show {{ fn show(self) {{ }} fn join(a, b, ...) {{ }} }}"
+        )),
+        Vec::<String>::new()
+    );
+    // the like may be declared after the impl
+    assert_eq!(
+        like_errors("impl P like L { fn f() { } } like L { fn f() } struct P {}"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        like_errors(&format!("{show}impl P like Show {{ fn show(self) {{ }} }}")),
+        ["`P` is missing the method `join` of `Show`"]
+    );
+    assert_eq!(
+        like_errors(&format!(
+            "{show}impl P like Show {{ fn show() {{ }} fn join(a, b) {{ }} }}"
+        )),
+        [
+            "`show` doesn't match its sig in `Show`",
+            "`join` doesn't match its sig in `Show`"
+        ]
+    );
+    // the param & return types are written the same way
+    let area = "struct P {} like Area { fn float area(self, int? n) } ";
+    assert_eq!(
+        like_errors(&format!(
+            "{area}impl P like Area {{ fn float area(self, int? n) {{ return 1.0 }} }}"
+        )),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        like_errors(&format!(
+            "{area}impl P like Area {{ fn int area(self, int? n) {{ return 1 }} }}"
+        )),
+        ["`area` doesn't match its sig in `Area`"]
+    );
+    assert_eq!(
+        like_errors(&format!(
+            "{area}impl P like Area {{ fn float area(self, int n) {{ return 1.0 }} }}"
+        )),
+        ["`area` doesn't match its sig in `Area`"]
+    );
+    assert_eq!(
+        like_errors(&format!(
+            "{area}impl P like Area {{ fn area(self, n) {{ }} }}"
+        )),
+        ["`area` doesn't match its sig in `Area`"]
+    );
+    assert_eq!(like_errors("impl int like Eq { }"), ["undefined like `Eq`"]);
+    // methods of every impl of a type share one namespace
+    assert_eq!(
+        like_errors(
+            "impl P { fn f() { } } impl P { fn g() { } fn f() { } } impl E { fn f() { } } struct P {} enum E {}"
+        ),
+        ["`P` already has a method `f`"]
+    );
+}
+
+#[test]
+fn impls_of_types() {
+    assert_eq!(
+        like_errors("impl Q { } impl ustr { } impl fn { } enum E { A } impl E { }"),
+        ["undefined type `Q`"]
+    );
+    // only the builtin names of values, `uint` values are `int`s
+    assert_eq!(
+        like_errors("impl uint like L { } like L { }"),
+        ["undefined type `uint`"]
+    );
+}
+
+/// the messages of the errors of a full parse
+fn errors(src: &str) -> Vec<String> {
+    let (_, errors) = Reader::new(src).module("test");
+    errors.iter().map(|err| err.to_string()).collect()
+}
+
+#[test]
+fn type_names() {
+    assert_eq!(
+        errors("let Q q = 1; fn f(Q q) { } struct P { Q q }"),
+        [
+            "undefined type `Q`",
+            "undefined type `Q`",
+            "undefined type `Q`"
+        ]
+    );
+    // generics, builtins & declared types
+    assert_eq!(
+        errors("struct P<T> { T t, uint n } fn f<U>(U u, P<int> p) { } let unsigned x = 1"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        errors("struct P { } enum P { A, A } struct S { int a, int a }"),
+        [
+            "variant `A` is declared twice",
+            "field `a` is declared twice",
+            "type `P` is already declared"
+        ]
+    );
+}
+
+/// the values filled in for the comps of `src`
+fn comps(src: &str) -> Vec<Option<Const>> {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    module
+        .items()
+        .iter()
+        .filter_map(|token| match token {
+            Token::Expr(Expr {
+                kind: ExprKind::Comp(value),
+                ..
+            }) => Some(*value),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn comp_values() {
+    assert_eq!(
+        comps("preconst a = 1 + 2; prelet b = a * 2; let c = comp (b - a)"),
+        [
+            Some(Const::Int(3)),
+            Some(Const::Int(6)),
+            Some(Const::Int(3))
+        ]
+    );
+    // `comp` binds like a unary operator
+    assert_eq!(comps("let c = comp 2 - 1"), [Some(Const::Int(2))]);
+    assert_eq!(
+        comps(
+            "prefn fib(n) { if n < 2 { return n } return fib(n - 1) + fib(n - 2) } \
+             let x = comp fib(10); return comp sq(1.5); prefn sq(x) { return x * x }"
+        ),
+        [Some(Const::Int(55)), Some(Const::Float(2.25_f64.to_bits()))]
+    );
+    assert_eq!(
+        comps(r#"predef s = "a" + "b"; let t = comp (s == "ab"); let n = comp nil"#),
+        [
+            Some(Const::Str("ab".into())),
+            Some(Const::Bool(true)),
+            Some(Const::Nil)
+        ]
+    );
+    // comps in a prefn run with it
+    assert_eq!(comps("prefn f(x) { return comp x }"), [None]);
+}
+
+#[test]
+fn comp_errors() {
+    assert_eq!(
+        errors("let a = 1; preconst b = a + 1"),
+        ["`a` is not a predef, it can't be used at compile time"]
+    );
+    assert_eq!(
+        errors("fn f() { return 1 } prefn g(x) { let y = x; return f() + y }"),
+        ["`f` is not a predef, it can't be used at compile time"]
+    );
+    assert_eq!(
+        errors("let x = comp undefined()"),
+        ["`undefined` is not a predef, it can't be used at compile time"]
+    );
+    assert_eq!(
+        errors("prefn f() { return |x| x } let g = comp f()"),
+        ["a fn value can't be computed at compile time"]
+    );
+    assert_eq!(
+        errors("let x = comp (1 / 0)"),
+        ["compile time evaluation failed: integer division by zero"]
+    );
+    // a comp makes a bounded number of calls
+    let src = "prefn fib(n) { if n < 2 { return n } return fib(n - 1) + fib(n - 2) } \
+               let x = comp fib(40)";
+    let (_, errors) = Reader::new(src).module("test");
+    assert_eq!(
+        errors.semantic,
+        [SemanticError::Comp(
+            BSpan::new(src.len() - 12, src.len()),
+            RuntimeError::OutOfFuel
+        )]
+    );
+    assert_eq!(
+        errors.semantic[0].to_string(),
+        "compile time evaluation failed: evaluation too long"
+    );
+}
+
+#[test]
+fn labels_resolve() {
+    assert_eq!(
+        errors("'a: { 'b: { break 'a 1 } break 'b }"),
+        ["undefined label `'b`"]
+    );
+    assert_eq!(
+        errors("'a: { 'a: { } }"),
+        ["label `'a` shadows a label of the same name"]
+    );
+    // fns & closures can't break out of the blocks around them
+    assert_eq!(
+        errors("'a: { fn f() { break 'a } let g = || { 'a: { break 'a } } }"),
+        ["undefined label `'a`"]
+    );
+    assert_eq!(
+        errors("let x = 'a: { if x { break 'a 1 } 'b: { break 'a 2 } }"),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn numbers() {
+    let ok = Vec::<String>::new();
+    assert_eq!(
+        errors("let unsigned a = 1; let b = a * 2; let float c = 1; let d = c / 2.5 + decimal(b)"),
+        ok
+    );
+    assert_eq!(
+        errors("let a = 1; let b = 1.5; let c = a + b"),
+        ["cannot mix `signed` & `decimal`, convert one of them"]
+    );
+    assert_eq!(
+        errors("let unsigned a = 1; let signed b = a"),
+        ["expected a `signed` but found a `unsigned`"]
+    );
+    assert_eq!(
+        errors("fn int f(float x) { return x } f(1); f(signed(2.5))"),
+        [
+            "expected a `signed` but found a `decimal`",
+            "expected a `decimal` but found a `signed`"
+        ]
+    );
+    assert_eq!(
+        errors("let a = 1; a = 2.5; a += unsigned(1); let uint b = 1; let c = -b"),
+        [
+            "expected a `signed` but found a `decimal`",
+            "cannot mix `signed` & `unsigned`, convert one of them",
+            "cannot negate an `unsigned`"
+        ]
+    );
+    // nor can `unsigned`s be subtracted, they may underflow
+    let sub = "subtracting `unsigned`s may underflow, convert them with `signed(..)` first";
+    assert_eq!(
+        errors("let unsigned x = 0; x -= 1; let y = x - x; let z = x + 1; let w = signed(x) - 1"),
+        [sub, sub]
+    );
+    assert_eq!(errors("let unsigned x = 3 - 1; let y = 2 - 3"), ok);
+    // a negated literal isn't `unsigned`, even as a comp
+    assert_eq!(
+        errors("let uint a = -1; let uint b = -(-1); let uint c = comp -2; let d = a + -1"),
+        [
+            "cannot negate an `unsigned`",
+            "cannot negate an `unsigned`",
+            "cannot negate an `unsigned`"
+        ]
+    );
+    assert_eq!(
+        errors("let int a = -1; let float b = -1; let c = -1 + b"),
+        ok
+    );
+    // untyped values & fns declared later are still checked where known
+    assert_eq!(errors("fn f(x) { return x + 1.5 } let y = f(1) + 1"), ok);
+    assert_eq!(
+        errors("let y = g() + 1.5; fn uint g() { return 1 }"),
+        ["cannot mix `unsigned` & `decimal`, convert one of them"]
+    );
+    assert_eq!(errors("preconst a = comp decimal(1) + 2.5"), ok);
+    // a block or `if` used as a value has the type of its tail exprs
+    assert_eq!(
+        errors("let uint a = { let b = 1.5; b } let c = if a > 1 { 1 } else { 2.5 } let d = c + 1"),
+        ["expected a `unsigned` but found a `decimal`"]
+    );
+    assert_eq!(
+        errors("let uint a = 1; let b = if true { a } else { 1.5 }"),
+        ["cannot mix `unsigned` & `decimal`, convert one of them"]
+    );
+    // a tagged block has the type of its tail & the values it breaks with
+    assert_eq!(
+        errors("let signed x = 'a: { break 'a 2.5 } let uint y = 'b: { 1.5 }"),
+        [
+            "expected a `signed` but found a `decimal`",
+            "expected a `unsigned` but found a `decimal`"
+        ]
+    );
+    assert_eq!(
+        errors("fn f(c) { let x = 'a: { if c { break 'a 1 } 2.5 } let int y = 'b: { 'c: { break 'b 1 } 2 } }"),
+        ok
+    );
+    assert_eq!(
+        errors("fn f(c) { let uint x = 'a: { if c { break 'a 1.5 } 2 } }"),
+        ["expected a `unsigned` but found a `decimal`"]
+    );
+}
+
+#[test]
+fn strings() {
+    assert_eq!(
+        errors(r#"let a = c"ab\x7f"; let b = b"\xff"; let c = cr"a""#),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        errors(r#"let a = c"é"; let b = c"\xff"; let c = cr"é""#),
+        [
+            "an `astr` can only hold ascii",
+            "an `astr` can only hold ascii",
+            "an `astr` can only hold ascii"
+        ]
+    );
+    assert_eq!(
+        errors(r#"let a = b"é"; let b = "\q"; let c = "a"#),
+        ["malformed string literal"; 3]
+    );
+    // a declared string type is checked against the value
+    let src = r#"fn f(astr s) { } fn bstr g() { return "x" }
+        let astr a = "héllo"; let bstr b = "x"; f("é"); let u = "u"; a = u; f(ustr(a))"#;
+    assert_eq!(
+        errors(src),
+        [
+            "expected a `bstr` but found a `ustr`, convert it with `bstr(..)`",
+            "expected a `astr` but found a `ustr`, convert it with `astr(..)`",
+            "expected a `bstr` but found a `ustr`, convert it with `bstr(..)`",
+            "expected a `astr` but found a `ustr`, convert it with `astr(..)`",
+            "expected a `astr` but found a `ustr`, convert it with `astr(..)`",
+            "expected a `astr` but found a `ustr`, convert it with `astr(..)`",
+        ]
+    );
+    assert_eq!(
+        errors(r#"fn f(astr s) { } let astr a = c"a"; let b = a; f(b); f(astr("x")); f(x)"#),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn nulls() {
+    let ok = Vec::<String>::new();
+    let maybe = |name| format!("`{name}` may be `nil`, check it first");
+    assert_eq!(
+        errors("fn f(int? x, y) { let a = y + 1; return x + 1 }"),
+        [maybe("x")]
+    );
+    // narrowed by tests
+    assert_eq!(
+        errors(
+            "fn f(int? x) { if x { return x + 1 } if x != nil { return -x } \
+             if !x { } else { return x * 2 } return x && x + 1 }"
+        ),
+        ok
+    );
+    // only inside the test
+    assert_eq!(
+        errors("fn f(int? x) { if x { } return x + 1 }"),
+        [maybe("x")]
+    );
+    // by an early return & by assignment
+    assert_eq!(
+        errors("fn f(int? x) { if x == nil { return 0 } let y = x + 1; x = nil; return x + y }"),
+        [maybe("x")]
+    );
+    assert_eq!(
+        errors("let int? x = nil; let y = x || 1; x = y; return x + y"),
+        ok
+    );
+    // untyped bindings of a maybe nil value, typed ones & args
+    assert_eq!(
+        errors("fn g(int n) { } fn f(int? x) { let y = x; let int z = x; g(x); return y.a }"),
+        [maybe("x"), maybe("x"), maybe("y")]
+    );
+    // a `nil` literal for a non optional type
+    assert_eq!(
+        errors("fn g(int n) { } let int x = nil; let ustr? y = nil; x = (nil); g(nil)"),
+        [
+            "`nil` assigned to non-nil `int`",
+            "`nil` assigned to non-nil `int`",
+            "`nil` assigned to non-nil `int`"
+        ]
+    );
+    // the narrowing at a break out of a tagged block
+    assert_eq!(
+        errors("fn f(int? x) { 'a: { if c { break 'a } x = 1 } return x + 1 }"),
+        [maybe("x")]
+    );
+    // a block used as a value may be `nil` if its tail expr may
+    assert_eq!(
+        errors("fn f(int? x) { let y = if x { x } else { 0 }; let z = { x }; return y + z }"),
+        [maybe("z")]
+    );
+    // so may a tagged block broken out of with a value that may, or none
+    assert_eq!(
+        errors(
+            "fn f(int? x, c) { let y = 'a: { if c { break 'a x } 1 } let z = 'b: { if c { break 'b } 1 } \
+             let w = 'c: { 'd: { break 'd x } 1 } return y + z + w }"
+        ),
+        [maybe("y"), maybe("z")]
+    );
+    // closures run later
+    assert_eq!(
+        errors("fn f(int? x) { if x { let g = || x + 1 } }"),
+        [maybe("x")]
+    );
+}
+
+/// the messages of the errors & warnings of resolving `src`, with `print`
+/// as a host global
+fn resolve_messages(src: &str) -> (Vec<String>, Vec<String>) {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    let resolution = resolve(&module, &["print".into()]);
+    let messages = |errs: &[crate::error::SemanticError]| {
+        errs.iter().map(ToString::to_string).collect::<Vec<_>>()
+    };
+    (messages(&resolution.errors), messages(&resolution.warnings))
+}
+
+#[test]
+fn scope_tree() {
+    let (module, _) =
+        Reader::new("let x = 1; fn f(a) { if a { let y = 2 } else { } } { }").module("test");
+    let resolution = resolve(&module, &[]);
+    let tree: Vec<_> = resolution
+        .scopes
+        .iter()
+        .map(|scope| (scope.kind, scope.parent, scope.bindings.len()))
+        .collect();
+    assert_eq!(
+        tree,
+        [
+            (ScopeKind::Module, None, 2),
+            (ScopeKind::Fn(2), Some(0), 1),
+            (ScopeKind::Block, Some(1), 1),
+            (ScopeKind::Block, Some(1), 0),
+            (ScopeKind::Block, Some(0), 0)
+        ]
+    );
+    // later globals are bound too
+    let (module, _) = Reader::new("fn f() { return g() } fn g() {}").module("test");
+    let uses: Vec<_> = resolve(&module, &[])
+        .uses
+        .into_iter()
+        .map(|(pos, binding)| (pos, binding_name(&module, binding).unwrap().as_str()))
+        .collect();
+    assert_eq!(uses, [(2, "g")]);
+}
+
+#[test]
+fn undefined_names() {
+    let none = Vec::<String>::new();
+    assert_eq!(
+        resolve_messages("let count = 1; print(cuont, prnt, x, true, nil)"),
+        (
+            vec![
+                "undefined name `cuont`, did you mean `count`?".to_owned(),
+                "undefined name `prnt`, did you mean `print`?".to_owned(),
+                "undefined name `x`".to_owned()
+            ],
+            none.clone()
+        )
+    );
+    // assignments create globals, bindings aren't visible before their decl
+    // or outside their block
+    assert_eq!(
+        resolve_messages("fn f() { return g } g = 1; { let y = 1 } print(y); let z = z"),
+        (
+            vec![
+                "undefined name `y`".to_owned(),
+                "undefined name `z`".to_owned()
+            ],
+            none.clone()
+        )
+    );
+    // outside of a fn a global only exists once it's assigned
+    let unassigned = |name| {
+        format!("`{name}` is read before it's assigned, declare it first with `let {name} = ..`")
+    };
+    assert_eq!(
+        resolve_messages("let a = 1; a, b = b, a; print(c); c = 1; print(c); d += 1; d = 2"),
+        (
+            vec![unassigned("b"), unassigned("c"), unassigned("d")],
+            none.clone()
+        )
+    );
+    assert_eq!(
+        resolve_messages("fn f() { h = 1 } fn g() { return h } if true { k = 1 } print(k)"),
+        (none.clone(), none.clone())
+    );
+    assert_eq!(
+        resolve_messages("fn f(a, b, a) { } let g = |c, c| c"),
+        (
+            vec![
+                "duplicate param `a`".to_owned(),
+                "duplicate param `c`".to_owned()
+            ],
+            none
+        )
+    );
+}
+
+#[test]
+fn shadowing() {
+    assert_eq!(
+        resolve_messages("let x = 1; fn f(x) { { let x = 2 } } let y = 1; let y = 2"),
+        (
+            Vec::new(),
+            vec![
+                "`x` shadows a binding of an outer scope".to_owned(),
+                "`x` shadows a binding of an outer scope".to_owned()
+            ]
+        )
+    );
+}
+
+#[test]
+fn match_patterns() {
+    let ok = Vec::<String>::new();
+    let src =
+        "enum E { A, B(int), C { y, z } } fn f(e) { let x = match e { E.A => 0, E.B(1) => 1 } }";
+    assert_eq!(
+        errors(src),
+        ["non-exhaustive match, `E.B(_)` is not covered"]
+    );
+    assert_eq!(
+        errors(
+            "enum E { A, C { y, z } } fn f(e) { match e { E.A => { } E.C { y = true } => { } } }"
+        ),
+        ["non-exhaustive match, `E.C { y = false, z = _ }` is not covered"]
+    );
+    assert_eq!(
+        errors("fn f(a, b) { match a, b { (true, _) => { } (_, false) => { } } }"),
+        ["non-exhaustive match, `(false, true)` is not covered"]
+    );
+    // a guarded arm may not match
+    assert_eq!(
+        errors("fn f(a) { match a { true => { } x if x => { } } }"),
+        ["non-exhaustive match, `false` is not covered"]
+    );
+    assert_eq!(
+        errors("fn f(a) { match a { 1 => { } 2 | 1 => { } _ => { } 3 => { } } }"),
+        ["unreachable pattern", "unreachable match arm"]
+    );
+    assert_eq!(
+        errors("fn f(a) { match a { (x, 1) | (2, y) => { } _ => { } } }"),
+        [
+            "`y` is not bound in every alternative",
+            "`x` is not bound in every alternative"
+        ]
+    );
+    assert_eq!(
+        errors("fn f(a) { match a { true => { } false => { } } match a { 1 => { } _ => { } } }"),
+        ok
+    );
+}
+
+#[test]
+fn match_values() {
+    let ok = Vec::<String>::new();
+    // the arms of a value match are unified like the branches of an if
+    assert_eq!(
+        errors("fn f(a) { let x = match a { 1 => 2, _ => { let y = 3; y } }; return x + 1 }"),
+        ok
+    );
+    assert_eq!(
+        errors("fn f(int? x, a) { let y = match a { 1 => x, _ => 0 }; return y + 1 }"),
+        ["`y` may be `nil`, check it first"]
+    );
+    // as an operand too
+    assert_eq!(
+        errors("fn f(int? x, a) { let y = (match a { 1 => x, _ => 0 }); return y + 1 }"),
+        ["`y` may be `nil`, check it first"]
+    );
+    // a value operand isn't a var, so it isn't checked
+    assert_eq!(
+        errors("fn f(int? x, c) { return if c { { x } } else { 0 } + 1 }"),
+        ok
+    );
+    // names bound by pats are only in scope in their arm
+    assert_eq!(
+        resolve_messages("fn f(a) { match a { (x, y) if x > y => { } _ => { } } return x }").0,
+        ["undefined name `x`"]
+    );
+}
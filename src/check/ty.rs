@@ -0,0 +1,75 @@
+//! checking the names of types
+//!
+//! A type named in a decl, param, return or field must be builtin or a
+//! struct or enum of the module, which may be declared after its use. No two
+//! structs or enums may share a name.
+
+use crate::error::SemanticError;
+use crate::parse::token::*;
+use crate::util::Symbol;
+
+use super::{like, Num};
+
+/// The undefined & twice declared types of the module, in order
+#[must_use]
+pub fn types(module: &Module) -> Vec<SemanticError> {
+    let mut errors = Vec::new();
+    let mut declared = Vec::new();
+    // the generic params in scope & the end of the item declaring them
+    let mut generics: Vec<(usize, Vec<Symbol>)> = Vec::new();
+    let mut pos = 0;
+    while pos < module.len() {
+        while generics.last().is_some_and(|&(end, _)| pos >= end) {
+            generics.pop();
+        }
+        let token = module.items()[pos];
+        let params = match token {
+            Token::FnDef(def) => Some(def.generics(pos)),
+            Token::FnSig(sig) => Some(sig.generics(pos)),
+            Token::Struct(def) => Some(def.generics),
+            Token::Enum(def) => Some(def.generics),
+            _ => None,
+        };
+        if let Some(params) = params {
+            let names = (params.from..params.to).filter_map(|at| match module.items()[at] {
+                Token::Type(Type {
+                    kind: TypeKind::Param(name),
+                    ..
+                }) => Some(name),
+                _ => None,
+            });
+            generics.push((module.end(pos), names.collect()));
+        }
+        match token {
+            Token::Struct(Struct { name, span, .. }) | Token::Enum(Enum { name, span, .. }) => {
+                if declared.contains(&name) {
+                    errors.push(SemanticError::DupeType(span, name));
+                }
+                declared.push(name);
+            }
+            Token::Type(ty) => match ty.kind {
+                TypeKind::Name(name) => {
+                    let generic = generics.iter().any(|(_, names)| names.contains(&name));
+                    if !generic && !is_type(module, name) {
+                        errors.push(SemanticError::NoType(ty.span, name));
+                    }
+                }
+                // the rest of a path is in another module
+                TypeKind::Path(_) => {
+                    pos = ty.end;
+                    continue;
+                }
+                _ => (),
+            },
+            _ => (),
+        }
+        pos += 1;
+    }
+    errors
+}
+
+/// true if `ty` names a value's type, a number type or a struct or enum of
+/// the module
+fn is_type(module: &Module, ty: Symbol) -> bool {
+    Num::from_name(ty.as_str()).is_some() || like::is_type(module, ty)
+}
@@ -2,7 +2,10 @@ use std::error::Error as StdError;
 use std::fmt::Display;
 
 use crate::lex;
+use crate::parse::token::{BinOp, UnOp};
+use crate::runtime::Value;
 use crate::span::BSpan;
+use crate::util::Symbol;
 
 // NOTE: types of errors:
 // - lexical    : encoding, definition, ident rules, token structure.
@@ -15,10 +18,37 @@ use crate::span::BSpan;
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ErrorMulti {
     pub lex: Vec<LexicalError>,
+    pub semantic: Vec<SemanticError>,
     pub other: Vec<String>,
+    /// shadowing & lints, these don't count as errors
+    pub warnings: Vec<SemanticError>,
 }
 
 impl ErrorMulti {
+    /// true if there are no errors, there may still be warnings
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.lex.is_empty() && self.semantic.is_empty() && self.other.is_empty()
+    }
+
+    /// The number of errors, without the warnings
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.lex.len() + self.semantic.len() + self.other.len()
+    }
+
+    /// Every error, lexical ones first
+    pub fn iter(&self) -> impl Iterator<Item = ErrorOnce> + '_ {
+        let lex = self.lex.iter().cloned().map(ErrorOnce::Lexical);
+        let semantic = self.semantic.iter().cloned().map(ErrorOnce::Semantic);
+        let other = self.other.iter().cloned().map(ErrorOnce::Other);
+        lex.chain(semantic).chain(other)
+    }
+
+    pub fn warn(&mut self, warning: SemanticError) {
+        self.warnings.push(warning);
+    }
+
     #[allow(clippy::match_wildcard_for_single_variants)]
     pub fn push(&mut self, err: impl Into<ErrorOnce>) {
         use ErrorOnce::*;
@@ -40,6 +70,7 @@ impl ErrorMulti {
                 _ => self.lex.push(DupeComma(new)),
             },
             Lexical(lex) => self.lex.push(lex),
+            Semantic(err) => self.semantic.push(err),
             Other(err) => self.other.push(err),
         }
     }
@@ -49,15 +80,34 @@ impl ErrorMulti {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ErrorOnce {
     Lexical(LexicalError),
+    Semantic(SemanticError),
     Other(String),
 }
 
+impl ErrorOnce {
+    /// `None` for errors without a location
+    #[must_use]
+    pub const fn span(&self) -> Option<BSpan> {
+        match self {
+            Self::Lexical(err) => Some(err.span()),
+            Self::Semantic(err) => Some(err.span()),
+            Self::Other(_) => None,
+        }
+    }
+}
+
 impl From<LexicalError> for ErrorOnce {
     fn from(value: LexicalError) -> Self {
         Self::Lexical(value)
     }
 }
 
+impl From<SemanticError> for ErrorOnce {
+    fn from(value: SemanticError) -> Self {
+        Self::Semantic(value)
+    }
+}
+
 // TODO: have expected use a u64 instead of a vec
 //
 // Should be a big space & speed improvement
@@ -72,16 +122,600 @@ pub enum LexicalError {
     Expected(BSpan, Vec<lex::LexKind>),
     /// Expected a token, eof found, should be extended in the future
     Eof(usize),
+    /// The left side of an assignment is not a variable, field or index
+    AssignTarget(BSpan),
+    /// A syntax error, with what was expected
+    Syntax(BSpan, &'static str),
+    /// Lua outside the subset [`LuaReader`](crate::parse::LuaReader) reads,
+    /// non utf-8 strings
+    NotInSubset(BSpan, &'static str),
+    /// A malformed lua number, escape or string
+    Malformed(BSpan, &'static str),
+    /// Too many errors, the ones after this position are dropped
+    TooMany(usize),
+    /// A statement on the same line as the last, without a `;`
+    Separator(BSpan),
+    /// An `if` used as a value without an `else`
+    MissingElse(BSpan),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticError {
+    /// Assignment to a `const` binding
+    ConstAssign(BSpan, Symbol),
+    /// `...` outside of a variadic fn
+    Varargs(BSpan),
+    /// A field the struct or variant `.1` doesn't declare
+    NoField(BSpan, Symbol, Symbol),
+    /// A variant the enum `.1` doesn't declare
+    NoVariant(BSpan, Symbol, Symbol),
+    /// A field left out of a constructor
+    MissingField(BSpan, Symbol),
+    /// A field set twice in a constructor
+    DupeField(BSpan, Symbol),
+    /// A field declared twice in a struct or variant
+    DupeFieldDecl(BSpan, Symbol),
+    /// A variant declared twice in an enum
+    DupeVariant(BSpan, Symbol),
+    /// A struct or enum with the name of one declared before it
+    DupeType(BSpan, Symbol),
+    /// The wrong number of values for a tuple variant
+    FieldCount {
+        span: BSpan,
+        expected: usize,
+        found: usize,
+    },
+    /// An `impl` claiming an undeclared like
+    NoLike(BSpan, Symbol),
+    /// A type, or the type of an `impl`, that is neither declared nor builtin
+    NoType(BSpan, Symbol),
+    /// An `impl` of `ty` without the method `name` of `like`
+    MissingMethod {
+        span: BSpan,
+        ty: Symbol,
+        like: Symbol,
+        name: Symbol,
+    },
+    /// A method whose params or return type differ from the sig in `.1`
+    MethodSig(BSpan, Symbol, Symbol),
+    /// A method defined twice for the type `.1`
+    DupeMethod(BSpan, Symbol, Symbol),
+    /// A non predef binding used at compile time
+    NotPredef(BSpan, Symbol),
+    /// A compile time value of a type without a [`Const`](crate::parse::token::Const)
+    NotConst(BSpan, &'static str),
+    /// An error raised while evaluating a `comp`
+    Comp(BSpan, RuntimeError),
+    /// A `break` to a label no enclosing tagged block has
+    NoLabel(BSpan, Symbol),
+    /// A tagged block inside another one with the same label
+    ShadowedLabel(BSpan, Symbol),
+    /// A lua `break` outside of a loop
+    LoopBreak(BSpan),
+    /// A lua `goto` without a visible label of the name
+    NoGotoLabel(BSpan, Symbol),
+    /// A lua label defined twice in the same fn where both are visible
+    DupeLabel(BSpan, Symbol),
+    /// A lua `goto` to the label `.1`, past the local `.2` in its block
+    GotoScope(BSpan, Symbol, Symbol),
+    /// Numbers of two different types in one operation
+    MixedNum {
+        span: BSpan,
+        lhs: &'static str,
+        rhs: &'static str,
+    },
+    /// A number of the wrong type for a binding, param or return
+    NumType {
+        span: BSpan,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// `-` on an unsigned number
+    NegUnsigned(BSpan),
+    /// A subtraction of `unsigned`s, which may underflow
+    SubUnsigned(BSpan),
+    /// A non ascii char or byte in an `astr` literal
+    NotAscii(BSpan),
+    /// A string of the wrong type for a binding, param or return
+    StrType {
+        span: BSpan,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// An unterminated string literal or one with an invalid escape
+    BadString(BSpan),
+    /// A use of a binding that may be `nil` without a check
+    MaybeNil(BSpan, Symbol),
+    /// A `nil` literal for a binding or param of the non optional type in `.1`
+    NilAssigned(BSpan, String),
+    /// A name without a binding or global, with the closest defined name
+    Undefined {
+        span: BSpan,
+        name: Symbol,
+        suggestion: Option<Symbol>,
+    },
+    /// A global read outside of a fn before any assignment creates it
+    Unassigned(BSpan, Symbol),
+    /// A param declared twice in one fn
+    DupeParam(BSpan, Symbol),
+    /// A binding that hides one of an enclosing scope, a warning
+    Shadowed(BSpan, Symbol),
+    /// A `match` without an arm for the value in `.1`
+    NonExhaustive(BSpan, String),
+    /// An arm after the arms matching every value it matches
+    UnreachableArm(BSpan),
+    /// An alternative pat after the ones matching every value it matches
+    UnreachablePat(BSpan),
+    /// A name bound by some of the alternative pats of an arm but not this one
+    AltBindings(BSpan, Symbol),
+    /// A finding of the [`Lint`](crate::lint::Lint) `name`
+    Lint {
+        span: BSpan,
+        name: &'static str,
+        message: String,
+    },
+}
+
+impl LexicalError {
+    #[must_use]
+    pub const fn span(&self) -> BSpan {
+        match *self {
+            Self::DupeComma(span)
+            | Self::Unclosed(span)
+            | Self::Expected(span, _)
+            | Self::AssignTarget(span)
+            | Self::Syntax(span, _)
+            | Self::NotInSubset(span, _)
+            | Self::Malformed(span, _)
+            | Self::Separator(span)
+            | Self::MissingElse(span) => span,
+            Self::Eof(pos) | Self::TooMany(pos) => BSpan::new(pos, pos),
+        }
+    }
+
+    /// Move the span by `by` bytes
+    pub(crate) const fn offset(&mut self, by: isize) {
+        match self {
+            Self::DupeComma(span)
+            | Self::Unclosed(span)
+            | Self::Expected(span, _)
+            | Self::AssignTarget(span)
+            | Self::Syntax(span, _)
+            | Self::NotInSubset(span, _)
+            | Self::Malformed(span, _)
+            | Self::Separator(span)
+            | Self::MissingElse(span) => *span = span.offset(by),
+            Self::Eof(pos) | Self::TooMany(pos) => *pos = pos.wrapping_add_signed(by),
+        }
+    }
+}
+
+impl SemanticError {
+    #[must_use]
+    pub const fn span(&self) -> BSpan {
+        match *self {
+            Self::ConstAssign(span, _)
+            | Self::Varargs(span)
+            | Self::NoField(span, ..)
+            | Self::NoVariant(span, ..)
+            | Self::MissingField(span, _)
+            | Self::DupeField(span, _)
+            | Self::DupeFieldDecl(span, _)
+            | Self::DupeVariant(span, _)
+            | Self::DupeType(span, _)
+            | Self::FieldCount { span, .. }
+            | Self::NoLike(span, _)
+            | Self::NoType(span, _)
+            | Self::MissingMethod { span, .. }
+            | Self::MethodSig(span, ..)
+            | Self::DupeMethod(span, ..)
+            | Self::NotPredef(span, _)
+            | Self::NotConst(span, _)
+            | Self::Comp(span, _)
+            | Self::NoLabel(span, _)
+            | Self::ShadowedLabel(span, _)
+            | Self::LoopBreak(span)
+            | Self::NoGotoLabel(span, _)
+            | Self::DupeLabel(span, _)
+            | Self::GotoScope(span, ..)
+            | Self::MixedNum { span, .. }
+            | Self::NumType { span, .. }
+            | Self::NegUnsigned(span)
+            | Self::SubUnsigned(span)
+            | Self::NotAscii(span)
+            | Self::StrType { span, .. }
+            | Self::BadString(span)
+            | Self::MaybeNil(span, _)
+            | Self::NilAssigned(span, _)
+            | Self::Undefined { span, .. }
+            | Self::Unassigned(span, _)
+            | Self::DupeParam(span, _)
+            | Self::Shadowed(span, _)
+            | Self::NonExhaustive(span, _)
+            | Self::UnreachableArm(span)
+            | Self::UnreachablePat(span)
+            | Self::AltBindings(span, _)
+            | Self::Lint { span, .. } => span,
+        }
+    }
+
+    /// Move the span by `by` bytes
+    pub(crate) const fn offset(&mut self, by: isize) {
+        match self {
+            Self::ConstAssign(span, _)
+            | Self::Varargs(span)
+            | Self::NoField(span, ..)
+            | Self::NoVariant(span, ..)
+            | Self::MissingField(span, _)
+            | Self::DupeField(span, _)
+            | Self::DupeFieldDecl(span, _)
+            | Self::DupeVariant(span, _)
+            | Self::DupeType(span, _)
+            | Self::FieldCount { span, .. }
+            | Self::NoLike(span, _)
+            | Self::NoType(span, _)
+            | Self::MissingMethod { span, .. }
+            | Self::MethodSig(span, ..)
+            | Self::DupeMethod(span, ..)
+            | Self::NotPredef(span, _)
+            | Self::NotConst(span, _)
+            | Self::Comp(span, _)
+            | Self::NoLabel(span, _)
+            | Self::ShadowedLabel(span, _)
+            | Self::LoopBreak(span)
+            | Self::NoGotoLabel(span, _)
+            | Self::DupeLabel(span, _)
+            | Self::GotoScope(span, ..)
+            | Self::MixedNum { span, .. }
+            | Self::NumType { span, .. }
+            | Self::NegUnsigned(span)
+            | Self::SubUnsigned(span)
+            | Self::NotAscii(span)
+            | Self::StrType { span, .. }
+            | Self::BadString(span)
+            | Self::MaybeNil(span, _)
+            | Self::NilAssigned(span, _)
+            | Self::Undefined { span, .. }
+            | Self::Unassigned(span, _)
+            | Self::DupeParam(span, _)
+            | Self::Shadowed(span, _)
+            | Self::NonExhaustive(span, _)
+            | Self::UnreachableArm(span)
+            | Self::UnreachablePat(span)
+            | Self::AltBindings(span, _)
+            | Self::Lint { span, .. } => *span = span.offset(by),
+        }
+    }
+}
+
+impl Display for LexicalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DupeComma(_) => f.write_str("duplicate comma"),
+            Self::Unclosed(_) => f.write_str("unclosed block"),
+            Self::Expected(_, kinds) => {
+                f.write_str("expected ")?;
+                for (i, kind) in kinds.iter().enumerate() {
+                    match i {
+                        0 => {}
+                        _ if i + 1 == kinds.len() => f.write_str(" or ")?,
+                        _ => f.write_str(", ")?,
+                    }
+                    f.write_str(kind.name())?;
+                }
+                Ok(())
+            }
+            Self::Eof(_) => f.write_str("unexpected end of file"),
+            Self::AssignTarget(_) => f.write_str("cannot assign to this expression"),
+            Self::Syntax(_, expected) => write!(f, "expected {expected}"),
+            Self::NotInSubset(_, what) => {
+                write!(f, "{what} are outside the lua subset allua reads")
+            }
+            Self::Malformed(_, what) => write!(f, "malformed {what}"),
+            Self::TooMany(_) => f.write_str("too many errors, the rest are not shown"),
+            Self::Separator(_) => f.write_str("expected `;` or a new line between statements"),
+            Self::MissingElse(_) => f.write_str("an `if` used as a value needs an `else`"),
+        }
+    }
+}
+
+impl Display for SemanticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ConstAssign(_, name) => write!(f, "cannot assign to const `{name}`"),
+            Self::Varargs(_) => f.write_str("`...` outside of a variadic fn"),
+            Self::NoField(_, ty, name) => write!(f, "`{ty}` has no field `{name}`"),
+            Self::NoVariant(_, ty, name) => write!(f, "`{ty}` has no variant `{name}`"),
+            Self::MissingField(_, name) => write!(f, "missing field `{name}`"),
+            Self::DupeField(_, name) => write!(f, "field `{name}` is set twice"),
+            Self::DupeFieldDecl(_, name) => write!(f, "field `{name}` is declared twice"),
+            Self::DupeVariant(_, name) => write!(f, "variant `{name}` is declared twice"),
+            Self::DupeType(_, name) => write!(f, "type `{name}` is already declared"),
+            Self::FieldCount {
+                expected, found, ..
+            } => {
+                let s = if *expected == 1 { "" } else { "s" };
+                write!(f, "expected {expected} field{s} but found {found}")
+            }
+            Self::NoLike(_, like) => write!(f, "undefined like `{like}`"),
+            Self::NoType(_, ty) => write!(f, "undefined type `{ty}`"),
+            Self::MissingMethod { ty, like, name, .. } => {
+                write!(f, "`{ty}` is missing the method `{name}` of `{like}`")
+            }
+            Self::MethodSig(_, like, name) => {
+                write!(f, "`{name}` doesn't match its sig in `{like}`")
+            }
+            Self::DupeMethod(_, ty, name) => write!(f, "`{ty}` already has a method `{name}`"),
+            Self::NotPredef(_, name) => {
+                write!(
+                    f,
+                    "`{name}` is not a predef, it can't be used at compile time"
+                )
+            }
+            Self::NotConst(_, ty) => write!(f, "a {ty} value can't be computed at compile time"),
+            Self::Comp(_, err) => write!(f, "compile time evaluation failed: {err}"),
+            Self::NoLabel(_, label) => write!(f, "undefined label `'{label}`"),
+            Self::ShadowedLabel(_, label) => {
+                write!(f, "label `'{label}` shadows a label of the same name")
+            }
+            Self::LoopBreak(_) => f.write_str("`break` outside of a loop"),
+            Self::NoGotoLabel(_, label) => write!(f, "no visible label `{label}` for `goto`"),
+            Self::DupeLabel(_, label) => write!(f, "label `{label}` is already defined"),
+            Self::GotoScope(_, label, local) => {
+                write!(f, "`goto {label}` jumps into the scope of local `{local}`")
+            }
+            Self::MixedNum { lhs, rhs, .. } => {
+                write!(f, "cannot mix `{lhs}` & `{rhs}`, convert one of them")
+            }
+            Self::NumType {
+                expected, found, ..
+            } => write!(f, "expected a `{expected}` but found a `{found}`"),
+            Self::NegUnsigned(_) => f.write_str("cannot negate an `unsigned`"),
+            Self::SubUnsigned(_) => f.write_str(
+                "subtracting `unsigned`s may underflow, convert them with `signed(..)` first",
+            ),
+            Self::NotAscii(_) => f.write_str("an `astr` can only hold ascii"),
+            Self::StrType {
+                expected, found, ..
+            } => write!(
+                f,
+                "expected a `{expected}` but found a `{found}`, convert it with `{expected}(..)`"
+            ),
+            Self::BadString(_) => f.write_str("malformed string literal"),
+            Self::MaybeNil(_, name) => write!(f, "`{name}` may be `nil`, check it first"),
+            Self::NilAssigned(_, ty) => write!(f, "`nil` assigned to non-nil `{ty}`"),
+            Self::Undefined {
+                name, suggestion, ..
+            } => match suggestion {
+                Some(suggestion) => {
+                    write!(f, "undefined name `{name}`, did you mean `{suggestion}`?")
+                }
+                None => write!(f, "undefined name `{name}`"),
+            },
+            Self::Unassigned(_, name) => write!(
+                f,
+                "`{name}` is read before it's assigned, declare it first with `let {name} = ..`"
+            ),
+            Self::DupeParam(_, name) => write!(f, "duplicate param `{name}`"),
+            Self::Shadowed(_, name) => write!(f, "`{name}` shadows a binding of an outer scope"),
+            Self::NonExhaustive(_, missing) => {
+                write!(f, "non-exhaustive match, `{missing}` is not covered")
+            }
+            Self::UnreachableArm(_) => f.write_str("unreachable match arm"),
+            Self::UnreachablePat(_) => f.write_str("unreachable pattern"),
+            Self::AltBindings(_, name) => {
+                write!(f, "`{name}` is not bound in every alternative")
+            }
+            Self::Lint { name, message, .. } => write!(f, "{message} (`{name}`)"),
+        }
+    }
+}
+
+/// An error raised while running a module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// A variable that was never declared
+    Undefined(Symbol),
+    /// A call of a non fn value, holds the type name
+    NotCallable(&'static str),
+    /// A binary operator used with unsupported operand types
+    BinOp(BinOp, &'static str, &'static str),
+    /// A unary operator used with an unsupported operand type
+    UnOp(UnOp, &'static str),
+    /// A field or index of a value that has none
+    Index(&'static str),
+    /// A userdata without the field or method
+    NoField(&'static str, Symbol),
+    /// Integer division or remainder by zero
+    DivZero,
+    /// A literal that can't be turned into a value
+    Literal(Symbol),
+    /// Too many nested calls
+    StackOverflow,
+    /// More calls made than the engine's fuel allows
+    OutOfFuel,
+    /// A start, limit or step of a numeric `for` that isn't a number
+    ForValue(&'static str, &'static str),
+    /// A numeric `for` with a step of 0
+    ForStep,
+    /// A table key that is `nil` or NaN, holds which
+    TableKey(&'static str),
+    /// A `<close>` local whose value has no `__close` metamethod
+    NotClosable(Symbol),
+    /// An `__index` or `__newindex` that goes through too many tables
+    MetaLoop(&'static str),
+    /// An error raised by the host fn `name`, with the span of the call
+    ///
+    /// The span is `None` when the host fn was called from rust.
+    Host(Option<BSpan>, Symbol, HostError),
+    /// A `return` or `break` out of a block used as a value, the engine
+    /// catches it at the statement around the block
+    #[doc(hidden)]
+    Unwind,
+}
+
+impl RuntimeError {
+    /// Attach the span of a call to a host error without one
+    #[must_use]
+    pub fn at(self, span: BSpan) -> Self {
+        match self {
+            Self::Host(None, name, err) => Self::Host(Some(span), name, err),
+            other => other,
+        }
+    }
+}
+
+/// An error raised by a host fn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostError {
+    /// The wrong number of args
+    Arity { expected: usize, found: usize },
+    /// The arg at `index` has the wrong type
+    Type {
+        index: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// A userdata borrowed mutably while in use, e.g. passed to its own method
+    Borrowed,
+    /// A custom error message
+    Custom(String),
+    /// An error raised by a script fn called from the host fn
+    Runtime(Box<RuntimeError>),
+}
+
+impl HostError {
+    #[must_use]
+    pub fn type_error(expected: &'static str, found: &Value) -> Self {
+        Self::Type {
+            index: 0,
+            expected,
+            found: found.type_name(),
+        }
+    }
+
+    /// Set the index of a type error
+    #[must_use]
+    pub fn at_arg(self, index: usize) -> Self {
+        match self {
+            Self::Type {
+                expected, found, ..
+            } => Self::Type {
+                index,
+                expected,
+                found,
+            },
+            other => other,
+        }
+    }
+
+    /// The error to raise from a call of the host fn `name`
+    #[must_use]
+    pub fn into_runtime(self, span: Option<BSpan>, name: Symbol) -> RuntimeError {
+        match self {
+            Self::Runtime(err) => *err,
+            err => RuntimeError::Host(span, name, err),
+        }
+    }
+}
+
+impl From<RuntimeError> for HostError {
+    fn from(value: RuntimeError) -> Self {
+        Self::Runtime(Box::new(value))
+    }
 }
 
+impl From<String> for HostError {
+    fn from(value: String) -> Self {
+        Self::Custom(value)
+    }
+}
+
+impl From<&str> for HostError {
+    fn from(value: &str) -> Self {
+        Self::Custom(value.to_owned())
+    }
+}
+
+impl Display for HostError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Arity { expected, found } => {
+                write!(f, "expected {expected} args but found {found}")
+            }
+            Self::Type {
+                index,
+                expected,
+                found,
+            } => write!(f, "arg {index} should be {expected} but was {found}"),
+            Self::Borrowed => f.write_str("userdata is already borrowed"),
+            Self::Custom(msg) => f.write_str(msg),
+            Self::Runtime(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl StdError for HostError {}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "undefined variable `{name}`"),
+            Self::NotCallable(ty) => write!(f, "attempt to call a {ty} value"),
+            Self::BinOp(op, lhs, rhs) => {
+                write!(
+                    f,
+                    "unsupported operands for `{}`: {lhs} & {rhs}",
+                    op.as_str()
+                )
+            }
+            Self::UnOp(op, ty) => write!(f, "unsupported operand for `{}`: {ty}", op.as_str()),
+            Self::Index(ty) => write!(f, "attempt to index a {ty} value"),
+            Self::NoField(ty, name) => write!(f, "{ty} has no field `{name}`"),
+            Self::DivZero => f.write_str("integer division by zero"),
+            Self::Literal(lit) => write!(f, "invalid literal `{lit}`"),
+            Self::StackOverflow => f.write_str("stack overflow"),
+            Self::OutOfFuel => f.write_str("evaluation too long"),
+            Self::ForValue(what, ty) => write!(f, "`for` {what} must be a number, not a {ty}"),
+            Self::ForStep => f.write_str("`for` step is zero"),
+            Self::TableKey(what) => write!(f, "table index is {what}"),
+            Self::NotClosable(name) => write!(f, "variable `{name}` got a non-closable value"),
+            Self::MetaLoop(name) => write!(f, "`{name}` chain too long, possible loop"),
+            Self::Host(Some(span), name, err) => {
+                write!(f, "in `{name}` at {}..{}: {err}", span.from, span.to)
+            }
+            Self::Host(None, name, err) => write!(f, "in `{name}`: {err}"),
+            Self::Unwind => f.write_str("`return` or `break` outside of a statement"),
+        }
+    }
+}
+
+impl StdError for RuntimeError {}
+
 impl Display for ErrorOnce {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("errors display not implemented")
+        match self {
+            Self::Lexical(err) => write!(f, "{err}"),
+            Self::Semantic(err) => write!(f, "{err}"),
+            Self::Other(err) => f.write_str(err),
+        }
     }
 }
 
 impl StdError for ErrorOnce {}
 
+/// Invalid json, at the byte `.0`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonError(pub usize);
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid json at byte {}", self.0)
+    }
+}
+
+impl StdError for JsonError {}
+
 pub type StdResult<T, E> = std::result::Result<T, E>;
 
 /// a parsing result
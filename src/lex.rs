@@ -11,6 +11,7 @@ use super::util::{is_id_continue, is_id_start, is_whitespace};
 pub mod test;
 
 pub mod cursor;
+pub mod lua;
 pub mod token;
 pub mod unescape;
 
@@ -409,6 +410,14 @@ impl Cursor<'_> {
 
     fn char(&mut self) -> LexKind {
         debug_assert!(self.prev() == '\'');
+        // a label, `'` is left unterminated & the name lexed as an ident
+        if self.at_label() {
+            let kind = Char { terminated: false };
+            return Literal {
+                kind,
+                suffix_start: 1,
+            };
+        }
         let terminated = self.single_quoted_string();
         let suffix_start = self.pos_within_token();
         if terminated {
@@ -418,6 +427,12 @@ impl Cursor<'_> {
         Literal { kind, suffix_start }
     }
 
+    /// true if an ident not closed by a `'` follows
+    fn at_label(&self) -> bool {
+        let mut chars = self.as_str().chars();
+        chars.next().is_some_and(is_id_start) && chars.find(|&c| !is_id_continue(c)) != Some('\'')
+    }
+
     fn single_quoted_string(&mut self) -> bool {
         debug_assert!(self.prev() == '\'');
         // Check if it's a one-symbol literal.
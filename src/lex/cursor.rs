@@ -8,7 +8,7 @@ use super::{token::LexKind, Lexeme};
 ///
 /// Next characters can be peeked via `first` method,
 /// and position can be shifted forward via `bump` method.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Cursor<'a> {
     pub(super) token_pos: usize,
     len_remaining: usize,
@@ -36,13 +36,30 @@ impl<'a> Cursor<'a> {
             prev_token: Lexeme::new(LexKind::Eof, 0),
         }
     }
+
+    /// A cursor starting at the byte `pos` of `input`
+    ///
+    /// Positions are still relative to the start of `input`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is not a char boundary of `input`
+    #[must_use]
+    pub fn at(input: &'a str, pos: usize) -> Self {
+        let rest = &input[pos..];
+        Self {
+            token_pos: pos,
+            len_remaining: rest.len(),
+            chars: rest.chars(),
+            ..Self::new(input)
+        }
+    }
 }
 
 pub const EOF_CHAR: char = '\0';
 
-impl Cursor<'_> {
+impl<'a> Cursor<'a> {
     /// The current subslice as a string
-
     #[inline]
     #[must_use]
     pub fn as_str(&self) -> &str {
@@ -63,7 +80,7 @@ impl Cursor<'_> {
 
     #[inline]
     #[must_use]
-    pub const fn src(&self) -> &str {
+    pub const fn src(&self) -> &'a str {
         self.src
     }
 
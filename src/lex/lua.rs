@@ -0,0 +1,352 @@
+//! pre-lexing of lua 5.4 source
+//!
+//! Like [`Cursor::advance`], keywords are names & numbers & strings aren't
+//! checked here.
+
+use super::cursor::{Cursor, EOF_CHAR};
+
+#[cfg(test)]
+mod test;
+
+/// A lua lexeme, its kind & its length in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LuaLexeme {
+    pub kind: LuaKind,
+    pub len: usize,
+}
+
+impl LuaLexeme {
+    #[must_use]
+    pub const fn new(kind: LuaKind, len: usize) -> Self {
+        Self { kind, len }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LuaKind {
+    /// `-- comment`
+    LineComment,
+    /// `--[[ comment ]]`, `--[==[ comment ]==]`
+    LongComment {
+        terminated: bool,
+    },
+    Whitespace,
+    /// a name or a keyword
+    Name,
+    /// `12`, `0xff`, `1.5e3`, `0x1p-4`
+    Number,
+    /// `"abc"`, `'abc'`
+    Str {
+        terminated: bool,
+    },
+    /// `[[abc]]`, `[==[abc]==]`, `level` is the number of `=`
+    LongStr {
+        level: usize,
+        terminated: bool,
+    },
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+    /// `*`
+    Star,
+    /// `/`
+    Slash,
+    /// `//`
+    SlashSlash,
+    /// `%`
+    Percent,
+    /// `^`
+    Caret,
+    /// `#`
+    Hash,
+    /// `&`
+    Amp,
+    /// `~`
+    Tilde,
+    /// `|`
+    Pipe,
+    /// `<<`
+    Shl,
+    /// `>>`
+    Shr,
+    /// `==`
+    EqEq,
+    /// `~=`
+    TildeEq,
+    /// `<=`
+    Le,
+    /// `>=`
+    Ge,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `=`
+    Eq,
+    /// `(`
+    OpenParen,
+    /// `)`
+    CloseParen,
+    /// `{`
+    OpenBrace,
+    /// `}`
+    CloseBrace,
+    /// `[`
+    OpenBracket,
+    /// `]`
+    CloseBracket,
+    /// `::`
+    ColonColon,
+    /// `;`
+    Semi,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+    /// `.`
+    Dot,
+    /// `..`
+    DotDot,
+    /// `...`
+    DotDotDot,
+    /// any other char
+    Unknown,
+    Eof,
+}
+
+impl LuaKind {
+    /// true for whitespace & comments
+    #[must_use]
+    pub const fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace | Self::LineComment | Self::LongComment { .. }
+        )
+    }
+}
+
+pub fn tokenize(input: &str) -> impl Iterator<Item = LuaLexeme> + '_ {
+    LuaCursor::new(input)
+}
+
+/// A lexer of lua source
+#[derive(Debug, Clone, Default)]
+pub struct LuaCursor<'a> {
+    cursor: Cursor<'a>,
+}
+
+impl<'a> LuaCursor<'a> {
+    #[must_use]
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            cursor: Cursor::new(input),
+        }
+    }
+
+    #[must_use]
+    pub fn pos(&self) -> usize {
+        self.cursor.pos()
+    }
+
+    /// Lexes the next lexeme
+    pub fn advance(&mut self) -> LuaLexeme {
+        use LuaKind::*;
+        let start = self.pos();
+        let Some(first) = self.cursor.bump() else {
+            return LuaLexeme::new(Eof, 0);
+        };
+        let kind = match first {
+            c if is_whitespace(c) => {
+                self.cursor.eat_while(is_whitespace);
+                Whitespace
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                self.cursor
+                    .eat_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                Name
+            }
+            '0'..='9' => self.number(first),
+            '.' => match (self.cursor.first(), self.cursor.second()) {
+                ('.', '.') => {
+                    self.bump_n(2);
+                    DotDotDot
+                }
+                ('.', _) => {
+                    self.cursor.bump();
+                    DotDot
+                }
+                (c, _) if c.is_ascii_digit() => self.number(first),
+                _ => Dot,
+            },
+            '"' | '\'' => self.string(first),
+            '[' => self.long_bracket().map_or(OpenBracket, |level| {
+                let terminated = self.long_body(level);
+                LongStr { level, terminated }
+            }),
+            '-' if self.cursor.first() == '-' => {
+                self.cursor.bump();
+                if self.cursor.first() == '[' {
+                    self.cursor.bump();
+                    if let Some(level) = self.long_bracket() {
+                        let terminated = self.long_body(level);
+                        return self.lexeme(LongComment { terminated }, start);
+                    }
+                }
+                self.cursor.eat_while(|c| c != '\n');
+                LineComment
+            }
+            '-' => Minus,
+            '+' => Plus,
+            '*' => Star,
+            '/' => self.glued('/', SlashSlash, Slash),
+            '%' => Percent,
+            '^' => Caret,
+            '#' => Hash,
+            '&' => Amp,
+            '~' => self.glued('=', TildeEq, Tilde),
+            '|' => Pipe,
+            '<' => match self.cursor.first() {
+                '<' => self.glued('<', Shl, Lt),
+                _ => self.glued('=', Le, Lt),
+            },
+            '>' => match self.cursor.first() {
+                '>' => self.glued('>', Shr, Gt),
+                _ => self.glued('=', Ge, Gt),
+            },
+            '=' => self.glued('=', EqEq, Eq),
+            '(' => OpenParen,
+            ')' => CloseParen,
+            '{' => OpenBrace,
+            '}' => CloseBrace,
+            ']' => CloseBracket,
+            ':' => self.glued(':', ColonColon, Colon),
+            ';' => Semi,
+            ',' => Comma,
+            _ => Unknown,
+        };
+        self.lexeme(kind, start)
+    }
+
+    fn lexeme(&self, kind: LuaKind, start: usize) -> LuaLexeme {
+        LuaLexeme::new(kind, self.pos() - start)
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.cursor.bump();
+        }
+    }
+
+    /// `glued` if the next char is `next`, else `single`
+    fn glued(&mut self, next: char, glued: LuaKind, single: LuaKind) -> LuaKind {
+        if self.cursor.first() == next {
+            self.cursor.bump();
+            glued
+        } else {
+            single
+        }
+    }
+
+    /// Like lua, eats every char that may be part of a numeral & leaves
+    /// checking it to the parser
+    fn number(&mut self, first: char) -> LuaKind {
+        let exponent = match (first, self.cursor.first()) {
+            ('0', 'x' | 'X') => {
+                self.cursor.bump();
+                ['p', 'P']
+            }
+            _ => ['e', 'E'],
+        };
+        loop {
+            let c = self.cursor.first();
+            if exponent.contains(&c) {
+                self.cursor.bump();
+                if matches!(self.cursor.first(), '+' | '-') {
+                    self.cursor.bump();
+                }
+            } else if c.is_ascii_hexdigit() || c == '.' {
+                self.cursor.bump();
+            } else {
+                break;
+            }
+        }
+        // a numeral touching a name is malformed
+        if self.cursor.first().is_ascii_alphabetic() || self.cursor.first() == '_' {
+            self.cursor.bump();
+        }
+        LuaKind::Number
+    }
+
+    fn string(&mut self, quote: char) -> LuaKind {
+        let terminated = loop {
+            match self.cursor.first() {
+                '\n' | '\r' => break false,
+                EOF_CHAR if self.cursor.is_eof() => break false,
+                '\\' => {
+                    self.cursor.bump();
+                    match self.cursor.bump() {
+                        Some(c @ ('\n' | '\r')) => self.newline(c),
+                        Some('z') => self.cursor.eat_while(is_whitespace),
+                        _ => {}
+                    }
+                }
+                c => {
+                    self.cursor.bump();
+                    if c == quote {
+                        break true;
+                    }
+                }
+            }
+        };
+        LuaKind::Str { terminated }
+    }
+
+    /// eat the rest of a `\r\n` or `\n\r` newline
+    fn newline(&mut self, first: char) {
+        let second = self.cursor.first();
+        if matches!(second, '\n' | '\r') && second != first {
+            self.cursor.bump();
+        }
+    }
+
+    /// After a `[`, the level of the long bracket starting there, if any
+    fn long_bracket(&mut self) -> Option<usize> {
+        let rest = self.cursor.as_str();
+        let level = rest.bytes().take_while(|&b| b == b'=').count();
+        if rest.as_bytes().get(level) != Some(&b'[') {
+            return None;
+        }
+        self.bump_n(level + 1);
+        Some(level)
+    }
+
+    /// Eats until the closing long bracket of `level`, false if it's missing
+    fn long_body(&mut self, level: usize) -> bool {
+        let close = format!("]{}]", "=".repeat(level));
+        let rest = self.cursor.as_str();
+        let (len, terminated) = rest
+            .find(&close)
+            .map_or((rest.len(), false), |at| (at + close.len(), true));
+        let end = self.pos() + len;
+        while self.pos() < end {
+            self.cursor.bump();
+        }
+        terminated
+    }
+}
+
+impl Iterator for LuaCursor<'_> {
+    type Item = LuaLexeme;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lex = self.advance();
+        (lex.kind != LuaKind::Eof).then_some(lex)
+    }
+}
+
+/// whitespace as lua sees it
+#[must_use]
+pub const fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0b' | '\x0c')
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+/// the kinds & texts of the lexemes of `src`, without whitespace
+fn lex(src: &str) -> Vec<(LuaKind, &str)> {
+    let mut from = 0;
+    tokenize(src)
+        .map(|lex| {
+            let text = &src[from..from + lex.len];
+            from += lex.len;
+            (lex.kind, text)
+        })
+        .filter(|(kind, _)| *kind != LuaKind::Whitespace)
+        .collect()
+}
+
+#[test]
+fn operators() {
+    use LuaKind::*;
+    let kinds: Vec<_> = lex("a ~= b .. c ... // << >>= ~ :: . # ^")
+        .into_iter()
+        .map(|(kind, _)| kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            Name, TildeEq, Name, DotDot, Name, DotDotDot, SlashSlash, Shl, Shr, Eq, Tilde,
+            ColonColon, Dot, Hash, Caret
+        ]
+    );
+}
+
+#[test]
+fn comments() {
+    use LuaKind::*;
+    assert_eq!(
+        lex("a -- b\n--[==[ c ]] ]==] d --[ e\n--[[ f"),
+        [
+            (Name, "a"),
+            (LineComment, "-- b"),
+            (LongComment { terminated: true }, "--[==[ c ]] ]==]"),
+            (Name, "d"),
+            (LineComment, "--[ e"),
+            (LongComment { terminated: false }, "--[[ f"),
+        ]
+    );
+}
+
+#[test]
+fn strings() {
+    use LuaKind::*;
+    let done = Str { terminated: true };
+    assert_eq!(
+        lex(r#"'a"b' "\"\z
+            c" "d\
+e" [[x]] [=[]]]=] "f"#),
+        [
+            (done, r#"'a"b'"#),
+            (done, "\"\\\"\\z\n            c\""),
+            (done, "\"d\\\ne\""),
+            (
+                LongStr {
+                    level: 0,
+                    terminated: true
+                },
+                "[[x]]"
+            ),
+            (
+                LongStr {
+                    level: 1,
+                    terminated: true
+                },
+                "[=[]]]=]"
+            ),
+            (Str { terminated: false }, "\"f"),
+        ]
+    );
+    assert_eq!(lex("'a\nb'")[0], (Str { terminated: false }, "'a"));
+    assert_eq!(
+        lex("[==[")[0],
+        (
+            LongStr {
+                level: 2,
+                terminated: false
+            },
+            "[==["
+        )
+    );
+    assert_eq!(lex("[=")[0], (OpenBracket, "["));
+}
+
+#[test]
+fn numbers() {
+    let texts: Vec<_> = lex("3 3.0 .5 3e-2 0xA.8p+1 0x1e-2 5..x 1a")
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect();
+    // like lua, `0x1e-2` is a hex int minus 2 & `5..x` is a malformed number
+    assert_eq!(
+        texts,
+        ["3", "3.0", ".5", "3e-2", "0xA.8p+1", "0x1e", "-", "2", "5..x", "1a"]
+    );
+}
@@ -3,7 +3,7 @@ use super::*;
 use expect_test::{expect, Expect};
 
 fn check_raw_str(s: &str, expected: Result<u8, RawStrError>) {
-    let s = format!("r{}", s);
+    let s = format!("r{s}");
     let mut cursor = Cursor::new(&s);
     cursor.bump();
     let res = cursor.raw_double_quoted_string(0);
@@ -52,7 +52,7 @@ fn test_unterminated() {
             found: 0,
             possible_terminator_offset: None,
         }),
-    )
+    );
 }
 
 #[test]
@@ -97,18 +97,20 @@ fn test_too_many_hashes() {
     );
 }
 
-fn check_lexing(src: &str, expect: Expect) {
-    let actual: String = tokenize(src)
-        .map(|token| format!("{:?}\n", token))
-        .collect();
-    expect.assert_eq(&actual)
+fn check_lexing(src: &str, expect: &Expect) {
+    use std::fmt::Write;
+    let mut actual = String::new();
+    for token in tokenize(src) {
+        writeln!(actual, "{token:?}").unwrap();
+    }
+    expect.assert_eq(&actual);
 }
 
 #[test]
 fn smoke_test() {
     check_lexing(
         "/* my source file */ fn main() { println!(\"zebra\"); }\n",
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: BlockComment { doc_style: None, terminated: true }, len: 20 }
             Lexeme { kind: Whitespace, len: 1 }
             Lexeme { kind: Ident, len: 2 }
@@ -129,7 +131,7 @@ fn smoke_test() {
             Lexeme { kind: CloseBrace, len: 1 }
             Lexeme { kind: Whitespace, len: 1 }
         "#]],
-    )
+    );
 }
 
 #[test]
@@ -146,7 +148,7 @@ fn comment_flavors() {
 /** outer doc block */
 /*! inner doc block */
 ",
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: Whitespace, len: 1 }
             Lexeme { kind: LineComment { doc_style: None }, len: 7 }
             Lexeme { kind: Whitespace, len: 1 }
@@ -167,25 +169,25 @@ fn comment_flavors() {
             Lexeme { kind: BlockComment { doc_style: Some(Inner), terminated: true }, len: 22 }
             Lexeme { kind: Whitespace, len: 1 }
         "#]],
-    )
+    );
 }
 
 #[test]
 fn nested_block_comments() {
     check_lexing(
         "/* /* */ */'a'",
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: BlockComment { doc_style: None, terminated: true }, len: 11 }
             Lexeme { kind: Literal { kind: Char { terminated: true }, suffix_start: 3 }, len: 3 }
         "#]],
-    )
+    );
 }
 
 #[test]
 fn characters() {
     check_lexing(
         "'a' ' ' '\\n'",
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: Literal { kind: Char { terminated: true }, suffix_start: 3 }, len: 3 }
             Lexeme { kind: Whitespace, len: 1 }
             Lexeme { kind: Literal { kind: Char { terminated: true }, suffix_start: 3 }, len: 3 }
@@ -198,9 +200,26 @@ fn characters() {
 #[test]
 fn incomplete_char() {
     check_lexing(
-        "'abc",
-        expect![[r#"
-            Lexeme { kind: Literal { kind: Char { terminated: false }, suffix_start: 4 }, len: 4 }
+        "'\\abc",
+        &expect![[r#"
+            Lexeme { kind: Literal { kind: Char { terminated: false }, suffix_start: 5 }, len: 5 }
+        "#]],
+    );
+}
+
+#[test]
+fn labels() {
+    check_lexing(
+        "'abc 'a: 'ab'",
+        &expect![[r#"
+            Lexeme { kind: Literal { kind: Char { terminated: false }, suffix_start: 1 }, len: 1 }
+            Lexeme { kind: Ident, len: 3 }
+            Lexeme { kind: Whitespace, len: 1 }
+            Lexeme { kind: Literal { kind: Char { terminated: false }, suffix_start: 1 }, len: 1 }
+            Lexeme { kind: Ident, len: 1 }
+            Lexeme { kind: Colon, len: 1 }
+            Lexeme { kind: Whitespace, len: 1 }
+            Lexeme { kind: Literal { kind: Char { terminated: true }, suffix_start: 4 }, len: 4 }
         "#]],
     );
 }
@@ -209,10 +228,10 @@ fn incomplete_char() {
 fn raw_string() {
     check_lexing(
         "r###\"\"#a\\b\x00c\"\"###",
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: Literal { kind: RawStr { n_hashes: Some(3) }, suffix_start: 17 }, len: 17 }
         "#]],
-    )
+    );
 }
 
 #[test]
@@ -232,7 +251,7 @@ b"a"
 r###"raw"###suffix
 br###"raw"###suffix
 "####,
-        expect![[r#"
+        &expect![[r#"
             Lexeme { kind: Whitespace, len: 1 }
             Lexeme { kind: Literal { kind: Char { terminated: true }, suffix_start: 3 }, len: 3 }
             Lexeme { kind: Whitespace, len: 1 }
@@ -259,5 +278,5 @@ br###"raw"###suffix
             Lexeme { kind: Literal { kind: RawByteStr { n_hashes: Some(3) }, suffix_start: 13 }, len: 19 }
             Lexeme { kind: Whitespace, len: 1 }
         "#]],
-    )
+    );
 }
@@ -304,7 +304,7 @@ fn scan_unicode(chars: &mut Chars<'_>, allow_unicode_escapes: bool) -> Result<ch
     loop {
         match chars.next() {
             None => return Err(EscapeError::UnclosedUnicodeEscape),
-            Some('_') => continue,
+            Some('_') => (),
             Some('}') => {
                 if n_digits > 6 {
                     return Err(EscapeError::OverlongUnicodeEscape);
@@ -101,7 +101,7 @@ fn test_unescape_str_warn() {
     fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
         let mut unescaped = Vec::with_capacity(literal.len());
         unescape_unicode(literal, Mode::Str, &mut |range, res| {
-            unescaped.push((range, res))
+            unescaped.push((range, res));
         });
         assert_eq!(unescaped, expected);
     }
@@ -140,7 +140,7 @@ fn test_unescape_str_good() {
                 }
             }
         });
-        assert_eq!(buf.as_deref(), Ok(expected))
+        assert_eq!(buf.as_deref(), Ok(expected));
     }
 
     check("foo", "foo");
@@ -148,7 +148,7 @@ fn test_unescape_str_good() {
     check(" \t\n", " \t\n");
 
     check("hello \\\n     world", "hello world");
-    check("thread's", "thread's")
+    check("thread's", "thread's");
 }
 
 #[test]
@@ -257,7 +257,7 @@ fn test_unescape_byte_str_good() {
                 }
             }
         });
-        assert_eq!(buf.as_deref(), Ok(expected))
+        assert_eq!(buf.as_deref(), Ok(expected));
     }
 
     check("foo", b"foo");
@@ -265,7 +265,7 @@ fn test_unescape_byte_str_good() {
     check(" \t\n", b" \t\n");
 
     check("hello \\\n     world", b"hello world");
-    check("thread's", b"thread's")
+    check("thread's", b"thread's");
 }
 
 #[test]
@@ -273,7 +273,7 @@ fn test_unescape_raw_str() {
     fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
         let mut unescaped = Vec::with_capacity(literal.len());
         unescape_unicode(literal, Mode::RawStr, &mut |range, res| {
-            unescaped.push((range, res))
+            unescaped.push((range, res));
         });
         assert_eq!(unescaped, expected);
     }
@@ -296,7 +296,7 @@ fn test_unescape_raw_byte_str() {
     fn check(literal: &str, expected: &[(Range<usize>, Result<char, EscapeError>)]) {
         let mut unescaped = Vec::with_capacity(literal.len());
         unescape_unicode(literal, Mode::RawByteStr, &mut |range, res| {
-            unescaped.push((range, res))
+            unescaped.push((range, res));
         });
         assert_eq!(unescaped, expected);
     }
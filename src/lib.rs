@@ -17,6 +17,8 @@
 #![allow(clippy::module_name_repetitions)]
 #![allow(clippy::equatable_if_let)]
 #![allow(clippy::wildcard_imports)]
+#![allow(clippy::needless_raw_string_hashes)]
+#![allow(clippy::unnecessary_semicolon)]
 // #![allow(clippy::multiple_crate_versions)]
 // #![allow(clippy::must_use_candidate)]
 // #![allow(clippy::wildcard_dependencies)]
@@ -27,9 +29,13 @@
 #![allow(rustdoc::invalid_html_tags)]
 // #![allow(dead_code)]
 
+pub mod check;
 pub mod error;
 pub mod lex;
+pub mod lint;
+pub mod lsp;
 pub mod parse;
+pub mod runtime;
 pub mod span;
 #[allow(clippy::unicode_not_nfc)]
 pub mod unicode;
@@ -0,0 +1,205 @@
+//! lints, findings in valid code that is likely a mistake
+//!
+//! Each [`Lint`] has a [`Level`]: allowed lints don't run, warnings go to
+//! [`ErrorMulti::warnings`] & denied lints are errors. Levels are set with
+//! [`Lints::set_level`], or in the source with a comment such as
+//! `// lint: allow(unused_let, empty_if)`.
+
+use std::collections::HashMap;
+
+use crate::check::{resolve, Resolution};
+use crate::error::{ErrorMulti, SemanticError};
+use crate::parse::token::*;
+use crate::span::BSpan;
+
+pub use flow::{EmptyIf, Unreachable};
+pub use style::{ConstPredef, SnakeCase};
+pub use unused::{UnusedLet, UnusedParam};
+
+mod flow;
+mod style;
+#[cfg(test)]
+mod test;
+mod unused;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "warn" => Some(Self::Warn),
+            "deny" => Some(Self::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// What a lint can see of a module
+#[derive(Debug)]
+pub struct Context<'a> {
+    pub module: &'a Module,
+    /// names resolved without any host globals
+    pub resolution: Resolution,
+}
+
+/// A check whose findings are warnings by default
+pub trait Lint {
+    /// The snake case name levels are set by
+    fn name(&self) -> &'static str;
+
+    fn default_level(&self) -> Level {
+        Level::Warn
+    }
+
+    /// Push the span & message of each finding
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>);
+}
+
+/// A set of lints & their levels
+pub struct Lints {
+    lints: Vec<Box<dyn Lint>>,
+    levels: HashMap<&'static str, Level>,
+}
+
+impl std::fmt::Debug for Lints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.lints.iter().map(|lint| lint.name()).collect();
+        f.debug_struct("Lints")
+            .field("lints", &names)
+            .field("levels", &self.levels)
+            .finish()
+    }
+}
+
+impl Default for Lints {
+    /// Every builtin lint, at its default level
+    fn default() -> Self {
+        let mut lints = Self::empty();
+        lints.add(UnusedLet);
+        lints.add(UnusedParam);
+        lints.add(Unreachable);
+        lints.add(EmptyIf);
+        lints.add(ConstPredef);
+        lints.add(SnakeCase);
+        lints
+    }
+}
+
+impl Lints {
+    /// A set without any lints
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            lints: Vec::new(),
+            levels: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, lint: impl Lint + 'static) {
+        self.levels.insert(lint.name(), lint.default_level());
+        self.lints.push(Box::new(lint));
+    }
+
+    /// Set the level of the lint `name`, false if there is no such lint
+    pub fn set_level(&mut self, name: &str, level: Level) -> bool {
+        let Some(old) = self.levels.get_mut(name) else {
+            return false;
+        };
+        *old = level;
+        true
+    }
+
+    /// The level of the lint `name`, `None` if there is no such lint
+    #[must_use]
+    pub fn level(&self, name: &str) -> Option<Level> {
+        self.levels.get(name).copied()
+    }
+
+    /// Apply every `// lint: <level>(<name>, ..)` comment of `src`
+    ///
+    /// Unknown levels & lints are ignored.
+    pub fn configure(&mut self, src: &str) {
+        for line in src.lines() {
+            let Some(directive) = line.trim().strip_prefix("//") else {
+                continue;
+            };
+            let Some(directive) = directive.trim().strip_prefix("lint:") else {
+                continue;
+            };
+            let Some((level, names)) = directive.trim().split_once('(') else {
+                continue;
+            };
+            let (Some(level), Some(names)) = (
+                Level::from_name(level.trim()),
+                names.trim().strip_suffix(')'),
+            ) else {
+                continue;
+            };
+            for name in names.split(',') {
+                self.set_level(name.trim(), level);
+            }
+        }
+    }
+
+    /// The findings of every lint that isn't allowed with their levels, in
+    /// source order
+    #[must_use]
+    pub fn run(&self, module: &Module) -> Vec<(Level, SemanticError)> {
+        let cx = Context {
+            module,
+            resolution: resolve(module, &[]),
+        };
+        let mut findings = Vec::new();
+        for lint in &self.lints {
+            let level = self.levels[lint.name()];
+            if level == Level::Allow {
+                continue;
+            }
+            let mut out = Vec::new();
+            lint.check(&cx, &mut out);
+            findings.extend(out.into_iter().map(|(span, message)| {
+                let err = SemanticError::Lint {
+                    span,
+                    name: lint.name(),
+                    message,
+                };
+                (level, err)
+            }));
+        }
+        findings.sort_by_key(|(_, err)| err.span().from);
+        findings
+    }
+
+    /// Add the findings to `errors`, as warnings or errors by level
+    pub fn report(&self, module: &Module, errors: &mut ErrorMulti) {
+        for (level, err) in self.run(module) {
+            match level {
+                Level::Deny => errors.push(err),
+                Level::Warn | Level::Allow => errors.warn(err),
+            }
+        }
+    }
+}
+
+/// the span of the first token of the stmt at `pos` with one
+fn stmt_span(module: &Module, pos: usize) -> BSpan {
+    (pos..module.end(pos))
+        .find_map(|pos| match module.items()[pos] {
+            Token::Decl(Decl { span, .. })
+            | Token::FnDef(FnDef { span, .. })
+            | Token::Expr(Expr { span, .. })
+            | Token::Tagged(Tagged { span, .. })
+            | Token::Match(Match { span, .. })
+            | Token::Break(Break { span, .. })
+            | Token::Flow(Flow::Goto(_, span) | Flow::Label(_, span)) => Some(span),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
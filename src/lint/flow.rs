@@ -0,0 +1,90 @@
+//! control flow lints
+
+use crate::parse::token::*;
+use crate::span::{BSpan, TSpan};
+
+use super::{stmt_span, Context, Lint};
+
+/// Stmts after a `return`, `break` or `goto` in the same block, up to a
+/// label
+#[derive(Debug, Clone, Copy)]
+pub struct Unreachable;
+
+impl Lint for Unreachable {
+    fn name(&self) -> &'static str {
+        "unreachable_code"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        let module = cx.module;
+        let mut check = |span: TSpan| {
+            let mut pos = span.from;
+            while pos < span.to {
+                let end = module.end(pos);
+                let exit = match module.items()[pos] {
+                    Token::Return(_) => "return",
+                    Token::Break(_) => "break",
+                    Token::Flow(Flow::Goto(..)) => "goto",
+                    _ => {
+                        pos = end;
+                        continue;
+                    }
+                };
+                let label = matches!(module.items().get(end), Some(Token::Flow(Flow::Label(..))));
+                if end < span.to && !label {
+                    out.push((
+                        stmt_span(module, end),
+                        format!("unreachable code after `{exit}`"),
+                    ));
+                }
+                break;
+            }
+        };
+        check(TSpan {
+            from: 0,
+            to: module.len(),
+        });
+        // every other list of stmts
+        for token in module.items() {
+            match *token {
+                Token::FnDef(FnDef { tokens, .. })
+                | Token::Tagged(Tagged { tokens, .. })
+                | Token::Expr(Expr {
+                    kind: ExprKind::Closure(Closure { tokens, .. }),
+                    ..
+                })
+                | Token::Block(Block { tokens, .. }) => check(tokens),
+                Token::Arm(arm) => check(arm.body),
+                Token::Flow(Flow::While(body) | Flow::Repeat(body)) => check(body),
+                Token::Flow(Flow::For(def)) => check(def.body),
+                Token::Flow(Flow::If(span, el)) => {
+                    check(span);
+                    if let Some(el) = el {
+                        check(el);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// An `if` whose block is empty
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyIf;
+
+impl Lint for EmptyIf {
+    fn name(&self) -> &'static str {
+        "empty_if"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        for (pos, token) in cx.module.items().iter().enumerate() {
+            if let Token::Flow(Flow::If(span, _)) = token {
+                if span.is_empty() {
+                    out.push((stmt_span(cx.module, pos), "empty `if` block".to_owned()));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! style lints
+
+use crate::parse::token::*;
+use crate::span::BSpan;
+
+use super::{Context, Lint};
+
+/// A `const` whose value could be computed at compile time
+#[derive(Debug, Clone, Copy)]
+pub struct ConstPredef;
+
+impl Lint for ConstPredef {
+    fn name(&self) -> &'static str {
+        "const_predef"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        for (pos, token) in cx.module.items().iter().enumerate() {
+            if let Token::Decl(
+                decl @ Decl {
+                    kind: DeclKind::Const,
+                    pre: false,
+                    value: true,
+                    ..
+                },
+            ) = *token
+            {
+                if constant(cx, pos + 1) {
+                    out.push((decl.span, format!("`{}` could be a `predef`", decl.name)));
+                }
+            }
+        }
+    }
+}
+
+/// true if the expr at `pos` only uses literals, predefs & operators
+fn constant(cx: &Context, pos: usize) -> bool {
+    let Some(Token::Expr(expr)) = cx.module.get(pos) else {
+        return false;
+    };
+    match expr.kind {
+        ExprKind::Value(_) | ExprKind::Comp(_) => true,
+        ExprKind::Var(name) => match cx.resolution.uses.get(&pos) {
+            Some(&binding) => matches!(cx.module.get(binding), Some(Token::Decl(decl)) if decl.pre),
+            None => matches!(name.as_str(), "true" | "false" | "nil"),
+        },
+        ExprKind::Unary(_) | ExprKind::Paren => constant(cx, pos + 1),
+        ExprKind::Binary(_) => constant(cx, pos + 1) && constant(cx, cx.module.end(pos + 1)),
+        _ => false,
+    }
+}
+
+/// A fn or method whose name isn't snake case
+#[derive(Debug, Clone, Copy)]
+pub struct SnakeCase;
+
+impl Lint for SnakeCase {
+    fn name(&self) -> &'static str {
+        "snake_case"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        for token in cx.module.items() {
+            let Token::FnDef(def) = *token else {
+                continue;
+            };
+            let snake = to_snake_case(def.name.as_str());
+            if snake != def.name.as_str() {
+                let message = format!("fn `{}` should be snake case: `{snake}`", def.name);
+                out.push((def.span, message));
+            }
+        }
+    }
+}
+
+/// `fooBar` & `FooBar` -> `foo_bar`
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len());
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if prev_lower {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+            prev_lower = false;
+        } else {
+            snake.push(c);
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+    }
+    snake
+}
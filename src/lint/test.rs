@@ -0,0 +1,180 @@
+use crate::error::ErrorMulti;
+use crate::error::SemanticError;
+use crate::lint::*;
+use crate::parse::{LuaReader, Reader};
+
+/// the lint names & messages of the findings of every builtin lint
+fn findings(src: &str) -> Vec<(&'static str, String)> {
+    lint(src, Reader::new(src).module("test"))
+}
+
+fn lua_findings(src: &str) -> Vec<(&'static str, String)> {
+    lint(src, LuaReader::new(src).module("test"))
+}
+
+fn lint(src: &str, (module, errors): (Module, ErrorMulti)) -> Vec<(&'static str, String)> {
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    let mut lints = Lints::default();
+    lints.configure(src);
+    lints
+        .run(&module)
+        .into_iter()
+        .map(|(_, err)| match err {
+            SemanticError::Lint { name, message, .. } => (name, message),
+            other => panic!("not a lint: {other}"),
+        })
+        .collect()
+}
+
+fn names(src: &str) -> Vec<&'static str> {
+    findings(src).into_iter().map(|(name, _)| name).collect()
+}
+
+#[test]
+fn unused() {
+    assert_eq!(
+        findings(
+            "let g = 1; fn f(a, b, self, _c) { let x = 1; let _y = 2; let z = 3; return b + z }"
+        ),
+        [
+            ("unused_param", "unused param `a`".to_owned()),
+            ("unused_let", "unused binding `x`".to_owned())
+        ]
+    );
+    // used by a closure, or only assigned
+    assert_eq!(
+        names("fn f() { let x = 1; let y = 2; y = 3; return || x }"),
+        Vec::<&str>::new()
+    );
+    assert_eq!(
+        names("{ let x = 1 } let f = |y| 1"),
+        ["unused_let", "unused_param"]
+    );
+}
+
+#[test]
+fn flow() {
+    assert_eq!(
+        findings("fn f(x) { return x; f(x); return 1 } 'a: { break 'a 1; f(1) }"),
+        [
+            (
+                "unreachable_code",
+                "unreachable code after `return`".to_owned()
+            ),
+            (
+                "unreachable_code",
+                "unreachable code after `break`".to_owned()
+            )
+        ]
+    );
+    assert_eq!(
+        findings("fn f(x) { if x { } else { return 1 } if x { return 2 } }"),
+        [("empty_if", "empty `if` block".to_owned())]
+    );
+    // code after a label is reachable through a `goto`
+    assert_eq!(
+        lua_findings(
+            "while x do break f() end for i = 1, 2 do goto l ::l:: f(i) end goto m g() ::m::"
+        ),
+        [
+            (
+                "unreachable_code",
+                "unreachable code after `break`".to_owned()
+            ),
+            (
+                "unreachable_code",
+                "unreachable code after `goto`".to_owned()
+            )
+        ]
+    );
+}
+
+#[test]
+fn style() {
+    assert_eq!(
+        findings("predef a = 2; const b = a * 2 + 1; const c = f(); const d = -b; fn f() { }"),
+        [("const_predef", "`b` could be a `predef`".to_owned())]
+    );
+    assert_eq!(
+        findings(
+            "fn getValue() { } fn HTTPGet() { } fn get_2d() { } impl int { fn toStr(self) { } }"
+        ),
+        [
+            (
+                "snake_case",
+                "fn `getValue` should be snake case: `get_value`".to_owned()
+            ),
+            (
+                "snake_case",
+                "fn `HTTPGet` should be snake case: `httpget`".to_owned()
+            ),
+            (
+                "snake_case",
+                "fn `toStr` should be snake case: `to_str`".to_owned()
+            )
+        ]
+    );
+}
+
+#[test]
+fn levels() {
+    let src = "fn f(a) { let x = 1 }";
+    // lint: allow(unused_param) in a comment of the source
+    assert_eq!(
+        names(&format!("// lint: allow(unused_param)\n{src}")),
+        ["unused_let"]
+    );
+    assert_eq!(
+        names(&format!(
+            "// lint: allow(unused_let, unused_param, nope)\n{src}"
+        )),
+        Vec::<&str>::new()
+    );
+
+    let (module, _) = Reader::new(src).module("test");
+    let mut lints = Lints::default();
+    assert!(lints.set_level("unused_let", Level::Deny));
+    assert!(!lints.set_level("nope", Level::Deny));
+    assert_eq!(lints.level("unused_param"), Some(Level::Warn));
+    let mut errors = ErrorMulti::default();
+    lints.report(&module, &mut errors);
+    assert_eq!(errors.semantic.len(), 1);
+    assert_eq!(errors.warnings.len(), 1);
+    assert!(!errors.is_empty());
+}
+
+/// a lint from outside the builtins
+struct NoX;
+
+impl Lint for NoX {
+    fn name(&self) -> &'static str {
+        "no_x"
+    }
+
+    fn default_level(&self) -> Level {
+        Level::Deny
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(crate::span::BSpan, String)>) {
+        for token in cx.module.items() {
+            if let crate::parse::token::Token::Decl(decl) = token {
+                if decl.name.as_str() == "x" {
+                    out.push((decl.span, "no `x`s".to_owned()));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn custom_lint() {
+    let (module, _) = Reader::new("let x = 1; let y = x").module("test");
+    let mut lints = Lints::empty();
+    lints.add(NoX);
+    let mut errors = ErrorMulti::default();
+    lints.report(&module, &mut errors);
+    assert_eq!(
+        errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(),
+        ["no `x`s (`no_x`)"]
+    );
+}
@@ -0,0 +1,70 @@
+//! bindings that are never used
+//!
+//! Top level bindings are globals the host may use, they are left out, as
+//! are names starting with `_`.
+
+use std::collections::HashSet;
+
+use crate::check::ScopeKind;
+use crate::parse::token::*;
+use crate::span::BSpan;
+
+use super::{Context, Lint};
+
+/// A local `let` or `const` that is never used
+#[derive(Debug, Clone, Copy)]
+pub struct UnusedLet;
+
+impl Lint for UnusedLet {
+    fn name(&self) -> &'static str {
+        "unused_let"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        for pos in unused(cx) {
+            if let Token::Decl(decl) = cx.module.items()[pos] {
+                out.push((decl.span, format!("unused binding `{}`", decl.name)));
+            }
+        }
+    }
+}
+
+/// A param of a fn or closure that is never used, other than `self`
+#[derive(Debug, Clone, Copy)]
+pub struct UnusedParam;
+
+impl Lint for UnusedParam {
+    fn name(&self) -> &'static str {
+        "unused_param"
+    }
+
+    fn check(&self, cx: &Context, out: &mut Vec<(BSpan, String)>) {
+        for pos in unused(cx) {
+            match cx.module.items()[pos] {
+                Token::FnDefParam(param) if param.name.as_str() != "self" => {
+                    out.push((param.span, format!("unused param `{}`", param.name)));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// the unused local bindings, in order
+fn unused(cx: &Context) -> Vec<usize> {
+    let used: HashSet<usize> = cx.resolution.uses.values().copied().collect();
+    let mut unused: Vec<usize> = cx
+        .resolution
+        .scopes
+        .iter()
+        .filter(|scope| scope.kind != ScopeKind::Module)
+        .flat_map(|scope| scope.bindings.iter().copied())
+        .filter(|pos| !used.contains(pos))
+        .filter(|&pos| {
+            crate::check::binding_name(cx.module, pos)
+                .is_some_and(|name| !name.as_str().starts_with('_'))
+        })
+        .collect();
+    unused.sort_unstable();
+    unused
+}
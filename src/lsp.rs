@@ -0,0 +1,758 @@
+//! a language server, speaking json-rpc over stdio
+//!
+//! Documents are synced in full & reparsed on every change. Positions are
+//! converted between byte offsets & the utf-16 columns of the protocol.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+
+use crate::check::{resolve, uses};
+use crate::error::{ErrorMulti, SemanticError};
+use crate::lex::{tokenize, LexKind, LiteralKind};
+use crate::lint::Lints;
+use crate::parse::token::*;
+use crate::parse::{Reader, KEYWORDS};
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+pub use json::Json;
+
+pub mod json;
+#[cfg(test)]
+mod test;
+
+/// The semantic token types, in the order of their legend indices
+const TOKEN_TYPES: &[&str] = &[
+    "keyword", "variable", "string", "number", "comment", "operator",
+];
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Serve messages from `input` until an `exit` notification or eof
+///
+/// # Errors
+///
+/// If reading or writing fails, or a message isn't framed properly
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server::default();
+    while let Some(body) = read_body(&mut input)? {
+        let replies = match Json::parse(&body) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error(&Json::Null, PARSE_ERROR, &err.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exit {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Read the body of a `Content-Length` framed message, `None` at eof
+///
+/// # Errors
+///
+/// If reading fails or the header is missing
+pub fn read_body(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            len = value.trim().parse().ok();
+        }
+    }
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let len = len.ok_or_else(|| invalid("missing Content-Length"))?;
+    let mut body = vec![0; len];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| invalid("body is not utf-8"))
+}
+
+/// Write a `Content-Length` framed message
+///
+/// # Errors
+///
+/// If writing fails
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+/// The state of a session
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    exit: bool,
+}
+
+impl Server {
+    /// Handle a message, returning the messages to send back
+    pub fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").as_str().unwrap_or_default();
+        let params = message.get("params");
+        let id = message.get("id");
+        if id.is_null() {
+            return self.notification(method, params);
+        }
+        match self.request(method, params) {
+            Ok(result) => vec![Json::object([
+                ("jsonrpc", "2.0".into()),
+                ("id", id.clone()),
+                ("result", result),
+            ])],
+            Err((code, msg)) => vec![error(id, code, &msg)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .as_str()
+            .unwrap_or_default();
+        let text = match method {
+            "exit" => {
+                self.exit = true;
+                return Vec::new();
+            }
+            "textDocument/didOpen" => params.at(&["textDocument", "text"]),
+            // only full syncs are supported, so the last change has every line
+            "textDocument/didChange" => params
+                .get("contentChanges")
+                .as_array()
+                .last()
+                .map_or(&Json::Null, |change| change.get("text")),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish(uri, Vec::new())];
+            }
+            _ => return Vec::new(),
+        };
+        let Some(text) = text.as_str() else {
+            return Vec::new();
+        };
+        let doc = Document::new(text.to_owned());
+        let diagnostics = doc.diagnostics();
+        self.documents.insert(uri.to_owned(), doc);
+        vec![publish(uri, diagnostics)]
+    }
+
+    fn request(&self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        if method == "initialize" {
+            return Ok(initialize());
+        } else if method == "shutdown" {
+            return Ok(Json::Null);
+        }
+        let uri = params
+            .at(&["textDocument", "uri"])
+            .as_str()
+            .unwrap_or_default();
+        let doc = self.documents.get(uri);
+        let offset = |doc: &Document| {
+            doc.offset(params.get("position"))
+                .ok_or_else(|| (INVALID_PARAMS, "invalid position".to_owned()))
+        };
+        let result = match (method, doc) {
+            ("textDocument/semanticTokens/full", doc) => {
+                let data = doc.map(Document::semantic_tokens).unwrap_or_default();
+                Json::object([("data", data.into())])
+            }
+            ("textDocument/documentSymbol", doc) => {
+                doc.map(Document::symbols).unwrap_or_default().into()
+            }
+            ("textDocument/foldingRange", doc) => {
+                doc.map(Document::folding_ranges).unwrap_or_default().into()
+            }
+            ("textDocument/definition", Some(doc)) => doc.definition(uri, offset(doc)?),
+            ("textDocument/hover", Some(doc)) => doc.hover(offset(doc)?),
+            ("textDocument/definition" | "textDocument/hover", None) => Json::Null,
+            _ => return Err((METHOD_NOT_FOUND, format!("unknown method `{method}`"))),
+        };
+        Ok(result)
+    }
+}
+
+fn initialize() -> Json {
+    let legend = Json::object([
+        (
+            "tokenTypes",
+            TOKEN_TYPES
+                .iter()
+                .map(|&ty| ty.into())
+                .collect::<Vec<_>>()
+                .into(),
+        ),
+        ("tokenModifiers", Json::Array(Vec::new())),
+    ]);
+    let capabilities = Json::object([
+        // full sync
+        ("textDocumentSync", 1.into()),
+        (
+            "semanticTokensProvider",
+            Json::object([("legend", legend), ("full", true.into())]),
+        ),
+        ("documentSymbolProvider", true.into()),
+        ("definitionProvider", true.into()),
+        ("hoverProvider", true.into()),
+        ("foldingRangeProvider", true.into()),
+    ]);
+    Json::object([
+        ("capabilities", capabilities),
+        ("serverInfo", Json::object([("name", "allua".into())])),
+    ])
+}
+
+fn error(id: &Json, code: i32, msg: &str) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.clone()),
+        (
+            "error",
+            Json::object([("code", code.into()), ("message", msg.into())]),
+        ),
+    ])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", "textDocument/publishDiagnostics".into()),
+        (
+            "params",
+            Json::object([("uri", uri.into()), ("diagnostics", diagnostics.into())]),
+        ),
+    ])
+}
+
+/// An open document & everything derived from it
+#[derive(Debug)]
+struct Document {
+    src: String,
+    module: Module,
+    errors: ErrorMulti,
+    /// the byte offset of every line
+    lines: Vec<usize>,
+    lexemes: Vec<(BSpan, LexKind)>,
+    /// `{` offset -> `}` end
+    braces: BTreeMap<usize, usize>,
+    /// use pos -> binding pos
+    uses: BTreeMap<usize, usize>,
+}
+
+impl Document {
+    fn new(src: String) -> Self {
+        let (module, mut errors) = Reader::new(&src).module("lsp");
+        // the host's globals are unknown, so undefined names aren't reported
+        let resolution = resolve(&module, &[]);
+        for err in resolution.errors {
+            if !matches!(err, SemanticError::Undefined { .. }) {
+                errors.push(err);
+            }
+        }
+        for warning in resolution.warnings {
+            errors.warn(warning);
+        }
+        let mut linter = Lints::default();
+        linter.configure(&src);
+        linter.report(&module, &mut errors);
+        let lines = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let mut pos = 0;
+        let lexemes: Vec<_> = tokenize(&src)
+            .map(|lex| {
+                let span = BSpan::from_len(pos, lex.len);
+                pos += lex.len;
+                (span, lex.kind)
+            })
+            .collect();
+        let mut braces = BTreeMap::new();
+        let mut open = Vec::new();
+        for &(span, kind) in &lexemes {
+            match kind {
+                LexKind::OpenBrace => open.push(span.from),
+                LexKind::CloseBrace => {
+                    if let Some(from) = open.pop() {
+                        braces.insert(from, span.to);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let uses = uses(&module);
+        Self {
+            src,
+            module,
+            errors,
+            lines,
+            lexemes,
+            braces,
+            uses,
+        }
+    }
+
+    /// the line & utf-16 column of a byte offset
+    fn line_col(&self, pos: usize) -> (usize, usize) {
+        let mut pos = pos.min(self.src.len());
+        while !self.src.is_char_boundary(pos) {
+            pos -= 1;
+        }
+        let line = self.lines.partition_point(|&start| start <= pos) - 1;
+        let col = self.src[self.lines[line]..pos].encode_utf16().count();
+        (line, col)
+    }
+
+    fn position(&self, pos: usize) -> Json {
+        let (line, col) = self.line_col(pos);
+        Json::object([("line", line.into()), ("character", col.into())])
+    }
+
+    fn range(&self, span: BSpan) -> Json {
+        Json::object([
+            ("start", self.position(span.from)),
+            ("end", self.position(span.to)),
+        ])
+    }
+
+    /// the byte offset of a position, `None` past the last line
+    fn offset(&self, position: &Json) -> Option<usize> {
+        let line = position.get("line").as_usize()?;
+        let character = position.get("character").as_usize()?;
+        let start = *self.lines.get(line)?;
+        let mut col = 0;
+        for (i, c) in self.src[start..].char_indices() {
+            if col >= character || c == '\n' {
+                return Some(start + i);
+            }
+            col += c.len_utf16();
+        }
+        Some(self.src.len())
+    }
+
+    fn diagnostics(&self) -> Vec<Json> {
+        let errors = self
+            .errors
+            .iter()
+            .map(|err| (err.span().unwrap_or_default(), err.to_string(), 1));
+        let warnings = self
+            .errors
+            .warnings
+            .iter()
+            .map(|warning| (warning.span(), warning.to_string(), 2));
+        errors
+            .chain(warnings)
+            .map(|(span, message, severity)| {
+                Json::object([
+                    ("range", self.range(span)),
+                    // 1 is an error, 2 a warning
+                    ("severity", severity.into()),
+                    ("source", "allua".into()),
+                    ("message", message.into()),
+                ])
+            })
+            .collect()
+    }
+
+    /// every lexeme with a token type, as relative `line, start, len, type, 0`
+    fn semantic_tokens(&self) -> Vec<Json> {
+        let mut data = Vec::new();
+        let (mut last_line, mut last_col) = (0, 0);
+        for &(span, kind) in &self.lexemes {
+            let text = &self.src[span.from..span.to];
+            let Some(ty) = token_type(kind, text) else {
+                continue;
+            };
+            // tokens may not span lines, so multi-line comments are split
+            let mut from = span.from;
+            for part in text.split('\n') {
+                let len = part.trim_end_matches('\r').encode_utf16().count();
+                let (line, col) = self.line_col(from);
+                from += part.len() + 1;
+                if len == 0 {
+                    continue;
+                }
+                let delta_col = if line == last_line {
+                    col - last_col
+                } else {
+                    col
+                };
+                for value in [line - last_line, delta_col, len, ty, 0] {
+                    data.push(value.into());
+                }
+                (last_line, last_col) = (line, col);
+            }
+        }
+        data
+    }
+
+    /// fns, decls & data types, with the ones declared in a fn as its children
+    fn symbols(&self) -> Vec<Json> {
+        self.symbols_in(0, self.module.len())
+    }
+
+    fn symbols_in(&self, from: usize, to: usize) -> Vec<Json> {
+        let mut symbols = Vec::new();
+        let mut pos = from;
+        while pos < to {
+            let end = self.module.end(pos);
+            match self.module.items()[pos] {
+                Token::Decl(decl) => {
+                    let last = match decl.value {
+                        true => self.expr_span(decl.value_pos(pos)).to,
+                        false => decl.span.to,
+                    };
+                    let kind = match decl.kind {
+                        DeclKind::Let => 13,
+                        DeclKind::Const | DeclKind::Close => 14,
+                    };
+                    let range = BSpan::new(decl.span.from, last);
+                    symbols.push(self.symbol(decl.name, kind, range, decl.span, Vec::new()));
+                }
+                Token::FnDef(def) => {
+                    let range = BSpan::new(def.span.from, self.fn_end(def.span));
+                    let children = self.symbols_in(def.tokens.from, def.tokens.to);
+                    symbols.push(self.symbol(def.name, 12, range, def.span, children));
+                }
+                Token::Struct(def) => {
+                    symbols.push(self.symbol(def.name, 23, def.span, def.span, Vec::new()));
+                }
+                Token::Enum(def) => {
+                    symbols.push(self.symbol(def.name, 10, def.span, def.span, Vec::new()));
+                }
+                Token::Impl(def) => {
+                    let children = self.symbols_in(def.methods.from, def.methods.to);
+                    symbols.push(self.symbol(def.ty, 5, def.span, def.span, children));
+                }
+                Token::Like(def) => {
+                    symbols.push(self.symbol(def.name, 11, def.span, def.span, Vec::new()));
+                }
+                Token::Block(block) => {
+                    symbols.extend(self.symbols_in(block.tokens.from, block.tokens.to));
+                }
+                Token::Tagged(tagged) => {
+                    symbols.extend(self.symbols_in(tagged.tokens.from, tagged.tokens.to));
+                }
+                Token::Flow(Flow::If(span, el)) => {
+                    symbols.extend(self.symbols_in(span.from, span.to));
+                    if let Some(el) = el {
+                        symbols.extend(self.symbols_in(el.from, el.to));
+                    }
+                }
+                Token::Flow(Flow::While(body) | Flow::Repeat(body)) => {
+                    symbols.extend(self.symbols_in(body.from, body.to));
+                }
+                Token::Flow(Flow::For(def)) => {
+                    symbols.extend(self.symbols_in(def.body.from, def.body.to));
+                }
+                _ => {}
+            }
+            pos = end;
+        }
+        symbols
+    }
+
+    fn symbol(
+        &self,
+        name: Symbol,
+        kind: u32,
+        range: BSpan,
+        selection: BSpan,
+        children: Vec<Json>,
+    ) -> Json {
+        Json::object([
+            ("name", name.as_str().into()),
+            ("kind", kind.into()),
+            ("range", self.range(range)),
+            ("selectionRange", self.range(selection)),
+            ("children", children.into()),
+        ])
+    }
+
+    fn expr_span(&self, pos: usize) -> BSpan {
+        match self.module.get(pos) {
+            Some(Token::Expr(expr)) => expr.span,
+            Some(Token::Tagged(tagged)) => tagged.span,
+            Some(Token::Block(block)) => block.span,
+            _ => BSpan::default(),
+        }
+    }
+
+    /// the end of the body of the fn named at `name`
+    fn fn_end(&self, name: BSpan) -> usize {
+        self.body_after(name.to)
+            .map_or(self.src.len(), |body| body.to)
+    }
+
+    /// the braces of the body starting at the first `{` after `from`,
+    /// outside of parens, `None` if a `;` or `}` comes first
+    fn body_after(&self, from: usize) -> Option<BSpan> {
+        let mut depth = 0_usize;
+        for &(span, kind) in self.lexemes.iter().filter(|(span, _)| span.from >= from) {
+            match kind {
+                LexKind::OpenParen => depth += 1,
+                LexKind::CloseParen => depth = depth.saturating_sub(1),
+                LexKind::OpenBrace if depth == 0 => {
+                    let to = self.braces.get(&span.from).copied();
+                    return Some(BSpan::new(span.from, to.unwrap_or(self.src.len())));
+                }
+                LexKind::Semi | LexKind::CloseBrace if depth == 0 => return None,
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// every block, body & block comment spanning lines
+    fn folding_ranges(&self) -> Vec<Json> {
+        let comments = self
+            .lexemes
+            .iter()
+            .filter(|(_, kind)| matches!(kind, LexKind::BlockComment { .. }))
+            .map(|&(span, _)| (span, Some("comment")));
+        let bodies = (0..self.module.len())
+            .flat_map(|pos| self.folds(pos))
+            .map(|span| (span, None));
+        comments
+            .chain(bodies)
+            .filter_map(|(span, kind)| {
+                let (start, _) = self.line_col(span.from);
+                let (end, _) = self.line_col(span.to);
+                let mut range = vec![("startLine", start.into()), ("endLine", end.into())];
+                if let Some(kind) = kind {
+                    range.push(("kind", kind.into()));
+                }
+                (start < end).then(|| Json::object(range))
+            })
+            .collect()
+    }
+
+    /// the spans of the token at `pos` that fold, from where it starts to
+    /// the end of its body
+    fn folds(&self, pos: usize) -> Vec<BSpan> {
+        let from = |span: BSpan| {
+            self.body_after(span.to)
+                .map(|body| BSpan::new(span.from, body.to))
+        };
+        match self.module.items()[pos] {
+            Token::Block(block) => vec![block.span],
+            Token::Expr(Expr {
+                kind: ExprKind::Closure(_),
+                span,
+                ..
+            }) => vec![span],
+            Token::FnDef(FnDef { span, .. })
+            | Token::Tagged(Tagged { span, .. })
+            | Token::Match(Match { span, .. })
+            | Token::Struct(Struct { span, .. })
+            | Token::Enum(Enum { span, .. })
+            | Token::Impl(Impl { span, .. })
+            | Token::Like(Like { span, .. }) => from(span).into_iter().collect(),
+            Token::Arm(arm) => {
+                // the `{` right after the `=>`, arms with an expr body don't fold
+                let after: Vec<_> = self.lexemes_after(arm.span.to).collect();
+                let open = after.windows(3).find_map(|window| match window {
+                    [(eq, LexKind::Eq), (gt, LexKind::Gt), next] if eq.to == gt.from => Some(*next),
+                    _ => None,
+                });
+                match open {
+                    Some((open, LexKind::OpenBrace)) => {
+                        let to = self.braces.get(&open.from).copied();
+                        vec![BSpan::new(arm.span.from, to.unwrap_or(self.src.len()))]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            Token::Flow(Flow::If(_, el)) => {
+                let Some(then) = from(self.expr_span(pos + 1)) else {
+                    return Vec::new();
+                };
+                let mut folds = vec![then];
+                // an `else if` folds as an if of its own
+                let mut after = self.lexemes_after(then.to);
+                if let (Some(_), Some((word, _)), Some((open, LexKind::OpenBrace))) =
+                    (el, after.next(), after.next())
+                {
+                    let to = self.braces.get(&open.from).copied();
+                    folds.push(BSpan::new(word.from, to.unwrap_or(self.src.len())));
+                }
+                folds
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// the lexemes at or after `from`, without whitespace & comments
+    fn lexemes_after(&self, from: usize) -> impl Iterator<Item = (BSpan, LexKind)> + '_ {
+        self.lexemes.iter().copied().filter(move |&(span, kind)| {
+            span.from >= from
+                && !matches!(
+                    kind,
+                    LexKind::Whitespace
+                        | LexKind::LineComment { .. }
+                        | LexKind::BlockComment { .. }
+                )
+        })
+    }
+
+    /// the pos & name span of the binding or use at `offset`
+    fn name_at(&self, offset: usize) -> Option<(usize, BSpan)> {
+        self.module
+            .items()
+            .iter()
+            .enumerate()
+            .find_map(|(pos, &token)| {
+                let span = name_span(token)?;
+                (span.from <= offset && offset <= span.to).then_some((pos, span))
+            })
+    }
+
+    /// the binding of the token at `pos`, which may be the token itself
+    fn binding(&self, pos: usize) -> Option<usize> {
+        let Token::Expr(Expr {
+            kind: ExprKind::Var(name) | ExprKind::FnCall(FnCall { name }),
+            ..
+        }) = self.module.items()[pos]
+        else {
+            return Some(pos);
+        };
+        if let Some(&binding) = self.uses.get(&pos) {
+            return Some(binding);
+        }
+        // a global declared later
+        let mut pos = 0;
+        while pos < self.module.len() {
+            match self.module.items()[pos] {
+                Token::Decl(Decl { name: other, .. }) | Token::FnDef(FnDef { name: other, .. })
+                    if other == name =>
+                {
+                    return Some(pos);
+                }
+                _ => {}
+            }
+            pos = self.module.end(pos);
+        }
+        None
+    }
+
+    fn definition(&self, uri: &str, offset: usize) -> Json {
+        let Some(binding) = self.name_at(offset).and_then(|(pos, _)| self.binding(pos)) else {
+            return Json::Null;
+        };
+        let Some(span) = name_span(self.module.items()[binding]) else {
+            return Json::Null;
+        };
+        Json::object([("uri", uri.into()), ("range", self.range(span))])
+    }
+
+    fn hover(&self, offset: usize) -> Json {
+        let Some((pos, span)) = self.name_at(offset) else {
+            return Json::Null;
+        };
+        let Some(signature) = self.binding(pos).and_then(|pos| self.signature(pos)) else {
+            return Json::Null;
+        };
+        let contents = Json::object([
+            ("kind", "markdown".into()),
+            ("value", format!("```allua\n{signature}\n```").into()),
+        ]);
+        Json::object([("contents", contents), ("range", self.range(span))])
+    }
+
+    /// how the binding at `pos` was declared
+    fn signature(&self, pos: usize) -> Option<String> {
+        let typed = |ty: Option<TSpan>, name: Symbol| {
+            ty.map_or_else(
+                || name.to_string(),
+                |ty| format!("{} {name}", self.module.type_str(ty.from)),
+            )
+        };
+        Some(match self.module.items()[pos] {
+            Token::Decl(decl) => {
+                let kind = match decl.kind {
+                    DeclKind::Let => "let",
+                    DeclKind::Const => "const",
+                    DeclKind::Close => "close",
+                };
+                format!("{kind} {}", typed(decl.ty, decl.name))
+            }
+            Token::FnDef(def) => {
+                let mut params = Vec::new();
+                let mut param = def.params.from;
+                while param < def.params.to {
+                    if let Token::FnDefParam(p) = self.module.items()[param] {
+                        params.push(typed(p.ty, p.name));
+                    }
+                    param = self.module.end(param);
+                }
+                if def.variadic {
+                    params.push("...".to_owned());
+                }
+                let mut name = typed(def.ty, def.name);
+                let generics = def.generics(pos);
+                if generics.from < generics.to {
+                    let generics: Vec<String> = (generics.from..generics.to)
+                        .map(|at| self.module.type_str(at))
+                        .collect();
+                    name = format!("{name}<{}>", generics.join(", "));
+                }
+                format!("fn {name}({})", params.join(", "))
+            }
+            Token::FnDefParam(param) => format!("param {}", typed(param.ty, param.name)),
+            _ => return None,
+        })
+    }
+}
+
+/// the span of the name a token binds or uses
+fn name_span(token: Token) -> Option<BSpan> {
+    match token {
+        Token::Decl(Decl { span, .. })
+        | Token::FnDef(FnDef { span, .. })
+        | Token::FnDefParam(FnDefParam { span, .. })
+        | Token::Expr(Expr {
+            kind: ExprKind::Var(_),
+            span,
+            ..
+        }) => Some(span),
+        Token::Expr(Expr {
+            kind: ExprKind::FnCall(FnCall { name }),
+            span,
+            ..
+        }) => Some(BSpan::from_len(span.from, name.len())),
+        _ => None,
+    }
+}
+
+/// the index of a lexeme's type in [`TOKEN_TYPES`]
+fn token_type(kind: LexKind, text: &str) -> Option<usize> {
+    use LexKind::*;
+    Some(match kind {
+        Ident | RawIdent
+            if KEYWORDS.contains(&text) || matches!(text, "true" | "false" | "nil") =>
+        {
+            0
+        }
+        Ident | RawIdent => 1,
+        Literal {
+            kind: LiteralKind::Int { .. } | LiteralKind::Float { .. },
+            ..
+        } => 3,
+        Literal { .. } => 2,
+        LineComment { .. } | BlockComment { .. } => 4,
+        Eq | Bang | Lt | Gt | Minus | And | Or | Plus | Star | Slash | Caret | Percent => 5,
+        _ => return None,
+    })
+}
@@ -0,0 +1,342 @@
+//! a minimal json value, enough for the language server
+
+use std::fmt::{self, Display, Write};
+
+use crate::error::JsonError;
+
+/// A parsed json value
+///
+/// Objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Self>),
+    Object(Vec<(String, Self)>),
+}
+
+static NULL: Json = Json::Null;
+
+impl Json {
+    /// # Errors
+    ///
+    /// If `src` isn't exactly one json value
+    pub fn parse(src: &str) -> Result<Self, JsonError> {
+        let mut parser = Parser { src, pos: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        match parser.pos == src.len() {
+            true => Ok(value),
+            false => Err(JsonError(parser.pos)),
+        }
+    }
+
+    /// An object from its pairs
+    pub fn object<'a>(pairs: impl IntoIterator<Item = (&'a str, Self)>) -> Self {
+        let pairs = pairs.into_iter().map(|(k, v)| (k.to_owned(), v));
+        Self::Object(pairs.collect())
+    }
+
+    /// The value of `key`, null if missing or if this isn't an object
+    #[must_use]
+    pub fn get(&self, key: &str) -> &Self {
+        match self {
+            Self::Object(pairs) => pairs
+                .iter()
+                .find(|(k, _)| k == key)
+                .map_or(&NULL, |(_, v)| v),
+            _ => &NULL,
+        }
+    }
+
+    /// Follow a path of keys
+    #[must_use]
+    pub fn at(&self, path: &[&str]) -> &Self {
+        path.iter().fold(self, |value, key| value.get(key))
+    }
+
+    #[must_use]
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// `None` unless this is a whole number that fits
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn as_usize(&self) -> Option<usize> {
+        match *self {
+            Self::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= u32::MAX.into() => {
+                Some(n as usize)
+            }
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn as_array(&self) -> &[Self] {
+        match self {
+            Self::Array(values) => values,
+            _ => &[],
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) if n.is_finite() => write!(f, "{n}"),
+            // json has no infinities or nans
+            Self::Null | Self::Number(_) => f.write_str("null"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(values) => {
+                f.write_char('[')?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(pairs) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c))?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+macro_rules! json_from {
+    ($($ty:ty => $value:ident),* $(,)?) => {$(
+        impl From<$ty> for Json {
+            fn from(value: $ty) -> Self {
+                Self::$value(value.into())
+            }
+        }
+    )*};
+}
+
+json_from!(
+    bool => Bool,
+    u32 => Number,
+    i32 => Number,
+    f64 => Number,
+    &str => String,
+    String => String,
+    Vec<Json> => Array,
+);
+
+impl From<usize> for Json {
+    #[allow(clippy::cast_precision_loss)]
+    fn from(value: usize) -> Self {
+        Self::Number(value as f64)
+    }
+}
+
+impl<T: Into<Self>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'n') => self.word("null", Json::Null),
+            Some(b't') => self.word("true", Json::Bool(true)),
+            Some(b'f') => self.word("false", Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.err()),
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        self.whitespace();
+        if self.eat(b']') {
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.whitespace();
+            if !self.eat(b',') {
+                return match self.eat(b']') {
+                    true => Ok(Json::Array(values)),
+                    false => Err(self.err()),
+                };
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.pos += 1;
+        let mut pairs = Vec::new();
+        self.whitespace();
+        if self.eat(b'}') {
+            return Ok(Json::Object(pairs));
+        }
+        loop {
+            self.whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.err());
+            }
+            let key = self.string()?;
+            self.whitespace();
+            if !self.eat(b':') {
+                return Err(self.err());
+            }
+            pairs.push((key, self.value()?));
+            self.whitespace();
+            if !self.eat(b',') {
+                return match self.eat(b'}') {
+                    true => Ok(Json::Object(pairs)),
+                    false => Err(self.err()),
+                };
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.src[self.pos..];
+            let Some(i) = rest.find(['"', '\\']) else {
+                return Err(JsonError(self.src.len()));
+            };
+            out.push_str(&rest[..i]);
+            self.pos += i + 1;
+            if rest.as_bytes()[i] == b'"' {
+                return Ok(out);
+            }
+            let c = match self.next().ok_or_else(|| self.err())? {
+                b'"' => '"',
+                b'\\' => '\\',
+                b'/' => '/',
+                b'b' => '\u{8}',
+                b'f' => '\u{c}',
+                b'n' => '\n',
+                b'r' => '\r',
+                b't' => '\t',
+                b'u' => self.unicode()?,
+                _ => return Err(JsonError(self.pos - 1)),
+            };
+            out.push(c);
+        }
+    }
+
+    /// the rest of a `\u` escape, which may be a surrogate pair
+    fn unicode(&mut self) -> Result<char, JsonError> {
+        let first = self.hex()?;
+        let code = match first {
+            0xd800..=0xdbff if self.src[self.pos..].starts_with("\\u") => {
+                self.pos += 2;
+                let second = self.hex()?;
+                0x10000 + ((first - 0xd800) << 10) + second.wrapping_sub(0xdc00)
+            }
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| self.err())
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.err())?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.err())?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.src[start..self.pos]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError(start))
+    }
+
+    fn word(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        match self.src[self.pos..].starts_with(word) {
+            true => {
+                self.pos += word.len();
+                Ok(value)
+            }
+            false => Err(self.err()),
+        }
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        self.pos += usize::from(found);
+        found
+    }
+
+    const fn err(&self) -> JsonError {
+        JsonError(self.pos)
+    }
+}
@@ -0,0 +1,237 @@
+use crate::lsp::*;
+
+const URI: &str = "file:///test.allua";
+
+fn request(id: i32, method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("id", id.into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn notification(method: &str, params: Json) -> Json {
+    Json::object([
+        ("jsonrpc", "2.0".into()),
+        ("method", method.into()),
+        ("params", params),
+    ])
+}
+
+fn open(src: &str) -> Json {
+    notification(
+        "textDocument/didOpen",
+        Json::object([(
+            "textDocument",
+            Json::object([("uri", URI.into()), ("text", src.into())]),
+        )]),
+    )
+}
+
+fn at(line: u32, character: u32) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        (
+            "position",
+            Json::object([("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+fn document() -> Json {
+    Json::object([("textDocument", Json::object([("uri", URI.into())]))])
+}
+
+/// the result of a request on `src`
+fn result(src: &str, method: &str, params: Json) -> Json {
+    let mut server = Server::default();
+    server.handle(&open(src));
+    let mut replies = server.handle(&request(1, method, params));
+    assert_eq!(replies.len(), 1);
+    replies.pop().unwrap().get("result").clone()
+}
+
+fn range(json: &Json) -> [usize; 4] {
+    let pos = |key| {
+        let pos = json.at(&["range", key]);
+        (
+            pos.get("line").as_usize().unwrap(),
+            pos.get("character").as_usize().unwrap(),
+        )
+    };
+    let (start, end) = (pos("start"), pos("end"));
+    [start.0, start.1, end.0, end.1]
+}
+
+#[test]
+fn json() {
+    let src = r#"{"a":[1,-2.5,true,null],"b":"x\"\né😀"}"#;
+    let json = Json::parse(src).unwrap();
+    assert_eq!(json.at(&["b"]).as_str(), Some("x\"\né😀"));
+    assert_eq!(json.get("a").as_array().len(), 4);
+    assert_eq!(json.get("a").as_array()[0].as_usize(), Some(1));
+    assert_eq!(json.get("a").as_array()[1].as_usize(), None);
+    assert!(json.get("c").is_null());
+    assert_eq!(Json::parse(&json.to_string()), Ok(json));
+
+    assert_eq!(Json::parse(" [ ] ").unwrap(), Json::Array(Vec::new()));
+    assert!(Json::parse("[1,]").is_err());
+    assert!(Json::parse("{\"a\" 1}").is_err());
+    assert!(Json::parse("\"abc").is_err());
+    assert!(Json::parse("1 2").is_err());
+}
+
+#[test]
+fn framing() {
+    let messages = [
+        request(1, "initialize", Json::object([])),
+        open("let x = "),
+        request(2, "unknown", Json::Null),
+        request(3, "shutdown", Json::Null),
+        notification("exit", Json::Null),
+        // never read
+        request(4, "shutdown", Json::Null),
+    ];
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    serve(input.as_slice(), &mut output).unwrap();
+
+    let mut output = output.as_slice();
+    let mut replies = Vec::new();
+    while let Some(body) = read_body(&mut output).unwrap() {
+        replies.push(Json::parse(&body).unwrap());
+    }
+    assert_eq!(replies.len(), 4);
+    assert!(!replies[0].at(&["result", "capabilities"]).is_null());
+    assert_eq!(
+        replies[1].get("method").as_str(),
+        Some("textDocument/publishDiagnostics")
+    );
+    assert_eq!(
+        replies[1].at(&["params", "diagnostics"]).as_array().len(),
+        1
+    );
+    assert_eq!(replies[2].at(&["error", "code"]), &Json::from(-32601));
+    assert_eq!(replies[3].get("id"), &Json::from(3));
+    assert!(replies[3].get("result").is_null());
+}
+
+#[test]
+fn diagnostics() {
+    let mut server = Server::default();
+    let replies = server.handle(&open("let x = 1\nfn f( {"));
+    let diagnostics = replies[0].at(&["params", "diagnostics"]).as_array();
+    assert!(!diagnostics.is_empty());
+    assert_eq!(range(&diagnostics[0])[0], 1);
+
+    let change = notification(
+        "textDocument/didChange",
+        Json::object([
+            ("textDocument", Json::object([("uri", URI.into())])),
+            (
+                "contentChanges",
+                Json::Array(vec![Json::object([("text", "let x = 1".into())])]),
+            ),
+        ]),
+    );
+    let replies = server.handle(&change);
+    assert!(replies[0]
+        .at(&["params", "diagnostics"])
+        .as_array()
+        .is_empty());
+}
+
+#[test]
+fn symbols() {
+    let src = "let a = 1\nfn f(int x) {\n  const b = x\n  return b\n}";
+    let symbols = result(src, "textDocument/documentSymbol", document());
+    let symbols = symbols.as_array();
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(symbols[0].get("name").as_str(), Some("a"));
+    assert_eq!(range(&symbols[0]), [0, 4, 0, 9]);
+    assert_eq!(symbols[1].get("name").as_str(), Some("f"));
+    assert_eq!(range(&symbols[1]), [1, 3, 4, 1]);
+    let children = symbols[1].get("children").as_array();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].get("name").as_str(), Some("b"));
+}
+
+#[test]
+fn definition() {
+    let src = "let a = 1\nfn f(int x) {\n  return x + a\n}\nf(a)";
+    let target = |line, character| {
+        let json = result(src, "textDocument/definition", at(line, character));
+        (!json.is_null()).then(|| range(&json))
+    };
+    // x
+    assert_eq!(target(2, 9), Some([1, 9, 1, 10]));
+    // a, inside & outside the fn
+    assert_eq!(target(2, 13), Some([0, 4, 0, 5]));
+    assert_eq!(target(4, 2), Some([0, 4, 0, 5]));
+    // f
+    assert_eq!(target(4, 0), Some([1, 3, 1, 4]));
+    // a binding is its own definition
+    assert_eq!(target(0, 4), Some([0, 4, 0, 5]));
+    // nothing there
+    assert_eq!(target(2, 3), None);
+}
+
+#[test]
+fn hover() {
+    let src = "fn f(int x, ...) {\n  return x\n}\nconst y = f(1)";
+    let hover = |line, character| {
+        let json = result(src, "textDocument/hover", at(line, character));
+        json.at(&["contents", "value"]).as_str().map(str::to_owned)
+    };
+    assert_eq!(hover(1, 9).as_deref(), Some("```allua\nparam int x\n```"));
+    assert_eq!(
+        hover(3, 10).as_deref(),
+        Some("```allua\nfn f(int x, ...)\n```")
+    );
+    assert_eq!(hover(3, 6).as_deref(), Some("```allua\nconst y\n```"));
+    assert_eq!(hover(1, 2), None);
+}
+
+#[test]
+fn folding() {
+    let src = "fn f() {\n  /* a\n  b */\n  return 1\n}\nfn g() { }";
+    let ranges = result(src, "textDocument/foldingRange", document());
+    let ranges: Vec<_> = ranges
+        .as_array()
+        .iter()
+        .map(|range| {
+            let line = |key| range.get(key).as_usize().unwrap();
+            (
+                line("startLine"),
+                line("endLine"),
+                range.get("kind").as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(ranges, [(1, 2, Some("comment")), (0, 4, None)]);
+}
+
+#[test]
+fn semantic_tokens() {
+    let src = "let é = \"a\"\n// x\n  fn";
+    let tokens = result(src, "textDocument/semanticTokens/full", document());
+    let data: Vec<_> = tokens
+        .get("data")
+        .as_array()
+        .iter()
+        .map(|n| n.as_usize().unwrap())
+        .collect();
+    #[rustfmt::skip]
+    assert_eq!(data, [
+        0, 0, 3, 0, 0,
+        0, 4, 1, 1, 0,
+        0, 2, 1, 5, 0,
+        0, 2, 3, 2, 0,
+        1, 0, 4, 4, 0,
+        1, 2, 2, 0, 0,
+    ]);
+}
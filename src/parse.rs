@@ -10,10 +10,6 @@
 //
 // should probably add it to other constructs as well
 
-// TODO: add operators
-//
-// should support every operator that rust does
-
 // TODO: add incomplete-expr system
 //
 // A single ident could be interpreted as an incomplete-expr
@@ -24,22 +20,21 @@
 
 // TODO: turn get most ident/rawident parsing to work the same
 
-// TODO: FIX: expr system is broken
-//
-// should be fixed before adding operators.
-// other errors should benefit from this as well
-
 use self::token::*;
 
 use crate::{
-    error::{ErrorMulti, ErrorOnce, LexicalError},
+    error::{ErrorMulti, ErrorOnce, LexicalError, SemanticError},
     lex::*,
     span::{BSpan, TSpan},
     util::*,
 };
 
+pub use incremental::Parsed;
+pub use lua::LuaReader;
 pub use secure::Reader;
 
+pub mod incremental;
+pub mod lua;
 /// a secure module for keeping certain fields safe.
 mod secure;
 #[cfg(test)]
@@ -48,12 +43,48 @@ pub mod token;
 
 pub const EXPECTED_CLOSE: [LexKind; 5] = [Ident, RawIdent, OpenBrace, CloseBrace, Eof];
 pub const EXPECTED: [LexKind; 4] = [Ident, RawIdent, OpenBrace, Eof];
-
-impl<'a> Reader<'a> {
+/// the start of an expr
+pub const EXPECTED_EXPR: [LexKind; 3] = [Ident, RawIdent, LITERAL];
+/// the start of a pattern at the top of an arm
+pub const EXPECTED_PAT: [LexKind; 4] = [Ident, RawIdent, LITERAL, OpenParen];
+/// the start of a call param
+pub const EXPECTED_PARAM: [LexKind; 4] = [Ident, RawIdent, LITERAL, CloseParen];
+
+/// Words with a meaning of their own at the start of a statement
+pub const KEYWORDS: &[&str] = &[
+    "let", "const", "fn", "if", "else", "return", "struct", "enum", "impl", "like", "prelet",
+    "preconst", "predef", "prefn", "comp", "break", "match",
+];
+/// Keywords that start a statement, parsing resumes at one after an error
+pub const SYNC: &[&str] = &[
+    "let", "const", "fn", "if", "return", "struct", "enum", "impl", "like", "prelet", "preconst",
+    "predef", "prefn", "break", "match",
+];
+/// The errors reported by a parse before the rest are dropped
+pub const MAX_ERRORS: usize = 32;
+
+/// The names of the fields of a struct or variant, `None` in a tuple
+type Fields = Vec<Option<Symbol>>;
+
+impl Reader<'_> {
     /// Parse a module
     #[must_use]
     pub fn module(mut self, name: &str) -> (Module, ErrorMulti) {
         while self.next() {}
+        self.finish(name)
+    }
+
+    /// The module parsed so far, closing every block left open
+    fn finish(self, name: &str) -> (Module, ErrorMulti) {
+        let (mut module, mut errors) = self.into_module(name);
+        for err in crate::check::passes(&mut module) {
+            errors.push(err);
+        }
+        (module, errors)
+    }
+
+    /// [`Self::finish`] without the semantic passes
+    fn into_module(self, name: &str) -> (Module, ErrorMulti) {
         let (cursor, mut errors, mut tokens, blocks) = self.into_parts();
 
         for (pos, span) in blocks {
@@ -61,10 +92,26 @@ impl<'a> Reader<'a> {
             errors.push(LexicalError::Unclosed(span));
             tokens.truncate(pos);
         }
-
         (Module::new(name, tokens), errors)
     }
 
+    /// Parse everything, true if a block is still open or the input ended
+    /// in the middle of a statement
+    ///
+    /// A REPL uses this to ask for more input.
+    #[must_use]
+    pub fn incomplete(mut self) -> bool {
+        while self.next() {}
+        let unclosed = self.blocks_left();
+        let (cursor, errors, ..) = self.into_parts();
+        let end = cursor.pos();
+        unclosed
+            || errors
+                .lex
+                .iter()
+                .any(|err| matches!(err, LexicalError::Eof(pos) if *pos == end))
+    }
+
     fn next(&mut self) -> bool {
         let lex = self.cursor.advance();
         match self.next_or_close_brace(lex) {
@@ -84,7 +131,16 @@ impl<'a> Reader<'a> {
         match kind {
             // (?doc)comments or whitespace. skip normal comments
             _ if self.filter_comment_or_whitespace(lex) => (),
-            Ident | RawIdent => self.ident(span),
+            Ident | RawIdent => self.stmt(span, |this| this.ident(span)),
+            Literal { .. } if is_label(lex) => self.stmt(span, |this| {
+                this.tagged(span, false);
+            }),
+            kind if self.starts_expr(kind) => self.stmt(span, |this| {
+                this.rewind(span.from);
+                this.expr_stmt();
+            }),
+            // an empty statement, or the end of one
+            Semi => (),
             OpenBrace => {
                 self.push_block(self.len());
                 self.dummy();
@@ -92,156 +148,158 @@ impl<'a> Reader<'a> {
             // code block end
             CloseBrace => return Z(()),
             Eof => return X(()),
-            _ => self.after_ident(lex),
+            _ => self.stmt(span, |this| this.top_level_expected(span)),
         };
 
         Y(())
     }
 
-    fn after_ident(&mut self, lex: Lexeme) {
-        let (pos, expr) = match self.get_expr() {
-            Ok(out) => out,
-            Err(true) => {
-                self.top_level_expected(lex);
-                return;
+    /// Run `parse` on the statement starting at `span`, recovering if it fails
+    fn stmt(&mut self, span: BSpan, parse: impl FnOnce(&mut Self)) {
+        let (pos, failures) = (self.len(), self.failures());
+        parse(self);
+        if self.failures() > failures {
+            self.recover(pos, span.from);
+            self.set_failures(failures);
+            return;
+        }
+        self.separator();
+    }
+
+    /// A statement must be followed by `;`, a new line, `}` or the end of
+    /// input, unless it ends with a block
+    fn separator(&mut self) {
+        let (lex, span) = self.peek();
+        let end = self.cursor.pos();
+        let src = self.src();
+        if !matches!(lex.kind, Semi | CloseBrace | Eof)
+            && !src[end..span.from].contains('\n')
+            && !src[..end].ends_with('}')
+        {
+            self.push_err(LexicalError::Separator(span));
+        }
+    }
+
+    /// Replace the tokens of a failed statement from `pos` with a
+    /// [`Token::Error`] & skip to a `;`, `}` or keyword that starts the
+    /// next statement. The statement started at the byte `from`.
+    fn recover(&mut self, pos: usize, from: usize) {
+        // a decl without its value is kept, so its name is still declared
+        match self.get_token(pos) {
+            Some(Token::Decl(decl)) if !decl.value => self.discard(pos + 1),
+            _ => self.discard(pos),
+        }
+        let mut depth = self.open_braces(from);
+        let mut to = self.cursor.pos();
+        loop {
+            let (lex, span) = self.peek();
+            match lex.kind {
+                Eof => break,
+                CloseBrace if depth == 0 => break,
+                Ident if depth == 0 && SYNC.contains(&self.str(span)) => break,
+                Semi if depth == 0 => {
+                    self.bump();
+                    to = span.to;
+                    break;
+                }
+                OpenBrace => depth += 1,
+                CloseBrace => depth -= 1,
+                _ => (),
             }
-            Err(false) => return,
-        };
-        // println!("{pos}: {expr:#?}");
+            self.bump();
+            to = span.to;
+        }
+        self.push_token(Token::Error(BSpan::new(from, to)));
+    }
 
-        if (expr.end != pos) && expr.end != self.len() {
-            self.pop_ident();
-            self.top_level_expected(lex);
+    /// the number of `{` left open between the byte `from` & the cursor
+    fn open_braces(&self, from: usize) -> usize {
+        let src = &self.src()[from..self.cursor.pos()];
+        crate::lex::tokenize(src).fold(0, |depth, lex| match lex.kind {
+            OpenBrace => depth + 1,
+            CloseBrace => depth.saturating_sub(1),
+            _ => depth,
+        })
+    }
+
+    /// true if the next lexeme is a keyword that starts a statement
+    fn at_keyword(&self) -> bool {
+        let (lex, span) = self.peek();
+        lex.kind == Ident && SYNC.contains(&self.str(span))
+    }
+
+    /// true if the next lexemes are the `fn (` of a closure
+    fn at_closure(&self) -> bool {
+        let mut cursor = self.cursor.clone();
+        let mut kinds = std::iter::from_fn(|| Some(cursor.advance().kind))
+            .filter(|kind| !matches!(kind, LineComment { .. } | BlockComment { .. } | Whitespace));
+        let (lex, span) = self.peek();
+        lex.kind == Ident && self.str(span) == "fn" && kinds.nth(1) == Some(OpenParen)
+    }
+
+    /// true if a lexeme that was not expected should be left for the next
+    /// statement to start at
+    fn syncs(&self, lex: Lexeme) -> bool {
+        match lex.kind {
+            Semi | CloseBrace => true,
+            Ident => SYNC.contains(&self.str(lex)),
+            _ => false,
+        }
+    }
+
+    /// an error at a keyword where a name was expected, leaving the keyword
+    /// to start the next statement
+    fn keyword_expected<T>(&mut self, expected: impl Into<Vec<LexKind>>) -> Filtered<T> {
+        let (lex, span) = self.peek();
+        self.err_expected(span, expected);
+        Other(lex)
+    }
+
+    /// an assignment or an expr used as a statement, starting at the given ident
+    fn ident_stmt(&mut self, first: BSpan) {
+        let pos = self.len();
+        self.push_ident(first);
+        if !self.postfix(pos).is_correct() {
+            self.truncate(pos);
             return;
         }
 
-        match expr.kind {
-            ExprKind::FnCall(call) => {
-                todo!()
-                // self.after_fn_call(lex, pos, expr.end, call);
-            }
-            ExprKind::Var(name) => match lex.kind {
-                OpenParen => self.parse_fn_call(pos, expr.end, name),
-                _ => self.top_level_expected(lex),
-            },
-            ExprKind::Value(_) => {
-                panic!("values not handled yet");
-            }
+        if self.peek().0.kind == Comma || self.peek_assign_op().is_some() {
+            let span = BSpan::new(first.from, self.cursor.pos());
+            self.assign(pos, span);
+            return;
+        }
+
+        if !self.binary_from(pos, 0).is_correct() {
+            self.truncate(pos);
         }
     }
 
-    fn parse_fn_call(&mut self, pos: usize, end: usize, name: Symbol) {
-        println!("here");
-        let mut comma = true;
-        let out = look_for!(match (self, lex, [Ident, RawIdent, LITERAL], span) {
-            Comma if comma => self.push_err(LexicalError::DupeComma(span)),
-            Comma => comma = true,
-            CloseParen => break ().into(),
-            Ident | RawIdent => {
-                self.ident(span);
-                comma = false;
-            }
-            Literal { kind, suffix_start } if comma => {
-                self.push_expr(Value::new(self.symbol(lex), kind, suffix_start));
-                comma = false;
-            }
-            _ => {
-                // self.after_ident(lex);
-                // println!("other: {lex:#?}");
-                // self.err_expected(span, [Ident, RawIdent, LITERAL]);
-            }
-        });
-        println!("there");
-        match out {
-            Correct(()) => (),
-            InputEnd | Other(_) => {
-                return;
-            }
+    /// an expr used as a statement, that doesn't start with an ident
+    fn expr_stmt(&mut self) {
+        let pos = self.len();
+        if !self.expr().is_correct() {
+            self.truncate(pos);
         }
-        println!("hare");
-
-        let kind = ExprKind::FnCall(FnCall { name, comma: false });
-        let expr = Expr { end, kind };
-        self.set_at(pos, expr);
-    }
-
-    // fn after_fn_call(&mut self, lex: Lexeme, pos: usize, mut end: usize, mut call: FnCall) {
-    //     let mut expr = Expr {
-    //         kind: call.into(),
-    //         end,
-    //     };
-    //
-    //     if let CloseParen = lex.kind {
-    //         self.top_level_expected(lex);
-    //         expr.kind = call.into();
-    //         self.set_at(pos, expr);
-    //         return;
-    //     }
-    //
-    //     match lex.kind {
-    //         Comma if end == pos => {
-    //             self.err_expected(self.span(lex), [Ident, RawIdent, LITERAL, CloseParen]);
-    //             return;
-    //         }
-    //         Comma if call.comma => {
-    //             self.push_err(LexicalError::DupeComma(self.span(lex)));
-    //             return;
-    //         }
-    //         Comma => {
-    //             call.comma = true;
-    //             expr.kind = call.into();
-    //             self.set_at(pos, expr);
-    //             return;
-    //         }
-    //         _ => (),
-    //     }
-    //
-    //     match lex.kind {
-    //         Literal { kind, suffix_start } => {
-    //             self.push_expr(ExprKind::Value(Value::new(
-    //                 self.symbol(lex),
-    //                 kind,
-    //                 suffix_start,
-    //             )));
-    //             end = self.len();
-    //             expr.end = end;
-    //             self.set_at(pos, expr);
-    //         }
-    //         _ => self.top_level_expected(lex),
-    //     }
-    // }
-
-    // OpenParen => {
-    //     let Some(pos) = self.last_ident() else {
-    //         self.top_level_expected(span);
-    //         return Either3::Y(());
-    //     };
-    //
-    //     let ident = match self.get_token(pos) {
-    //         Some(Token::Expr(Expr::Var(token))) => token,
-    //         other => {
-    //             self.push_err(ErrorOnce::Other(format!(
-    //                 "invalid ident received at {pos}: {other:#?}"
-    //             )));
-    //             return Either3::Y(());
-    //         }
-    //     };
-    //
-    //     self.fn_call(ident, false);
-    //     eprintln!("{:#?}", self.get_token(pos));
-    // }
+    }
 
     fn ident(&mut self, span: BSpan) {
         match self.str(span) {
             "let" => {
-                self.decl(DeclKind::Let);
+                self.decl(DeclKind::Let, false);
             }
             "const" => {
-                self.decl(DeclKind::Const);
+                self.decl(DeclKind::Const, false);
             }
-            "fn" => {
-                self.fn_def();
+            "prelet" => {
+                self.decl(DeclKind::Let, true);
             }
+            // `predef` is the same as `preconst`
+            "preconst" | "predef" => {
+                self.decl(DeclKind::Const, true);
+            }
+            "fn" => self.fn_def(false),
+            "prefn" => self.fn_def(true),
             "if" => {
                 self.parse_if();
             }
@@ -250,188 +308,493 @@ impl<'a> Reader<'a> {
                     self.top_level_expected(span);
                 }
             }
-            "return" => {
-                let set_idx = self.dummy();
-                if self.parse_return().is_correct() {
-                    self.set_at(set_idx, Token::Return);
-                } else {
-                    self.truncate(set_idx);
-                };
+            "return" => self.parse_return(),
+            "break" => self.parse_break(),
+            "match" => {
+                self.parse_match(span);
             }
-            _ => self.push_ident(span),
+            "struct" => self.struct_def(),
+            "enum" => self.enum_def(),
+            "impl" => self.impl_def(),
+            "like" => self.like_def(),
+            _ => self.ident_stmt(span),
         }
     }
 
-    /// `let|const` `<name>` `(?= <expr>)`;
-    fn decl(&mut self, kind: DeclKind) -> bool {
-        // get either var-name or type-name
-        let Correct(first) = self.until_ident() else {
-            return false;
+    /// <target>, .. (?<op>)= <value>, ..
+    ///
+    /// The first target has already been parsed at `pos`
+    fn assign(&mut self, pos: usize, first: BSpan) {
+        self.insert_at(pos, Token::Dummy);
+        let mut targets = vec![(pos + 1, first)];
+
+        while self.peek().0.kind == Comma {
+            self.bump();
+            let Correct(from) = self.until_ident() else {
+                self.truncate(pos);
+                return;
+            };
+            let target = self.len();
+            self.push_ident(from);
+            if !self.postfix(target).is_correct() {
+                self.truncate(pos);
+                return;
+            }
+            targets.push((target, BSpan::new(from.from, self.cursor.pos())));
+        }
+
+        let (lex, span) = self.peek();
+        let op = match self.peek_assign_op() {
+            Some((op, len)) if op.is_some() && targets.len() > 1 => {
+                self.bump_glued(len);
+                let span = BSpan::new(span.from, self.cursor.pos());
+                self.err_expected(span, [Eq]);
+                self.truncate(pos);
+                return;
+            }
+            Some((op, len)) => {
+                self.bump_glued(len);
+                op
+            }
+            None => {
+                match lex.kind {
+                    Eof => {
+                        self.bump();
+                        self.err_eof();
+                    }
+                    _ => self.err_expected(span, [Comma, Eq]),
+                }
+                self.truncate(pos);
+                return;
+            }
         };
-        let set_idx = self.dummy();
 
-        let name;
-        let value;
-        let type_name;
+        let values = self.len();
+        loop {
+            if !self.expr().is_correct() {
+                self.truncate(pos);
+                return;
+            }
+            if op.is_some() || self.peek().0.kind != Comma {
+                break;
+            }
+            self.bump();
+        }
 
-        match self.eq_or_ident() {
-            Correct(A(())) => {
-                self.expr();
-                value = is_expr(self.get_token(set_idx + 1));
-                name = self.str(first);
-                type_name = None;
+        for (target, span) in targets {
+            match self.get_token(target) {
+                Some(Token::Expr(Expr {
+                    kind: ExprKind::Var(name),
+                    ..
+                })) => {
+                    if self.decl_kind(name) == Some(DeclKind::Const) {
+                        self.push_err(SemanticError::ConstAssign(span, name));
+                    }
+                }
+                Some(Token::Expr(Expr {
+                    kind: ExprKind::Field(_) | ExprKind::Index,
+                    ..
+                })) => (),
+                _ => self.push_err(LexicalError::AssignTarget(span)),
             }
-            Correct(B(second)) => {
-                if !self.until_eq().is_correct() {
-                    return false;
-                };
-                self.expr();
-                value = is_expr(self.get_token(set_idx + 1));
-                name = self.str(second);
-                type_name = Some(self.symbol(first));
+        }
+
+        let token = Assign {
+            op,
+            targets: TSpan {
+                from: pos + 1,
+                to: values,
+            },
+            values: TSpan {
+                from: values,
+                to: self.len(),
+            },
+        };
+        self.set_at(pos, token);
+    }
+
+    /// The kind of the `let` or `const` named `name` visible at the end of the tokens
+    fn decl_kind(&self, name: Symbol) -> Option<DeclKind> {
+        let mut found = None;
+        let mut i = 0;
+        while let Some(token) = self.get_token(i) {
+            // skip closed scopes
+            i = match token {
+                Token::Decl(decl) if decl.name == name => {
+                    found = Some(decl.kind);
+                    i + 1
+                }
+                Token::FnDefParam(param) if param.name == name => {
+                    found = Some(DeclKind::Let);
+                    i + 1
+                }
+                Token::FnDef(def) => def.tokens.to,
+                Token::Expr(Expr {
+                    kind: ExprKind::Closure(closure),
+                    ..
+                }) => closure.tokens.to,
+                Token::Block(block) => block.tokens.to,
+                Token::Flow(Flow::If(span, el)) => el.map_or(span.to, |el| el.to),
+                _ => i + 1,
+            };
+        }
+        found
+    }
+
+    /// The struct or enum named `name`
+    fn data(&self, name: Symbol) -> Option<Token> {
+        self.data_in(name).map(|(tokens, pos)| tokens[pos])
+    }
+
+    /// The tokens with the struct or enum named `name` & its pos in them,
+    /// those parsed so far, or else the [`types`] of the whole source
+    fn data_in(&self, name: Symbol) -> Option<(&[Token], usize)> {
+        [self.tokens(), self.types()]
+            .into_iter()
+            .find_map(|tokens| {
+                let pos = tokens.iter().position(|token| match token {
+                    Token::Struct(def) => def.name == name,
+                    Token::Enum(def) => def.name == name,
+                    _ => false,
+                })?;
+                Some((tokens, pos))
+            })
+    }
+
+    /// The kind & the field names of the struct `ty`, or of the `variant` of
+    /// the enum `ty`, a tuple's fields have no names
+    fn data_fields(&self, ty: Symbol, variant: Option<Symbol>) -> Option<(VariantKind, Fields)> {
+        let (tokens, pos) = self.data_in(ty)?;
+        let (kind, fields) = match (tokens[pos], variant) {
+            (Token::Struct(def), None) => (VariantKind::Struct, def.fields),
+            (Token::Enum(def), Some(name)) => {
+                let mut i = def.variants.from;
+                loop {
+                    let Some(Token::Variant(variant)) = tokens.get(i) else {
+                        return None;
+                    };
+                    if variant.name == name {
+                        break (variant.kind, variant.fields);
+                    }
+                    i = variant.fields.to;
+                }
             }
-            InputEnd | Other(_) => return false,
+            _ => return None,
+        };
+        let names = tokens[fields.from..fields.to]
+            .iter()
+            .filter_map(|token| match token {
+                Token::Field(field) => Some(field.name),
+                _ => None,
+            });
+        Some((kind, names.collect()))
+    }
+
+    /// `let|const` (?`<type>`) `<name>` `(?= <expr>)`;
+    ///
+    /// The value of a `pre` decl is wrapped in a [`ExprKind::Comp`]
+    fn decl(&mut self, kind: DeclKind, pre: bool) -> bool {
+        let set_idx = self.dummy();
+        let Correct((ty, span)) = self.typed_name() else {
+            self.truncate(set_idx);
+            return false;
         };
+        if !self.until_eq().is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let at = self.len();
+        if !self.decl_value(pre) {
+            self.truncate(at);
+        }
+        let value = self.get_token(at).is_some();
         let decl = Decl {
             kind,
-            type_name,
-            name: name.into(),
+            ty,
+            name: self.symbol(span),
+            span,
             value,
+            pre,
         };
+        if pre && value {
+            self.wrap_expr(at, ExprKind::Comp(None));
+        }
         self.set_at(set_idx, decl);
         true
     }
 
-    // /// (..) | ..)
-    // fn fn_call(&mut self, s: impl Into<AsStr<'a>>, check_paren: bool) {
-    //     if check_paren && !self.open_paren().is_correct() {
-    //         return;
-    //     }
-    //
-    //     let from = self.dummy();
-    //
-    //     let mut comma = true;
-    //     loop {
-    //         match self.call_params(comma) {
-    //             Correct(None) => break,
-    //             Correct(Some(found)) => comma = found,
-    //             InputEnd | OtherToken(_) => {
-    //                 self.truncate(from);
-    //                 return;
-    //             }
-    //         };
-    //     }
-    //
-    //     let set_idx = from;
-    //     let to = self.len();
-    //     let from = from + 1;
-    //     self.set_at(set_idx, Expr::FnCall(self.symbol(s), TSpan { from, to }));
-    // }
-    // /// ..)
-    // ///
-    // /// `true` = `CloseParen`
-    // fn call_params(&mut self, mut comma_found: bool) -> Filtered<Option<bool>> {
-    //     let err = |comma_found: bool| match comma_found {
-    //         true => vec![CloseParen, Ident, RawIdent, LITERAL],
-    //         false => vec![Comma, CloseParen, Ident, RawIdent, LITERAL],
-    //     };
-    //     look_for!(match (self, token, err(comma_found), span) {
-    //         CloseParen => break None.into(),
-    //         Comma if comma_found => self.push_err(LexicalError::DupeComma(span)),
-    //         Comma => comma_found = true,
-    //         Ident | RawIdent => break self.call_param_ident(self.span(token)),
-    //         Literal { kind, suffix_start } => {
-    //             self.push_token(Value::new(self.symbol(token), kind, suffix_start));
-    //             break Some(false).into();
-    //         }
-    //     })
-    // }
-    //
-    // fn call_param_ident(&mut self, ident: BSpan) -> Filtered<Option<bool>> {
-    //     look_for!(match (self, token, [OpenParen, CloseParen, Comma]) {
-    //         CloseParen => {
-    //             self.push_token(Expr::Var(self.symbol(ident)));
-    //             break None.into();
-    //         }
-    //         OpenParen => {
-    //             self.fn_call(ident, false);
-    //             break Some(false).into();
-    //         }
-    //         Comma => {
-    //             self.push_token(Expr::Var(self.symbol(ident)));
-    //             break Some(true).into();
-    //         }
-    //     })
-    // }
-
-    /// return ..
-    fn parse_return(&mut self) -> Filtered<()> {
-        look_for!(match (self, lex, [Ident, RawIdent, LITERAL], span) {
-            // Ident | RawIdent => {
-            //     break look_for!(match (self, after, [CloseParen]) {
-            //         OpenParen => {
-            //             self.fn_call(span, false);
-            //             break ().into();
-            //         }
-            //     });
-            // }
-            Literal { kind, suffix_start } => {
-                self.push_expr(Value::new(self.symbol(lex), kind, suffix_start));
-                break ().into();
-            }
-        })
+    /// `<expr>` | '`<label>`: { (?`<token>`?,) }
+    ///
+    /// The value of a `pre` decl can't start with a block, `if` or `match`
+    fn decl_value(&mut self, pre: bool) -> bool {
+        let (lex, span) = self.peek();
+        if !(is_label(lex) || pre && self.starts_value(lex, span)) {
+            return self.expr().is_correct();
+        }
+        self.bump();
+        if pre {
+            self.err_expected(span, EXPECTED_EXPR);
+            return false;
+        }
+        self.tagged(span, true)
     }
 
-    /// if <cond> {<token>}
+    /// { (?`<token>`?,) } used as a value
     ///
-    /// false if parse not success
-    fn parse_if(&mut self) -> bool {
+    /// The `{` at `open` has already been parsed
+    fn value_block(&mut self, open: BSpan) -> bool {
         let set_idx = self.dummy();
+        if !self.value_body() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let block = Block {
+            span: BSpan::new(open.from, self.cursor.pos()),
+            tokens: TSpan {
+                from: set_idx + 1,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, block);
+        true
+    }
 
-        let open = look_for!(match (self, lex, [Ident, RawIdent]) {
-            OpenBrace => break true.into(),
-            Ident | RawIdent => {
-                break self
-                    .ident_until(
-                        lex,
-                        |lex| match lex.kind {
-                            OpenBrace => Some(()),
-                            _ => None,
-                        },
-                        &[OpenBrace, Eof],
-                    )
-                    .map(|_| true);
-            }
-        });
-
-        let Correct(open) = open else {
+    /// `if` `<cond>` { .. } `else` { .. } used as a value, the `else` can't
+    /// be left out
+    ///
+    /// The `if` at `span` has already been parsed
+    fn value_if(&mut self, span: BSpan) -> bool {
+        let set_idx = self.dummy();
+        if !self.cond(Self::expr).is_correct() || !self.open_brace().is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let from = self.len();
+        if !self.value_body() {
             self.truncate(set_idx);
             return false;
+        }
+        let then = TSpan {
+            from,
+            to: self.len(),
         };
 
-        if !is_expr(self.get_token(set_idx + 1)) {
+        let (lex, else_span) = self.peek();
+        if lex.kind != Ident || self.str(else_span) != "else" {
+            let span = BSpan::new(span.from, self.cursor.pos());
+            self.push_err(LexicalError::MissingElse(span));
+            self.set_at(set_idx, Flow::If(then, None));
+            return true;
+        }
+        self.bump();
+
+        let from = self.len();
+        let parsed = match self.open_brace_or_ident() {
+            Correct(A(())) => self.value_body(),
+            Correct(B(ident)) if self.str(ident) == "if" => self.value_if(ident),
+            Correct(B(ident)) => {
+                self.top_level_expected(ident);
+                false
+            }
+            InputEnd | Other(_) => false,
+        };
+        if !parsed {
             self.truncate(set_idx);
             return false;
         }
+        let el = TSpan {
+            from,
+            to: self.len(),
+        };
+        self.set_at(set_idx, Flow::If(then, Some(el)));
+        true
+    }
+
+    /// ..} used as a value, its tail is wrapped in a [`Token::Tail`]
+    fn value_body(&mut self) -> bool {
+        let from = self.len();
+        let out = self.body();
+        if out {
+            self.wrap_tail(from, self.len(), Token::Tail);
+        }
+        out
+    }
+
+    /// Wrap the tail of the body `from..to` in `wrap`, an expr statement
+    /// ending it without a `;`, or the tails of a block, `if` or `match`
+    /// ending it
+    fn wrap_tail(&mut self, from: usize, to: usize, wrap: fn(TSpan) -> Token) {
+        let mut last = None;
+        let mut pos = from;
+        while pos < to {
+            last = Some(pos);
+            pos = end_of(self.tokens(), pos);
+        }
+        let Some(pos) = last else {
+            return;
+        };
+        match self.tokens()[pos] {
+            Token::Expr(expr) if !self.semi_after(expr.span.to) => {
+                self.insert_at(pos, Token::Dummy);
+                let values = TSpan {
+                    from: pos + 1,
+                    to: to + 1,
+                };
+                self.set_at(pos, wrap(values));
+            }
+            // the body of an arm
+            Token::Tail(values) => self.set_at(pos, wrap(values)),
+            Token::Block(block) => self.wrap_tail(block.tokens.from, block.tokens.to, wrap),
+            Token::Tagged(tagged) => self.wrap_tail(tagged.tokens.from, tagged.tokens.to, wrap),
+            Token::Flow(Flow::If(then, el)) => {
+                // the later body first, wrapping one shifts what follows it
+                if let Some(el) = el {
+                    self.wrap_tail(el.from, el.to, wrap);
+                }
+                self.wrap_tail(then.from, then.to, wrap);
+            }
+            Token::Match(def) => {
+                let mut arms = Vec::new();
+                let mut arm = def.arms.from;
+                while arm < def.arms.to {
+                    arms.push(arm);
+                    arm = end_of(self.tokens(), arm);
+                }
+                for arm in arms.into_iter().rev() {
+                    if let Token::Arm(arm) = self.tokens()[arm] {
+                        self.wrap_tail(arm.body.from, arm.body.to, wrap);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// true if a `;` ends the statement ending at the byte `pos`
+    fn semi_after(&self, pos: usize) -> bool {
+        let mut cursor = Cursor::at(self.src(), pos);
+        loop {
+            match cursor.advance().kind {
+                // a paren around the whole expr isn't in its span
+                LineComment { .. } | BlockComment { .. } | Whitespace | CloseParen => (),
+                kind => return kind == Semi,
+            }
+        }
+    }
 
-        // expect open brace if not found
-        if !open && !self.open_brace().is_correct() {
+    /// '`<label>`: { (?`<token>`?,) }
+    ///
+    /// The `'` at `quote` has already been parsed, the tail of a tagged block
+    /// used as a `value` is wrapped like a block's
+    fn tagged(&mut self, quote: BSpan, value: bool) -> bool {
+        let Some((label, span)) = self.label(quote) else {
+            return false;
+        };
+        if !self.colon().is_correct() || !self.open_brace().is_correct() {
+            return false;
+        }
+        let set_idx = self.dummy();
+        let parsed = if value {
+            self.value_body()
+        } else {
+            self.body()
+        };
+        if !parsed {
             self.truncate(set_idx);
             return false;
+        }
+        let tokens = TSpan {
+            from: set_idx + 1,
+            to: self.len(),
+        };
+        self.set_at(
+            set_idx,
+            Tagged {
+                label,
+                span,
+                tokens,
+            },
+        );
+        true
+    }
+
+    /// `break` '`<label>` (?`<expr>`?,)
+    ///
+    /// Like a return, a break without values must be followed by `}` or the
+    /// end of input
+    fn parse_break(&mut self) {
+        let (lex, quote) = self.peek();
+        self.bump();
+        if !is_label(lex) {
+            self.err_expected(quote, [LABEL]);
+            return;
+        }
+        let Some((label, span)) = self.label(quote) else {
+            return;
+        };
+        let set_idx = self.dummy();
+        if !matches!(self.peek().0.kind, CloseBrace | Semi | Eof) && !self.expr_list().is_correct()
+        {
+            self.truncate(set_idx);
+            return;
+        }
+        let values = TSpan {
+            from: set_idx + 1,
+            to: self.len(),
+        };
+        self.set_at(
+            set_idx,
+            Break {
+                label,
+                span,
+                values,
+            },
+        );
+    }
+
+    /// the name of a label & its span, including the `'` at `quote`
+    fn label(&mut self, quote: BSpan) -> Option<(Symbol, BSpan)> {
+        let lex = self.cursor.advance();
+        if lex.kind != Ident {
+            self.err_expected(lex, [Ident]);
+            return None;
+        }
+        let span = BSpan::new(quote.from, self.span(lex).to);
+        Some((self.symbol(lex), span))
+    }
+
+    /// return (?`<expr>`?,)
+    ///
+    /// A return without values must be followed by `;`, `}` or the end of input
+    fn parse_return(&mut self) {
+        let set_idx = self.dummy();
+        if !matches!(self.peek().0.kind, CloseBrace | Semi | Eof) && !self.expr_list().is_correct()
+        {
+            self.truncate(set_idx);
+            return;
+        }
+        let values = TSpan {
+            from: set_idx + 1,
+            to: self.len(),
         };
+        self.set_at(set_idx, Token::Return(values));
+    }
+
+    /// if <cond> {<token>}
+    ///
+    /// false if parse not success
+    fn parse_if(&mut self) -> bool {
+        let set_idx = self.dummy();
+
+        if !self.cond(Self::expr).is_correct() || !self.open_brace().is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
 
         let token_start = self.len();
-        loop {
-            let lex = self.cursor.advance();
-            match self.next_or_close_brace(lex) {
-                X(()) => {
-                    self.truncate(set_idx);
-                    self.err_eof();
-                    return false;
-                }
-                Y(()) => (),
-                Z(()) => break,
-            };
+        if !self.body() {
+            self.truncate(set_idx);
+            return false;
         }
 
         let token = Flow::If(
@@ -443,7 +806,7 @@ impl<'a> Reader<'a> {
         );
         self.push_flow(set_idx);
         self.set_at(set_idx, token);
-        true.into()
+        true
     }
 
     /// false if parse not success
@@ -494,17 +857,9 @@ impl<'a> Reader<'a> {
         }
 
         let token_start = self.len();
-        loop {
-            let lex = self.cursor.advance();
-            match self.next_or_close_brace(lex) {
-                X(()) => {
-                    self.truncate(token_start);
-                    self.err_eof();
-                    return false;
-                }
-                Y(()) => (),
-                Z(()) => break,
-            };
+        if !self.body() {
+            self.truncate(token_start);
+            return false;
         }
 
         let token = Flow::If(
@@ -518,57 +873,1048 @@ impl<'a> Reader<'a> {
         true
     }
 
-    /// `fn` (?`<type>`) `<name>` ((?`<param>`?,)) { (?`<token>`?,) }
-    fn fn_def(&mut self) {
-        let Correct(first) = self.until_ident() else {
-            return;
-        };
-
-        let (name, type_name) = match self.open_paren_or_ident() {
-            Correct(A(())) => (first, None),
-            Correct(B(span)) => match self.open_paren() {
-                Correct(()) => (span, Some(first)),
-                InputEnd | Other(_) => return,
-            },
-            InputEnd | Other(_) => return,
+    /// `match` `<expr>` (?, `<expr>`) { (?`<arm>`?,) }
+    ///
+    /// The `match` at `span` has already been parsed. The comma after an arm
+    /// with a block body may be left out.
+    fn parse_match(&mut self, span: BSpan) -> bool {
+        let set_idx = self.dummy();
+        if !self.cond(Self::expr_list).is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let span = BSpan::new(span.from, self.cursor.pos());
+        if !self.open_brace().is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let from = self.len();
+        // whether an arm may start & whether the last arm's body was a block
+        let (mut comma, mut block) = (true, false);
+        let out = loop {
+            let (lex, span) = self.peek();
+            match lex.kind {
+                CloseBrace => {
+                    self.bump();
+                    break Correct(());
+                }
+                Comma if !comma || block => {
+                    self.bump();
+                    (comma, block) = (true, false);
+                }
+                Eof => {
+                    self.bump();
+                    self.err_eof();
+                    break InputEnd;
+                }
+                _ if comma => match self.arm() {
+                    Correct(is_block) => (comma, block) = (is_block, is_block),
+                    other => break other.map(|_| ()),
+                },
+                _ => {
+                    self.err_expected(span, [Comma, CloseBrace]);
+                    break Other(lex);
+                }
+            }
+        };
+        if !out.is_correct() {
+            self.truncate(set_idx);
+            return false;
+        }
+        let arms = TSpan {
+            from,
+            to: self.len(),
         };
+        self.set_at(set_idx, Match { span, arms });
+        true
+    }
 
+    /// `<pat>` (?| `<pat>`) (?`if` `<expr>`) => `<expr>` | { (?`<token>`?,) }
+    ///
+    /// `true` if the body is a block
+    fn arm(&mut self) -> Filtered<bool> {
         let set_idx = self.dummy();
+        let from = self.peek().1.from;
         loop {
-            match self.def_params() {
-                Correct(true) => break,
-                Correct(false) => (),
-                Other(_) | InputEnd => {
-                    self.truncate(set_idx);
-                    return;
+            let out = self.pattern(true);
+            if !out.is_correct() {
+                return out.map(|()| false);
+            }
+            if self.peek().0.kind != Or {
+                break;
+            }
+            self.bump();
+        }
+        let span = BSpan::new(from, self.cursor.pos());
+        let pats = TSpan {
+            from: set_idx + 1,
+            to: self.len(),
+        };
+
+        let (lex, guard) = self.peek();
+        if lex.kind == Ident && self.str(guard) == "if" {
+            self.bump();
+            let out = self.expr();
+            if !out.is_correct() {
+                return out.map(|()| false);
+            }
+        }
+        let (lex, arrow) = self.peek();
+        match self.peek_glued() {
+            [Eq, Gt, _] => self.bump_glued(2),
+            [Eof, ..] => {
+                self.bump();
+                self.err_eof();
+                return InputEnd;
+            }
+            _ => {
+                self.push_err(LexicalError::Syntax(arrow, "`=>`"));
+                return Other(lex);
+            }
+        }
+
+        let body = self.len();
+        let block = self.peek().0.kind == OpenBrace;
+        if block {
+            self.bump();
+            if !self.value_body() {
+                return InputEnd;
+            }
+        } else {
+            let out = self.expr();
+            if !out.is_correct() {
+                return out.map(|()| false);
+            }
+            self.insert_at(body, Token::Dummy);
+            let values = TSpan {
+                from: body + 1,
+                to: self.len(),
+            };
+            self.set_at(body, Token::Tail(values));
+        }
+        let body = TSpan {
+            from: body,
+            to: self.len(),
+        };
+        self.set_at(set_idx, Arm { span, pats, body });
+        Correct(block)
+    }
+
+    /// `_` | `<name>` | `<literal>` | (`<pat>`, ..) | `<struct>` { .. } |
+    /// `<enum>`.`<variant>` (?(`<pat>`, ..) | { .. })
+    ///
+    /// A tuple is only allowed at the `top` of an arm
+    fn pattern(&mut self, top: bool) -> Filtered<()> {
+        let pos = self.dummy();
+        let (lex, span) = self.peek();
+        let kind = match lex.kind {
+            Ident | RawIdent => {
+                self.bump();
+                let name = self.symbol(lex);
+                match (name.as_str(), self.data(name), self.peek().0.kind) {
+                    ("_", ..) => PatKind::Wild,
+                    ("true", ..) => PatKind::Const(Const::Bool(true)),
+                    ("false", ..) => PatKind::Const(Const::Bool(false)),
+                    ("nil", ..) => PatKind::Const(Const::Nil),
+                    (_, Some(Token::Struct(def)), OpenBrace) => {
+                        self.bump();
+                        let construct = Construct {
+                            ty: def.name,
+                            variant: None,
+                            kind: VariantKind::Struct,
+                        };
+                        let fields = self.data_fields(def.name, None).map(|(_, fields)| fields);
+                        let out = self.pat_fields(construct, fields.as_deref());
+                        if !out.is_correct() {
+                            return out;
+                        }
+                        PatKind::Data(construct)
+                    }
+                    (_, Some(Token::Enum(def)), Dot) => match self.variant_pat(span, def) {
+                        Correct(construct) => PatKind::Data(construct),
+                        InputEnd => return InputEnd,
+                        Other(lex) => return Other(lex),
+                    },
+                    _ => PatKind::Bind(name),
+                }
+            }
+            Literal { .. } if !is_label(lex) => {
+                self.bump();
+                self.pat_const(lex, false)
+            }
+            Minus
+                if matches!(
+                    self.peek_glued(),
+                    [
+                        _,
+                        Literal {
+                            kind: LiteralKind::Int { .. } | LiteralKind::Float { .. },
+                            ..
+                        },
+                        _
+                    ]
+                ) =>
+            {
+                self.bump();
+                let lex = self.cursor.advance();
+                self.pat_const(lex, true)
+            }
+            OpenParen if top => {
+                self.bump();
+                let out = self.pat_list();
+                if !out.is_correct() {
+                    return out.map(|_| ());
                 }
+                PatKind::Tuple
+            }
+            Eof => {
+                self.bump();
+                self.err_eof();
+                return InputEnd;
+            }
+            _ => {
+                if top {
+                    self.err_expected(span, EXPECTED_PAT);
+                } else {
+                    self.err_expected(span, EXPECTED_EXPR);
+                }
+                if !self.syncs(lex) {
+                    self.bump();
+                }
+                return Other(lex);
+            }
+        };
+        let pat = Pat {
+            end: self.len(),
+            kind,
+            span: BSpan::new(span.from, self.cursor.pos()),
+        };
+        self.set_at(pos, pat);
+        Correct(())
+    }
+
+    /// `.<variant>` (?(`<pat>`, ..) | { (?`<name>` (?= `<pat>`)?,) })
+    ///
+    /// The enum `def` at `from` has already been parsed.
+    fn variant_pat(&mut self, from: BSpan, def: Enum) -> Filtered<Construct> {
+        self.bump();
+        let name = match self.until_ident() {
+            Correct(span) => span,
+            InputEnd => return InputEnd,
+            Other(lex) => return Other(lex),
+        };
+        let variant = self.symbol(name);
+        let found = self.data_fields(def.name, Some(variant));
+        let (kind, fields) = if let Some((kind, fields)) = found {
+            (kind, Some(fields))
+        } else {
+            self.push_err(SemanticError::NoVariant(name, def.name, variant));
+            // keep parsing whatever follows
+            let kind = match self.peek().0.kind {
+                OpenParen => VariantKind::Tuple,
+                OpenBrace => VariantKind::Struct,
+                _ => VariantKind::Unit,
             };
+            (kind, None)
+        };
+        let construct = Construct {
+            ty: def.name,
+            variant: Some(variant),
+            kind,
+        };
+        let out = match kind {
+            VariantKind::Unit => Correct(()),
+            VariantKind::Tuple => {
+                let out = self.open_paren();
+                if !out.is_correct() {
+                    return out.map(|()| construct);
+                }
+                let Correct(found) = self.pat_list() else {
+                    return InputEnd;
+                };
+                let expected = fields.as_ref().map_or(found, Vec::len);
+                if found != expected {
+                    let span = BSpan::new(from.from, self.cursor.pos());
+                    self.push_err(SemanticError::FieldCount {
+                        span,
+                        expected,
+                        found,
+                    });
+                }
+                Correct(())
+            }
+            VariantKind::Struct => {
+                let out = self.open_brace();
+                if !out.is_correct() {
+                    return out.map(|()| construct);
+                }
+                self.pat_fields(construct, fields.as_deref())
+            }
+        };
+        out.map(|()| construct)
+    }
+
+    /// `<pat>`, ..)
+    ///
+    /// The number of pats
+    fn pat_list(&mut self) -> Filtered<usize> {
+        let mut found = 0;
+        let mut comma = true;
+        loop {
+            let (lex, span) = self.peek();
+            match lex.kind {
+                CloseParen => {
+                    self.bump();
+                    break Correct(found);
+                }
+                Comma if !comma => {
+                    self.bump();
+                    comma = true;
+                }
+                _ if comma => {
+                    let out = self.pattern(false);
+                    if !out.is_correct() {
+                        break out.map(|()| found);
+                    }
+                    found += 1;
+                    comma = false;
+                }
+                Eof => {
+                    self.bump();
+                    self.err_eof();
+                    break InputEnd;
+                }
+                _ => {
+                    self.err_expected(span, [Comma, CloseParen]);
+                    break Other(lex);
+                }
+            }
+        }
+    }
+
+    /// (?`<name>` (?= `<pat>`)?,) }
+    ///
+    /// Each name must be one of `fields`, if the declaration was found.
+    fn pat_fields(
+        &mut self,
+        construct: Construct,
+        fields: Option<&[Option<Symbol>]>,
+    ) -> Filtered<()> {
+        let ty = construct.variant.unwrap_or(construct.ty);
+        let mut seen = Vec::new();
+        let mut comma = true;
+        look_for!(match (self, lex, [Ident, RawIdent, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent if comma => {
+                comma = false;
+                let name = self.symbol(first);
+                let field = self.dummy();
+                if self.peek().0.kind == Eq {
+                    self.bump();
+                    let out = self.pattern(false);
+                    if !out.is_correct() {
+                        break out;
+                    }
+                } else {
+                    self.push_token(Pat {
+                        end: field + 2,
+                        kind: PatKind::Bind(name),
+                        span: first,
+                    });
+                }
+                let pat = Pat {
+                    end: self.len(),
+                    kind: PatKind::Field(name),
+                    span: first,
+                };
+                self.set_at(field, pat);
+
+                let declared = fields.is_none_or(|fields| fields.contains(&Some(name)));
+                if !declared {
+                    self.push_err(SemanticError::NoField(first, ty, name));
+                } else if seen.contains(&name) {
+                    self.push_err(SemanticError::DupeField(first, name));
+                }
+                seen.push(name);
+            }
+        })
+    }
+
+    /// The literal `lex` of a pattern, negated if `neg`
+    fn pat_const(&mut self, lex: Lexeme, neg: bool) -> PatKind {
+        let span = self.span(lex);
+        let Literal { kind, suffix_start } = lex.kind else {
+            unreachable!("non literal pattern {lex:?}");
+        };
+        let lit = Value::new(self.symbol(lex), kind, suffix_start);
+        let Ok(value) = crate::runtime::Value::from_literal(lit) else {
+            self.push_err(LexicalError::Malformed(span, "literal"));
+            return PatKind::Wild;
+        };
+        match value.to_const() {
+            Some(Const::Int(i)) if neg => PatKind::Const(Const::Int(i.wrapping_neg())),
+            Some(Const::Float(f)) if neg => {
+                PatKind::Const(Const::Float((-f64::from_bits(f)).to_bits()))
+            }
+            Some(value) => PatKind::Const(value),
+            None => {
+                self.push_err(SemanticError::NotConst(span, value.type_name()));
+                PatKind::Wild
+            }
         }
+    }
+
+    /// The return type, name & generic params of a fn, up to its `(`
+    ///
+    /// `fn` has already been parsed, the type & params are pushed as tokens
+    fn fn_name(&mut self) -> Option<(BSpan, Option<TSpan>)> {
+        let from = self.len();
+        let ty = if self.fn_typed() {
+            if !self.ty().is_correct() {
+                return None;
+            }
+            Some(TSpan {
+                from,
+                to: self.len(),
+            })
+        } else {
+            None
+        };
+        let Correct(name) = self.until_ident() else {
+            return None;
+        };
+        if !self.generics().is_correct() {
+            return None;
+        }
+        self.open_paren().is_correct().then_some((name, ty))
+    }
+
+    /// true if the next lexemes of a fn are its return type, not its name
+    fn fn_typed(&self) -> bool {
+        let (lex, span) = self.peek();
+        match lex.kind {
+            OpenParen => true,
+            Ident if self.str(span) == "fn" => true,
+            Ident | RawIdent => {
+                let mut cursor = Cursor::at(self.src(), span.to);
+                match peek_kind(&mut cursor) {
+                    // `<name><<generic>, ..>(` or `<type><<arg>, ..> <name>`
+                    Lt => after_type_args(cursor) != Some(OpenParen),
+                    Ident | RawIdent | Dot | Question => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// `fn|prefn` (?`<type>`) `<name>` (?<`<generic>`?,>) ((?`<param>`?,))
+    /// { (?`<token>`?,) }
+    fn fn_def(&mut self, pre: bool) {
+        let set_idx = self.dummy();
+        let Some((name, ty)) = self.fn_name() else {
+            self.truncate(set_idx);
+            return;
+        };
+
+        let param_start = self.len();
+        let Correct(variadic) = self.def_param_list() else {
+            self.truncate(set_idx);
+            return;
+        };
         let param_end = self.len();
 
+        if !self.open_brace().is_correct() || !self.fn_body(variadic) {
+            self.truncate(set_idx);
+            return;
+        };
+
+        let token = FnDef {
+            name: self.symbol(name),
+            span: name,
+            ty,
+            variadic,
+            pre,
+            params: TSpan {
+                from: param_start,
+                to: param_end,
+            },
+            tokens: TSpan {
+                from: param_end,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+    }
+
+    /// `impl` `<type>` (?`like` `<like>`) { (?`<fn>`) }
+    fn impl_def(&mut self) {
+        let Correct(ty) = self.until_ident() else {
+            return;
+        };
+        let like = match self.open_brace_or_ident() {
+            Correct(A(())) => None,
+            Correct(B(span)) if self.str(span) == "like" => {
+                let Correct(like) = self.until_ident() else {
+                    return;
+                };
+                if !self.open_brace().is_correct() {
+                    return;
+                }
+                Some(self.symbol(like))
+            }
+            Correct(B(span)) => {
+                self.err_expected(span, [OpenBrace]);
+                return;
+            }
+            InputEnd | Other(_) => return,
+        };
+        let set_idx = self.dummy();
+        let out = look_for!(match (self, lex, [Ident, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Ident if self.str(first) == "fn" => self.fn_def(false),
+        });
+        if !out.is_correct() {
+            self.truncate(set_idx);
+            return;
+        }
+        let token = Impl {
+            ty: self.symbol(ty),
+            span: ty,
+            like,
+            methods: TSpan {
+                from: set_idx + 1,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+    }
+
+    /// `like` `<name>` { (?`<fn sig>`?;) }
+    fn like_def(&mut self) {
+        let Correct(name) = self.until_ident() else {
+            return;
+        };
         if !self.open_brace().is_correct() {
+            return;
+        }
+        let set_idx = self.dummy();
+        let out = look_for!(match (self, lex, [Ident, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Semi => (),
+            Ident if self.str(first) == "fn" => {
+                if !self.fn_sig() {
+                    break InputEnd;
+                }
+            }
+        });
+        if !out.is_correct() {
             self.truncate(set_idx);
             return;
+        }
+        let token = Like {
+            name: self.symbol(name),
+            span: name,
+            sigs: TSpan {
+                from: set_idx + 1,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+    }
+
+    /// `fn` (?`<type>`) `<name>` (?<`<generic>`?,>) ((?`<param>`?,))
+    ///
+    /// false if parse not success
+    fn fn_sig(&mut self) -> bool {
+        let set_idx = self.dummy();
+        let Some((name, ty)) = self.fn_name() else {
+            self.truncate(set_idx);
+            return false;
+        };
+        let param_start = self.len();
+        let Correct(variadic) = self.def_param_list() else {
+            self.truncate(set_idx);
+            return false;
+        };
+        let token = FnSig {
+            name: self.symbol(name),
+            span: name,
+            ty,
+            variadic,
+            params: TSpan {
+                from: param_start,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+        true
+    }
+
+    /// `struct` `<name>` (?<`<generic>`?,>) { (?`<field>`?,) }
+    fn struct_def(&mut self) {
+        let Correct(name) = self.until_ident() else {
+            return;
+        };
+        let set_idx = self.dummy();
+        let Correct(generics) = self.generics() else {
+            self.truncate(set_idx);
+            return;
+        };
+        if !self.open_brace().is_correct() || !self.fields().is_correct() {
+            self.truncate(set_idx);
+            return;
+        }
+        let token = Struct {
+            name: self.symbol(name),
+            span: name,
+            generics,
+            fields: TSpan {
+                from: generics.to,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+    }
+
+    /// `enum` `<name>` (?<`<generic>`?,>) { (?`<variant>`?,) }
+    fn enum_def(&mut self) {
+        let Correct(name) = self.until_ident() else {
+            return;
+        };
+        let set_idx = self.dummy();
+        let Correct(generics) = self.generics() else {
+            self.truncate(set_idx);
+            return;
+        };
+        if !self.open_brace().is_correct() {
+            self.truncate(set_idx);
+            return;
+        }
+        let from = self.len();
+        let mut comma = true;
+        let out = look_for!(match (self, lex, [Ident, RawIdent, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent if comma => {
+                comma = false;
+                let name = self.symbol(first);
+                let dupe = (from..self.len()).any(|i| {
+                    matches!(self.get_token(i), Some(Token::Variant(variant)) if variant.name == name)
+                });
+                if dupe {
+                    self.push_err(SemanticError::DupeVariant(first, name));
+                }
+                let out = self.variant_def(first);
+                if !out.is_correct() {
+                    break out;
+                }
+            }
+        });
+        if !out.is_correct() {
+            self.truncate(set_idx);
+            return;
+        }
+        let token = Enum {
+            name: self.symbol(name),
+            span: name,
+            generics,
+            variants: TSpan {
+                from: generics.to,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+    }
+
+    /// `<name>` (?(`<type>`?,) | { (?`<field>`?,) })
+    fn variant_def(&mut self, name: BSpan) -> Filtered<()> {
+        let set_idx = self.dummy();
+        let (kind, out) = match self.peek().0.kind {
+            OpenParen => {
+                self.bump();
+                (VariantKind::Tuple, self.tuple_fields())
+            }
+            OpenBrace => {
+                self.bump();
+                (VariantKind::Struct, self.fields())
+            }
+            _ => (VariantKind::Unit, Correct(())),
+        };
+        let token = Variant {
+            name: self.symbol(name),
+            span: name,
+            kind,
+            fields: TSpan {
+                from: set_idx + 1,
+                to: self.len(),
+            },
+        };
+        self.set_at(set_idx, token);
+        out
+    }
+
+    /// (?`<type>`) `<name>`, ..}
+    fn fields(&mut self) -> Filtered<()> {
+        let from = self.len();
+        let mut comma = true;
+        look_for!(match (self, lex, [Ident, RawIdent, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent | OpenParen if comma => {
+                comma = false;
+                self.rewind(first.from);
+                let pos = self.dummy();
+                let (ty, span) = match self.typed_name() {
+                    Correct(typed) => typed,
+                    InputEnd => break InputEnd,
+                    Other(lex) => break Other(lex),
+                };
+                let name = self.symbol(span);
+                let dupe = (from..pos).any(|i| {
+                    matches!(self.get_token(i), Some(Token::Field(field)) if field.name == Some(name))
+                });
+                if dupe {
+                    self.push_err(SemanticError::DupeFieldDecl(span, name));
+                }
+                let field = Field {
+                    ty,
+                    name: Some(name),
+                    span,
+                };
+                self.set_at(pos, field);
+            }
+        })
+    }
+
+    /// `<type>`, ..)
+    fn tuple_fields(&mut self) -> Filtered<()> {
+        let mut comma = true;
+        look_for!(match (self, lex, [Ident, RawIdent, CloseParen], first) {
+            CloseParen => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent | OpenParen if comma => {
+                comma = false;
+                self.rewind(first.from);
+                let pos = self.dummy();
+                let out = self.ty();
+                if !out.is_correct() {
+                    break out;
+                }
+                let field = Field {
+                    ty: Some(TSpan {
+                        from: pos + 1,
+                        to: self.len(),
+                    }),
+                    name: None,
+                    span: BSpan::new(first.from, self.cursor.pos()),
+                };
+                self.set_at(pos, field);
+            }
+        })
+    }
+
+    /// (?`<type>`) `<name>`, the type is pushed as tokens
+    ///
+    /// A name is a type if another name, `<`, `.` or `?` follows it.
+    fn typed_name(&mut self) -> Filtered<(Option<TSpan>, BSpan)> {
+        let from = self.len();
+        let (lex, span) = self.peek();
+        let out = if lex.kind == OpenParen || (lex.kind == Ident && self.str(span) == "fn") {
+            self.ty()
+        } else {
+            let first = match self.until_ident() {
+                Correct(first) => first,
+                InputEnd => return InputEnd,
+                Other(lex) => return Other(lex),
+            };
+            let typed = match self.peek().0.kind {
+                Lt | Dot | Question => true,
+                Ident | RawIdent => !self.at_keyword(),
+                _ => false,
+            };
+            if !typed {
+                return Correct((None, first));
+            }
+            self.ty_from(first)
         };
+        if !out.is_correct() {
+            return out.map(|()| (None, span));
+        }
+        let ty = TSpan {
+            from,
+            to: self.len(),
+        };
+        self.until_ident().map(|name| (Some(ty), name))
+    }
+
+    /// `<path>` (?<`<type>`?,>) | (`<type>`?,) | `fn` (?`<type>`)(`<type>`?,),
+    /// then an optional `?`
+    fn ty(&mut self) -> Filtered<()> {
+        let (lex, span) = self.peek();
+        let pos = self.len();
+        let out = match lex.kind {
+            OpenParen => {
+                self.bump();
+                self.dummy();
+                let out = self.type_list(CloseParen);
+                self.set_type(pos, span.from, TypeKind::Tuple);
+                out
+            }
+            Ident if self.str(span) == "fn" => {
+                self.bump();
+                self.dummy();
+                let ret = self.peek().0.kind != OpenParen;
+                let mut out = if ret { self.ty() } else { Correct(()) };
+                if out.is_correct() {
+                    out = self.open_paren();
+                }
+                if out.is_correct() {
+                    out = self.type_list(CloseParen);
+                }
+                self.set_type(pos, span.from, TypeKind::Fn { ret });
+                out
+            }
+            _ => {
+                return match self.until_ident() {
+                    Correct(first) => self.ty_from(first),
+                    InputEnd => InputEnd,
+                    Other(lex) => Other(lex),
+                };
+            }
+        };
+        if out.is_correct() {
+            self.optional_type(pos, span.from);
+        }
+        out
+    }
+
+    /// a type starting with the name `first`, which has already been parsed
+    fn ty_from(&mut self, first: BSpan) -> Filtered<()> {
+        let pos = self.len();
+        let out = self.path(first);
+        if out.is_correct() {
+            self.optional_type(pos, first.from);
+        }
+        out
+    }
+
+    /// `<name>`(?.`<name>`..) (?<`<type>`?,>)
+    fn path(&mut self, first: BSpan) -> Filtered<()> {
+        let pos = self.dummy();
+        let name = self.symbol(first);
+        let (kind, out) = match self.peek().0.kind {
+            Dot => {
+                self.bump();
+                let out = match self.until_ident() {
+                    Correct(next) => self.path(next),
+                    InputEnd => InputEnd,
+                    Other(lex) => Other(lex),
+                };
+                (TypeKind::Path(name), out)
+            }
+            Lt => (TypeKind::Name(name), self.type_args()),
+            _ => (TypeKind::Name(name), Correct(())),
+        };
+        self.set_type(pos, first.from, kind);
+        out
+    }
+
+    /// <`<type>`?,>
+    fn type_args(&mut self) -> Filtered<()> {
+        self.bump();
+        self.type_list(Gt)
+    }
+
+    /// `<type>`?, up to & including `close`
+    fn type_list(&mut self, close: LexKind) -> Filtered<()> {
+        loop {
+            if self.peek().0.kind == close {
+                self.bump();
+                return Correct(());
+            }
+            let out = self.ty();
+            if !out.is_correct() {
+                return out;
+            }
+            let out = look_for!(match (self, lex, [Comma, close]) {
+                Comma => break false.into(),
+                kind if kind == close => break true.into(),
+            });
+            match out {
+                Correct(true) => return Correct(()),
+                Correct(false) => (),
+                InputEnd => return InputEnd,
+                Other(lex) => return Other(lex),
+            }
+        }
+    }
+
+    /// wrap the type at `pos` in [`TypeKind::Optional`] if a `?` follows it
+    fn optional_type(&mut self, pos: usize, from: usize) {
+        if self.question() {
+            self.insert_at(pos, Token::Dummy);
+            self.set_type(pos, from, TypeKind::Optional);
+        }
+    }
+
+    /// set the type at `pos`, its sub types are every token after it
+    fn set_type(&mut self, pos: usize, from: usize, kind: TypeKind) {
+        let ty = Type {
+            end: self.len(),
+            kind,
+            span: BSpan::new(from, self.cursor.pos()),
+        };
+        self.set_at(pos, ty);
+    }
+
+    /// (?<`<name>`?,>), the generic params of a fn, struct or enum
+    fn generics(&mut self) -> Filtered<TSpan> {
+        let from = self.len();
+        if self.peek().0.kind != Lt {
+            return Correct(TSpan { from, to: from });
+        }
+        self.bump();
+        let mut comma = true;
+        let out = look_for!(match (self, lex, [Ident, RawIdent, Gt], first) {
+            Gt => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent if comma => {
+                comma = false;
+                let ty = Type {
+                    end: self.len() + 1,
+                    kind: TypeKind::Param(self.symbol(first)),
+                    span: first,
+                };
+                self.push_token(ty);
+            }
+        });
+        out.map(|()| TSpan {
+            from,
+            to: self.len(),
+        })
+    }
 
-        loop {
+    /// ..}
+    ///
+    /// false if eof was found before the closing brace
+    fn body(&mut self) -> bool {
+        let outer = self.set_cond(false);
+        let depth = self.block_depth();
+        let out = loop {
             let lex = self.cursor.advance();
             match self.next_or_close_brace(lex) {
                 X(()) => {
-                    self.truncate(set_idx);
                     self.err_eof();
-                    return;
+                    break false;
                 }
                 Y(()) => (),
-                Z(()) => break,
+                // close an inner block
+                Z(()) if self.block_depth() > depth => self.set_block(lex),
+                Z(()) => break true,
             };
+        };
+        self.set_cond(outer);
+        out
+    }
+
+    /// `fn` ((?`<param>`?,)) { (?`<token>`?,) }
+    ///
+    /// The `fn` has already been parsed
+    fn closure(&mut self, pos: usize, from: usize) -> Filtered<()> {
+        self.bump();
+        self.dummy();
+        let variadic = match self.def_param_list() {
+            Correct(variadic) => variadic,
+            other => return other.map(|_| ()),
+        };
+        let param_end = self.len();
+
+        let out = self.open_brace();
+        if !out.is_correct() {
+            return out;
+        }
+        if !self.fn_body(variadic) {
+            return InputEnd;
         }
 
-        let token = FnDef {
-            name: self.symbol(name),
-            type_name: type_name.map(|span| self.symbol(span)),
-            params: TSpan {
+        self.set_closure(pos, from, param_end, variadic);
+        self.postfix(pos)
+    }
+
+    /// `|`(?`<param>`?,)(?`...`)`|` (`<expr>` | { (?`<token>`?,) })
+    ///
+    /// An expr body is stored as a return of the expr
+    fn pipe_closure(&mut self, pos: usize, from: usize) -> Filtered<()> {
+        self.dummy();
+        let mut variadic = false;
+        if let [Or, Or, _] = self.peek_glued() {
+            self.bump_glued(2);
+        } else {
+            self.bump();
+            let mut comma = true;
+            let out = look_for!(match (self, lex, [Ident, RawIdent, Or], first) {
+                Or => break ().into(),
+                Comma if !comma && !variadic => comma = true,
+                Dot if comma && matches!(self.peek_glued(), [Dot, Dot, _]) => {
+                    self.bump_glued(2);
+                    variadic = true;
+                    comma = false;
+                }
+                Ident | RawIdent | OpenParen if comma => {
+                    comma = false;
+                    self.rewind(first.from);
+                    let pos = self.dummy();
+                    let (ty, name) = match self.typed_name() {
+                        Correct(typed) => typed,
+                        InputEnd => return InputEnd,
+                        Other(lex) => return Other(lex),
+                    };
+                    let param = FnDefParam {
+                        ty,
+                        name: self.symbol(name),
+                        span: name,
+                        value: false,
+                    };
+                    self.set_at(pos, param);
+                }
+            });
+            if !out.is_correct() {
+                return out;
+            }
+        }
+        let param_end = self.len();
+
+        if self.peek().0.kind == OpenBrace {
+            self.bump();
+            if !self.fn_body(variadic) {
+                return InputEnd;
+            }
+        } else {
+            let set_idx = self.dummy();
+            self.push_fn(variadic);
+            let out = self.expr();
+            self.pop_fn();
+            if !out.is_correct() {
+                return out;
+            }
+            let values = TSpan {
                 from: set_idx + 1,
+                to: self.len(),
+            };
+            self.set_at(set_idx, Token::Return(values));
+        }
+
+        self.set_closure(pos, from, param_end, variadic);
+        Correct(())
+    }
+
+    fn set_closure(&mut self, pos: usize, from: usize, param_end: usize, variadic: bool) {
+        let closure = Closure {
+            variadic,
+            params: TSpan {
+                from: pos + 1,
                 to: param_end,
             },
             tokens: TSpan {
@@ -576,7 +1922,40 @@ impl<'a> Reader<'a> {
                 to: self.len(),
             },
         };
-        self.set_at(set_idx, token);
+        let end = self.len();
+        let span = BSpan::new(from, self.cursor.pos());
+        self.set_at(pos, Expr::from(closure).end(end).span(span));
+    }
+
+    /// the body of a fn, `...` is only allowed if it is variadic
+    ///
+    /// Its tail is returned, see [`Self::wrap_tail`]
+    fn fn_body(&mut self, variadic: bool) -> bool {
+        self.push_fn(variadic);
+        let from = self.len();
+        let out = self.body();
+        self.pop_fn();
+        if out {
+            self.wrap_tail(from, self.len(), Token::Return);
+        }
+        out
+    }
+
+    /// ..(?`...`))
+    ///
+    /// `true` if the fn is variadic
+    fn def_param_list(&mut self) -> Filtered<bool> {
+        loop {
+            if self.peek_glued() == [Dot, Dot, Dot] {
+                self.bump_glued(3);
+                return self.close_paren().map(|()| true);
+            }
+            match self.def_params() {
+                Correct(true) => return Correct(false),
+                Correct(false) => (),
+                other => return other.map(|_| false),
+            };
+        }
     }
 
     /// ..)
@@ -585,23 +1964,29 @@ impl<'a> Reader<'a> {
     fn def_params(&mut self) -> Filtered<bool> {
         look_for!(match (self, lex, [Ident, RawIdent, CloseParen], first) {
             CloseParen => break true.into(),
-            Ident | RawIdent => break Self::def_params_ident(self, first),
+            Ident | RawIdent | OpenParen => {
+                self.rewind(first.from);
+                break self.def_param();
+            }
         })
     }
 
-    fn def_params_ident(&mut self, first: BSpan) -> Filtered<bool> {
-        let filtered = self.until_ident();
-        let Correct(second) = filtered else {
-            return filtered.map(|_| false);
-        };
+    /// (?`<type>`) `<name>` (?= `<expr>`), then a `,` or `)`
+    fn def_param(&mut self) -> Filtered<bool> {
         let set_idx = self.dummy();
+        let (ty, name) = match self.typed_name() {
+            Correct(typed) => typed,
+            InputEnd => return InputEnd,
+            Other(lex) => return Other(lex),
+        };
+        let value = self.len();
         let close = look_for!(match (self, lex, [Eq, Comma, CloseParen]) {
-            CloseParen => return true.into(),
+            CloseParen => break true.into(),
             Comma => break false.into(),
             Eq => {
                 let out = self.expr();
                 if !out.is_correct() {
-                    return out.map(|_| false);
+                    return out.map(|()| false);
                 };
                 break false.into();
             }
@@ -610,52 +1995,522 @@ impl<'a> Reader<'a> {
             return close;
         }
         let fn_def_param = FnDefParam {
-            type_name: self.str(first).into(),
-            name: self.str(second).into(),
-            value: is_expr(self.get_token(set_idx + 1)),
+            ty,
+            name: self.symbol(name),
+            span: name,
+            value: is_expr(self.get_token(value)),
         };
         self.set_at(set_idx, fn_def_param);
         close
     }
 
-    fn ident_until<F, T>(
-        &mut self,
-        ident: impl Into<AsStr<'a>>,
-        until: F,
-        uncaught: &[LexKind],
-    ) -> Filtered<T>
-    where
-        F: Fn(Lexeme) -> Option<T>,
-    {
-        self.push_ident(ident);
+    /// parse an expr
+    fn expr(&mut self) -> Filtered<()> {
+        self.binary(0)
+    }
+
+    /// `<expr>` (?, `<expr>`)
+    fn expr_list(&mut self) -> Filtered<()> {
         loop {
-            let Some(lex) = self.lex_non_wc() else {
-                continue;
-            };
-            if let Eof = lex.kind {
-                self.err_eof();
-                break InputEnd;
+            let out = self.expr();
+            if !out.is_correct() || self.peek().0.kind != Comma {
+                break out;
+            }
+            self.bump();
+        }
+    }
+
+    /// true if an expr may start with the given peeked lexeme
+    fn starts_expr(&self, kind: LexKind) -> bool {
+        match kind {
+            Ident | RawIdent | Literal { .. } | OpenParen | Minus | Bang | Or => true,
+            Dot => self.peek_glued() == [Dot, Dot, Dot],
+            _ => false,
+        }
+    }
+
+    /// true if an operand may start with the given peeked lexeme
+    fn starts_operand(&self, kind: LexKind) -> bool {
+        (kind == OpenBrace && !self.in_cond()) || self.starts_expr(kind)
+    }
+
+    /// `parse` the condition of an `if` or the values of a `match`, where a
+    /// `{` opens the body
+    fn cond<T>(&mut self, parse: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = self.set_cond(true);
+        let out = parse(self);
+        self.set_cond(outer);
+        out
+    }
+
+    /// true if `lex` at `span` starts a block, `if` or `match` used as a value
+    fn starts_value(&self, lex: Lexeme, span: BSpan) -> bool {
+        match lex.kind {
+            OpenBrace => true,
+            Ident => matches!(self.str(span), "if" | "match"),
+            _ => false,
+        }
+    }
+
+    /// parse an expr whose operators bind tighter than `min`
+    fn binary(&mut self, min: u8) -> Filtered<()> {
+        let pos = self.len();
+        let out = self.unary();
+        if !out.is_correct() {
+            return out;
+        }
+        self.binary_from(pos, min)
+    }
+
+    /// the operators binding tighter than `min` after the operand at `pos`
+    fn binary_from(&mut self, pos: usize, min: u8) -> Filtered<()> {
+        loop {
+            let Some((op, len)) = self.peek_bin_op() else {
+                break Correct(());
             };
-            if let Some(t) = until(lex) {
-                break t.into();
+            if op.precedence() <= min {
+                break Correct(());
             }
-            self.err_expected(lex, uncaught);
-            break Other(lex);
+            self.bump_glued(len);
+            self.wrap_expr(pos, ExprKind::Binary(op));
+            let out = self.binary(op.precedence());
+            if !out.is_correct() {
+                return out;
+            }
+            self.close_expr(pos);
         }
     }
 
-    /// parse an expr, until the given lexeme
-    fn expr(&mut self) -> Filtered<()> {
-        look_for!(match (self, lex, [Ident, RawIdent, LITERAL], span) {
-            Ident | RawIdent => {
-                self.push_ident(span);
-                break ().into();
+    /// parse a unary, `comp` or primary expr
+    ///
+    /// Unexpected lexemes are skipped until the start of an expr is found.
+    fn unary(&mut self) -> Filtered<()> {
+        let mut skipped = false;
+        let failures = self.failures();
+        let (lex, span) = loop {
+            let (lex, span) = self.peek();
+            match lex.kind {
+                Ident
+                    if self.at_keyword() && !self.at_closure() && !self.starts_value(lex, span) =>
+                {
+                    self.err_expected(span, EXPECTED_EXPR);
+                    return Other(lex);
+                }
+                kind if self.starts_operand(kind) => {
+                    // the skipped lexemes don't fail the statement
+                    self.set_failures(failures);
+                    break (lex, span);
+                }
+                Eof => {
+                    self.bump();
+                    if !skipped {
+                        self.err_eof();
+                    }
+                    return InputEnd;
+                }
+                OpenBrace | CloseBrace | Semi => {
+                    self.err_expected(span, EXPECTED_EXPR);
+                    return Other(lex);
+                }
+                _ => {
+                    self.bump();
+                    self.err_expected(span, EXPECTED_EXPR);
+                    skipped = true;
+                }
             }
+        };
+
+        let pos = self.len();
+        let kind = match lex.kind {
+            Minus => ExprKind::Unary(UnOp::Neg),
+            Bang => ExprKind::Unary(UnOp::Not),
+            Or => return self.pipe_closure(pos, span.from),
+            Ident if self.str(span) == "comp" => ExprKind::Comp(None),
+            _ => return self.primary(),
+        };
+        self.bump();
+        self.push_expr(kind);
+        let out = self.unary();
+        if out.is_correct() {
+            self.close_expr(pos);
+        }
+        out
+    }
+
+    /// an ident, literal or a parenthesized expr, followed by any postfix
+    /// exprs, or a block, `if` or `match`
+    fn primary(&mut self) -> Filtered<()> {
+        let pos = self.len();
+        let lex = self.bump();
+        let from = self.span(lex).from;
+        let span = self.span(lex);
+        if self.starts_value(lex, span) {
+            let parsed = match lex.kind {
+                OpenBrace => self.value_block(span),
+                _ if self.str(span) == "if" => self.value_if(span),
+                _ => self.parse_match(span),
+            };
+            return if parsed { Correct(()) } else { InputEnd };
+        }
+        match lex.kind {
             Literal { kind, suffix_start } => {
-                self.push_expr(Value::new(self.str(lex.len).into(), kind, suffix_start));
-                break ().into();
+                self.push_expr(Value::new(self.symbol(lex), kind, suffix_start));
             }
-        })
+            Ident if self.str(lex) == "fn" && self.peek().0.kind == OpenParen => {
+                return self.closure(pos, from);
+            }
+            Dot => {
+                self.bump_glued(2);
+                let span = BSpan::new(from, self.cursor.pos());
+                if !self.in_variadic() {
+                    self.push_err(SemanticError::Varargs(span));
+                }
+                self.push_expr(ExprKind::Varargs);
+                self.expr_from(pos, from);
+            }
+            OpenParen => {
+                let out = self.expr();
+                if !out.is_correct() {
+                    return out;
+                }
+                let out = self.close_paren();
+                if !out.is_correct() {
+                    return out;
+                }
+                // truncate to a single value
+                if let Some(Token::Expr(expr)) = self.get_token(pos) {
+                    if expr.kind.is_multi() {
+                        self.wrap_expr(pos, ExprKind::Paren);
+                        self.expr_from(pos, from);
+                    }
+                }
+            }
+            _ => {
+                let name = self.span(lex);
+                let def = self.data(self.symbol(name));
+                if self.at_type_args(def.is_some()) {
+                    // values aren't typed, the args are only checked
+                    let len = self.len();
+                    let out = self.type_args();
+                    self.truncate(len);
+                    if !out.is_correct() {
+                        return out;
+                    }
+                }
+                match (def, self.peek().0.kind) {
+                    (Some(Token::Struct(def)), OpenBrace) => {
+                        self.bump();
+                        let construct = Construct {
+                            ty: def.name,
+                            variant: None,
+                            kind: VariantKind::Struct,
+                        };
+                        let fields = self.data_fields(def.name, None).map(|(_, fields)| fields);
+                        let out = self.construct(pos, from, construct, fields);
+                        if !out.is_correct() {
+                            return out;
+                        }
+                    }
+                    (Some(Token::Enum(def)), Dot) => {
+                        let out = self.enum_construct(pos, from, def);
+                        if !out.is_correct() {
+                            return out;
+                        }
+                    }
+                    _ => {
+                        self.push_ident(name);
+                        // past the type args if any
+                        self.expr_from(pos, from);
+                    }
+                }
+            }
+        }
+        self.postfix(pos)
+    }
+
+    /// true if a `<` right after a name starts type args, `<name><<type>, ..>`
+    /// followed by a call, or by a construct if the name is of a struct or enum
+    ///
+    /// `a < b` with a space is always a comparison.
+    fn at_type_args(&self, data: bool) -> bool {
+        let mut cursor = self.cursor.clone();
+        if cursor.advance().kind != Lt {
+            return false;
+        }
+        match after_type_args(self.cursor.clone()) {
+            Some(OpenParen) => true,
+            Some(OpenBrace | Dot) => data,
+            _ => false,
+        }
+    }
+
+    /// `.<variant>` (?(`<expr>`?,) | { (?`<name>` = `<expr>`?,) })
+    ///
+    /// The name of the enum `def_enum` has already been parsed.
+    fn enum_construct(&mut self, pos: usize, from: usize, def_enum: Enum) -> Filtered<()> {
+        self.bump();
+        let name = match self.until_ident() {
+            Correct(span) => span,
+            other => return other.map(|_| ()),
+        };
+        let variant = self.symbol(name);
+        let found = self.data_fields(def_enum.name, Some(variant));
+        let (kind, fields) = if let Some((kind, fields)) = found {
+            (kind, Some(fields))
+        } else {
+            self.push_err(SemanticError::NoVariant(name, def_enum.name, variant));
+            // keep parsing whatever follows
+            let kind = match self.peek().0.kind {
+                OpenParen => VariantKind::Tuple,
+                OpenBrace => VariantKind::Struct,
+                _ => VariantKind::Unit,
+            };
+            (kind, None)
+        };
+        let construct = Construct {
+            ty: def_enum.name,
+            variant: Some(variant),
+            kind,
+        };
+        match kind {
+            VariantKind::Unit => {
+                self.push_token(Expr {
+                    end: pos + 1,
+                    kind: construct.into(),
+                    span: BSpan::new(from, self.cursor.pos()),
+                });
+                Correct(())
+            }
+            VariantKind::Tuple => {
+                let out = self.open_paren();
+                if !out.is_correct() {
+                    return out;
+                }
+                self.push_expr(construct);
+                self.expr_from(pos, from);
+                let out = self.call_params();
+                if !out.is_correct() {
+                    return out;
+                }
+                self.close_expr(pos);
+                let mut found = 0;
+                let mut i = pos + 1;
+                while let Some(Token::Expr(expr)) = self.get_token(i) {
+                    found += 1;
+                    i = expr.end;
+                }
+                let expected = fields.as_ref().map_or(found, Vec::len);
+                if found != expected {
+                    let span = BSpan::new(from, self.cursor.pos());
+                    self.push_err(SemanticError::FieldCount {
+                        span,
+                        expected,
+                        found,
+                    });
+                }
+                Correct(())
+            }
+            VariantKind::Struct => {
+                let out = self.open_brace();
+                if !out.is_correct() {
+                    return out;
+                }
+                self.construct(pos, from, construct, fields)
+            }
+        }
+    }
+
+    /// (?`<name>` = `<expr>`?,) }
+    ///
+    /// Each name must be one of `fields`, if the declaration was found.
+    fn construct(
+        &mut self,
+        pos: usize,
+        from: usize,
+        construct: Construct,
+        fields: Option<Fields>,
+    ) -> Filtered<()> {
+        self.push_expr(construct);
+        self.expr_from(pos, from);
+        let ty = construct.variant.unwrap_or(construct.ty);
+        let mut seen = Vec::new();
+        let mut comma = true;
+        let out = look_for!(match (self, lex, [Ident, RawIdent, CloseBrace], first) {
+            CloseBrace => break ().into(),
+            Comma if !comma => comma = true,
+            Ident | RawIdent if comma => {
+                comma = false;
+                let name = self.symbol(first);
+                let out = self.until_eq();
+                if !out.is_correct() {
+                    break out;
+                }
+                let init = self.len();
+                self.push_token(Expr {
+                    end: init + 1,
+                    kind: ExprKind::FieldInit(name),
+                    span: first,
+                });
+                let out = self.expr();
+                if !out.is_correct() {
+                    break out;
+                }
+                self.close_expr(init);
+
+                let declared = fields
+                    .as_ref()
+                    .is_none_or(|fields| fields.contains(&Some(name)));
+                if !declared {
+                    self.push_err(SemanticError::NoField(first, ty, name));
+                } else if seen.contains(&name) {
+                    self.push_err(SemanticError::DupeField(first, name));
+                }
+                seen.push(name);
+            }
+        });
+        if !out.is_correct() {
+            return out;
+        }
+        self.close_expr(pos);
+
+        let span = BSpan::new(from, self.cursor.pos());
+        for name in fields.into_iter().flatten().flatten() {
+            if !seen.contains(&name) {
+                self.push_err(SemanticError::MissingField(span, name));
+            }
+        }
+        Correct(())
+    }
+
+    /// parse any calls, fields or indexes following the expr at `pos`
+    fn postfix(&mut self, pos: usize) -> Filtered<()> {
+        loop {
+            let (lex, _) = self.peek();
+            let kind = match lex.kind {
+                OpenParen => {
+                    self.bump();
+                    match self.get_token(pos) {
+                        Some(Token::Expr(Expr {
+                            kind: ExprKind::Var(name),
+                            span,
+                            ..
+                        })) => {
+                            self.pop_token();
+                            self.wrap_expr(pos, FnCall { name });
+                            self.expr_from(pos, span.from);
+                        }
+                        _ => self.wrap_expr(pos, ExprKind::Call),
+                    };
+                    let out = self.call_params();
+                    if !out.is_correct() {
+                        return out;
+                    }
+                    self.close_expr(pos);
+                    continue;
+                }
+                Dot => {
+                    self.bump();
+                    let name = match self.peek().0.kind {
+                        // a tuple field
+                        Literal {
+                            kind: LiteralKind::Int { .. },
+                            ..
+                        } => {
+                            let lex = self.bump();
+                            self.symbol(lex)
+                        }
+                        _ => match self.until_ident() {
+                            Correct(name) => self.symbol(name),
+                            other => return other.map(|_| ()),
+                        },
+                    };
+                    match self.peek().0.kind {
+                        OpenParen => {
+                            self.bump();
+                            self.wrap_expr(pos, ExprKind::MethodCall(name));
+                            let out = self.call_params();
+                            if !out.is_correct() {
+                                return out;
+                            }
+                            self.close_expr(pos);
+                            continue;
+                        }
+                        _ => ExprKind::Field(name),
+                    }
+                }
+                OpenBracket => {
+                    self.bump();
+                    self.wrap_expr(pos, ExprKind::Index);
+                    let out = self.expr();
+                    if !out.is_correct() {
+                        return out;
+                    }
+                    let out = self.close_bracket();
+                    if !out.is_correct() {
+                        return out;
+                    }
+                    self.close_expr(pos);
+                    continue;
+                }
+                _ => break Correct(()),
+            };
+            self.wrap_expr(pos, kind);
+        }
+    }
+
+    /// ..)
+    fn call_params(&mut self) -> Filtered<()> {
+        // `None` if no param has been found yet,
+        // `Some(true)` if the last lexeme was a comma.
+        let mut comma = None;
+        let failures = self.failures();
+        loop {
+            let (lex, span) = self.peek();
+            match lex.kind {
+                CloseParen => {
+                    self.bump();
+                    // the skipped lexemes don't fail the statement
+                    self.set_failures(failures);
+                    break Correct(());
+                }
+                Comma => {
+                    self.bump();
+                    match comma {
+                        None => self.err_expected(span, EXPECTED_PARAM),
+                        Some(true) => self.push_err(LexicalError::DupeComma(span)),
+                        Some(false) => comma = Some(true),
+                    }
+                }
+                kind if self.starts_operand(kind) && comma != Some(false) => match self.expr() {
+                    Correct(()) => comma = Some(false),
+                    InputEnd => {
+                        self.err_eof();
+                        break InputEnd;
+                    }
+                    other @ Other(_) => break other,
+                },
+                Eof => {
+                    self.bump();
+                    self.err_eof();
+                    break InputEnd;
+                }
+                OpenBrace | CloseBrace | Semi => {
+                    self.err_expected(span, [Comma, CloseParen]);
+                    break Other(lex);
+                }
+                Ident if self.at_keyword() && !self.at_closure() => {
+                    self.err_expected(span, [Comma, CloseParen]);
+                    break Other(lex);
+                }
+                _ => {
+                    self.bump();
+                    match comma {
+                        Some(false) => self.err_expected(span, [Comma, CloseParen]),
+                        _ => self.err_expected(span, EXPECTED_PARAM),
+                    }
+                }
+            }
+        }
     }
 
     /// `A` = `OpenParen`, `C` = `Ident`
@@ -666,20 +2521,13 @@ impl<'a> Reader<'a> {
         })
     }
 
-    /// `A` = `OpenParen`, `C` = `Ident`
-    fn open_paren_or_ident(&mut self) -> Filtered<Either<(), BSpan>> {
-        look_for!(match (self, lex, [OpenParen, Ident, RawIdent]) {
-            OpenParen => break A(()).into(),
-            Ident | RawIdent => break B(self.span(lex)).into(),
-        })
-    }
-
-    /// `A` `Eq`, `B(len)` `Ident`
-    fn eq_or_ident(&mut self) -> Filtered<Either<(), BSpan>> {
-        look_for!(match (self, lex, [Eq, Ident]) {
-            Eq => break A(()).into(),
-            Ident | RawIdent => break B(self.span(lex)).into(),
-        })
+    /// skip a `?` after a type name, true if there was one
+    fn question(&mut self) -> bool {
+        let question = self.peek().0.kind == Question;
+        if question {
+            self.bump();
+        }
+        question
     }
 
     fn until_eq(&mut self) -> Filtered<()> {
@@ -690,6 +2538,9 @@ impl<'a> Reader<'a> {
 
     /// `A(true)` if eof, `A(true)` if non ident, else `B(Ident)`
     fn until_ident(&mut self) -> Filtered<BSpan> {
+        if self.at_keyword() {
+            return self.keyword_expected([Ident, RawIdent]);
+        }
         look_for!(match (self, lex, [Ident, RawIdent]) {
             Ident | RawIdent => break self.span(lex).into(),
         })
@@ -701,6 +2552,24 @@ impl<'a> Reader<'a> {
         })
     }
 
+    fn close_paren(&mut self) -> Filtered<()> {
+        look_for!(match (self, lex, [CloseParen]) {
+            CloseParen => break ().into(),
+        })
+    }
+
+    fn close_bracket(&mut self) -> Filtered<()> {
+        look_for!(match (self, lex, [CloseBracket]) {
+            CloseBracket => break ().into(),
+        })
+    }
+
+    fn colon(&mut self) -> Filtered<()> {
+        look_for!(match (self, lex, [Colon]) {
+            Colon => break ().into(),
+        })
+    }
+
     fn open_brace(&mut self) -> Filtered<()> {
         look_for!(match (self, lex, [OpenBrace]) {
             OpenBrace => break ().into(),
@@ -722,6 +2591,105 @@ impl<'a> Reader<'a> {
         self.push_err(LexicalError::Eof(self.lex_pos()));
     }
 
+    /// the next lexeme that is not whitespace or a comment, without consuming it
+    fn peek(&self) -> (Lexeme, BSpan) {
+        let mut cursor = self.cursor.clone();
+        loop {
+            let lex = cursor.advance();
+            if !matches!(
+                lex.kind,
+                LineComment { .. } | BlockComment { .. } | Whitespace
+            ) {
+                let from = cursor.lex_pos();
+                return (lex, BSpan::from_len(from, lex.len));
+            }
+        }
+    }
+
+    /// the next lexeme that is not whitespace or a comment, along with
+    /// the two lexemes directly after it
+    fn peek_glued(&self) -> [LexKind; 3] {
+        let mut cursor = self.cursor.clone();
+        let first = loop {
+            let lex = cursor.advance();
+            if !matches!(
+                lex.kind,
+                LineComment { .. } | BlockComment { .. } | Whitespace
+            ) {
+                break lex.kind;
+            }
+        };
+        [first, cursor.advance().kind, cursor.advance().kind]
+    }
+
+    /// a binary operator & the number of lexemes it is made of
+    ///
+    /// compound assignments such as `+=` are not binary operators
+    fn peek_bin_op(&self) -> Option<(BinOp, usize)> {
+        let op = match self.peek_glued() {
+            [Plus | Minus | Star | Slash | Percent | Caret | And | Or, Eq, _]
+            | [Lt, Lt, Eq]
+            | [Gt, Gt, Eq] => return None,
+            [Plus, ..] => (BinOp::Add, 1),
+            [Minus, ..] => (BinOp::Sub, 1),
+            [Star, ..] => (BinOp::Mul, 1),
+            [Slash, ..] => (BinOp::Div, 1),
+            [Percent, ..] => (BinOp::Rem, 1),
+            [Caret, ..] => (BinOp::BitXor, 1),
+            [And, And, _] => (BinOp::And, 2),
+            [And, ..] => (BinOp::BitAnd, 1),
+            [Or, Or, _] => (BinOp::Or, 2),
+            [Or, ..] => (BinOp::BitOr, 1),
+            [Lt, Lt, _] => (BinOp::Shl, 2),
+            [Lt, Eq, _] => (BinOp::Le, 2),
+            [Lt, ..] => (BinOp::Lt, 1),
+            [Gt, Gt, _] => (BinOp::Shr, 2),
+            [Gt, Eq, _] => (BinOp::Ge, 2),
+            [Gt, ..] => (BinOp::Gt, 1),
+            [Eq, Eq, _] => (BinOp::Eq, 2),
+            [Bang, Eq, _] => (BinOp::Ne, 2),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// `=` or a compound assignment, & the number of lexemes it is made of
+    fn peek_assign_op(&self) -> Option<(Option<BinOp>, usize)> {
+        let op = match self.peek_glued() {
+            [Eq, Eq, _] => return None,
+            [Eq, ..] => None,
+            [Plus, Eq, _] => Some(BinOp::Add),
+            [Minus, Eq, _] => Some(BinOp::Sub),
+            [Star, Eq, _] => Some(BinOp::Mul),
+            [Slash, Eq, _] => Some(BinOp::Div),
+            [Percent, Eq, _] => Some(BinOp::Rem),
+            [Caret, Eq, _] => Some(BinOp::BitXor),
+            [And, Eq, _] => Some(BinOp::BitAnd),
+            [Or, Eq, _] => Some(BinOp::BitOr),
+            [Lt, Lt, Eq] => return Some((Some(BinOp::Shl), 3)),
+            [Gt, Gt, Eq] => return Some((Some(BinOp::Shr), 3)),
+            _ => return None,
+        };
+        Some((op, if op.is_some() { 2 } else { 1 }))
+    }
+
+    /// consume a lexeme made of `len` glued lexemes
+    fn bump_glued(&mut self, len: usize) {
+        self.bump();
+        for _ in 1..len {
+            self.cursor.advance();
+        }
+    }
+
+    /// consume the next lexeme that is not whitespace or a comment
+    fn bump(&mut self) -> Lexeme {
+        loop {
+            if let Some(lex) = self.lex_non_wc() {
+                break lex;
+            }
+        }
+    }
+
     fn lex_non_wc(&mut self) -> Option<Lexeme> {
         let lex = self.cursor.advance();
         (!self.filter_comment_or_whitespace(lex)).then_some(lex)
@@ -742,10 +2710,72 @@ impl<'a> Reader<'a> {
 
 #[inline]
 #[must_use]
+/// the kind of the next lexeme at `cursor` that is not whitespace or a
+/// comment, leaving `cursor` before it
+fn peek_kind(cursor: &mut Cursor) -> LexKind {
+    loop {
+        let mut ahead = cursor.clone();
+        let kind = ahead.advance().kind;
+        if !matches!(kind, LineComment { .. } | BlockComment { .. } | Whitespace) {
+            return kind;
+        }
+        *cursor = ahead;
+    }
+}
+
+/// The kind of the lexeme after the `<`..`>` at `cursor`, `None` if they
+/// can't be type args
+fn after_type_args(mut cursor: Cursor) -> Option<LexKind> {
+    let mut depth = 0_usize;
+    loop {
+        let kind = peek_kind(&mut cursor);
+        cursor.advance();
+        match kind {
+            Lt => depth += 1,
+            Gt if depth == 1 => return Some(peek_kind(&mut cursor)),
+            Gt if depth > 1 => depth -= 1,
+            Ident | RawIdent | Comma | Dot | Question | OpenParen | CloseParen if depth > 0 => {}
+            _ => return None,
+        }
+    }
+}
+
+/// The struct & enum decls of `src`, wherever they are, so a construct or
+/// pat may name a type declared after it
+fn types(src: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    for lex in tokenize(src) {
+        let word = &src[pos..pos + lex.len];
+        pos += lex.len;
+        if lex.kind != Ident || !matches!(word, "struct" | "enum") {
+            continue;
+        }
+        let mut reader = Reader::at(src, pos, tokens);
+        match word {
+            "struct" => reader.struct_def(),
+            _ => reader.enum_def(),
+        }
+        (_, _, tokens, _) = reader.into_parts();
+    }
+    tokens
+}
+
 const fn is_expr(token: Option<Token>) -> bool {
     matches!(token, Some(Token::Expr(_)))
 }
 
+/// the `'` of a label, lexed as an unterminated char
+const LABEL: LexKind = LexKind::Literal {
+    kind: LiteralKind::Char { terminated: false },
+    suffix_start: 1,
+};
+
+/// true if `lex` is the `'` of a label
+const fn is_label(lex: Lexeme) -> bool {
+    matches!(lex.kind, LABEL) && lex.len == 1
+}
+
 const LITERAL: LexKind = LexKind::Literal {
     kind: LiteralKind::Int {
         base: Base::Binary,
@@ -764,6 +2794,7 @@ macro_rules! look_for {
     }) => {{
         use LexKind::*;
         loop {
+            let cursor = $this.cursor.clone();
             let Some($lex) = $this.lex_non_wc() else {
                 continue;
             };
@@ -779,6 +2810,10 @@ macro_rules! look_for {
                 #[allow(unreachable_patterns)]
                 _ => {
                     $this.err_expected($lex, $expected);
+                    // left for the next statement
+                    if $this.syncs($lex) {
+                        $this.cursor = cursor;
+                    }
                     break Other($lex);
                 }
             }
@@ -0,0 +1,368 @@
+//! parsing an edited source again, without parsing all of it
+//!
+//! An edit is lexed again from the first lexeme that may have peeked at it,
+//! until a new lexeme starts where an old one did. If the edit is inside the
+//! body of a top level fn or block, only that item is parsed again & the
+//! tokens & errors after it are moved. Anything else is parsed in full, as
+//! is an edit once there may be more than [`parse::MAX_ERRORS`] errors. The
+//! semantic passes run over the whole module after either.
+
+use crate::check;
+use crate::error::{ErrorMulti, ErrorOnce, LexicalError, SemanticError};
+use crate::lex::{Cursor, LexKind, Lexeme};
+use crate::parse::token::{Module, Token};
+use crate::parse::{self, Reader};
+use crate::span::{BSpan, TSpan};
+
+#[cfg(test)]
+mod test;
+
+/// A lexeme peeks at most two chars past its end, of at most 4 bytes each
+const LOOKAHEAD: usize = 2 * 4;
+
+/// A source with its lexemes, module & errors, kept up to date with edits
+///
+/// The result of every edit is the same as parsing the new source in full.
+#[derive(Debug, Clone)]
+pub struct Parsed {
+    src: String,
+    lexemes: Vec<Lexeme>,
+    module: Module,
+    errors: ErrorMulti,
+    /// the errors of parsing alone, before the semantic passes
+    parse_errors: ErrorMulti,
+    /// the top level statements that produced tokens, in order
+    items: Vec<Item>,
+}
+
+/// A top level statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Item {
+    bytes: BSpan,
+    tokens: TSpan,
+}
+
+impl Parsed {
+    #[must_use]
+    pub fn new(name: &str, src: String) -> Self {
+        let mut parsed = Self {
+            lexemes: Cursor::new(&src).collect(),
+            src,
+            module: Module::new(name, Vec::new()),
+            errors: ErrorMulti::default(),
+            parse_errors: ErrorMulti::default(),
+            items: Vec::new(),
+        };
+        parsed.parse();
+        parsed
+    }
+
+    #[must_use]
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    #[must_use]
+    pub fn lexemes(&self) -> &[Lexeme] {
+        &self.lexemes
+    }
+
+    #[must_use]
+    pub const fn module(&self) -> &Module {
+        &self.module
+    }
+
+    #[must_use]
+    pub const fn errors(&self) -> &ErrorMulti {
+        &self.errors
+    }
+
+    /// Replace the bytes of `span` with `text`
+    ///
+    /// Returns true if only the enclosing top level fn or block was parsed
+    /// again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `span` is out of bounds or not on char boundaries
+    pub fn edit(&mut self, span: BSpan, text: &str) -> bool {
+        self.src.replace_range(span.from..span.to, text);
+        self.relex(span, text.len());
+        let reparsed = self.reparse(span, text.len());
+        if !reparsed {
+            self.parse();
+        }
+        reparsed
+    }
+
+    fn parse(&mut self) {
+        let mut reader = Reader::new(&self.src);
+        self.items = reader.items(None);
+        (self.module, self.parse_errors) = reader.into_module(self.module.name().as_str());
+        self.check();
+    }
+
+    /// run the semantic passes over the module
+    fn check(&mut self) {
+        self.errors = self.parse_errors.clone();
+        for err in check::passes(&mut self.module) {
+            self.errors.push(err);
+        }
+    }
+
+    /// lex the edit of `span`, now `len` bytes long, again
+    fn relex(&mut self, span: BSpan, len: usize) {
+        let (mut i, mut from) = (0, 0);
+        while let Some(lex) = self.lexemes.get(i) {
+            if from + lex.len + LOOKAHEAD > span.from {
+                break;
+            }
+            from += lex.len;
+            i += 1;
+        }
+
+        let edit_end = span.from + len;
+        let (mut j, mut old) = (i, from);
+        let mut pos = from;
+        let mut new = Vec::new();
+        let mut lexer = Cursor::at(&self.src, from);
+        loop {
+            if pos >= edit_end {
+                // lexing is the same from here on if an old lexeme started
+                // at the same place before the edit
+                let before = pos - edit_end + span.to;
+                while old < before {
+                    let Some(lex) = self.lexemes.get(j) else {
+                        break;
+                    };
+                    old += lex.len;
+                    j += 1;
+                }
+                if old == before {
+                    break;
+                }
+            }
+            let Some(lex) = lexer.next() else {
+                j = self.lexemes.len();
+                break;
+            };
+            pos += lex.len;
+            new.push(lex);
+        }
+        self.lexemes.splice(i..j, new);
+    }
+
+    /// parse the item enclosing the edit of `span` again, false if the
+    /// whole source must be parsed
+    #[allow(clippy::cast_possible_wrap)]
+    fn reparse(&mut self, span: BSpan, len: usize) -> bool {
+        let bytes = len as isize - (span.to - span.from) as isize;
+        let Some(k) = self
+            .items
+            .iter()
+            .position(|item| item.bytes.from < span.from && span.to < item.bytes.to)
+        else {
+            return false;
+        };
+        let item = self.items[k];
+        let end = item.bytes.to.wrapping_add_signed(bytes);
+        let next = self.items.get(k + 1).map_or(self.src.len(), |next| {
+            next.bytes.from.wrapping_add_signed(bytes)
+        });
+        if !self.reparsable(item, span, end, next) {
+            return false;
+        }
+        let Some((mut errors, after_errors)) = self.split_errors(item.bytes) else {
+            return false;
+        };
+
+        let mut tokens = std::mem::take(&mut self.module.items);
+        let after = tokens.split_off(item.tokens.to);
+        tokens.truncate(item.tokens.from);
+        let mut reader = Reader::resume(&self.src, item.bytes.from, tokens);
+        let items = reader.items(Some(end));
+        let (cursor, new_errors, mut tokens, blocks) = reader.into_parts();
+
+        let new = Item {
+            bytes: BSpan::new(item.bytes.from, end),
+            tokens: TSpan {
+                from: item.tokens.from,
+                to: tokens.len(),
+            },
+        };
+        let inside = new_errors.iter().all(|err| {
+            err.span()
+                .is_some_and(|span| new.bytes.from <= span.from && span.to <= end)
+        });
+        // the errors past the cap depend on those before the item
+        let count = errors.len() + new_errors.len() + after_errors.len();
+        let capped = self.parse_errors.len().max(count) > parse::MAX_ERRORS;
+        if items != [new] || cursor.pos() != end || !blocks.is_empty() || !inside || capped {
+            return false;
+        }
+
+        let moved = new.tokens.to as isize - item.tokens.to as isize;
+        tokens.extend(after.into_iter().map(|mut token| {
+            token.offset(moved, bytes);
+            token
+        }));
+        self.module.items = tokens;
+
+        for err in new_errors
+            .iter()
+            .chain(after_errors.into_iter().map(|mut err| {
+                match &mut err {
+                    ErrorOnce::Lexical(err) => err.offset(bytes),
+                    ErrorOnce::Semantic(err) => err.offset(bytes),
+                    ErrorOnce::Other(_) => {}
+                }
+                err
+            }))
+        {
+            errors.push(err);
+        }
+        self.parse_errors = errors;
+        self.check();
+
+        self.items[k] = new;
+        for item in &mut self.items[k + 1..] {
+            item.bytes = item.bytes.offset(bytes);
+            item.tokens = item.tokens.offset(moved);
+        }
+        true
+    }
+
+    /// true if `item` is a fn or block, the edit of `span` leaves the `fn`
+    /// or `{` starting it alone & no `else` before the `next` item could
+    /// belong to an if in it
+    fn reparsable(&self, item: Item, span: BSpan, end: usize, next: usize) -> bool {
+        let fn_or_block = match self.module.get(item.tokens.from) {
+            Some(Token::FnDef(def)) => def.tokens.to == item.tokens.to,
+            Some(Token::Block(block)) => block.tokens.to == item.tokens.to,
+            _ => false,
+        };
+        let text = |from: usize, lex: Lexeme| &self.src[from..from + lex.len];
+        let mut lexemes = self.lexemes_from(item.bytes.from);
+        let starts = lexemes.next().is_some_and(|(from, lex)| {
+            let opens = match lex.kind {
+                LexKind::OpenBrace => true,
+                LexKind::Ident => text(from, lex) == "fn",
+                _ => false,
+            };
+            from == item.bytes.from && from + lex.len <= span.from && opens
+        });
+        let no_else = lexemes
+            .skip_while(|&(from, _)| from < end)
+            .take_while(|&(from, _)| from <= next)
+            .all(|(from, lex)| lex.kind != LexKind::Ident || text(from, lex) != "else");
+        fn_or_block && starts && no_else
+    }
+
+    /// the lexemes starting at or after `pos`, with their starts
+    fn lexemes_from(&self, pos: usize) -> impl Iterator<Item = (usize, Lexeme)> + '_ {
+        self.lexemes
+            .iter()
+            .scan(0, |from, &lex| {
+                let start = *from;
+                *from += lex.len;
+                Some((start, lex))
+            })
+            .skip_while(move |&(from, _)| from < pos)
+    }
+
+    /// the errors before `region` & the errors after it, `None` if an error
+    /// is in neither or isn't in order
+    fn split_errors(&self, region: BSpan) -> Option<(ErrorMulti, Vec<ErrorOnce>)> {
+        let errors = &self.parse_errors;
+        if !errors.other.is_empty() {
+            return None;
+        }
+        let (lex, lex_after) = split(&errors.lex, LexicalError::span, region)?;
+        let (semantic, semantic_after) = split(&errors.semantic, SemanticError::span, region)?;
+        let after = lex_after
+            .into_iter()
+            .map(ErrorOnce::Lexical)
+            .chain(semantic_after.into_iter().map(ErrorOnce::Semantic));
+        let before = ErrorMulti {
+            lex,
+            semantic,
+            other: Vec::new(),
+            warnings: Vec::new(),
+        };
+        Some((before, after.collect()))
+    }
+}
+
+/// the errors before & after `region`
+fn split<T: Clone>(
+    errors: &[T],
+    span: impl Fn(&T) -> BSpan,
+    region: BSpan,
+) -> Option<(Vec<T>, Vec<T>)> {
+    #[derive(PartialEq, PartialOrd)]
+    enum Place {
+        Before,
+        Inside,
+        After,
+    }
+    let (mut before, mut after) = (Vec::new(), Vec::new());
+    let mut last = Place::Before;
+    for err in errors {
+        let span = span(err);
+        let place = match span {
+            // the statement before may report the lexeme starting the region
+            _ if span.from == region.from => return None,
+            // can't tell which side made it
+            _ if span.is_empty() && span.from == region.to => return None,
+            _ if span.to <= region.from => Place::Before,
+            _ if region.from <= span.from && span.to <= region.to => Place::Inside,
+            _ if region.to <= span.from => Place::After,
+            _ => return None,
+        };
+        if place < last {
+            return None;
+        }
+        match place {
+            Place::Before => before.push(err.clone()),
+            Place::Inside => {}
+            Place::After => after.push(err.clone()),
+        }
+        last = place;
+    }
+    Some((before, after))
+}
+
+impl Reader<'_> {
+    /// Parse until eof, or until `end` is reached outside of any block
+    ///
+    /// Returns every top level statement that produced tokens.
+    fn items(&mut self, end: Option<usize>) -> Vec<Item> {
+        let mut items = Vec::new();
+        let mut start = (0, 0);
+        loop {
+            if self.block_depth() == 0 {
+                start = (self.cursor.pos(), self.len());
+            }
+            if !self.next() {
+                break;
+            }
+            if self.block_depth() > 0 {
+                continue;
+            }
+            let (from, tokens) = start;
+            if self.len() > tokens {
+                items.push(Item {
+                    bytes: BSpan::new(from, self.cursor.pos()),
+                    tokens: TSpan {
+                        from: tokens,
+                        to: self.len(),
+                    },
+                });
+            }
+            if end.is_some_and(|end| self.cursor.pos() >= end) {
+                break;
+            }
+        }
+        items
+    }
+}
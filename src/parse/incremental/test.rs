@@ -0,0 +1,200 @@
+use crate::lex::tokenize;
+use crate::parse::incremental::*;
+use crate::parse::Reader;
+use crate::span::BSpan;
+use pretty_assertions::assert_eq;
+
+const SRCS: &[&str] = &[
+    "",
+    "let a = 1\nfn f(int x) {\n  const b = x + a\n  return b\n}\nf(a)",
+    "fn f() {\n  if a {\n    g()\n  }\n}\nif b {} else {}\n{ let c = \"é\" }",
+    "const k = 1\nfn f(...) {\n  k = 2\n  return ...\n}\n{\n  /* c */ h(1, 2)\n}\nk = 3",
+    "fn f() { return fn(x) { return |y| x + y } }\nfn g() {\n  if x { } else if y { } else { }\n}",
+    "fn f() {\n  let = \n}\nfn g( {\n}\n{ ) }\nlet x = ",
+    "{ { } }\n// comment\nfn f(int x = 1, y) {\n  x, y = y, x\n  x += 1\n}\nreturn f",
+    "fn f(int? x, unsigned u) {\n  let signed s = u\n  return x\n}\nlet astr a = c\"a\"\nf(nil, 1)",
+    "enum E { A, B }\nfn f(e) {\n  return match e { E.A => 1, _ => 2 }\n}\n{ match 1 { 1 => { } } }",
+    "prefn p(n) {\n  return n + 1\n}\npreconst k = comp p(1)\nfn g() {\n  'a: { break 'a }\n}",
+];
+
+/// what may be typed in an edit
+const TEXTS: &[&str] = &[
+    "",
+    "x",
+    "}",
+    "{",
+    "\"",
+    "/*",
+    "*/",
+    "(",
+    ")",
+    ",",
+    "=",
+    "+",
+    "1.",
+    "é",
+    "\n",
+    "// c\n",
+    "fn g() {}",
+    "let y = 1\n",
+    " else {}",
+    "return x",
+    "if x {",
+    "...",
+    "const",
+    "nil",
+    "?",
+    "E.B => 0,",
+];
+
+/// A small deterministic rng
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize % n.max(1)
+    }
+}
+
+/// the char boundary at or before `pos`
+fn boundary(src: &str, mut pos: usize) -> usize {
+    while !src.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+fn assert_full(parsed: &Parsed) {
+    let src = parsed.src();
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(parsed.module(), &module, "{src:?}");
+    assert_eq!(parsed.errors(), &errors, "{src:?}");
+    assert_eq!(
+        parsed.lexemes(),
+        tokenize(src).collect::<Vec<_>>(),
+        "{src:?}"
+    );
+    assert_eq!(
+        parsed.items,
+        Parsed::new("test", src.to_owned()).items,
+        "{src:?}"
+    );
+}
+
+#[test]
+fn same_as_full() {
+    let mut rng = Rng(1);
+    let mut incremental = 0;
+    for &src in SRCS {
+        for _ in 0..50 {
+            let mut parsed = Parsed::new("test", src.to_owned());
+            for _ in 0..10 {
+                let len = parsed.src().len();
+                let from = boundary(parsed.src(), rng.below(len + 1));
+                let to = boundary(parsed.src(), (from + rng.below(4)).min(len)).max(from);
+                let text = TEXTS[rng.below(TEXTS.len())];
+                incremental += usize::from(parsed.edit(BSpan::new(from, to), text));
+                assert_full(&parsed);
+            }
+        }
+    }
+    // most random edits land outside of a body or unbalance it
+    assert!(incremental > 200, "{incremental}");
+}
+
+#[test]
+fn edits() {
+    let src = "let a = 1\nfn f() {\n  return a\n}\nf()";
+    let mut parsed = Parsed::new("test", src.to_owned());
+    let at = |parsed: &Parsed, s: &str| parsed.src().find(s).unwrap();
+
+    // inside a fn body
+    let from = at(&parsed, "a\n}");
+    assert!(parsed.edit(BSpan::new(from, from + 1), "a + 1"));
+    assert_full(&parsed);
+    let from = at(&parsed, "return");
+    assert!(parsed.edit(BSpan::new(from, from), "let b = 2\n  "));
+    assert_full(&parsed);
+    // an unbalanced brace changes the items
+    assert!(!parsed.edit(BSpan::new(from, from), "{"));
+    assert_full(&parsed);
+    assert!(!parsed.errors().is_empty());
+    assert!(!parsed.edit(BSpan::new(from, from + 1), ""));
+    assert_full(&parsed);
+    assert!(parsed.errors().is_empty());
+    // outside of any fn or block
+    assert!(!parsed.edit(BSpan::new(8, 9), "2"));
+    assert_full(&parsed);
+    // the `fn` starting the item
+    let from = at(&parsed, "fn");
+    assert!(!parsed.edit(BSpan::new(from + 2, from + 2), "x"));
+    assert_full(&parsed);
+}
+
+#[test]
+fn semantic_errors() {
+    let mut parsed = Parsed::new("test", "fn f(int? x) {\n  return 1\n}".to_owned());
+    let from = parsed.src().find('1').unwrap();
+    assert!(parsed.edit(BSpan::new(from, from + 1), "x + 1"));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 1);
+    assert!(parsed.edit(BSpan::new(from, from + 1), "1"));
+    assert_full(&parsed);
+    assert!(parsed.errors().is_empty());
+    // a nil for a non optional type
+    let from = parsed.src().find("return").unwrap();
+    assert!(parsed.edit(BSpan::new(from, from), "let int y = nil\n  "));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 1);
+    // a string of the wrong type
+    let from = parsed.src().find("let").unwrap();
+    assert!(parsed.edit(BSpan::new(from, from), "let astr s = \"é\"\n  "));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 2);
+    // a match left non exhaustive
+    let src = "fn f(a) {\n  match a { true => { } false => { } }\n}";
+    let mut parsed = Parsed::new("test", src.to_owned());
+    let from = parsed.src().find("false").unwrap();
+    assert!(parsed.edit(BSpan::new(from, from + 5), "true"));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 2);
+}
+
+#[test]
+fn near_error_cap() {
+    let src = format!("{}fn f() {{\n  return 1\n}}", "let = 1\n".repeat(31));
+    let mut parsed = Parsed::new("test", src);
+    assert_eq!(parsed.errors().len(), 31);
+    let from = parsed.src().find("return").unwrap();
+    // a body error still fits
+    assert!(parsed.edit(BSpan::new(from, from), "let = 2\n  "));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 32);
+    // the next one is past the cap
+    assert!(!parsed.edit(BSpan::new(from, from), "let = 3\n  "));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 33);
+    // & so is removing one while past it
+    assert!(!parsed.edit(BSpan::new(from, from + 10), ""));
+    assert_full(&parsed);
+    assert_eq!(parsed.errors().len(), 32);
+}
+
+#[test]
+fn relex() {
+    let mut parsed = Parsed::new("test", "f(\"a\", b)\nfn g() { }".to_owned());
+    // an open string swallows everything after it
+    parsed.edit(BSpan::new(11, 11), "\"");
+    assert_full(&parsed);
+    parsed.edit(BSpan::new(11, 12), "");
+    assert_full(&parsed);
+    // a block comment
+    parsed.edit(BSpan::new(0, 0), "/*");
+    assert_full(&parsed);
+    parsed.edit(BSpan::new(parsed.src().len(), parsed.src().len()), "*/");
+    assert_full(&parsed);
+}
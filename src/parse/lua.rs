@@ -0,0 +1,1393 @@
+//! reading lua 5.4 source into a [`Module`]
+//!
+//! Lua maps onto the same tokens as allua: a `local` is a [`Decl`], a global
+//! `function` at the top level is a [`FnDef`] & any other `function`
+//! statement is an [`Assign`] of a [`Closure`]. Strings are decoded here &
+//! stored as raw string literals, numbers as decimal literals.
+//!
+//! Loops & `goto` are [`Flow`]s, a table constructor is a [`Table`] expr.
+//! `goto`s are checked against their labels here, as lua does when it
+//! compiles. Only non utf-8 strings are reported as
+//! [`LexicalError::NotInSubset`]. Like lua, parsing stops at the first
+//! syntax error, dropping the top level statement it is in.
+//!
+//! [`Table`]: ExprKind::Table
+//!
+//! A `local` with more than one name declares its names before its values
+//! are evaluated.
+
+use std::ops::Range;
+
+use crate::error::{ErrorMulti, LexicalError, SemanticError};
+use crate::lex::lua::{is_whitespace, LuaCursor, LuaKind};
+use crate::lex::{Base, LiteralKind};
+use crate::parse::token::*;
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+#[cfg(test)]
+mod test;
+
+/// The reserved words of lua
+pub const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// The priority of unary operators, higher binds tighter
+const UNARY_PRIORITY: u8 = 12;
+
+/// Parsing stopped at a syntax error, which has been reported
+struct Stop;
+
+type Parse<T = ()> = Result<T, Stop>;
+
+/// A label, or a `goto` waiting for its label
+#[derive(Debug, Clone, Copy)]
+struct Jump {
+    name: Symbol,
+    span: BSpan,
+    /// the locals in scope at it
+    locals: usize,
+    /// the number of blocks around it
+    depth: usize,
+}
+
+/// A parser of lua source
+#[derive(Debug, Clone)]
+pub struct LuaReader<'a> {
+    src: &'a str,
+    /// every lexeme but whitespace & comments, ending with `Eof`
+    lexemes: Vec<(LuaKind, BSpan)>,
+    /// the index of the next lexeme
+    next: usize,
+    /// the end of the last lexeme eaten
+    last_end: usize,
+    tokens: Vec<Token>,
+    errors: ErrorMulti,
+    /// whether each fn being parsed is variadic
+    fns: Vec<bool>,
+    /// the locals in scope, innermost last
+    locals: Vec<(Symbol, DeclKind)>,
+    /// the number of locals in scope at the start of each open block
+    blocks: Vec<usize>,
+    /// the loops around the statement being parsed, in its fn
+    loops: usize,
+    /// the visible labels of the fn being parsed
+    labels: Vec<Jump>,
+    /// the `goto`s of the fn being parsed to labels not yet seen
+    gotos: Vec<Jump>,
+}
+
+impl<'a> LuaReader<'a> {
+    #[must_use]
+    pub fn new(src: &'a str) -> Self {
+        let mut cursor = LuaCursor::new(src);
+        let mut lexemes = Vec::new();
+        loop {
+            let from = cursor.pos();
+            let lex = cursor.advance();
+            let span = BSpan::new(from, from + lex.len);
+            match lex.kind {
+                LuaKind::Eof => {
+                    lexemes.push((LuaKind::Eof, span));
+                    break;
+                }
+                // an unclosed comment is reported when it is reached
+                LuaKind::LongComment { terminated: false } => lexemes.push((lex.kind, span)),
+                kind if kind.is_trivia() => {}
+                kind => lexemes.push((kind, span)),
+            }
+        }
+        Self {
+            src,
+            lexemes,
+            next: 0,
+            last_end: 0,
+            tokens: Vec::new(),
+            errors: ErrorMulti::default(),
+            fns: Vec::new(),
+            locals: Vec::new(),
+            blocks: Vec::new(),
+            loops: 0,
+            labels: Vec::new(),
+            gotos: Vec::new(),
+        }
+    }
+
+    /// Parse a module
+    #[must_use]
+    pub fn module(mut self, name: &str) -> (Module, ErrorMulti) {
+        loop {
+            let pos = self.len();
+            let out = match self.peek() {
+                LuaKind::Eof => break,
+                _ if self.is_word("return") => self.ret(),
+                _ if self.block_follow() => Err(self.error("the end of the file")),
+                _ => self.statement(),
+            };
+            if out.is_err() {
+                self.tokens.truncate(pos);
+                break;
+            }
+        }
+        self.unresolved();
+        (Module::new(name, self.tokens), self.errors)
+    }
+
+    /// a block, ending before the lexeme that follows it
+    fn block(&mut self) -> Parse {
+        self.open_block();
+        let out = self.statements();
+        self.close_block();
+        out
+    }
+
+    fn open_block(&mut self) {
+        self.blocks.push(self.locals.len());
+    }
+
+    /// End the innermost block, its pending `goto`s may still jump to a
+    /// label after it
+    fn close_block(&mut self) {
+        let scope = self.blocks.pop().unwrap_or(0);
+        self.locals.truncate(scope);
+        let depth = self.blocks.len();
+        self.labels.retain(|label| label.depth <= depth);
+        for goto in &mut self.gotos {
+            goto.depth = goto.depth.min(depth);
+            goto.locals = goto.locals.min(scope);
+        }
+    }
+
+    /// Report the `goto`s of the fn without a label
+    fn unresolved(&mut self) {
+        for goto in std::mem::take(&mut self.gotos) {
+            self.push_err(SemanticError::NoGotoLabel(goto.span, goto.name));
+        }
+    }
+
+    fn statements(&mut self) -> Parse {
+        while !self.block_follow() {
+            if self.is_word("return") {
+                return self.ret();
+            }
+            self.statement()?;
+        }
+        Ok(())
+    }
+
+    /// true at the end of a block
+    fn block_follow(&self) -> bool {
+        self.peek() == LuaKind::Eof
+            || ["else", "elseif", "end", "until"]
+                .iter()
+                .any(|word| self.is_word(word))
+    }
+
+    fn statement(&mut self) -> Parse {
+        let (kind, span) = self.peek_lexeme();
+        match kind {
+            LuaKind::Semi => {
+                self.bump();
+                Ok(())
+            }
+            LuaKind::ColonColon => self.label(),
+            LuaKind::Name => match self.text(span) {
+                "if" => self.if_stat(),
+                "while" => self.while_stat(),
+                "do" => self.do_stat(),
+                "for" => self.for_stat(),
+                "repeat" => self.repeat_stat(),
+                "function" => self.function_stat(),
+                "local" => {
+                    self.bump();
+                    match self.eat_word("function") {
+                        true => self.local_function(),
+                        false => self.local(),
+                    }
+                }
+                "break" => {
+                    self.bump();
+                    if self.loops == 0 {
+                        self.push_err(SemanticError::LoopBreak(span));
+                    }
+                    let values = TSpan {
+                        from: self.len() + 1,
+                        to: self.len() + 1,
+                    };
+                    self.push_token(Break {
+                        label: "".into(),
+                        span,
+                        values,
+                    });
+                    Ok(())
+                }
+                "goto" => self.goto(),
+                _ => self.expr_stat(),
+            },
+            _ => self.expr_stat(),
+        }
+    }
+
+    /// `return` (?<exprs>) (?`;`), which must end its block
+    fn ret(&mut self) -> Parse {
+        self.bump();
+        let pos = self.dummy();
+        if !self.block_follow() && self.peek() != LuaKind::Semi {
+            self.expr_list()?;
+        }
+        self.eat(LuaKind::Semi);
+        if !self.block_follow() {
+            return Err(self.error("the end of the block after `return`"));
+        }
+        self.set_at(
+            pos,
+            Token::Return(TSpan {
+                from: pos + 1,
+                to: self.len(),
+            }),
+        );
+        Ok(())
+    }
+
+    /// `if`|`elseif` <cond> `then` <block> (?`elseif` ..) (?`else` <block>) `end`
+    fn if_stat(&mut self) -> Parse {
+        self.bump();
+        let pos = self.dummy();
+        self.expr()?;
+        self.expect_word("then", "`then`")?;
+        let from = self.len();
+        self.block()?;
+        let then = TSpan {
+            from,
+            to: self.len(),
+        };
+        let el = if self.is_word("elseif") {
+            // ends with the `end` of the whole chain
+            let from = self.len();
+            self.if_stat()?;
+            Some(from)
+        } else if self.eat_word("else") {
+            let from = self.len();
+            self.block()?;
+            self.expect_word("end", "`end` to close `if`")?;
+            Some(from)
+        } else {
+            self.expect_word("end", "`end` to close `if`")?;
+            None
+        };
+        let el = el.map(|from| TSpan {
+            from,
+            to: self.len(),
+        });
+        self.set_at(pos, Flow::If(then, el));
+        Ok(())
+    }
+
+    /// `do` <block> `end`
+    fn do_stat(&mut self) -> Parse {
+        let from = self.bump().from;
+        let pos = self.dummy();
+        self.block()?;
+        let end = self.expect_word("end", "`end` to close `do`")?;
+        let block = Block {
+            span: BSpan::new(from, end.to),
+            tokens: TSpan {
+                from: pos + 1,
+                to: self.len(),
+            },
+        };
+        self.set_at(pos, block);
+        Ok(())
+    }
+
+    /// `while` <cond> `do` <block> `end`
+    fn while_stat(&mut self) -> Parse {
+        self.bump();
+        let pos = self.dummy();
+        self.expr()?;
+        self.expect_word("do", "`do`")?;
+        let from = self.len();
+        self.loop_body()?;
+        self.expect_word("end", "`end` to close `while`")?;
+        let body = TSpan {
+            from,
+            to: self.len(),
+        };
+        self.set_at(pos, Flow::While(body));
+        Ok(())
+    }
+
+    /// `repeat` <block> `until` <cond>
+    fn repeat_stat(&mut self) -> Parse {
+        self.bump();
+        let pos = self.dummy();
+        // the condition sees the locals of the block
+        self.open_block();
+        self.loops += 1;
+        let out = self.statements();
+        self.loops -= 1;
+        let out = out.and_then(|()| {
+            let body = TSpan {
+                from: pos + 1,
+                to: self.len(),
+            };
+            self.expect_word("until", "`until` to close `repeat`")?;
+            self.expr()?;
+            self.set_at(pos, Flow::Repeat(body));
+            Ok(())
+        });
+        self.close_block();
+        out
+    }
+
+    /// `for` <name> `=` <expr>, <expr> (?, <expr>) `do` <block> `end`
+    ///
+    /// `for` <name>, .. `in` <exprs> `do` <block> `end`
+    fn for_stat(&mut self) -> Parse {
+        self.bump();
+        let pos = self.dummy();
+        let mut names = vec![self.name()?];
+        let numeric = self.eat(LuaKind::Eq);
+        if numeric {
+            self.expr()?;
+            self.expect(LuaKind::Comma, "`,`")?;
+            self.expr()?;
+            if self.eat(LuaKind::Comma) {
+                self.expr()?;
+            }
+        } else {
+            while self.eat(LuaKind::Comma) {
+                names.push(self.name()?);
+            }
+            self.expect_word("in", "`=` or `in`")?;
+            self.expr_list()?;
+        }
+        self.expect_word("do", "`do`")?;
+        let vars = self.len();
+        let scope = self.locals.len();
+        for &name in &names {
+            self.push_token(Decl {
+                kind: DeclKind::Let,
+                ty: None,
+                name: self.symbol(name),
+                span: name,
+                value: false,
+                pre: false,
+            });
+            self.locals.push((self.symbol(name), DeclKind::Let));
+        }
+        let from = self.len();
+        let out = self.loop_body();
+        self.locals.truncate(scope);
+        out?;
+        self.expect_word("end", "`end` to close `for`")?;
+        let def = For {
+            numeric,
+            vars: TSpan {
+                from: vars,
+                to: from,
+            },
+            body: TSpan {
+                from,
+                to: self.len(),
+            },
+        };
+        self.set_at(pos, Flow::For(def));
+        Ok(())
+    }
+
+    /// the block of a loop, which `break` may leave
+    fn loop_body(&mut self) -> Parse {
+        self.loops += 1;
+        let out = self.block();
+        self.loops -= 1;
+        out
+    }
+
+    /// `::`<name>`::`, visible in its block & the blocks in it
+    ///
+    /// Like lua, a label followed only by void statements is at the end of
+    /// its block, outside of the scope of the locals before it.
+    fn label(&mut self) -> Parse {
+        self.bump();
+        let span = self.name()?;
+        self.expect(LuaKind::ColonColon, "`::`")?;
+        let name = self.symbol(span);
+        self.push_token(Flow::Label(name, span));
+        while self.peek() == LuaKind::Semi {
+            self.bump();
+        }
+        if self.labels.iter().any(|label| label.name == name) {
+            self.push_err(SemanticError::DupeLabel(span, name));
+        }
+        let depth = self.blocks.len();
+        let at_end = self.block_follow() && !self.is_word("until");
+        let locals = match at_end {
+            true => self.blocks.last().copied().unwrap_or(0),
+            false => self.locals.len(),
+        };
+        let label = Jump {
+            name,
+            span,
+            locals,
+            depth,
+        };
+        let mut i = 0;
+        while i < self.gotos.len() {
+            let goto = self.gotos[i];
+            if goto.name != name || goto.depth != depth {
+                i += 1;
+                continue;
+            }
+            self.gotos.remove(i);
+            if goto.locals < label.locals {
+                let (local, _) = self.locals[goto.locals];
+                self.push_err(SemanticError::GotoScope(goto.span, name, local));
+            }
+        }
+        self.labels.push(label);
+        Ok(())
+    }
+
+    /// `goto` <name>, back to a visible label or on to one later in an
+    /// enclosing block
+    fn goto(&mut self) -> Parse {
+        self.bump();
+        let span = self.name()?;
+        let name = self.symbol(span);
+        self.push_token(Flow::Goto(name, span));
+        if !self.labels.iter().any(|label| label.name == name) {
+            self.gotos.push(Jump {
+                name,
+                span,
+                locals: self.locals.len(),
+                depth: self.blocks.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// `function` <name>(?.<name>..)(?:<name>) <body>
+    fn function_stat(&mut self) -> Parse {
+        let from = self.bump().from;
+        let name = self.name()?;
+        if self.blocks.is_empty() && !matches!(self.peek(), LuaKind::Dot | LuaKind::Colon) {
+            let pos = self.dummy();
+            let (variadic, param_end) = self.fn_body(false)?;
+            let def = FnDef {
+                name: self.symbol(name),
+                span: name,
+                ty: None,
+                variadic,
+                pre: false,
+                params: TSpan {
+                    from: pos + 1,
+                    to: param_end,
+                },
+                tokens: TSpan {
+                    from: param_end,
+                    to: self.len(),
+                },
+            };
+            self.set_at(pos, def);
+            return Ok(());
+        }
+
+        let pos = self.dummy();
+        let target = self.len();
+        self.push_expr(ExprKind::Var(self.symbol(name)), name);
+        let mut method = false;
+        while matches!(self.peek(), LuaKind::Dot | LuaKind::Colon) {
+            method = self.bump_kind() == LuaKind::Colon;
+            let field = self.name()?;
+            self.wrap(target, ExprKind::Field(self.symbol(field)));
+            if method {
+                break;
+            }
+        }
+        self.check_target(target)?;
+        let values = self.len();
+        self.closure(from, method)?;
+        self.set_at(
+            pos,
+            Assign {
+                op: None,
+                targets: TSpan {
+                    from: target,
+                    to: values,
+                },
+                values: TSpan {
+                    from: values,
+                    to: self.len(),
+                },
+            },
+        );
+        Ok(())
+    }
+
+    /// `local function` <name> <body>
+    fn local_function(&mut self) -> Parse {
+        let name = self.name()?;
+        // in scope of its own body
+        self.locals.push((self.symbol(name), DeclKind::Let));
+        let pos = self.dummy();
+        let (variadic, param_end) = self.fn_body(false)?;
+        let def = FnDef {
+            name: self.symbol(name),
+            span: name,
+            ty: None,
+            variadic,
+            pre: false,
+            params: TSpan {
+                from: pos + 1,
+                to: param_end,
+            },
+            tokens: TSpan {
+                from: param_end,
+                to: self.len(),
+            },
+        };
+        self.set_at(pos, def);
+        Ok(())
+    }
+
+    /// `local` <name> (?<attrib>), .. (?= <exprs>)
+    fn local(&mut self) -> Parse {
+        let mut names = Vec::new();
+        loop {
+            let span = self.name()?;
+            let kind = self.attrib()?;
+            names.push((self.symbol(span), span, kind));
+            if !self.eat(LuaKind::Comma) {
+                break;
+            }
+        }
+        let decls = self.len();
+        for &(name, span, kind) in &names {
+            self.push_token(Decl {
+                kind,
+                ty: None,
+                name,
+                span,
+                value: false,
+                pre: false,
+            });
+        }
+        if self.eat(LuaKind::Eq) {
+            let values = self.len();
+            self.expr_list()?;
+            if names.len() == 1 && self.count_exprs(values) == 1 {
+                if let Token::Decl(decl) = &mut self.tokens[decls] {
+                    decl.value = true;
+                }
+            } else {
+                // assigned after being declared
+                for (i, &(name, span, _)) in names.iter().enumerate() {
+                    let var = Expr::from(ExprKind::Var(name))
+                        .span(span)
+                        .end(values + i + 1);
+                    self.insert_at(values + i, var);
+                }
+                let targets = TSpan {
+                    from: values + 1,
+                    to: values + 1 + names.len(),
+                };
+                let assign = Assign {
+                    op: None,
+                    targets,
+                    values: TSpan {
+                        from: targets.to,
+                        to: self.len() + 1,
+                    },
+                };
+                self.insert_at(values, assign);
+            }
+        }
+        for (name, _, kind) in names {
+            self.locals.push((name, kind));
+        }
+        Ok(())
+    }
+
+    /// (?`<const>`|`<close>`)
+    fn attrib(&mut self) -> Parse<DeclKind> {
+        if !self.eat(LuaKind::Lt) {
+            return Ok(DeclKind::Let);
+        }
+        let name = self.name()?;
+        self.expect(LuaKind::Gt, "`>`")?;
+        match self.text(name) {
+            "const" => Ok(DeclKind::Const),
+            "close" => Ok(DeclKind::Close),
+            _ => Err(self.error_at(name, "`const` or `close`")),
+        }
+    }
+
+    /// a call, or an assignment to one or more targets
+    fn expr_stat(&mut self) -> Parse {
+        let pos = self.len();
+        self.suffixed()?;
+        if !matches!(self.peek(), LuaKind::Eq | LuaKind::Comma) {
+            return match self.tokens[pos] {
+                Token::Expr(expr) if expr.kind.is_call() => Ok(()),
+                _ => Err(self.error("`=` or a call")),
+            };
+        }
+        let mut targets = vec![pos];
+        while self.eat(LuaKind::Comma) {
+            targets.push(self.len());
+            self.suffixed()?;
+        }
+        self.expect(LuaKind::Eq, "`=`")?;
+        for target in targets {
+            self.check_target(target)?;
+        }
+        let values = self.len();
+        self.expr_list()?;
+        let assign = Assign {
+            op: None,
+            targets: TSpan {
+                from: pos + 1,
+                to: values + 1,
+            },
+            values: TSpan {
+                from: values + 1,
+                to: self.len() + 1,
+            },
+        };
+        self.insert_at(pos, assign);
+        Ok(())
+    }
+
+    /// report assigning to a const local, fail on a target that can't be assigned
+    fn check_target(&mut self, pos: usize) -> Parse {
+        let Token::Expr(expr) = self.tokens[pos] else {
+            unreachable!("assignment target at {pos} isn't an expr");
+        };
+        match expr.kind {
+            ExprKind::Var(name) => {
+                let kind = self.locals.iter().rev().find(|(local, _)| *local == name);
+                if let Some((_, DeclKind::Const | DeclKind::Close)) = kind {
+                    self.push_err(SemanticError::ConstAssign(expr.span, name));
+                }
+                Ok(())
+            }
+            ExprKind::Field(_) | ExprKind::Index => Ok(()),
+            _ => {
+                self.push_err(LexicalError::AssignTarget(expr.span));
+                Err(Stop)
+            }
+        }
+    }
+
+    /// `function` <body> as an expr, `from` is the start of `function`
+    fn closure(&mut self, from: usize, method: bool) -> Parse {
+        let pos = self.dummy();
+        let (variadic, param_end) = self.fn_body(method)?;
+        let closure = Closure {
+            variadic,
+            params: TSpan {
+                from: pos + 1,
+                to: param_end,
+            },
+            tokens: TSpan {
+                from: param_end,
+                to: self.len(),
+            },
+        };
+        let expr = Expr::from(closure)
+            .end(self.len())
+            .span(BSpan::new(from, self.last_end));
+        self.set_at(pos, expr);
+        Ok(())
+    }
+
+    /// `(` <params> `)` <block> `end`, with a `self` param first for methods
+    ///
+    /// Returns whether the fn is variadic & the end of its params.
+    fn fn_body(&mut self, method: bool) -> Parse<(bool, usize)> {
+        let scope = self.locals.len();
+        if method {
+            let span = BSpan::new(self.last_end, self.last_end);
+            self.push_param("self".into(), span);
+        }
+        self.expect(LuaKind::OpenParen, "`(`")?;
+        let mut variadic = false;
+        if self.peek() != LuaKind::CloseParen {
+            loop {
+                if self.eat(LuaKind::DotDotDot) {
+                    variadic = true;
+                    break;
+                }
+                let name = self.name()?;
+                self.push_param(self.symbol(name), name);
+                if !self.eat(LuaKind::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(LuaKind::CloseParen, "`)`")?;
+        let param_end = self.len();
+
+        // labels & loops don't cross fns
+        self.fns.push(variadic);
+        let loops = std::mem::take(&mut self.loops);
+        let labels = std::mem::take(&mut self.labels);
+        let gotos = std::mem::take(&mut self.gotos);
+        let out = self.block();
+        self.unresolved();
+        self.fns.pop();
+        (self.loops, self.labels, self.gotos) = (loops, labels, gotos);
+        out?;
+        self.locals.truncate(scope);
+        self.expect_word("end", "`end` to close `function`")?;
+        Ok((variadic, param_end))
+    }
+
+    fn push_param(&mut self, name: Symbol, span: BSpan) {
+        self.locals.push((name, DeclKind::Let));
+        self.push_token(FnDefParam {
+            ty: None,
+            name,
+            span,
+            value: false,
+        });
+    }
+
+    /// <expr>, ..
+    fn expr_list(&mut self) -> Parse {
+        self.expr()?;
+        while self.eat(LuaKind::Comma) {
+            self.expr()?;
+        }
+        Ok(())
+    }
+
+    /// the number of exprs from `pos` to the end of the tokens
+    fn count_exprs(&self, mut pos: usize) -> usize {
+        let mut count = 0;
+        while let Some(Token::Expr(expr)) = self.tokens.get(pos) {
+            pos = expr.end;
+            count += 1;
+        }
+        count
+    }
+
+    fn expr(&mut self) -> Parse {
+        self.sub_expr(0)
+    }
+
+    /// an expr of operators binding tighter than `limit`
+    fn sub_expr(&mut self, limit: u8) -> Parse {
+        let pos = self.len();
+        if let Some(op) = self.unary_op() {
+            let span = self.bump();
+            self.push_expr(ExprKind::Unary(op), span);
+            self.sub_expr(UNARY_PRIORITY)?;
+            self.close(pos);
+        } else {
+            self.simple_expr()?;
+        }
+        while let Some((op, left, right)) = self.binary_op() {
+            if left <= limit {
+                break;
+            }
+            self.bump();
+            self.wrap(pos, ExprKind::Binary(op));
+            self.sub_expr(right)?;
+            self.close(pos);
+        }
+        Ok(())
+    }
+
+    fn unary_op(&self) -> Option<UnOp> {
+        match self.peek() {
+            LuaKind::Minus => Some(UnOp::Neg),
+            LuaKind::Hash => Some(UnOp::Len),
+            LuaKind::Tilde => Some(UnOp::BitNot),
+            _ if self.is_word("not") => Some(UnOp::Not),
+            _ => None,
+        }
+    }
+
+    /// the next binary operator & its left & right priorities
+    fn binary_op(&self) -> Option<(BinOp, u8, u8)> {
+        use BinOp::*;
+        let op = match self.peek() {
+            LuaKind::Plus => (Add, 10, 10),
+            LuaKind::Minus => (Sub, 10, 10),
+            LuaKind::Star => (Mul, 11, 11),
+            LuaKind::Slash => (FDiv, 11, 11),
+            LuaKind::SlashSlash => (IDiv, 11, 11),
+            LuaKind::Percent => (Mod, 11, 11),
+            LuaKind::Caret => (Pow, 14, 13),
+            LuaKind::DotDot => (Concat, 9, 8),
+            LuaKind::Amp => (BitAnd, 6, 6),
+            LuaKind::Tilde => (BitXor, 5, 5),
+            LuaKind::Pipe => (BitOr, 4, 4),
+            LuaKind::Shl => (Shl, 7, 7),
+            LuaKind::Shr => (Shr, 7, 7),
+            LuaKind::EqEq => (Eq, 3, 3),
+            LuaKind::TildeEq => (Ne, 3, 3),
+            LuaKind::Lt => (Lt, 3, 3),
+            LuaKind::Le => (Le, 3, 3),
+            LuaKind::Gt => (Gt, 3, 3),
+            LuaKind::Ge => (Ge, 3, 3),
+            _ if self.is_word("and") => (And, 2, 2),
+            _ if self.is_word("or") => (Or, 1, 1),
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// a literal, `...`, a table, a closure or a suffixed expr
+    fn simple_expr(&mut self) -> Parse {
+        let (kind, span) = self.peek_lexeme();
+        match kind {
+            LuaKind::Number => {
+                self.bump();
+                let Some(value) = number(self.text(span)) else {
+                    self.push_err(LexicalError::Malformed(span, "number"));
+                    return Err(Stop);
+                };
+                self.push_expr(value, span);
+            }
+            LuaKind::Str { .. } | LuaKind::LongStr { .. } => self.string()?,
+            LuaKind::DotDotDot => {
+                self.bump();
+                if !self.fns.last().copied().unwrap_or(true) {
+                    self.push_err(SemanticError::Varargs(span));
+                }
+                self.push_expr(ExprKind::Varargs, span);
+            }
+            LuaKind::OpenBrace => self.table()?,
+            LuaKind::Name if matches!(self.text(span), "nil" | "true" | "false") => {
+                self.bump();
+                self.push_expr(ExprKind::Var(self.symbol(span)), span);
+            }
+            LuaKind::Name if self.text(span) == "function" => {
+                self.bump();
+                self.closure(span.from, false)?;
+            }
+            _ => self.suffixed()?,
+        }
+        Ok(())
+    }
+
+    /// a name or a parenthesized expr, followed by fields, indexes & calls
+    fn suffixed(&mut self) -> Parse {
+        let pos = self.len();
+        let (kind, span) = self.peek_lexeme();
+        match kind {
+            LuaKind::Name if !self.is_keyword(span) => {
+                self.bump();
+                self.push_expr(ExprKind::Var(self.symbol(span)), span);
+            }
+            LuaKind::OpenParen => {
+                self.bump();
+                self.expr()?;
+                self.expect(LuaKind::CloseParen, "`)`")?;
+                // truncate to a single value
+                if let Token::Expr(expr) = self.tokens[pos] {
+                    if expr.kind.is_multi() {
+                        self.wrap(pos, ExprKind::Paren);
+                        self.close(pos);
+                        if let Token::Expr(paren) = &mut self.tokens[pos] {
+                            paren.span.from = span.from;
+                        }
+                    }
+                }
+            }
+            _ => return Err(self.error("an expression")),
+        }
+
+        loop {
+            match self.peek() {
+                LuaKind::Dot => {
+                    self.bump();
+                    let name = self.name()?;
+                    self.wrap(pos, ExprKind::Field(self.symbol(name)));
+                }
+                LuaKind::OpenBracket => {
+                    self.bump();
+                    self.wrap(pos, ExprKind::Index);
+                    self.expr()?;
+                    self.expect(LuaKind::CloseBracket, "`]`")?;
+                    self.close(pos);
+                }
+                LuaKind::Colon => {
+                    self.bump();
+                    let name = self.name()?;
+                    self.wrap(pos, ExprKind::MethodCall(self.symbol(name)));
+                    self.args()?;
+                    self.close(pos);
+                }
+                LuaKind::OpenParen
+                | LuaKind::Str { .. }
+                | LuaKind::LongStr { .. }
+                | LuaKind::OpenBrace => {
+                    match self.tokens[pos] {
+                        Token::Expr(Expr {
+                            kind: ExprKind::Var(name),
+                            span,
+                            ..
+                        }) => {
+                            self.tokens.pop();
+                            self.push_expr(FnCall { name }, span);
+                        }
+                        _ => self.wrap(pos, ExprKind::Call),
+                    }
+                    self.args()?;
+                    self.close(pos);
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    /// `(` (?<exprs>) `)` | <string> | <table>
+    fn args(&mut self) -> Parse {
+        match self.peek() {
+            LuaKind::Str { .. } | LuaKind::LongStr { .. } => self.string(),
+            LuaKind::OpenBrace => self.table(),
+            _ => {
+                self.expect(LuaKind::OpenParen, "`(`")?;
+                if !self.eat(LuaKind::CloseParen) {
+                    self.expr_list()?;
+                    self.expect(LuaKind::CloseParen, "`)`")?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// `{` (?<field>) .. `}`, a field is `[`<key>`] =` <value>, <name> `=`
+    /// <value> or a value of the list
+    fn table(&mut self) -> Parse {
+        let pos = self.len();
+        let open = self.bump();
+        self.push_expr(ExprKind::Table, open);
+        while self.peek() != LuaKind::CloseBrace {
+            let field = self.len();
+            match self.peek() {
+                LuaKind::OpenBracket => {
+                    let span = self.bump();
+                    self.push_expr(ExprKind::Entry, span);
+                    self.expr()?;
+                    self.expect(LuaKind::CloseBracket, "`]`")?;
+                    self.expect(LuaKind::Eq, "`=`")?;
+                    self.expr()?;
+                    self.close(field);
+                }
+                LuaKind::Name if self.peek_nth(1) == LuaKind::Eq => {
+                    let name = self.name()?;
+                    self.bump();
+                    self.push_expr(ExprKind::FieldInit(self.symbol(name)), name);
+                    self.expr()?;
+                    self.close(field);
+                }
+                _ => self.expr()?,
+            }
+            if !self.eat(LuaKind::Comma) && !self.eat(LuaKind::Semi) {
+                break;
+            }
+        }
+        self.expect(LuaKind::CloseBrace, "`}` to close the table")?;
+        self.close(pos);
+        Ok(())
+    }
+
+    /// a short or long string literal
+    fn string(&mut self) -> Parse {
+        let (kind, span) = self.peek_lexeme();
+        let text = self.text(span);
+        let bytes = match kind {
+            LuaKind::Str { terminated: true } => {
+                unescape(&text[1..text.len() - 1]).map_err(|range| {
+                    let span = BSpan::new(span.from + 1 + range.start, span.from + 1 + range.end);
+                    self.push_err(LexicalError::Malformed(span, "escape sequence"));
+                    Stop
+                })?
+            }
+            LuaKind::LongStr {
+                level,
+                terminated: true,
+            } => long_string(&text[level + 2..text.len() - level - 2]).into_bytes(),
+            _ => {
+                self.push_err(LexicalError::Unclosed(span));
+                return Err(Stop);
+            }
+        };
+        self.bump();
+        let string = String::from_utf8(bytes).unwrap_or_else(|err| {
+            self.push_err(LexicalError::NotInSubset(span, "non utf-8 strings"));
+            String::from_utf8_lossy(err.as_bytes()).into_owned()
+        });
+        self.push_expr(raw_str(&string), span);
+        Ok(())
+    }
+
+    /// a name that isn't a keyword
+    fn name(&mut self) -> Parse<BSpan> {
+        let (kind, span) = self.peek_lexeme();
+        if kind != LuaKind::Name || self.is_keyword(span) {
+            return Err(self.error("a name"));
+        }
+        self.bump();
+        Ok(span)
+    }
+
+    fn is_keyword(&self, span: BSpan) -> bool {
+        KEYWORDS.contains(&self.text(span))
+    }
+
+    /// Report the next lexeme not being what was `expected`
+    fn error(&mut self, expected: &'static str) -> Stop {
+        let (_, span) = self.peek_lexeme();
+        self.error_at(span, expected)
+    }
+
+    fn error_at(&mut self, span: BSpan, expected: &'static str) -> Stop {
+        let kind = self
+            .lexemes
+            .iter()
+            .find(|(_, other)| *other == span)
+            .map(|&(kind, _)| kind);
+        let err = match kind {
+            Some(LuaKind::Eof) => LexicalError::Eof(span.from),
+            Some(
+                LuaKind::Str { terminated: false }
+                | LuaKind::LongStr {
+                    terminated: false, ..
+                }
+                | LuaKind::LongComment { terminated: false },
+            ) => LexicalError::Unclosed(span),
+            _ => LexicalError::Syntax(span, expected),
+        };
+        self.push_err(err);
+        Stop
+    }
+
+    fn expect(&mut self, kind: LuaKind, expected: &'static str) -> Parse<BSpan> {
+        match self.peek() == kind {
+            true => Ok(self.bump()),
+            false => Err(self.error(expected)),
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, expected: &'static str) -> Parse<BSpan> {
+        match self.is_word(word) {
+            true => Ok(self.bump()),
+            false => Err(self.error(expected)),
+        }
+    }
+
+    fn eat(&mut self, kind: LuaKind) -> bool {
+        let found = self.peek() == kind;
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = self.is_word(word);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn is_word(&self, word: &str) -> bool {
+        let (kind, span) = self.peek_lexeme();
+        kind == LuaKind::Name && self.text(span) == word
+    }
+
+    fn peek(&self) -> LuaKind {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> LuaKind {
+        self.lexemes
+            .get(self.next + n)
+            .map_or(LuaKind::Eof, |&(kind, _)| kind)
+    }
+
+    fn peek_lexeme(&self) -> (LuaKind, BSpan) {
+        self.lexemes[self.next.min(self.lexemes.len() - 1)]
+    }
+
+    /// Eat the next lexeme, returning its span
+    fn bump(&mut self) -> BSpan {
+        let (_, span) = self.peek_lexeme();
+        if self.next + 1 < self.lexemes.len() {
+            self.next += 1;
+        }
+        self.last_end = span.to;
+        span
+    }
+
+    /// Eat the next lexeme, returning its kind
+    fn bump_kind(&mut self) -> LuaKind {
+        let kind = self.peek();
+        self.bump();
+        kind
+    }
+
+    fn text(&self, span: BSpan) -> &'a str {
+        &self.src[span.from..span.to]
+    }
+
+    fn symbol(&self, span: BSpan) -> Symbol {
+        self.text(span).into()
+    }
+
+    const fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    fn dummy(&mut self) -> usize {
+        let pos = self.len();
+        self.tokens.push(Token::Dummy);
+        pos
+    }
+
+    fn push_token(&mut self, token: impl Into<Token>) {
+        self.tokens.push(token.into());
+    }
+
+    fn set_at(&mut self, pos: usize, token: impl Into<Token>) {
+        self.tokens[pos] = token.into();
+    }
+
+    fn push_err(&mut self, err: impl Into<crate::error::ErrorOnce>) {
+        self.errors.push(err);
+    }
+
+    /// push a unit expr
+    fn push_expr(&mut self, kind: impl Into<ExprKind>, span: BSpan) {
+        let end = self.len() + 1;
+        self.push_token(Expr::from(kind.into()).end(end).span(span));
+    }
+
+    /// Insert an expr at `pos`, making every token after it its operands
+    fn wrap(&mut self, pos: usize, kind: ExprKind) {
+        let from = match self.tokens.get(pos) {
+            Some(Token::Expr(expr)) => expr.span.from,
+            _ => self.last_end,
+        };
+        self.insert_at(pos, Token::Dummy);
+        let expr = Expr::from(kind)
+            .end(self.len())
+            .span(BSpan::new(from, self.last_end));
+        self.set_at(pos, expr);
+    }
+
+    /// End the expr at `pos` after the last token & lexeme
+    fn close(&mut self, pos: usize) {
+        let (end, last_end) = (self.len(), self.last_end);
+        if let Token::Expr(expr) = &mut self.tokens[pos] {
+            expr.end = end;
+            expr.span.to = last_end;
+        }
+    }
+
+    /// Insert a token at `pos`, shifting every token after it
+    fn insert_at(&mut self, pos: usize, token: impl Into<Token>) {
+        for token in &mut self.tokens[pos..] {
+            token.shift(pos);
+        }
+        self.tokens.insert(pos, token.into());
+    }
+}
+
+/// The value of a lua numeral, as a decimal int or float literal
+pub(crate) fn number(text: &str) -> Option<Value> {
+    let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"));
+    let (digits, exponent) = match hex {
+        Some(hex) => match hex.split_once(['p', 'P']) {
+            Some((digits, exponent)) => (digits, Some(exponent.parse::<i32>().ok()?)),
+            None => (hex, None),
+        },
+        None => (text, None),
+    };
+
+    if let Some(hex) = hex.map(|_| digits) {
+        let (int, frac) = hex.split_once('.').unwrap_or((hex, ""));
+        let is_hex = |s: &str| s.bytes().all(|b| b.is_ascii_hexdigit());
+        if int.len() + frac.len() == 0 || !is_hex(int) || !is_hex(frac) {
+            return None;
+        }
+        let digits = int
+            .chars()
+            .chain(frac.chars())
+            .filter_map(|c| c.to_digit(16));
+        if exponent.is_none() && !hex.contains('.') {
+            // like lua, hex ints wrap around
+            let int = digits.fold(0u64, |n, d| n.wrapping_mul(16).wrapping_add(u64::from(d)));
+            return Some(int_literal(int.cast_signed()));
+        }
+        let mantissa = digits.fold(0.0, |n: f64, d| n.mul_add(16.0, f64::from(d)));
+        let shift = i32::try_from(frac.len()).ok()?.checked_mul(4)?;
+        let exponent = exponent.unwrap_or(0).checked_sub(shift)?;
+        return Some(float_literal(mantissa * 2f64.powi(exponent)));
+    }
+
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        if let Ok(int) = text.parse() {
+            return Some(int_literal(int));
+        }
+    }
+    let valid = text
+        .bytes()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'+' | b'-'));
+    valid
+        .then(|| text.parse().ok())
+        .flatten()
+        .map(float_literal)
+}
+
+fn int_literal(int: i64) -> Value {
+    let text = int.to_string();
+    let kind = LiteralKind::Int {
+        base: Base::Decimal,
+        empty_int: false,
+    };
+    Value::new(text.as_str().into(), kind, text.len())
+}
+
+fn float_literal(float: f64) -> Value {
+    let text = format!("{float:?}");
+    let kind = LiteralKind::Float {
+        base: Base::Decimal,
+        empty_exponent: false,
+    };
+    Value::new(text.as_str().into(), kind, text.len())
+}
+
+/// `string` as a raw string literal
+fn raw_str(string: &str) -> Value {
+    // enough hashes that no `"#..` inside ends the literal
+    let hashes = string
+        .split('"')
+        .skip(1)
+        .map(|after| after.bytes().take_while(|&b| b == b'#').count() + 1)
+        .max()
+        .unwrap_or(0);
+    let hashes = u8::try_from(hashes).unwrap_or(u8::MAX);
+    let fence = "#".repeat(usize::from(hashes));
+    let text = format!("r{fence}\"{string}\"{fence}");
+    let kind = LiteralKind::RawStr {
+        n_hashes: Some(hashes),
+    };
+    Value::new(text.as_str().into(), kind, text.len())
+}
+
+/// The bytes of the inside of a short string, or the range of a bad escape
+fn unescape(body: &str) -> Result<Vec<u8>, Range<usize>> {
+    let bytes = body.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while let Some(&b) = bytes.get(i) {
+        i += 1;
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+        let start = i - 1;
+        let Some(&c) = bytes.get(i) else {
+            return Err(start..i);
+        };
+        i += 1;
+        match c {
+            b'a' => out.push(7),
+            b'b' => out.push(8),
+            b'f' => out.push(12),
+            b'n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            b'v' => out.push(11),
+            b'\\' | b'"' | b'\'' => out.push(c),
+            b'\n' | b'\r' => {
+                out.push(b'\n');
+                if bytes
+                    .get(i)
+                    .is_some_and(|&n| matches!(n, b'\n' | b'\r') && n != c)
+                {
+                    i += 1;
+                }
+            }
+            b'x' => {
+                let hex = bytes
+                    .get(i..i + 2)
+                    .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+                let Some(hex) = hex else {
+                    return Err(start..(i + 2).min(bytes.len()));
+                };
+                let hex = std::str::from_utf8(hex).unwrap_or_default();
+                out.push(u8::from_str_radix(hex, 16).unwrap_or_default());
+                i += 2;
+            }
+            b'z' => {
+                while bytes.get(i).is_some_and(|&b| is_whitespace(char::from(b))) {
+                    i += 1;
+                }
+            }
+            b'0'..=b'9' => {
+                let len = bytes[i - 1..]
+                    .iter()
+                    .take(3)
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                let digits = &body[i - 1..i - 1 + len];
+                i += len - 1;
+                match digits.parse::<u8>() {
+                    Ok(byte) => out.push(byte),
+                    Err(_) => return Err(start..i),
+                }
+            }
+            b'u' => {
+                let close = body[i..].find('}').map(|at| i + at);
+                let digits = close
+                    .filter(|_| bytes.get(i) == Some(&b'{'))
+                    .map(|close| &body[i + 1..close])
+                    .filter(|digits| !digits.is_empty());
+                let code = digits.and_then(|digits| u32::from_str_radix(digits, 16).ok());
+                let (Some(code), Some(close)) = (code.filter(|&code| code < 1 << 31), close) else {
+                    return Err(start..close.map_or(i, |close| close + 1));
+                };
+                i = close + 1;
+                match char::from_u32(code) {
+                    Some(c) => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    // lua encodes these anyway, a str can't hold them
+                    None => out.push(0xff),
+                }
+            }
+            _ => return Err(start..i),
+        }
+    }
+    Ok(out)
+}
+
+/// The inside of a long string, without a first newline & with every
+/// newline as `\n`
+fn long_string(body: &str) -> String {
+    let body = ["\r\n", "\n\r", "\n", "\r"]
+        .iter()
+        .find_map(|newline| body.strip_prefix(newline))
+        .unwrap_or(body);
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' | '\r' => {
+                out.push('\n');
+                chars.next_if(|&n| matches!(n, '\n' | '\r') && n != c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,197 @@
+use crate::error::ErrorMulti;
+use crate::parse::lua::*;
+use crate::parse::test::write::{write_errs, write_module};
+use crate::runtime::{Engine, Value as RtValue};
+use pretty_assertions::assert_eq;
+
+fn parse(src: &str) -> (Module, ErrorMulti) {
+    LuaReader::new(src).module("test")
+}
+
+/// the tokens of `src` as text, asserting it has no errors
+fn tokens(src: &str) -> String {
+    let (module, errors) = parse(src);
+    assert_eq!(write_errs(src, &errors), "", "{src:?}");
+    write_module(src, &module).join(" ")
+}
+
+/// the tokens & errors of `src` as text
+fn errors(src: &str) -> (String, String) {
+    let (module, errors) = parse(src);
+    (
+        write_module(src, &module).join(" "),
+        write_errs(src, &errors),
+    )
+}
+
+fn run(src: &str) -> Vec<RtValue> {
+    let (module, errors) = parse(src);
+    assert!(errors.is_empty(), "{errors:#?}");
+    Engine::new().run(module).unwrap()
+}
+
+#[test]
+fn statements() {
+    assert_eq!(
+        tokens("local a, b <const> = 1\nlocal c = a -- c\n--[[ d ]] a.b[1] = f 's'"),
+        "let a const b a , b = 1 let c = a a . b [ 1 ] = f ( r\"s\" )"
+    );
+    assert_eq!(
+        tokens("local function f(x, ...) return x end\nfunction g() end\nfunction t.a:m() end"),
+        "fn f ... x return x fn g t . a . m = fn ( self ) { }"
+    );
+    // only a global fn at the top level is a fn def
+    assert_eq!(tokens("do function f() end end"), "{ f = fn ( ) { } }");
+    assert_eq!(
+        tokens("if a then f() elseif b then g() else do h() end end"),
+        "if a f ( ) else if b g ( ) else { h ( ) }"
+    );
+    assert_eq!(
+        tokens("x, y = y, x; a:m'b'.c 'd'"),
+        "x , y = y , x a . m ( r\"b\" ) . c ( r\"d\" )"
+    );
+}
+
+#[test]
+fn expressions() {
+    assert_eq!(
+        tokens("return 1 + 2 * 3 ^ 2 ^ 1 .. 'a' .. 'b', not a == b, a or b and c, #s, ~1 ~ 2"),
+        "return 1 + 2 * 3 ** 2 ** 1 .. r\"a\" .. r\"b\" , ! a == b , a || b && c , # s , ~ 1 ^ 2"
+    );
+    assert_eq!(
+        tokens("return (f()), (...), (a), function(...) return ... end"),
+        "return ( f ( ) ) , ( ... ) , a , fn ( ... ) { return ... }"
+    );
+}
+
+#[test]
+fn literals() {
+    assert_eq!(
+        tokens("return 0xff, 0xffffffffffffffff, 1e2, .5, 0x.8p1, 9223372036854775808, 3"),
+        "return 255 , -1 , 100.0 , 0.5 , 1.0 , 9.223372036854776e18 , 3"
+    );
+    assert_eq!(
+        tokens("return 'a\\tb\\65\\x41\\u{e9}\\z   c', [==[\nx]]\"#]==], \"q\\\"\""),
+        "return r\"a\tbAAéc\" , r##\"x]]\"#\"## , r#\"q\"\"#"
+    );
+}
+
+#[test]
+fn syntax_errors() {
+    // parsing stops at the first error, dropping its top level statement
+    assert_eq!(
+        errors("local a = 1\nlocal b = 2 +\nlocal c = 3"),
+        (
+            "let a = 1".to_owned(),
+            "expected pos 26,31 to be an expression but was \"local\"".to_owned()
+        )
+    );
+    let err = |src| errors(src).1;
+    assert_eq!(err("f() g"), "eof 5");
+    assert_eq!(err("if x then"), "eof 9");
+    assert_eq!(
+        err("return 1 x = 2"),
+        "expected pos 9,10 to be the end of the block after `return` but was \"x\""
+    );
+    assert_eq!(
+        err("return 1 end"),
+        "expected pos 9,12 to be the end of the file but was \"end\""
+    );
+    assert_eq!(err("f() = 1"), "assign target 0,3 = \"f()\"");
+    assert_eq!(err("return 'a"), "unclosed 7,9 = \"'a\"");
+    assert_eq!(err("--[[ abc"), "unclosed 0,8 = \"--[[ abc\"");
+    assert_eq!(err("x = '\\q'"), "malformed escape sequence 5,7 = \"\\q\"");
+    assert_eq!(err("x = 3x"), "malformed number 4,6 = \"3x\"");
+    assert_eq!(
+        err("local x <foo> = 1"),
+        "expected pos 9,12 to be `const` or `close` but was \"foo\""
+    );
+}
+
+#[test]
+fn loops() {
+    assert_eq!(
+        tokens("while x do f() break end\nrepeat local z until z\nfor i = 1, 2 do end"),
+        "while x { f ( ) break } repeat { let z } until z for i = 1 , 2 { }"
+    );
+    assert_eq!(
+        tokens("for k, v in pairs(t) do local k <close> = v end"),
+        "for k , v in pairs ( t ) { close k = v }"
+    );
+}
+
+#[test]
+fn tables() {
+    assert_eq!(
+        tokens("local t = {1, x = 2, [3] = 4; f()}"),
+        "let t = { 1 , x = 2 , [ 3 ] = 4 , f ( ) }"
+    );
+    assert_eq!(tokens("local t = {}"), "let t = { }");
+}
+
+#[test]
+fn gotos() {
+    assert_eq!(
+        tokens("goto l do goto l end ::l:: while x do goto continue ::continue:: end"),
+        "goto l { goto l } ::l:: while x { goto continue ::continue:: }"
+    );
+    let src = "break\ngoto a\n::b:: ::b::\ngoto c local x ::c:: f()\n\
+               function f() goto b end\ndo ::d:: end goto d";
+    assert_eq!(
+        errors(src).1,
+        "loop break 0,5 = \"break\"
+dupe label \"b\" 21,22 = \"b\"
+goto \"c\" into \"x\" 30,31 = \"c\"
+no goto label \"b\" 68,69 = \"b\"
+no goto label \"a\" 11,12 = \"a\"
+no goto label \"d\" 92,93 = \"d\""
+    );
+}
+
+#[test]
+fn semantic_errors() {
+    let src = "local x <const> = 1\nx = 2\n\
+               function f(...) local y <const> = 1 return function() y = ... end end\n\
+               local x = 3 x = 4";
+    assert_eq!(
+        errors(src).1,
+        "const assign 20,21 to \"x\" = \"x\"
+const assign 80,81 to \"y\" = \"y\"
+varargs 84,87 = \"...\""
+    );
+}
+
+#[test]
+fn runs() {
+    use RtValue::*;
+    let src = "
+        local function fact(n)
+            if n <= 1 then return 1 else return n * fact(n - 1) end
+        end
+        local a, b = 1, 2
+        a, b = b, a
+        function add(...) return ... end
+        local s = [[
+long]] .. ' ' .. 1 .. ' ' .. 2.5
+        return fact(5), s, 7 // 2, -7 // 2, 7.5 // 2, 2 ^ 10, #'abc', ~5, 5 ~= 6, a, b, add(1, 2)
+    ";
+
+    assert_eq!(
+        run(src),
+        [
+            Int(120),
+            Str("long 1 2.5".into()),
+            Int(3),
+            Int(-4),
+            Float(3.0),
+            Float(1024.0),
+            Int(3),
+            Int(-6),
+            Bool(true),
+            Int(2),
+            Int(1),
+            Int(1),
+            Int(2),
+        ]
+    );
+}
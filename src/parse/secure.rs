@@ -2,14 +2,17 @@
 use std::collections::VecDeque;
 
 use crate::{
-    error::{ErrorMulti, ErrorOnce},
+    error::{ErrorMulti, ErrorOnce, LexicalError},
     lex::{Cursor, Lexeme},
-    parse::{self, ExprKind, LITERAL},
+    parse::{self, ExprKind},
     span::{BSpan, TSpan},
     util::Symbol,
 };
 
-use super::{token::Token, AsBSpan, AsStr, Expr, LexKind};
+use super::{
+    token::{Block, Token},
+    AsBSpan, AsStr, Expr,
+};
 
 /// Reads tokens into a tokenstream
 #[derive(Debug, Default)]
@@ -21,8 +24,15 @@ pub struct Reader<'a> {
     blocks: Vec<(usize, BSpan)>,
     /// a backlog of control flows
     flows: VecDeque<usize>,
-    /// a backlog of idents
-    exprs: VecDeque<usize>,
+    /// whether each fn being parsed is variadic
+    fns: Vec<bool>,
+    /// the number of expected lexemes not found, a statement failed if this grew
+    failures: usize,
+    /// in the condition of an `if` or the values of a `match`, where a `{`
+    /// opens the body instead of a block
+    cond: bool,
+    /// the struct & enum decls of the whole source, see [`parse::types`]
+    types: Vec<Token>,
 }
 
 impl<'a> Reader<'a> {
@@ -30,6 +40,25 @@ impl<'a> Reader<'a> {
     pub fn new(src: &'a str) -> Self {
         Self {
             cursor: Cursor::new(src),
+            types: parse::types(src),
+            ..Default::default()
+        }
+    }
+
+    /// A reader continuing after `tokens`, from the byte `pos` of `src`
+    #[must_use]
+    pub fn resume(src: &'a str, pos: usize, tokens: Vec<Token>) -> Self {
+        Self {
+            types: parse::types(src),
+            ..Self::at(src, pos, tokens)
+        }
+    }
+
+    /// [`Self::resume`] without looking for the types of `src`
+    pub(super) fn at(src: &'a str, pos: usize, tokens: Vec<Token>) -> Self {
+        Self {
+            cursor: Cursor::at(src, pos),
+            tokens,
             ..Default::default()
         }
     }
@@ -42,9 +71,11 @@ impl<'a> Reader<'a> {
             tokens,
             blocks,
             flows: _,
-            exprs,
+            fns: _,
+            failures: _,
+            cond: _,
+            types: _,
         } = self;
-        // println!("{exprs:#?}");
 
         (cursor, errors, tokens, blocks)
     }
@@ -56,14 +87,17 @@ impl<'a> Reader<'a> {
     }
 
     pub fn set_block(&mut self, token: Lexeme) {
-        let Some((pos, _)) = self.blocks.pop() else {
+        let Some((pos, open)) = self.blocks.pop() else {
             self.err_expected(token, parse::EXPECTED);
             return;
         };
 
-        self.tokens[pos] = Token::Block(TSpan {
-            from: pos,
-            to: self.len(),
+        self.tokens[pos] = Token::Block(Block {
+            span: BSpan::new(open.from, self.span(token).to),
+            tokens: TSpan {
+                from: pos + 1,
+                to: self.len(),
+            },
         });
     }
 
@@ -72,103 +106,98 @@ impl<'a> Reader<'a> {
             .push((pos, BSpan::new(self.lex_pos(), self.cursor.pos())));
     }
 
-    /// returns `Err(false)` if compiler error
-    pub fn get_expr(&mut self) -> Result<(usize, Expr), bool> {
-        let pos = self.first_ident().ok_or(true)?;
-        // println!("checking pos: {pos}");
-        match self.get_token(pos) {
-            // Some(Token::Expr(expr)) if expr.end != pos => match self.get_token(expr.end - 1) {
-            //     Some(Token::Expr(expr)) => Ok((pos, expr)),
-            //     Some(other) => {
-            //         self.compiler_error(format!(
-            //             "non expr token found at end {}: {other:#?}",
-            //             expr.end
-            //         ));
-            //         Err(false)
-            //     }
-            //     None => {
-            //         self.compiler_error(format!("no token found at end {}", expr.end));
-            //         Err(false)
-            //     }
-            // },
-            Some(Token::Expr(expr)) => Ok((pos, expr)),
-            Some(other) => {
-                self.compiler_error(format!("non expr token found at pos {pos}: {other:#?}"));
-                Err(false)
-            }
-            None => {
-                self.compiler_error(format!("no token found at pos {pos}"));
-                Err(false)
-            }
-        }
-    }
-
     pub fn push_ident(&mut self, symbol: impl Into<AsStr<'a>>) {
-        // use LexKind::*;
-        let pos = self.len();
         self.push_expr(ExprKind::Var(self.symbol(symbol)));
-        // println!("{:#?}", self.tokens);
-        if let Some((pos, mut expr)) = self.get_expr().ok() {
-            // println!("another one: {pos}");
-            let ExprKind::FnCall(call) = &mut expr.kind else {
-                return;
-            };
-            if expr.end != pos {
-                // TODO: error handling here
-                if expr.end + 1 != self.len() {
-                    // println!("DAMN 1");
-                    return;
-                }
-                if !call.comma {
-                    // println!("DAMN 2");
-                    return;
-                }
-            }
-            call.comma = false;
-            expr.end = self.len();
-            self.set_at(pos, expr);
-            // println!("{self:#?}");
-        }
-        self.exprs.push_back(pos);
     }
 
-    pub fn pop_ident(&mut self) -> Option<usize> {
-        self.exprs.pop_front()
+    /// Insert an expr at `pos`, making every token after it its operands.
+    pub fn wrap_expr(&mut self, pos: usize, kind: impl Into<ExprKind>) {
+        let end = self.len() + 1;
+        let from = match self.tokens.get(pos) {
+            Some(Token::Expr(expr)) => expr.span.from,
+            _ => self.lex_pos(),
+        };
+        self.insert_at(
+            pos,
+            Expr {
+                end,
+                kind: kind.into(),
+                span: BSpan::new(from, self.cursor.pos()),
+            },
+        );
     }
 
-    pub fn first_ident(&mut self) -> Option<usize> {
-        self.exprs.front().copied()
+    /// Insert a token at `pos`, shifting every token after it & growing
+    /// the tokens owning it
+    pub fn insert_at(&mut self, pos: usize, token: impl Into<Token>) {
+        let (before, after) = self.tokens.split_at_mut(pos);
+        for token in before {
+            token.shift(pos + 1);
+        }
+        for token in after {
+            token.shift(pos);
+        }
+        for flow in &mut self.flows {
+            if *flow >= pos {
+                *flow += 1;
+            }
+        }
+        self.tokens.insert(pos, token.into());
     }
 
     pub fn push_flow(&mut self, pos: usize) {
         self.flows.push_back(pos);
     }
 
+    /// Run `run` with the last control flow not yet given an else
     // TODO: rename this
     pub fn last_flow(&mut self, run: impl Fn(&mut Self, usize) -> bool) -> bool {
-        let Some(&flow) = self.flows.front() else {
+        let Some(&flow) = self.flows.back() else {
             return false;
         };
         let used = run(self, flow);
+        // an else if pushes its own flow after this one
         if used {
-            self.flows.pop_front();
+            if let Some(i) = self.flows.iter().rposition(|&pos| pos == flow) {
+                self.flows.remove(i);
+            }
         }
         true
     }
 
+    pub fn push_fn(&mut self, variadic: bool) {
+        self.fns.push(variadic);
+    }
+
+    pub fn pop_fn(&mut self) {
+        self.fns.pop();
+    }
+
+    /// true if `...` may be used, the top level of a module is variadic
     #[must_use]
-    pub fn blocks_left(&self) -> bool {
+    pub fn in_variadic(&self) -> bool {
+        self.fns.last().copied().unwrap_or(true)
+    }
+
+    /// the number of open blocks
+    #[must_use]
+    pub const fn block_depth(&self) -> usize {
+        self.blocks.len()
+    }
+
+    #[must_use]
+    pub const fn blocks_left(&self) -> bool {
         !self.blocks.is_empty()
     }
 
     #[inline]
     #[must_use]
-    pub fn len(&self) -> usize {
+    pub const fn len(&self) -> usize {
         self.tokens.len()
     }
 
     #[must_use]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.tokens.is_empty()
     }
 
@@ -176,17 +205,67 @@ impl<'a> Reader<'a> {
         self.tokens.truncate(len);
     }
 
+    /// Truncate to `len`, also forgetting the blocks & control flows past it
+    pub fn discard(&mut self, len: usize) {
+        self.truncate(len);
+        while self.blocks.last().is_some_and(|&(pos, _)| pos >= len) {
+            self.blocks.pop();
+        }
+        self.flows.retain(|&pos| pos < len);
+    }
+
     /// Replace the given index with the given token
     pub fn set_at(&mut self, set_idx: usize, token: impl Into<Token>) {
         self.tokens[set_idx] = token.into();
     }
 
+    /// Push an error, past [`parse::MAX_ERRORS`] errors are dropped
     pub fn push_err(&mut self, err: impl Into<ErrorOnce>) {
-        self.errors.push(err);
+        let err = err.into();
+        if matches!(
+            err,
+            ErrorOnce::Lexical(
+                LexicalError::Expected(..) | LexicalError::Eof(_) | LexicalError::Syntax(..)
+            )
+        ) {
+            self.failures += 1;
+        }
+        match self.errors.len() {
+            len if len < parse::MAX_ERRORS => self.errors.push(err),
+            len if len == parse::MAX_ERRORS => {
+                self.errors.push(LexicalError::TooMany(self.lex_pos()));
+            }
+            _ => (),
+        }
+    }
+
+    #[must_use]
+    pub const fn failures(&self) -> usize {
+        self.failures
+    }
+
+    #[must_use]
+    pub const fn in_cond(&self) -> bool {
+        self.cond
+    }
+
+    /// Returns the previous value
+    pub const fn set_cond(&mut self, cond: bool) -> bool {
+        std::mem::replace(&mut self.cond, cond)
+    }
+
+    /// Move the cursor back to the byte `pos`
+    pub fn rewind(&mut self, pos: usize) {
+        self.cursor = Cursor::at(self.cursor.src(), pos);
+    }
+
+    /// Forget the failures after the first `failures`, once they are recovered from
+    pub const fn set_failures(&mut self, failures: usize) {
+        self.failures = failures;
     }
 
     pub fn compiler_error(&mut self, err: impl Into<String>) {
-        self.errors.push(ErrorOnce::Other(err.into()));
+        self.push_err(ErrorOnce::Other(err.into()));
     }
 
     pub fn push_token(&mut self, token: impl Into<Token>) {
@@ -197,15 +276,33 @@ impl<'a> Reader<'a> {
     ///
     /// # NOTE
     ///
-    /// The span inputted with this will always be empty, so this
+    /// The span inputted with this will always be a single token, so this
     /// function should only be used by unit items.
     pub fn push_expr(&mut self, kind: impl Into<ExprKind>) {
         self.push_token(Expr {
-            end: self.len(),
+            end: self.len() + 1,
             kind: kind.into(),
+            span: BSpan::new(self.lex_pos(), self.cursor.pos()),
         });
     }
 
+    /// Set the end of the expr at `pos` to the current length
+    pub fn close_expr(&mut self, pos: usize) {
+        let end = self.len();
+        let to = self.cursor.pos();
+        if let Some(Token::Expr(expr)) = self.tokens.get_mut(pos) {
+            expr.end = end;
+            expr.span.to = to;
+        }
+    }
+
+    /// Set the start of the span of the expr at `pos`
+    pub fn expr_from(&mut self, pos: usize, from: usize) {
+        if let Some(Token::Expr(expr)) = self.tokens.get_mut(pos) {
+            expr.span.from = from;
+        }
+    }
+
     pub fn pop_token(&mut self) -> Option<Token> {
         self.tokens.pop()
     }
@@ -246,6 +343,16 @@ impl<'a> Reader<'a> {
         self.tokens.get(index).copied()
     }
 
+    #[must_use]
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    #[must_use]
+    pub fn types(&self) -> &[Token] {
+        &self.types
+    }
+
     #[must_use]
     pub fn last_token(&self) -> Option<Token> {
         self.tokens.last().copied()
@@ -1,8 +1,13 @@
-use super::Reader;
+use super::{
+    token::{Flow, Token},
+    Reader,
+};
+use crate::{error::ErrorMulti, span::line_col};
 use pretty_assertions::assert_eq;
+use std::{fs, path::Path};
 
 use write::*;
-mod write;
+pub(super) mod write;
 
 const PUNCT_SRC: &str = "}()[],.@#~?:$=!<>-&|+*/^%";
 
@@ -16,17 +21,14 @@ fn map_errs(s: &str) -> String {
             errs.push_str(s);
             errs.push('\n');
         });
-    if errs.chars().last() == Some('\n') {
+    if errs.ends_with('\n') {
         errs.pop();
     }
     errs
 }
 
 fn map_tokens(tokens: &[&str]) -> Vec<String> {
-    tokens
-        .into_iter()
-        .map(|&s| s.to_owned())
-        .collect::<Vec<_>>()
+    tokens.iter().map(|&s| s.to_owned()).collect::<Vec<_>>()
 }
 
 macro_rules! pos {
@@ -74,9 +76,13 @@ fn empty() {
 fn unexpected_punct() {
     do_test!(
         PUNCT_SRC,
-        [],
+        ["error"],
         r#"
-expected pos 0,25 to be "ident | r#ident | open brace | end of file" but was "}()[],.@#~?:$=!<>-&|+*/^%""#,
+        expected pos 0,1 to be "ident | r#ident | open brace | end of file" but was "}"
+        expected pos 2,14 to be "ident | r#ident | literal" but was ")[],.@#~?:$="
+        expected pos 15,17 to be "ident | r#ident | literal" but was "<>"
+        expected pos 18,19 to be "ident | r#ident | literal" but was "&"
+        expected pos 20,21 to be "ident | r#ident | |" but was "+""#,
     );
 }
 
@@ -92,10 +98,14 @@ fn let_punct_fail() {
     // the errors afterwards are for base unexpected errors
     do_test!(
         &("let ".to_owned() + PUNCT_SRC),
-        [],
+        ["error", "error"],
         r#"
         expected pos 4,5 to be "ident | r#ident" but was "}"
-        expected pos 5,29 to be "ident | r#ident | open brace | end of file" but was "()[],.@#~?:$=!<>-&|+*/^%""#,
+        expected pos 4,5 to be "ident | r#ident | open brace | end of file" but was "}"
+        expected pos 6,18 to be "ident | r#ident | literal" but was ")[],.@#~?:$="
+        expected pos 19,21 to be "ident | r#ident | literal" but was "<>"
+        expected pos 22,23 to be "ident | r#ident | literal" but was "&"
+        expected pos 24,25 to be "ident | r#ident | |" but was "+""#,
     );
 }
 
@@ -105,12 +115,12 @@ fn multi_err() {
         "\
         let aa = // \n\
         /**/ ^@@ # !/*/*/**/*/",
-        ["let", "aa"],
+        ["let", "aa", "error"],
         r##"
             expected pos 18,21 to be "ident | r#ident | literal" but was "^@@"
             expected pos 22,23 to be "ident | r#ident | literal" but was "#"
-            expected pos 24,25 to be "ident | r#ident | literal" but was "!"
             unclosed 25,35 = "/*/*/**/*/"
+            eof 35
             "##,
     );
 }
@@ -125,24 +135,20 @@ fn decl() {
 
 #[test]
 fn decl_with_type() {
+    do_test!("let ustr yeah = 3", ["let", "ustr", "yeah", "=", "3"], "");
     do_test!(
-        "let string yeah = 3",
-        ["let", "string", "yeah", "=", "3"],
+        "const ustr yeah = 3",
+        ["const", "ustr", "yeah", "=", "3"],
         ""
     );
     do_test!(
-        "const string yeah = 3",
-        ["const", "string", "yeah", "=", "3"],
+        "let ustr r#yeah = 3",
+        ["let", "ustr", "r#yeah", "=", "3"],
         ""
     );
     do_test!(
-        "let string r#yeah = 3",
-        ["let", "string", "r#yeah", "=", "3"],
-        ""
-    );
-    do_test!(
-        "const string r#yeah = 3",
-        ["const", "string", "r#yeah", "=", "3"],
+        "const ustr r#yeah = 3",
+        ["const", "ustr", "r#yeah", "=", "3"],
         ""
     );
 }
@@ -150,13 +156,13 @@ fn decl_with_type() {
 #[test]
 fn let_chain() {
     do_test!(
-        &"let yeah = 3 ".repeat(5),
+        &"let yeah = 3; ".repeat(5),
         [
             "let", "yeah", "=", "3", "let", "yeah", "=", "3", "let", "yeah", "=", "3", "let",
             "yeah", "=", "3", "let", "yeah", "=", "3"
         ],
         "",
-    )
+    );
 }
 
 #[test]
@@ -186,32 +192,31 @@ fn fn_call_str() {
 
 #[test]
 fn fn_fail_single_param() {
-    do_test!(r#"print("""#, [], "eof 8");
-    do_test!(r#"print("#, [], "eof 6");
-    do_test!(r#"print(print"#, [], "eof 11");
+    do_test!(r#"print("""#, ["error"], "eof 8");
+    do_test!(r#"print("#, ["error"], "eof 6");
+    do_test!(r#"print(print"#, ["error"], "eof 11");
     do_test!(
         r#"print(print("#,
-        [],
+        ["error"],
         r#"
         eof 12
         eof 12"#,
     );
-    do_test!(r#"print"#, [], r#"eof 5"#);
 }
 
 #[test]
 fn fn_fail_multi_param() {
-    do_test!(r#"print("yeah","""#, [], "eof 15");
-    do_test!(r#"print(one"#, [], "eof 9");
-    do_test!(r#"print(yeah, yeah(), """#, [], r#"eof 22"#);
+    do_test!(r#"print("yeah","""#, ["error"], "eof 15");
+    do_test!(r#"print(one"#, ["error"], "eof 9");
+    do_test!(r#"print(yeah, yeah(), """#, ["error"], r#"eof 22"#);
     do_test!(
         r#"print(print(), print("#,
-        [],
+        ["error"],
         r#"
             eof 21
             eof 21"#,
     );
-    do_test!(r#"print(yeah, 1, print"#, [], "eof 20");
+    do_test!(r#"print(yeah, 1, print"#, ["error"], "eof 20");
 }
 
 #[test]
@@ -226,7 +231,7 @@ fn nested_fn() {
         r#"n0(n1(n2(n3(n4(n5(n6()))))))"#,
         [
             "n0", "(", "n1", "(", "n2", "(", "n3", "(", "n4", "(", "n5", "(", "n6", "(", ")", ")",
-            ")", ")", ")",
+            ")", ")", ")", ")", ")",
         ],
         "",
     );
@@ -253,26 +258,26 @@ fn block() {
     do_test!(
         r#"{
             {
-                let string yeah = ""
+                let ustr yeah = ""
                 print(yeah)
             }
         }"#,
-        ["{", "{", "let", "string", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}"],
+        ["{", "{", "let", "ustr", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}"],
         "",
     );
     do_test!(
         &r#"
         {
             {
-                let string yeah = ""
+                let ustr yeah = ""
                 print(yeah)
             }
         }"#
         .repeat(3),
         [
-            "{", "{", "let", "string", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}",
-            "{", "{", "let", "string", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}",
-            "{", "{", "let", "string", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}",
+            "{", "{", "let", "ustr", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}", "{",
+            "{", "let", "ustr", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}", "{", "{",
+            "let", "ustr", "yeah", "=", "\"\"", "print", "(", "yeah", ")", "}", "}",
         ],
         "",
     );
@@ -290,17 +295,17 @@ fn unclosed_block() {
         "#,
     );
     do_test!(
-        "print(yeah){{}",
+        "print(yeah);{{}",
         ["print", "(", "yeah", ")"],
         r#"
-        unclosed 11,14 = "{{}"
+        unclosed 12,15 = "{{}"
         "#,
     );
     do_test!(
-        "print(yeah){{print(yeah)}",
+        "print(yeah);{{print(yeah)}",
         ["print", "(", "yeah", ")"],
         r#"
-        unclosed 11,25 = "{{print(yeah)}"
+        unclosed 12,26 = "{{print(yeah)}"
         "#,
     );
 }
@@ -309,16 +314,16 @@ fn unclosed_block() {
 fn empty_fn() {
     do_test!(r#"fn yeah() {}"#, ["fn", "yeah",], "",);
     do_test!(
-        r#"fn yeah(string yeah) {}"#,
-        ["fn", "yeah", "string", "yeah",],
+        r#"fn yeah(ustr yeah) {}"#,
+        ["fn", "yeah", "ustr", "yeah",],
         "",
     );
     do_test!(
-        r#"fn yeah(string yeah, string b = "") {}"#,
-        ["fn", "yeah", "string", "yeah", "string", "b", "=", "\"\"",],
+        r#"fn yeah(ustr yeah, ustr b = "") {}"#,
+        ["fn", "yeah", "ustr", "yeah", "ustr", "b", "=", "\"\"",],
         "",
     );
-    do_test!(r#"fn string yeah() {}"#, ["fn", "string", "yeah"], "",);
+    do_test!(r#"fn ustr yeah() {}"#, ["fn", "ustr", "yeah"], "",);
 }
 
 #[test]
@@ -327,48 +332,48 @@ fn assorted_fn() {
     do_test!(
         r#"
 fn yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     print("${hello}, ${world}!")
 }"#,
         [
             "fn", "yeah",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
-            "print", "(", "\"${hello}, ${world}!\"", ")"
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
+            "return", "print", "(", "\"${hello}, ${world}!\"", ")"
         ],
         "",
     );
     do_test!(
         r#"
-fn string yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+fn ustr yeah() {
+    const ustr hello = "Hello"
+    const ustr world = "World"
     return "${hello}, ${world}!"
 }"#,
         [
-            "fn", "string", "yeah",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "fn", "ustr", "yeah",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "return",  "\"${hello}, ${world}!\""
         ],
         "",
     );
     do_test!(
         r#"
-fn string yeah() {
-    fn string yeah_inner() {
-        const string hello = "Hello"
-        const string world = "World"
+fn ustr yeah() {
+    fn ustr yeah_inner() {
+        const ustr hello = "Hello"
+        const ustr world = "World"
         return "${hello}, ${world}!"
     }
     return yeah_inner()
 }"#,
         [
-            "fn", "string", "yeah",
-            "fn", "string", "yeah_inner",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "fn", "ustr", "yeah",
+            "fn", "ustr", "yeah_inner",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "return",  "\"${hello}, ${world}!\"",
             "return",  "yeah_inner", "(", ")"
         ],
@@ -376,20 +381,20 @@ fn string yeah() {
     );
     do_test!(
         r#"
-fn string yeah() {
-    const string hello = "Hello"
-    const string world = "World"
-    fn string yeah_inner(string hello, string world) {
+fn ustr yeah() {
+    const ustr hello = "Hello"
+    const ustr world = "World"
+    fn ustr yeah_inner(ustr hello, ustr world) {
         return "${hello}, ${world}!"
     }
     return yeah_inner(hello, world)
 }"#,
         [
-            "fn", "string", "yeah",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
-            "fn", "string", "yeah_inner",
-            "string", "hello", "string", "world",
+            "fn", "ustr", "yeah",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
+            "fn", "ustr", "yeah_inner",
+            "ustr", "hello", "ustr", "world",
             "return",  "\"${hello}, ${world}!\"",
             "return",  "yeah_inner", "(", "hello", ",", "world", ")"
         ],
@@ -399,13 +404,13 @@ fn string yeah() {
 
 #[test]
 fn assorted_fn_fail() {
-    do_test!(r#"fn"#, [], "eof 2",);
-    do_test!(r#"fn yeah"#, [], "eof 7",);
-    do_test!(r#"fn string yeah"#, [], "eof 14",);
-    do_test!(r#"fn string yeah("#, [], "eof 15",);
-    do_test!(r#"fn string yeah()"#, [], "eof 16",);
-    do_test!(r#"fn string yeah() {"#, [], "eof 18",);
-    do_test!(r#"fn string yeah(string yeah = ""#, [], "eof 30",);
+    do_test!(r#"fn"#, ["error"], "eof 2",);
+    do_test!(r#"fn yeah"#, ["error"], "eof 7",);
+    do_test!(r#"fn ustr yeah"#, ["error"], "eof 12",);
+    do_test!(r#"fn ustr yeah("#, ["error"], "eof 13",);
+    do_test!(r#"fn ustr yeah()"#, ["error"], "eof 14",);
+    do_test!(r#"fn ustr yeah() {"#, ["error"], "eof 16",);
+    do_test!(r#"fn ustr yeah(ustr yeah = ""#, ["error"], "eof 26",);
 }
 
 #[test]
@@ -427,16 +432,28 @@ fn empty_if() {
         chain_expected.push("true");
     }
     do_test!(&chain_src, &chain_expected, "",);
+    // an else belongs to the last if before it
+    let (module, _) = Reader::new("if a { if b {} } else {}").module("test");
+    assert!(matches!(
+        module.items()[0],
+        Token::Flow(Flow::If(_, Some(_)))
+    ));
+    assert!(matches!(module.items()[2], Token::Flow(Flow::If(_, None))));
+    do_test!(
+        r#"if a {} if b {} else {}"#,
+        ["if", "a", "if", "b", "else"],
+        "",
+    );
 }
 
 #[test]
 fn empty_if_fail() {
-    do_test!(r#"if true {"#, [], "eof 9",);
-    do_test!(r#"if true("#, [], "eof 8",);
-    do_test!(r#"if true() {"#, [], "eof 11",);
+    do_test!(r#"if true {"#, ["error"], "eof 9",);
+    do_test!(r#"if true("#, ["error"], "eof 8",);
+    do_test!(r#"if true() {"#, ["error"], "eof 11",);
     do_test!(
         r#"else {}"#,
-        ["{", "}"],
+        ["error"],
         r#"expected pos 0,4 to be "ident | r#ident | open brace | end of file" but was "else""#,
     );
 }
@@ -447,14 +464,14 @@ fn assorted_if() {
     do_test!(
         r#"
 if yeah {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     print("${hello}, ${world}!")
 }"#,
         [
             "if", "yeah",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "print", "(", "\"${hello}, ${world}!\"", ")"
         ],
         "",
@@ -462,14 +479,14 @@ if yeah {
     do_test!(
         r#"
 if yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     return "${hello}, ${world}!"
 }"#,
         [
             "if", "yeah", "(", ")",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "return", "\"${hello}, ${world}!\""
         ],
         "",
@@ -478,8 +495,8 @@ if yeah() {
         r#"
 if yeah() {
     if yeah_inner() {
-        const string hello = "Hello"
-        const string world = "World"
+        const ustr hello = "Hello"
+        const ustr world = "World"
         return "${hello}, ${world}!"
     }
     return yeah_inner()
@@ -487,8 +504,8 @@ if yeah() {
         [
             "if", "yeah", "(", ")",
             "if", "yeah_inner", "(", ")",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "return", "\"${hello}, ${world}!\"",
             "return", "yeah_inner", "(", ")"
         ],
@@ -497,16 +514,16 @@ if yeah() {
     do_test!(
         r#"
 if yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     if yeah_inner(hello, world) {
         return "${hello}, ${world}!"
     }
 }"#,
         [
             "if", "yeah", "(", ")",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "if", "yeah_inner", "(", "hello", ",", "world", ")",
             "return", "\"${hello}, ${world}!\"",
         ],
@@ -515,27 +532,27 @@ if yeah() {
     do_test!(
         r#"
 if yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     if yeah_inner(hello, world) {
         return "${hello}, ${world}!"
     }
 } else if yeah() {
-    const string hello = "Hello"
-    const string world = "World"
+    const ustr hello = "Hello"
+    const ustr world = "World"
     if yeah_inner(hello, world) {
         return "${hello}, ${world}!"
     }
 }"#,
         [
             "if", "yeah", "(", ")",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "if", "yeah_inner", "(", "hello", ",", "world", ")",
             "return", "\"${hello}, ${world}!\"",
             "else", "if", "yeah", "(", ")",
-            "const", "string", "hello", "=", "\"Hello\"",
-            "const", "string", "world", "=", "\"World\"",
+            "const", "ustr", "hello", "=", "\"Hello\"",
+            "const", "ustr", "world", "=", "\"World\"",
             "if", "yeah_inner", "(", "hello", ",", "world", ")",
             "return", "\"${hello}, ${world}!\"",
         ],
@@ -570,3 +587,899 @@ dupe commas 19,22 = ",,,"
 "#
     );
 }
+
+#[test]
+fn postfix() {
+    do_test!(
+        "let x = a.b.c",
+        ["let", "x", "=", "a", ".", "b", ".", "c"],
+        ""
+    );
+    do_test!("let x = a[i]", ["let", "x", "=", "a", "[", "i", "]"], "");
+    do_test!("obj.method(x)", ["obj", ".", "method", "(", "x", ")"], "");
+    do_test!("f(x)(y)", ["f", "(", "x", ")", "(", "y", ")"], "");
+    do_test!(
+        "let x = a.b(c).d[e]",
+        ["let", "x", "=", "a", ".", "b", "(", "c", ")", ".", "d", "[", "e", "]"],
+        ""
+    );
+    do_test!(
+        "a[f(1)].b(c[2], d.e)",
+        [
+            "a", "[", "f", "(", "1", ")", "]", ".", "b", "(", "c", "[", "2", "]", ",", "d", ".",
+            "e", ")"
+        ],
+        ""
+    );
+    do_test!("if a.b[0] {}", ["if", "a", ".", "b", "[", "0", "]"], "");
+}
+
+#[test]
+fn postfix_end() {
+    use super::token::{Expr, ExprKind::*, Token};
+    use crate::error::ErrorMulti;
+
+    let (module, errors) = Reader::new("let x = a.b(c).d[e]").module("test");
+    assert_eq!(errors, ErrorMulti::default());
+    let ends = module.items[1..]
+        .iter()
+        .map(|token| match token {
+            Token::Expr(Expr { end, kind, .. }) => (*end, *kind),
+            other => panic!("non expr token: {other:#?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ends,
+        [
+            (7, Index),
+            (6, Field("d".into())),
+            (6, MethodCall("b".into())),
+            (5, Var("a".into())),
+            (6, Var("c".into())),
+            (7, Var("e".into())),
+        ]
+    );
+}
+
+#[test]
+fn statements() {
+    do_test!(";;", [], "");
+    do_test!("a; b; 1 + 2", ["a", "b", "1", "+", "2"], "");
+    do_test!("f()\ng()", ["f", "(", ")", "g", "(", ")"], "");
+    do_test!(
+        "let x = 1 let y = 2",
+        ["let", "x", "=", "1", "let", "y", "=", "2"],
+        r#"separator 10,13 = "let""#
+    );
+    // a block ends a statement on its own
+    do_test!("if a { } f()", ["if", "a", "f", "(", ")"], "");
+    // the last expr of a fn body is returned, unless it has a `;`
+    do_test!(
+        "fn f(x) { x + 1 }",
+        ["fn", "f", "x", "return", "x", "+", "1"],
+        ""
+    );
+    do_test!("fn f(x) { x + 1; }", ["fn", "f", "x", "x", "+", "1"], "");
+    do_test!(
+        "fn f(x) { g(x) return; }",
+        ["fn", "f", "x", "g", "(", "x", ")", "return"],
+        r#"separator 15,21 = "return""#
+    );
+}
+
+#[test]
+fn postfix_fail() {
+    do_test!("a.", ["error"], "eof 2");
+    do_test!("a[1", ["error"], "eof 3");
+    do_test!(
+        "a.b c()",
+        ["a", ".", "b", "c", "(", ")"],
+        r#"separator 4,5 = "c""#
+    );
+    do_test!(
+        "a.(",
+        ["error"],
+        r#"expected pos 2,3 to be "ident | r#ident" but was "(""#
+    );
+}
+
+#[test]
+fn assign() {
+    do_test!("x = 1", ["x", "=", "1"], "");
+    do_test!("a.b = c", ["a", ".", "b", "=", "c"], "");
+    do_test!(
+        "a[i] = f(x)",
+        ["a", "[", "i", "]", "=", "f", "(", "x", ")"],
+        ""
+    );
+    do_test!("a, b = b, a", ["a", ",", "b", "=", "b", ",", "a"], "");
+    do_test!(
+        "a.b, c[0], d = 1, 2",
+        ["a", ".", "b", ",", "c", "[", "0", "]", ",", "d", "=", "1", ",", "2"],
+        ""
+    );
+    do_test!(
+        "let x = 1; x = 2",
+        ["let", "x", "=", "1", "x", "=", "2"],
+        ""
+    );
+}
+
+#[test]
+fn compound_assign() {
+    for op in ["+", "-", "*", "/", "%", "&", "|", "^", "<<", ">>"] {
+        let src = format!("x {op}= 1");
+        let op = format!("{op}=");
+        do_test!(&src, ["x", &op, "1"], "");
+    }
+    do_test!("a.b += 2", ["a", ".", "b", "+=", "2"], "");
+    do_test!(
+        "a[i] -= f(1)",
+        ["a", "[", "i", "]", "-=", "f", "(", "1", ")"],
+        ""
+    );
+    do_test!(
+        "a, b += 1",
+        ["error"],
+        r#"expected pos 5,7 to be "=" but was "+=""#
+    );
+}
+
+#[test]
+fn assign_fail() {
+    do_test!("x =", ["error"], "eof 3");
+    do_test!("a, b", ["error"], "eof 4");
+    do_test!(
+        "a, = 1",
+        ["error"],
+        r#"expected pos 3,4 to be "ident | r#ident" but was "=""#
+    );
+    do_test!(
+        "f() = 1",
+        ["f", "(", ")", "=", "1"],
+        r#"assign target 0,3 = "f()""#
+    );
+    do_test!(
+        "a, f().b, g() = 1",
+        ["a", ",", "f", "(", ")", ".", "b", ",", "g", "(", ")", "=", "1"],
+        r#"assign target 10,13 = "g()""#
+    );
+}
+
+#[test]
+fn const_assign() {
+    do_test!(
+        "const x = 1; x = 2",
+        ["const", "x", "=", "1", "x", "=", "2"],
+        r#"const assign 13,14 to "x" = "x""#
+    );
+    do_test!(
+        "const x = 1; x += 2",
+        ["const", "x", "=", "1", "x", "+=", "2"],
+        r#"const assign 13,14 to "x" = "x""#
+    );
+    do_test!(
+        "const x = 1; a, x = 2, 3",
+        ["const", "x", "=", "1", "a", ",", "x", "=", "2", ",", "3"],
+        r#"const assign 16,17 to "x" = "x""#
+    );
+    // fields of a const binding may be assigned
+    do_test!(
+        "const x = t; x.a = 2",
+        ["const", "x", "=", "t", "x", ".", "a", "=", "2"],
+        ""
+    );
+    // shadowed by a let
+    do_test!(
+        "const x = 1; let x = 2; x = 3",
+        ["const", "x", "=", "1", "let", "x", "=", "2", "x", "=", "3"],
+        ""
+    );
+    // a const in a closed scope is not visible
+    do_test!(
+        "let x = 1; { const x = 2 } x = 3",
+        ["let", "x", "=", "1", "{", "const", "x", "=", "2", "}", "x", "=", "3"],
+        ""
+    );
+    do_test!(
+        "const x = 1; fn f(int x) { x = 2 }",
+        ["const", "x", "=", "1", "fn", "f", "int", "x", "x", "=", "2"],
+        ""
+    );
+    do_test!(
+        "const x = 1; if c { x = 2 }",
+        ["const", "x", "=", "1", "if", "c", "x", "=", "2"],
+        r#"const assign 20,21 to "x" = "x""#
+    );
+}
+
+#[test]
+fn binary() {
+    do_test!("let x = a + b", ["let", "x", "=", "a", "+", "b"], "");
+    do_test!(
+        "let x = -a * !b",
+        ["let", "x", "=", "-", "a", "*", "!", "b"],
+        ""
+    );
+    do_test!(
+        "let x = a == b && c != d || e <= f",
+        ["let", "x", "=", "a", "==", "b", "&&", "c", "!=", "d", "||", "e", "<=", "f"],
+        ""
+    );
+    do_test!(
+        "x = (a + b) * c.d(1 << 2)",
+        ["x", "=", "a", "+", "b", "*", "c", ".", "d", "(", "1", "<<", "2", ")"],
+        ""
+    );
+}
+
+#[test]
+fn binary_precedence() {
+    use super::token::{BinOp::*, Expr, ExprKind::*, Token};
+
+    let kinds = |src: &str| {
+        let (module, _) = Reader::new(src).module("test");
+        module.items[1..]
+            .iter()
+            .map(|token| match token {
+                Token::Expr(Expr { end, kind, .. }) => (*end, *kind),
+                other => panic!("non expr token: {other:#?}"),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        kinds("let x = a + b * c"),
+        [
+            (6, Binary(Add)),
+            (3, Var("a".into())),
+            (6, Binary(Mul)),
+            (5, Var("b".into())),
+            (6, Var("c".into())),
+        ]
+    );
+    assert_eq!(
+        kinds("let x = a - b - c"),
+        [
+            (6, Binary(Sub)),
+            (5, Binary(Sub)),
+            (4, Var("a".into())),
+            (5, Var("b".into())),
+            (6, Var("c".into())),
+        ]
+    );
+    assert_eq!(
+        kinds("let x = (a || b) && c"),
+        [
+            (6, Binary(And)),
+            (5, Binary(Or)),
+            (4, Var("a".into())),
+            (5, Var("b".into())),
+            (6, Var("c".into())),
+        ]
+    );
+}
+
+#[test]
+fn closure() {
+    do_test!(
+        "let f = fn(int x) { return x }",
+        ["let", "f", "=", "fn", "(", "int", "x", ")", "{", "return", "x", "}"],
+        ""
+    );
+    do_test!(
+        "let f = fn(x, y) { g(x) }",
+        ["let", "f", "=", "fn", "(", "x", "y", ")", "{", "return", "g", "(", "x", ")", "}"],
+        ""
+    );
+    do_test!(
+        "let f = |x, y| x + y",
+        ["let", "f", "=", "fn", "(", "x", "y", ")", "{", "return", "x", "+", "y", "}"],
+        ""
+    );
+    do_test!(
+        "let f = |x| { g(x) }",
+        ["let", "f", "=", "fn", "(", "x", ")", "{", "return", "g", "(", "x", ")", "}"],
+        ""
+    );
+    do_test!(
+        "let f = || 1",
+        ["let", "f", "=", "fn", "(", ")", "{", "return", "1", "}"],
+        ""
+    );
+    do_test!(
+        "map(|int x| x * 2, list)",
+        [
+            "map", "(", "fn", "(", "int", "x", ")", "{", "return", "x", "*", "2", "}", ",", "list",
+            ")"
+        ],
+        ""
+    );
+    do_test!(
+        "fn(x) { return x }(1)",
+        ["error"],
+        r#"expected pos 6,7 to be "ident | r#ident" but was "{""#
+    );
+    do_test!(
+        "let y = fn() { return 1 }()",
+        ["let", "y", "=", "fn", "(", ")", "{", "return", "1", "}", "(", ")"],
+        ""
+    );
+}
+
+#[test]
+fn closure_nested_blocks() {
+    do_test!(
+        "{ let f = fn() { { g() } h() } }",
+        [
+            "{", "let", "f", "=", "fn", "(", ")", "{", "{", "g", "(", ")", "}", "return", "h", "(",
+            ")", "}", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "fn a() { if b { c() } { d() } e() }",
+        ["fn", "a", "if", "b", "c", "(", ")", "{", "d", "(", ")", "}", "return", "e", "(", ")"],
+        ""
+    );
+}
+
+#[test]
+fn closure_fail() {
+    do_test!("let f = fn(x) { g()", ["let", "f", "error"], "eof 19");
+    do_test!("let f = |x", ["let", "f", "error"], "eof 10");
+    do_test!(
+        "let f = |x y z| 1",
+        ["let", "f", "error"],
+        r#"expected pos 13,14 to be "ident | r#ident | |" but was "z""#
+    );
+}
+
+#[test]
+fn return_list() {
+    do_test!("return 1, 2", ["return", "1", ",", "2"], "");
+    do_test!(
+        "return f(x), y",
+        ["return", "f", "(", "x", ")", ",", "y"],
+        ""
+    );
+    do_test!("fn a() { return }", ["fn", "a", "return"], "");
+    do_test!("return", ["return"], "");
+    do_test!("return 1,", ["error"], "eof 9");
+}
+
+#[test]
+fn varargs() {
+    do_test!(
+        "fn a(...) { return ... }",
+        ["fn", "a", "...", "return", "..."],
+        ""
+    );
+    do_test!(
+        "fn a(int x, ...) { f(x, ...) }",
+        ["fn", "a", "...", "int", "x", "return", "f", "(", "x", ",", "...", ")"],
+        ""
+    );
+    do_test!(
+        "let f = |x, ...| (...)",
+        ["let", "f", "=", "fn", "(", "x", "...", ")", "{", "return", "(", "...", ")", "}"],
+        ""
+    );
+    do_test!(
+        "let f = fn(...) { return (g()), (1) }",
+        [
+            "let", "f", "=", "fn", "(", "...", ")", "{", "return", "(", "g", "(", ")", ")", ",",
+            "1", "}"
+        ],
+        ""
+    );
+    do_test!("return ...", ["return", "..."], "");
+}
+
+#[test]
+fn varargs_fail() {
+    do_test!(
+        "fn a(x) { return ... }",
+        ["fn", "a", "x", "return", "..."],
+        r#"varargs 17,20 = "...""#
+    );
+    do_test!(
+        "fn a(...) { let f = |x| ... }",
+        ["fn", "a", "...", "let", "f", "=", "fn", "(", "x", ")", "{", "return", "...", "}"],
+        r#"varargs 24,27 = "...""#
+    );
+    do_test!(
+        "fn a(..., x) { }",
+        ["error"],
+        r#"expected pos 8,9 to be "close parenthesis" but was ",""#
+    );
+}
+
+#[test]
+fn data_decls() {
+    do_test!(
+        "struct Point { int x, int y, }",
+        ["struct", "Point", "{", "int", "x", "int", "y", "}"],
+        ""
+    );
+    do_test!("struct Unit {}", ["struct", "Unit", "{", "}"], "");
+    do_test!(
+        "enum Shape { A, B(int, float), C { x } }",
+        ["enum", "Shape", "{", "A", "B", "(", "int", "float", ")", "C", "{", "x", "}", "}"],
+        ""
+    );
+    do_test!(
+        "struct P { x, x }",
+        ["struct", "P", "{", "x", "x", "}"],
+        r#"dupe field decl "x" 14,15 = "x""#
+    );
+    do_test!(
+        "enum E { A, A(int) } struct E { Q q }",
+        ["enum", "E", "{", "A", "A", "(", "int", ")", "}", "struct", "E", "{", "Q", "q", "}"],
+        r#"dupe variant "A" 12,13 = "A"
+        dupe type "E" 28,29 = "E"
+        no type "Q" 32,33 = "Q""#
+    );
+    do_test!("struct P { x", ["error"], "eof 12");
+    do_test!(
+        "enum E { A(1) }",
+        ["error"],
+        r#"expected pos 11,12 to be "ident | r#ident | close parenthesis" but was "1""#
+    );
+}
+
+#[test]
+fn data_constructors() {
+    do_test!(
+        "struct P { x, y } let p = P { y = 1, x = 2 }",
+        [
+            "struct", "P", "{", "x", "y", "}", "let", "p", "=", "P", "{", "y", "=", "1", ",", "x",
+            "=", "2", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "enum E { A, B(int), C { x } } return E.A, E.B(1), E.C { x = 2 }.x",
+        [
+            "enum", "E", "{", "A", "B", "(", "int", ")", "C", "{", "x", "}", "}", "return", "E",
+            ".", "A", ",", "E", ".", "B", "(", "1", ")", ",", "E", ".", "C", "{", "x", "=", "2",
+            "}", ".", "x"
+        ],
+        ""
+    );
+    // an undeclared name is a var followed by a block
+    do_test!(
+        "let p = P; { x = 1 }",
+        ["let", "p", "=", "P", "{", "x", "=", "1", "}"],
+        ""
+    );
+    // a type may be declared after its use
+    do_test!(
+        "fn f() { return P { x = 1 }, E.B(2) } struct P { x } enum E { A, B(int) }",
+        [
+            "fn", "f", "return", "P", "{", "x", "=", "1", "}", ",", "E", ".", "B", "(", "2", ")",
+            "struct", "P", "{", "x", "}", "enum", "E", "{", "A", "B", "(", "int", ")", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "match p { P { x = 1 } => 1, _ => 0 } struct P { x }",
+        [
+            "match", "p", "{", "P", "{", "x", "=", "1", "}", "=>", "tail", "1", ",", "_", "=>",
+            "tail", "0", ",", "}", "struct", "P", "{", "x", "}"
+        ],
+        ""
+    );
+}
+
+#[test]
+fn data_constructors_fail() {
+    do_test!(
+        "struct P { x, y } return P { x = 1, z = 2, x = 3 }",
+        [
+            "struct", "P", "{", "x", "y", "}", "return", "P", "{", "x", "=", "1", ",", "z", "=",
+            "2", ",", "x", "=", "3", "}"
+        ],
+        r#"
+no field "z" in "P" 36,37 = "z" 
+dupe field "x" 43,44 = "x" 
+missing field "y" 25,50 = "P { x = 1, z = 2, x = 3 }""#
+    );
+    do_test!(
+        "enum E { A, B(int) } return E.D, E.B(1, 2)",
+        [
+            "enum", "E", "{", "A", "B", "(", "int", ")", "}", "return", "E", ".", "D", ",", "E",
+            ".", "B", "(", "1", ",", "2", ")"
+        ],
+        r#"
+no variant "D" in "E" 30,31 = "D" 
+field count 2 of 1 33,42 = "E.B(1, 2)""#
+    );
+    // checked against a type declared later too
+    do_test!(
+        "return P { y = 1 }, E.B(1, 2); struct P { x } enum E { B(int) }",
+        [
+            "return", "P", "{", "y", "=", "1", "}", ",", "E", ".", "B", "(", "1", ",", "2", ")",
+            "struct", "P", "{", "x", "}", "enum", "E", "{", "B", "(", "int", ")", "}"
+        ],
+        r#"
+no field "y" in "P" 11,12 = "y" 
+missing field "x" 7,18 = "P { y = 1 }" 
+field count 2 of 1 20,29 = "E.B(1, 2)""#
+    );
+    let (_, errors) = Reader::new("enum E { B(int) } return E.B(1, 2)").module("test");
+    assert_eq!(
+        errors.semantic[0].to_string(),
+        "expected 1 field but found 2"
+    );
+}
+
+#[test]
+fn impls() {
+    do_test!(
+        "struct P { x } impl P { fn get(self) { return self.x } fn int size() { } }",
+        [
+            "struct", "P", "{", "x", "}", "impl", "P", "{", "fn", "get", "self", "return", "self",
+            ".", "x", "fn", "int", "size", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "like Show { fn ustr show(self); fn len(self) } impl int like Show { fn ustr show(self) { } fn len(self) { } }",
+        [
+            "like", "Show", "{", "fn", "ustr", "show", "self", "fn", "len", "self", "}", "impl", "int",
+            "like", "Show", "{", "fn", "ustr", "show", "self", "fn", "len", "self", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "like Empty {} impl P like Empty {} struct P {}",
+        [
+            "like", "Empty", "{", "}", "impl", "P", "like", "Empty", "{", "}", "struct", "P", "{",
+            "}"
+        ],
+        ""
+    );
+    do_test!(
+        "impl Q { fn f() { } }",
+        ["impl", "Q", "{", "fn", "f", "}"],
+        r#"no type "Q" 5,6 = "Q""#
+    );
+    do_test!(
+        "impl P for Q {}",
+        ["error"],
+        r#"expected pos 7,10 to be "open brace" but was "for""#
+    );
+}
+
+#[test]
+fn predefs() {
+    do_test!(
+        "prelet a = 1; preconst b = a; predef c = 2; prefn f(x) { return comp -x + 1 }",
+        [
+            "prelet", "a", "=", "comp", "1", "preconst", "b", "=", "comp", "a", "preconst", "c",
+            "=", "comp", "2", "prefn", "f", "x", "return", "comp", "-", "x", "+", "1"
+        ],
+        ""
+    );
+    do_test!("let x = comp", ["let", "x", "error"], "eof 12");
+}
+
+#[test]
+fn expr_spans() {
+    let spans = |src: &str| {
+        let (module, errors) = Reader::new(src).module("test");
+        assert_eq!(errors, ErrorMulti::default());
+        module
+            .items()
+            .iter()
+            .filter_map(|token| match token {
+                Token::Expr(expr) => Some(src[expr.span.from..expr.span.to].to_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(spans("f(a, 1)"), ["f(a, 1)", "a", "1"]);
+    assert_eq!(
+        spans("let x = -a.b[c] + 2"),
+        ["-a.b[c] + 2", "-a.b[c]", "a.b[c]", "a.b", "a", "c", "2"]
+    );
+    assert_eq!(spans("return (f()), |x| x"), ["(f())", "f()", "|x| x", "x"]);
+    assert_eq!(
+        spans("g(fn(...) { return ... })(1)"),
+        [
+            "g(fn(...) { return ... })(1)",
+            "g(fn(...) { return ... })",
+            "fn(...) { return ... }",
+            "...",
+            "1"
+        ]
+    );
+}
+
+#[test]
+fn incomplete() {
+    for src in ["fn f() {", "{ let x = 1", "if x { f() } else {", "let x ="] {
+        assert!(Reader::new(src).incomplete(), "{src}");
+    }
+    for src in ["", "fn f() {}", "let x = 1", "f() }"] {
+        assert!(!Reader::new(src).incomplete(), "{src}");
+    }
+}
+
+#[test]
+fn error_messages() {
+    use crate::span::line_col;
+
+    let src = "let x = 1\nfn f( {";
+    let (_, errors) = Reader::new(src).module("test");
+    let messages: Vec<_> = errors
+        .iter()
+        .map(|err| {
+            let (line, col) = line_col(src, err.span().unwrap().from);
+            format!("{line}:{col} {err}")
+        })
+        .collect();
+    assert_eq!(
+        messages,
+        ["2:7 expected ident, r#ident or close parenthesis"]
+    );
+    assert_eq!(line_col("aé\nb", 4), (2, 1));
+    assert_eq!(line_col("aé", 3), (1, 3));
+    assert_eq!(line_col("a", 9), (1, 2));
+}
+
+#[test]
+fn tagged() {
+    do_test!(
+        "'a: { 'b: { break 'a 1, 2 } break 'b } let x = 'c: { break 'c }",
+        [
+            "'a:", "{", "'b:", "{", "break", "'a", "1", ",", "2", "}", "break", "'b", "}", "let",
+            "x", "=", "'c:", "{", "break", "'c", "}"
+        ],
+        r#"no label "b" 34,36 = "'b" "#
+    );
+    do_test!(
+        "'a: { 'a: { } } break x",
+        ["'a:", "{", "'a:", "{", "}", "}", "error"],
+        r#"
+expected pos 22,23 to be "literal" but was "x" 
+shadowed label "a" 6,8 = "'a""#
+    );
+}
+
+#[test]
+fn value_blocks() {
+    do_test!(
+        "let y = { let t = f(); t }",
+        ["let", "y", "=", "{", "let", "t", "=", "f", "(", ")", "tail", "t", "}"],
+        ""
+    );
+    do_test!(
+        "let x = if c { 1 } else if d { 2 } else { f(); 3 };",
+        [
+            "let", "x", "=", "if", "c", "tail", "1", "else", "if", "d", "tail", "2", "else", "f",
+            "(", ")", "tail", "3"
+        ],
+        ""
+    );
+    // without a tail expr the value is `nil`
+    do_test!(
+        "let x = { f(); }",
+        ["let", "x", "=", "{", "f", "(", ")", "}"],
+        ""
+    );
+    do_test!(
+        "let x = if c { 1 } f()",
+        ["let", "x", "=", "if", "c", "tail", "1", "f", "(", ")"],
+        r#"missing else 8,18 = "if c { 1 }""#
+    );
+    // an `if` or block is an operand anywhere an expr is
+    do_test!(
+        "fn f(c) { return if c { 1 } else { 2 } } x = { 5 }; let y = if c { 1 } else { 2 } + 1",
+        [
+            "fn", "f", "c", "return", "if", "c", "tail", "1", "else", "tail", "2", "x", "=", "{",
+            "tail", "5", "}", "let", "y", "=", "if", "c", "tail", "1", "else", "tail", "2", "+",
+            "1"
+        ],
+        ""
+    );
+    // the tail of a body is found through nested blocks & `if`s
+    do_test!(
+        "fn f(c) { if c { 1 } else { { 2 } } }",
+        ["fn", "f", "c", "if", "c", "return", "1", "else", "{", "return", "2", "}"],
+        ""
+    );
+    do_test!(
+        "prelet x = { 1 }",
+        ["prelet", "x", "error"],
+        r#"expected pos 11,12 to be "ident | r#ident | literal" but was "{""#
+    );
+}
+
+#[test]
+fn matches() {
+    do_test!(
+        "enum E { A, B(int), C { y } } \
+         let v = match e { E.A | E.B(_) => 1, E.C { y = 1 } if f() => { g(); y } _ => 0 }",
+        [
+            "enum", "E", "{", "A", "B", "(", "int", ")", "C", "{", "y", "}", "}", "let", "v", "=",
+            "match", "e", "{", "E", ".", "A", "|", "E", ".", "B", "(", "_", ")", "=>", "tail", "1",
+            ",", "E", ".", "C", "{", "y", "=", "1", "}", "if", "f", "(", ")", "=>", "g", "(", ")",
+            "tail", "y", ",", "_", "=>", "tail", "0", ",", "}"
+        ],
+        ""
+    );
+    do_test!(
+        "match a, b { (1, x) => f(x), (-2, \"s\") => { } }",
+        [
+            "match", "a", ",", "b", "{", "(", "1", ",", "x", ")", "=>", "tail", "f", "(", "x", ")",
+            ",", "(", "-2", ",", "s", ")", "=>", ",", "}"
+        ],
+        r#"non exhaustive `(_, _)` 0,10 = "match a, b""#
+    );
+    do_test!(
+        "fn f(a) { return match a { 1 => 2, _ => 3 } } \
+         x = match a { _ => 1 }; print(match a { _ => 1 })",
+        [
+            "fn", "f", "a", "return", "match", "a", "{", "1", "=>", "tail", "2", ",", "_", "=>",
+            "tail", "3", ",", "}", "x", "=", "match", "a", "{", "_", "=>", "tail", "1", ",", "}",
+            "print", "(", "match", "a", "{", "_", "=>", "tail", "1", ",", "}", ")"
+        ],
+        ""
+    );
+    // like rust, an arm with a block body may still have a comma after it
+    do_test!(
+        "match a { 1 => { 5 }, _ => 0 }",
+        ["match", "a", "{", "1", "=>", "tail", "5", ",", "_", "=>", "tail", "0", ",", "}"],
+        ""
+    );
+    do_test!(
+        "match a { 1 => { 5 },, _ => 0 }",
+        ["error"],
+        r#"expected pos 21,22 to be "ident | r#ident | literal | open parenthesis" but was ",""#
+    );
+    do_test!(
+        "match a { x 1 }",
+        ["error"],
+        r#"expected pos 12,13 to be `=>` but was "1""#
+    );
+    do_test!(
+        "match a { x => 1 y => 2 }",
+        ["error"],
+        r#"expected pos 17,18 to be "comma | close brace" but was "y""#
+    );
+    // a tuple is only allowed at the top of an arm
+    do_test!(
+        "match a { (x, (y)) => 1 }",
+        ["error"],
+        r#"expected pos 14,15 to be "ident | r#ident | literal" but was "(""#
+    );
+    do_test!(
+        "struct S { x } enum E { A } match a { S { y } => 1, E.Z => 2, _ => 3 }",
+        [
+            "struct", "S", "{", "x", "}", "enum", "E", "{", "A", "}", "match", "a", "{", "S", "{",
+            "y", "=", "y", "}", "=>", "tail", "1", ",", "E", ".", "Z", "=>", "tail", "2", ",", "_",
+            "=>", "tail", "3", ",", "}"
+        ],
+        r#"
+no field "y" in "S" 42,43 = "y"
+no variant "Z" in "E" 54,55 = "Z""#
+    );
+}
+
+#[test]
+fn optional_types() {
+    do_test!(
+        "let int? x = nil; fn f(ustr? s, int n) { } let g = |int? y| y",
+        [
+            "let", "int", "?", "x", "=", "nil", "fn", "f", "ustr", "?", "s", "int", "n", "let",
+            "g", "=", "fn", "(", "int", "?", "y", ")", "{", "return", "y", "}"
+        ],
+        ""
+    );
+    // a `?` must be followed by a name
+    do_test!(
+        "let int? = 1 let x = 1",
+        ["error", "let", "x", "=", "1"],
+        r#"expected pos 9,10 to be "ident | r#ident" but was "=""#
+    );
+}
+
+#[test]
+fn types() {
+    do_test!(
+        "struct List<T> { } struct Map<K, V> { } let List<int> xs = l; let Map<ustr, List<int>> m = n",
+        [
+            "struct", "List", "<", "T", ">", "{", "}", "struct", "Map", "<", "K", ",", "V", ">", "{",
+            "}", "let", "List", "<", "int", ">", "xs", "=", "l", "let", "Map", "<", "ustr", ",",
+            "List", "<", "int", ">", ">", "m", "=", "n"
+        ],
+        ""
+    );
+    do_test!(
+        "let (int, ustr?) t = x; let geo.Vec2 v = y; let fn int(ustr) f = z",
+        [
+            "let", "(", "int", ",", "ustr", "?", ")", "t", "=", "x", "let", "geo", ".", "Vec2",
+            "v", "=", "y", "let", "fn", "int", "(", "ustr", ")", "f", "=", "z"
+        ],
+        ""
+    );
+    do_test!(
+        "struct List<T> { } fn (int, int) swap(fn(int) f, List<fn ustr()>? g) { }",
+        [
+            "struct", "List", "<", "T", ">", "{", "}", "fn", "(", "int", ",", "int", ")", "swap",
+            "fn", "(", "int", ")", "f", "List", "<", "fn", "ustr", "(", ")", ">", "?", "g"
+        ],
+        ""
+    );
+    do_test!(
+        "struct Pair<A, B> { A a, B b } enum Opt<T,> { Some(T), None }",
+        [
+            "struct", "Pair", "<", "A", ",", "B", ">", "{", "A", "a", "B", "b", "}", "enum", "Opt",
+            "<", "T", ">", "{", "Some", "(", "T", ")", "None", "}"
+        ],
+        ""
+    );
+    // a `<` glued to a name & closed before a call starts type args
+    do_test!(
+        "fn T id<T>(T x) { return x } let y = id<int>(1); let z = a < b",
+        [
+            "fn", "T", "id", "<", "T", ">", "T", "x", "return", "x", "let", "y", "=", "id", "(",
+            "1", ")", "let", "z", "=", "a", "<", "b"
+        ],
+        ""
+    );
+    do_test!(
+        "struct P<T> { T x } let p = P<int> { x = 1 } let q = a<b, c>d",
+        [
+            "struct", "P", "<", "T", ">", "{", "T", "x", "}", "let", "p", "=", "P", "{", "x", "=",
+            "1", "}", "let", "q", "=", "a", "<", "b", "error"
+        ],
+        r#"separator 56,57 = ","
+        expected pos 56,57 to be "ident | r#ident | open brace | end of file" but was ",""#
+    );
+    do_test!(
+        "let list<int x = 1",
+        ["error"],
+        r#"expected pos 13,14 to be "comma | >" but was "x""#
+    );
+}
+
+/// The tokens of a module & its errors as `line:col: message`
+fn diagnostics(src: &str) -> String {
+    use std::fmt::Write;
+    let (module, errors) = Reader::new(src).module("test");
+    let mut out = write_module(src, &module).join(" ");
+    out.push('\n');
+    for err in errors.iter() {
+        let (line, col) = err.span().map_or((0, 0), |span| line_col(src, span.from));
+        writeln!(out, "{line}:{col}: {err}").unwrap();
+    }
+    out
+}
+
+/// Every broken input in `test/recovery` against the `.snap` of its
+/// diagnostics next to it, `UPDATE_EXPECT=1` writes the snapshots
+#[test]
+fn recovery() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/parse/test/recovery");
+    let mut paths = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "al"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let update = std::env::var_os("UPDATE_EXPECT").is_some();
+    for path in paths {
+        let actual = diagnostics(&fs::read_to_string(&path).unwrap());
+        let snap = path.with_extension("snap");
+        if update {
+            fs::write(&snap, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&snap).unwrap_or_default();
+        assert_eq!(actual, expected, "{}", path.display());
+    }
+}
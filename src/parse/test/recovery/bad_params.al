@@ -0,0 +1,6 @@
+fn f(a b c) {
+  return a
+}
+fn g() {
+  return 1
+}
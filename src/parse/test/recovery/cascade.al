@@ -0,0 +1,40 @@
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
+let = 1
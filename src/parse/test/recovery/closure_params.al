@@ -0,0 +1,2 @@
+let f = fn(x y z) { return x }
+let g = f(1)
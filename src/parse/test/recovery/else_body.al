@@ -0,0 +1,4 @@
+if a {
+} else b {
+}
+let c = 1
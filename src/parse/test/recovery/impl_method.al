@@ -0,0 +1,8 @@
+impl P {
+  fn get(self {
+    return self.x
+  }
+  fn set(self, x) {
+  }
+}
+let z = 1
@@ -0,0 +1,3 @@
+let fn = 1
+const if = 2
+let ok = 3
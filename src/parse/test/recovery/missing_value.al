@@ -0,0 +1,5 @@
+let x = 1 +
+fn f(a b) {
+  return a
+}
+let y = 2
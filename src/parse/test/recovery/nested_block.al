@@ -0,0 +1,7 @@
+fn outer() {
+  if x == {
+    g()
+  }
+  return 1
+}
+outer()
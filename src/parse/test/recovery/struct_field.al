@@ -0,0 +1,5 @@
+struct P {
+  int x,
+  int 3
+}
+let p = P { x = 1 }
@@ -0,0 +1,5 @@
+fn f() {
+  print(1, 2
+  let x = 3
+}
+f()
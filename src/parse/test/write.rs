@@ -1,7 +1,8 @@
 // TODO: add detail to printing
 use crate::{
-    error::ErrorMulti,
+    error::{ErrorMulti, SemanticError},
     parse::token::{Module, *},
+    span::TSpan,
     util::Symbol,
 };
 
@@ -21,15 +22,11 @@ impl<'a> Writer<'a> {
             AS::Str(s) => s.to_owned(),
             AS::String(s) => s,
             AS::Symbol(s) => s.as_str().to_owned(),
-        })
+        });
     }
 
     fn write_close(&mut self) {
-        loop {
-            let Some(&(i, s)) = self.scheduled.last() else {
-                break;
-            };
-
+        while let Some(&(i, s)) = self.scheduled.last() {
             if i != self.pos + 1 {
                 break;
             }
@@ -38,24 +35,28 @@ impl<'a> Writer<'a> {
         }
     }
 
+    #[allow(clippy::too_many_lines)]
     fn write_token(&mut self, token: Token) {
         match token {
-            Token::FnDef(FnDef {
-                name, type_name, ..
-            }) => {
-                self.push("fn");
-                type_name.inspect(|s| self.push(s.as_str()));
-                self.push(name.as_str());
+            Token::FnDef(def) => {
+                self.push(if def.pre { "prefn" } else { "fn" });
+                self.write_ty(def.ty);
+                self.push(def.name.as_str());
+                self.write_generics(def.generics(self.pos));
+                if def.variadic {
+                    self.push("...");
+                }
             }
             Token::Decl(decl) => {
-                self.push(match decl.kind {
-                    DeclKind::Let => "let",
-                    DeclKind::Const => "const",
+                self.push(match (decl.kind, decl.pre) {
+                    (DeclKind::Let, false) => "let",
+                    (DeclKind::Const, false) => "const",
+                    (DeclKind::Let, true) => "prelet",
+                    (DeclKind::Const, true) => "preconst",
+                    (DeclKind::Close, _) => "close",
                 });
 
-                if let Some(type_name) = decl.type_name {
-                    self.push(type_name.as_str());
-                }
+                self.write_ty(decl.ty);
                 self.push(decl.name.as_str());
 
                 if decl.value {
@@ -63,24 +64,132 @@ impl<'a> Writer<'a> {
                 }
             }
             Token::Expr(expr) => self.write_expr(expr),
-            Token::Return => self.push("return"),
+            Token::Return(values) => {
+                self.push("return");
+                self.write_list(values.to);
+            }
+            Token::Tail(values) => {
+                self.push("tail");
+                self.write_list(values.to);
+            }
+            Token::Assign(assign) => {
+                self.write_list(assign.targets.to);
+                match assign.op {
+                    Some(op) => self.push(format!("{}=", op.as_str())),
+                    None => self.push("="),
+                }
+                self.write_list(assign.values.to);
+            }
             // Token::Value(val) => self.write_val(val),
             Token::Import(_) => todo!("imports not added yet"),
-            Token::Block(span) => {
+            Token::Block(block) => {
+                self.push("{");
+                self.scheduled.push((block.tokens.to, "}"));
+            }
+            Token::Tagged(tagged) => {
+                self.push(format!("'{}:", tagged.label));
                 self.push("{");
-                self.scheduled.push((span.to, "}"));
+                self.scheduled.push((tagged.tokens.to, "}"));
+            }
+            Token::Break(brk) => {
+                self.push("break");
+                if !brk.label.is_empty() {
+                    self.push(format!("'{}", brk.label));
+                }
+                self.write_list(brk.values.to);
             }
             Token::FnDefParam(FnDefParam {
-                type_name,
-                name,
-                value,
+                ty, name, value, ..
             }) => {
-                self.push(type_name);
+                self.write_ty(ty);
                 self.push(name);
                 if value {
                     self.push("=");
                 }
             }
+            Token::Struct(def) => {
+                self.push("struct");
+                self.push(def.name);
+                self.write_generics(def.generics);
+                self.push("{");
+                self.scheduled.push((def.fields.to, "}"));
+            }
+            Token::Enum(def) => {
+                self.push("enum");
+                self.push(def.name);
+                self.write_generics(def.generics);
+                self.push("{");
+                self.scheduled.push((def.variants.to, "}"));
+            }
+            Token::Variant(variant) => {
+                self.push(variant.name);
+                let close = match variant.kind {
+                    VariantKind::Unit => None,
+                    VariantKind::Tuple => Some(("(", ")")),
+                    VariantKind::Struct => Some(("{", "}")),
+                };
+                if let Some((open, close)) = close {
+                    self.push(open);
+                    self.scheduled.push((variant.fields.to, close));
+                }
+            }
+            Token::Field(field) => {
+                self.write_ty(field.ty);
+                if let Some(name) = field.name {
+                    self.push(name);
+                }
+            }
+            Token::Impl(def) => {
+                self.push("impl");
+                self.push(def.ty);
+                if let Some(like) = def.like {
+                    self.push("like");
+                    self.push(like);
+                }
+                self.push("{");
+                self.scheduled.push((def.methods.to, "}"));
+            }
+            Token::Like(def) => {
+                self.push("like");
+                self.push(def.name);
+                self.push("{");
+                self.scheduled.push((def.sigs.to, "}"));
+            }
+            Token::FnSig(sig) => {
+                self.push("fn");
+                self.write_ty(sig.ty);
+                self.push(sig.name);
+                self.write_generics(sig.generics(self.pos));
+                if sig.variadic {
+                    self.push("...");
+                }
+            }
+            Token::Match(def) => {
+                self.push("match");
+                self.write_list(def.arms.from);
+                self.push("{");
+                self.scheduled.push((def.arms.to, "}"));
+            }
+            Token::Arm(arm) => {
+                let mut first = true;
+                while self.pos + 1 < arm.pats.to {
+                    if !first {
+                        self.push("|");
+                    }
+                    first = false;
+                    self.pos += 1;
+                    self.write_pat();
+                }
+                if arm.pats.to < arm.body.from {
+                    self.push("if");
+                    self.write_operand();
+                }
+                self.push("=>");
+                self.scheduled.push((arm.body.to, ","));
+            }
+            Token::Pat(_) => self.write_pat(),
+            Token::Type(_) => self.write_type(),
+            Token::Error(_) => self.push("error"),
             Token::Dummy => self.push("dummy"),
             Token::Flow(Flow::If(s, el)) => {
                 self.push("if");
@@ -88,45 +197,313 @@ impl<'a> Writer<'a> {
                     self.scheduled.push((s.to, "else"));
                 }
             }
+            Token::Flow(Flow::While(body)) => {
+                self.push("while");
+                self.scheduled.push((body.to, "}"));
+                self.scheduled.push((body.from, "{"));
+            }
+            Token::Flow(Flow::Repeat(body)) => {
+                self.push("repeat");
+                self.push("{");
+                self.scheduled.push((body.to, "until"));
+                self.scheduled.push((body.to, "}"));
+            }
+            Token::Flow(Flow::For(def)) => {
+                self.push("for");
+                let names: Vec<_> = self.items[def.vars.from..def.vars.to]
+                    .iter()
+                    .filter_map(|token| match token {
+                        Token::Decl(decl) => Some(decl.name.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                self.push(names.join(" , "));
+                self.push(if def.numeric { "=" } else { "in" });
+                self.write_list(def.vars.from);
+                self.pos = def.vars.to - 1;
+                self.push("{");
+                self.scheduled.push((def.body.to, "}"));
+            }
+            Token::Flow(Flow::Goto(label, _)) => {
+                self.push("goto");
+                self.push(label);
+            }
+            Token::Flow(Flow::Label(label, _)) => self.push(format!("::{label}::")),
         };
 
         self.write_close();
     }
 
+    /// write the expr at `self.pos`, leaving `self.pos` at its last token
     fn write_expr(&mut self, expr: Expr) {
         match expr.kind {
-            ExprKind::FnCall(call) => self.write_fn_call(call, expr),
+            ExprKind::FnCall(call) => {
+                self.push(call.name);
+                self.write_params(expr);
+            }
+            ExprKind::Call => {
+                self.write_operand();
+                self.write_params(expr);
+            }
+            ExprKind::Field(name) => {
+                self.write_operand();
+                self.push(".");
+                self.push(name);
+            }
+            ExprKind::MethodCall(name) => {
+                self.write_operand();
+                self.push(".");
+                self.push(name);
+                self.write_params(expr);
+            }
+            ExprKind::Index => {
+                self.write_operand();
+                self.push("[");
+                self.write_operand();
+                self.push("]");
+            }
+            ExprKind::Binary(op) => {
+                self.write_operand();
+                self.push(op.as_str());
+                self.write_operand();
+            }
+            ExprKind::Unary(op) => {
+                self.push(op.as_str());
+                self.write_operand();
+            }
+            ExprKind::Closure(closure) => {
+                // closes scheduled outside the closure must wait for its end
+                let outer = std::mem::take(&mut self.scheduled);
+                self.push("fn");
+                self.push("(");
+                self.write_tokens(closure.params.to);
+                if closure.variadic {
+                    self.push("...");
+                }
+                self.push(")");
+                self.push("{");
+                self.write_tokens(closure.tokens.to);
+                self.push("}");
+                self.scheduled = outer;
+            }
+            ExprKind::Varargs => self.push("..."),
+            ExprKind::Paren => {
+                self.push("(");
+                self.write_operand();
+                self.push(")");
+            }
+            ExprKind::Construct(construct) => {
+                self.push(construct.ty);
+                if let Some(variant) = construct.variant {
+                    self.push(".");
+                    self.push(variant);
+                }
+                match construct.kind {
+                    VariantKind::Unit => (),
+                    VariantKind::Tuple => self.write_params(expr),
+                    VariantKind::Struct => {
+                        self.push("{");
+                        self.write_list(expr.end);
+                        self.push("}");
+                    }
+                }
+            }
+            ExprKind::Comp(_) => {
+                self.push("comp");
+                self.write_operand();
+            }
+            ExprKind::FieldInit(name) => {
+                self.push(name);
+                self.push("=");
+                self.write_operand();
+            }
+            ExprKind::Table => {
+                self.push("{");
+                self.write_list(expr.end);
+                self.push("}");
+            }
+            ExprKind::Entry => {
+                self.push("[");
+                self.write_operand();
+                self.push("]");
+                self.push("=");
+                self.write_operand();
+            }
             ExprKind::Var(name) => self.write_var(name),
             ExprKind::Value(val) => self.write_val(val),
         };
     }
 
-    fn write_fn_call(&mut self, call: FnCall, expr: Expr) {
-        self.push(call.name);
+    /// write every token up to `end`
+    fn write_tokens(&mut self, end: usize) {
+        while self.pos + 1 < end {
+            self.pos += 1;
+            self.write_token(self.items[self.pos]);
+        }
+    }
+
+    fn write_operand(&mut self) {
+        self.pos += 1;
+        match self.items[self.pos] {
+            Token::Expr(expr) => self.write_expr(expr),
+            token @ (Token::Block(_) | Token::Flow(_) | Token::Match(_)) => {
+                // closes scheduled outside the operand must wait for its end
+                let outer = std::mem::take(&mut self.scheduled);
+                let end = end_of(self.items, self.pos);
+                self.write_token(token);
+                self.write_tokens(end);
+                self.scheduled = outer;
+            }
+            other => panic!("non expr operand at index {}: '{other:#?}'", self.pos),
+        }
+    }
+
+    fn write_params(&mut self, expr: Expr) {
         self.push("(");
-        if self.pos == expr.end {
-            // if call.closed {
-            self.push(")");
-            // }
-            return;
+        self.write_list(expr.end);
+        self.push(")");
+    }
+
+    /// write the exprs up to `end`, separated by commas
+    fn write_list(&mut self, end: usize) {
+        let mut first = true;
+        while self.pos + 1 < end {
+            if !first {
+                self.push(",");
+            }
+            first = false;
+            self.write_operand();
+        }
+    }
+
+    /// write the pat at `self.pos`, leaving `self.pos` at its last token
+    fn write_pat(&mut self) {
+        let Token::Pat(pat) = self.items[self.pos] else {
+            panic!("non pat at index {}", self.pos);
+        };
+        match pat.kind {
+            PatKind::Wild => self.push("_"),
+            PatKind::Bind(name) => self.push(name),
+            PatKind::Const(value) => self.push(crate::runtime::Value::from(value).to_string()),
+            PatKind::Tuple => {
+                self.push("(");
+                self.write_pats(pat.end);
+                self.push(")");
+            }
+            PatKind::Data(construct) => {
+                self.push(construct.ty);
+                if let Some(variant) = construct.variant {
+                    self.push(".");
+                    self.push(variant);
+                }
+                match construct.kind {
+                    VariantKind::Unit => (),
+                    VariantKind::Tuple => {
+                        self.push("(");
+                        self.write_pats(pat.end);
+                        self.push(")");
+                    }
+                    VariantKind::Struct => {
+                        self.push("{");
+                        self.write_pats(pat.end);
+                        self.push("}");
+                    }
+                }
+            }
+            PatKind::Field(name) => {
+                self.push(name);
+                self.push("=");
+                self.pos += 1;
+                self.write_pat();
+            }
         }
+    }
 
-        while self.pos + 1 < expr.end {
+    /// write the pats up to `end`, separated by commas
+    fn write_pats(&mut self, end: usize) {
+        let mut first = true;
+        while self.pos + 1 < end {
+            if !first {
+                self.push(",");
+            }
+            first = false;
             self.pos += 1;
-            let token = self.items[self.pos as usize];
-            if let Token::Expr(new_expr) = token {
-                // prevent infinite recursion
-                if token == Token::Expr(expr) {
-                    panic!("same expr at index {} found: '{new_expr:#?}'", self.pos)
-                }
-                self.write_expr(new_expr)
-            } else {
-                self.write_token(token)
-            };
-            self.push(",");
+            self.write_pat();
+        }
+    }
+
+    /// write the type following the token at `self.pos`, if it has one
+    fn write_ty(&mut self, ty: Option<TSpan>) {
+        if ty.is_some() {
+            self.pos += 1;
+            self.write_type();
+        }
+    }
+
+    /// write the type at `self.pos`, leaving `self.pos` at its last token
+    fn write_type(&mut self) {
+        let Token::Type(ty) = self.items[self.pos] else {
+            panic!("non type at index {}", self.pos);
+        };
+        match ty.kind {
+            TypeKind::Name(name) => {
+                self.push(name);
+                if self.pos + 1 < ty.end {
+                    self.push("<");
+                    self.write_types(ty.end);
+                    self.push(">");
+                }
+            }
+            TypeKind::Path(name) => {
+                self.push(name);
+                self.push(".");
+                self.pos += 1;
+                self.write_type();
+            }
+            TypeKind::Tuple => {
+                self.push("(");
+                self.write_types(ty.end);
+                self.push(")");
+            }
+            TypeKind::Optional => {
+                self.pos += 1;
+                self.write_type();
+                self.push("?");
+            }
+            TypeKind::Fn { ret } => {
+                self.push("fn");
+                if ret {
+                    self.pos += 1;
+                    self.write_type();
+                }
+                self.push("(");
+                self.write_types(ty.end);
+                self.push(")");
+            }
+            TypeKind::Param(name) => self.push(name),
+        }
+    }
+
+    /// write the types up to `end`, separated by commas
+    fn write_types(&mut self, end: usize) {
+        let mut first = true;
+        while self.pos + 1 < end {
+            if !first {
+                self.push(",");
+            }
+            first = false;
+            self.pos += 1;
+            self.write_type();
+        }
+    }
+
+    /// write the generic params of a fn, struct or enum
+    fn write_generics(&mut self, generics: TSpan) {
+        if generics.from < generics.to {
+            self.push("<");
+            self.write_types(generics.to);
+            self.push(">");
         }
-        self.out.pop();
-        self.push(")");
     }
 
     fn write_var(&mut self, name: Symbol) {
@@ -148,10 +525,7 @@ pub fn write_module(src: &str, module: &Module) -> Vec<String> {
         scheduled: Vec::new(),
     };
 
-    loop {
-        let Some(&token) = module.items.get(writer.pos as usize) else {
-            break;
-        };
+    while let Some(&token) = module.items.get(writer.pos) {
         writer.write_token(token);
         writer.pos += 1;
     }
@@ -161,6 +535,7 @@ pub fn write_module(src: &str, module: &Module) -> Vec<String> {
     out
 }
 
+#[allow(clippy::too_many_lines)]
 pub fn write_errs(src: &str, errs: &ErrorMulti) -> String {
     use crate::error::LexicalError::{self, *};
     use std::fmt::Write;
@@ -201,6 +576,21 @@ pub fn write_errs(src: &str, errs: &ErrorMulti) -> String {
                 )
             }
             Eof(pos) => writeln!(out, r#"eof {pos} "#),
+            TooMany(pos) => writeln!(out, r#"too many {pos} "#),
+            Separator(s) => writeln!(
+                out,
+                r#"separator {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            MissingElse(s) => writeln!(
+                out,
+                r#"missing else {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
             DupeComma(s) => writeln!(
                 out,
                 r#"dupe commas {},{} = "{}" "#,
@@ -208,9 +598,351 @@ pub fn write_errs(src: &str, errs: &ErrorMulti) -> String {
                 s.to,
                 &src[s.from..s.to]
             ),
+            AssignTarget(s) => writeln!(
+                out,
+                r#"assign target {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            Syntax(s, expected) => writeln!(
+                out,
+                r#"expected pos {},{} to be {} but was "{}" "#,
+                s.from,
+                s.to,
+                expected,
+                &src[s.from..s.to]
+            ),
+            NotInSubset(s, what) => writeln!(
+                out,
+                r#"not in subset {what} {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            Malformed(s, what) => writeln!(
+                out,
+                r#"malformed {what} {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
         }
     };
     errs.lex.iter().try_for_each(write_lex).unwrap();
+    let write_semantic = |err: &SemanticError| {
+        if out.get(out.len().saturating_sub(2)..) == Some(" \n") {
+            out.remove(out.len() - 2);
+        }
+        match err {
+            SemanticError::ConstAssign(s, name) => writeln!(
+                out,
+                r#"const assign {},{} to "{name}" = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Varargs(s) => writeln!(
+                out,
+                r#"varargs {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoField(s, ty, name) => writeln!(
+                out,
+                r#"no field "{name}" in "{ty}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoVariant(s, ty, name) => writeln!(
+                out,
+                r#"no variant "{name}" in "{ty}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::MissingField(s, name) => writeln!(
+                out,
+                r#"missing field "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeField(s, name) => writeln!(
+                out,
+                r#"dupe field "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeFieldDecl(s, name) => writeln!(
+                out,
+                r#"dupe field decl "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeVariant(s, name) => writeln!(
+                out,
+                r#"dupe variant "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeType(s, name) => writeln!(
+                out,
+                r#"dupe type "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoLike(s, like) => writeln!(
+                out,
+                r#"no like "{like}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoType(s, ty) => writeln!(
+                out,
+                r#"no type "{ty}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::MissingMethod {
+                span: s,
+                like,
+                name,
+                ..
+            } => writeln!(
+                out,
+                r#"missing method "{name}" of "{like}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::MethodSig(s, like, name) => writeln!(
+                out,
+                r#"method sig "{name}" of "{like}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeMethod(s, _, name) => writeln!(
+                out,
+                r#"dupe method "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NotPredef(s, name) => writeln!(
+                out,
+                r#"not predef "{name}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NotConst(s, ty) => writeln!(
+                out,
+                r#"not const {ty} {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Comp(s, err) => writeln!(
+                out,
+                r#"comp failed "{err}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoLabel(s, label) => writeln!(
+                out,
+                r#"no label "{label}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::ShadowedLabel(s, label) => writeln!(
+                out,
+                r#"shadowed label "{label}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::LoopBreak(s) => writeln!(
+                out,
+                r#"loop break {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NoGotoLabel(s, label) => writeln!(
+                out,
+                r#"no goto label "{label}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeLabel(s, label) => writeln!(
+                out,
+                r#"dupe label "{label}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::GotoScope(s, label, local) => writeln!(
+                out,
+                r#"goto "{label}" into "{local}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::MixedNum { span: s, lhs, rhs } => writeln!(
+                out,
+                r#"mixed "{lhs}" & "{rhs}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NumType {
+                span: s,
+                expected,
+                found,
+            }
+            | SemanticError::StrType {
+                span: s,
+                expected,
+                found,
+            } => writeln!(
+                out,
+                r#"expected "{expected}" found "{found}" {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NegUnsigned(s) => writeln!(
+                out,
+                r#"negated unsigned {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::SubUnsigned(s) => writeln!(
+                out,
+                r#"subtracted unsigned {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NotAscii(s) => writeln!(
+                out,
+                r#"not ascii {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::BadString(s) => writeln!(
+                out,
+                r#"bad string {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::MaybeNil(s, name) => writeln!(
+                out,
+                r#"maybe nil `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NilAssigned(s, ty) => writeln!(
+                out,
+                r#"nil to `{ty}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Undefined { span: s, name, .. } => writeln!(
+                out,
+                r#"undefined `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Unassigned(s, name) => writeln!(
+                out,
+                r#"unassigned `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::DupeParam(s, name) => writeln!(
+                out,
+                r#"dupe param `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Shadowed(s, name) => writeln!(
+                out,
+                r#"shadowed `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::Lint { span: s, name, .. } => writeln!(
+                out,
+                r#"lint `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::NonExhaustive(s, missing) => writeln!(
+                out,
+                r#"non exhaustive `{missing}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::UnreachableArm(s) => writeln!(
+                out,
+                r#"unreachable arm {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::UnreachablePat(s) => writeln!(
+                out,
+                r#"unreachable pat {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::AltBindings(s, name) => writeln!(
+                out,
+                r#"alt bindings `{name}` {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+            SemanticError::FieldCount {
+                span: s,
+                expected,
+                found,
+            } => writeln!(
+                out,
+                r#"field count {found} of {expected} {},{} = "{}" "#,
+                s.from,
+                s.to,
+                &src[s.from..s.to]
+            ),
+        }
+    };
+    errs.semantic.iter().try_for_each(write_semantic).unwrap();
     errs.other
         .iter()
         .try_for_each(|err| writeln!(out, r#"other error = "{err}""#))
@@ -234,12 +966,12 @@ impl<'a> From<&'a str> for AS<'a> {
         Self::Str(value)
     }
 }
-impl<'a> From<String> for AS<'a> {
+impl From<String> for AS<'_> {
     fn from(value: String) -> Self {
         Self::String(value)
     }
 }
-impl<'a> From<Symbol> for AS<'a> {
+impl From<Symbol> for AS<'_> {
     fn from(value: Symbol) -> Self {
         Self::Symbol(value)
     }
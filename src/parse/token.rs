@@ -1,4 +1,4 @@
-use crate::span::TSpan;
+use crate::span::{BSpan, TSpan};
 use crate::{lex, util::Symbol};
 
 /// a module of code
@@ -19,18 +19,191 @@ impl Module {
     }
 
     #[must_use]
-    pub fn len(&self) -> usize {
+    pub const fn len(&self) -> usize {
         self.items.len()
     }
 
     #[must_use]
-    pub fn is_empty(&self) -> bool {
+    pub const fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
     pub fn push(&mut self, token: impl Into<Token>) {
         self.items.push(token.into());
     }
+
+    #[must_use]
+    pub const fn name(&self) -> Symbol {
+        self.name
+    }
+
+    #[must_use]
+    pub fn items(&self) -> &[Token] {
+        &self.items
+    }
+
+    #[must_use]
+    pub fn get(&self, pos: usize) -> Option<Token> {
+        self.items.get(pos).copied()
+    }
+
+    /// The pos of the struct or enum named `name`
+    #[must_use]
+    pub fn data(&self, name: Symbol) -> Option<usize> {
+        self.items.iter().position(|token| match token {
+            Token::Struct(def) => def.name == name,
+            Token::Enum(def) => def.name == name,
+            _ => false,
+        })
+    }
+
+    /// The pos of the variant `name` of the enum at `pos`
+    #[must_use]
+    pub fn variant(&self, pos: usize, name: Symbol) -> Option<usize> {
+        let Some(Token::Enum(def)) = self.get(pos) else {
+            return None;
+        };
+        let mut pos = def.variants.from;
+        while pos < def.variants.to {
+            match self.items[pos] {
+                Token::Variant(variant) if variant.name == name => return Some(pos),
+                _ => pos = self.end(pos),
+            }
+        }
+        None
+    }
+
+    /// The fields of the struct or variant at `pos`
+    #[must_use]
+    pub fn fields(&self, pos: usize) -> Vec<Field> {
+        let fields = match self.get(pos) {
+            Some(Token::Struct(def)) => def.fields,
+            Some(Token::Variant(variant)) => variant.fields,
+            _ => return Vec::new(),
+        };
+        self.items[fields.from..fields.to]
+            .iter()
+            .filter_map(|token| match token {
+                Token::Field(field) => Some(*field),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// true if `ty` is an optional type, `<type>?`
+    #[must_use]
+    pub fn optional(&self, ty: Option<TSpan>) -> bool {
+        ty.is_some_and(|ty| {
+            matches!(
+                self.items[ty.from],
+                Token::Type(Type {
+                    kind: TypeKind::Optional,
+                    ..
+                })
+            )
+        })
+    }
+
+    /// The name of `ty` if it is a plain name, or an optional one
+    #[must_use]
+    pub fn type_name(&self, ty: Option<TSpan>) -> Option<Symbol> {
+        let ty = ty?;
+        match self.items[ty.from] {
+            Token::Type(Type {
+                kind: TypeKind::Optional,
+                ..
+            }) => self.type_name(Some(TSpan {
+                from: ty.from + 1,
+                to: ty.to,
+            })),
+            Token::Type(Type {
+                kind: TypeKind::Name(name),
+                end,
+                ..
+            }) if end == ty.from + 1 => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The type at `pos` as it would be written
+    #[must_use]
+    pub fn type_str(&self, pos: usize) -> String {
+        let Some(Token::Type(ty)) = self.get(pos) else {
+            return String::new();
+        };
+        let mut subs = Vec::new();
+        let mut at = pos + 1;
+        while at < ty.end {
+            subs.push(self.type_str(at));
+            at = self.end(at);
+        }
+        match ty.kind {
+            TypeKind::Name(name) | TypeKind::Param(name) if subs.is_empty() => name.to_string(),
+            TypeKind::Name(name) | TypeKind::Param(name) => format!("{name}<{}>", subs.join(", ")),
+            TypeKind::Path(name) => format!("{name}.{}", subs.concat()),
+            TypeKind::Tuple => format!("({})", subs.join(", ")),
+            TypeKind::Optional => format!("{}?", subs.concat()),
+            TypeKind::Fn { ret: true } => {
+                let ret = subs.remove(0);
+                format!("fn {ret}({})", subs.join(", "))
+            }
+            TypeKind::Fn { ret: false } => format!("fn({})", subs.join(", ")),
+        }
+    }
+
+    /// The end of the token at `pos`, including every token it owns
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is out of bounds
+    #[must_use]
+    pub fn end(&self, pos: usize) -> usize {
+        end_of(&self.items, pos)
+    }
+}
+
+/// The end of the token at `pos` of `items`, see [`Module::end`]
+pub(crate) fn end_of(items: &[Token], pos: usize) -> usize {
+    let after = |pos| match items.get(pos) {
+        Some(Token::Expr(expr)) => expr.end,
+        Some(Token::Tagged(tagged)) => tagged.tokens.to,
+        Some(Token::Block(block)) => block.tokens.to,
+        Some(Token::Flow(Flow::If(span, el))) => el.map_or(span.to, |el| el.to),
+        Some(Token::Match(def)) => def.arms.to,
+        _ => pos,
+    };
+    match items[pos] {
+        Token::Flow(Flow::If(span, el)) => el.map_or(span.to, |el| el.to),
+        Token::Flow(Flow::While(body)) => body.to,
+        Token::Flow(Flow::Repeat(body)) => after(body.to),
+        Token::Flow(Flow::For(def)) => def.body.to,
+        Token::FnDef(def) => def.tokens.to,
+        Token::Block(block) => block.tokens.to,
+        Token::Tagged(tagged) => tagged.tokens.to,
+        Token::Break(brk) => brk.values.to,
+        Token::Expr(expr) => expr.end,
+        Token::Assign(assign) => assign.values.to,
+        Token::Return(values) | Token::Tail(values) => values.to,
+        Token::Struct(def) => def.fields.to,
+        Token::Enum(def) => def.variants.to,
+        Token::Variant(variant) => variant.fields.to,
+        Token::Impl(def) => def.methods.to,
+        Token::Like(def) => def.sigs.to,
+        Token::FnSig(sig) => sig.params.to,
+        Token::Match(def) => def.arms.to,
+        Token::Arm(arm) => arm.body.to,
+        Token::Pat(pat) => pat.end,
+        Token::Type(ty) => ty.end,
+        Token::Decl(decl) if decl.value => after(decl.value_pos(pos)),
+        Token::FnDefParam(param) if param.value => after(param.value_pos(pos)),
+        Token::Decl(Decl { ty, .. })
+        | Token::FnDefParam(FnDefParam { ty, .. })
+        | Token::Field(Field { ty, .. }) => ty.map_or(pos + 1, |ty| ty.to),
+        Token::Flow(Flow::Goto(..) | Flow::Label(..))
+        | Token::Import(_)
+        | Token::Error(_)
+        | Token::Dummy => pos + 1,
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -40,34 +213,319 @@ pub enum Token {
     Decl(Decl),
     // NOTE: never have expr be under another token, instead refer to a span, etc
     Expr(Expr),
-    Return,
+    /// return <values>
+    Return(TSpan),
+    /// The tail expr of a block used as a value, its value
+    Tail(TSpan),
+    Assign(Assign),
     // Value(Value),
     Import(Import),
-    Block(TSpan),
+    Block(Block),
+    /// '<label>: { <token>, .. }
+    Tagged(Tagged),
+    Break(Break),
     FnDefParam(FnDefParam),
+    Struct(Struct),
+    Enum(Enum),
+    Variant(Variant),
+    Field(Field),
+    Impl(Impl),
+    Like(Like),
+    FnSig(FnSig),
+    Match(Match),
+    Arm(Arm),
+    Pat(Pat),
+    Type(Type),
+    /// A statement that failed to parse, in place of what was parsed of it
+    Error(BSpan),
     /// A dummy token. should never appear in the final output.
     Dummy,
 }
 
+impl Token {
+    /// Shift every token index at or after `from` by one.
+    ///
+    /// Used when a token is inserted before already parsed tokens.
+    pub(crate) fn shift(&mut self, from: usize) {
+        let shift = |i: &mut usize| {
+            if *i >= from {
+                *i += 1;
+            }
+        };
+        let shift_span = |span: &mut TSpan| {
+            shift(&mut span.from);
+            shift(&mut span.to);
+        };
+        match self {
+            Self::Flow(Flow::If(span, el)) => {
+                shift_span(span);
+                if let Some(el) = el {
+                    shift_span(el);
+                }
+            }
+            Self::Flow(Flow::While(body) | Flow::Repeat(body)) => shift_span(body),
+            Self::Flow(Flow::For(def)) => {
+                shift_span(&mut def.vars);
+                shift_span(&mut def.body);
+            }
+            Self::FnDef(def) => {
+                if let Some(ty) = &mut def.ty {
+                    shift_span(ty);
+                }
+                shift_span(&mut def.params);
+                shift_span(&mut def.tokens);
+            }
+            Self::Expr(expr) => {
+                shift(&mut expr.end);
+                if let ExprKind::Closure(closure) = &mut expr.kind {
+                    shift_span(&mut closure.params);
+                    shift_span(&mut closure.tokens);
+                }
+            }
+            Self::Assign(assign) => {
+                shift_span(&mut assign.targets);
+                shift_span(&mut assign.values);
+            }
+            Self::Block(block) => shift_span(&mut block.tokens),
+            Self::Tagged(tagged) => shift_span(&mut tagged.tokens),
+            Self::Break(brk) => shift_span(&mut brk.values),
+            Self::Return(values) | Self::Tail(values) => shift_span(values),
+            Self::Struct(def) => {
+                shift_span(&mut def.generics);
+                shift_span(&mut def.fields);
+            }
+            Self::Enum(def) => {
+                shift_span(&mut def.generics);
+                shift_span(&mut def.variants);
+            }
+            Self::Variant(variant) => shift_span(&mut variant.fields),
+            Self::Impl(def) => shift_span(&mut def.methods),
+            Self::Like(def) => shift_span(&mut def.sigs),
+            Self::FnSig(sig) => {
+                if let Some(ty) = &mut sig.ty {
+                    shift_span(ty);
+                }
+                shift_span(&mut sig.params);
+            }
+            Self::Match(def) => shift_span(&mut def.arms),
+            Self::Arm(arm) => {
+                shift_span(&mut arm.pats);
+                shift_span(&mut arm.body);
+            }
+            Self::Pat(pat) => shift(&mut pat.end),
+            Self::Type(ty) => shift(&mut ty.end),
+            Self::Decl(Decl { ty: Some(ty), .. })
+            | Self::FnDefParam(FnDefParam { ty: Some(ty), .. })
+            | Self::Field(Field { ty: Some(ty), .. }) => shift_span(ty),
+            Self::Decl(_)
+            | Self::FnDefParam(_)
+            | Self::Field(_)
+            | Self::Flow(Flow::Goto(..) | Flow::Label(..))
+            | Self::Import(_)
+            | Self::Error(_)
+            | Self::Dummy => {}
+        }
+    }
+
+    /// Move every token index by `tokens` & every byte span by `bytes`
+    ///
+    /// Used when the tokens before this one are parsed again.
+    pub(crate) fn offset(&mut self, tokens: isize, bytes: isize) {
+        match self {
+            Self::Flow(Flow::If(span, el)) => {
+                *span = span.offset(tokens);
+                *el = el.map(|el| el.offset(tokens));
+            }
+            Self::Flow(Flow::While(body) | Flow::Repeat(body)) => *body = body.offset(tokens),
+            Self::Flow(Flow::For(def)) => {
+                def.vars = def.vars.offset(tokens);
+                def.body = def.body.offset(tokens);
+            }
+            Self::FnDef(def) => {
+                def.span = def.span.offset(bytes);
+                def.ty = def.ty.map(|ty| ty.offset(tokens));
+                def.params = def.params.offset(tokens);
+                def.tokens = def.tokens.offset(tokens);
+            }
+            Self::Decl(Decl { ty, span, .. })
+            | Self::FnDefParam(FnDefParam { ty, span, .. })
+            | Self::Field(Field { ty, span, .. }) => {
+                *ty = ty.map(|ty| ty.offset(tokens));
+                *span = span.offset(bytes);
+            }
+            Self::Struct(def) => {
+                def.span = def.span.offset(bytes);
+                def.generics = def.generics.offset(tokens);
+                def.fields = def.fields.offset(tokens);
+            }
+            Self::Enum(def) => {
+                def.span = def.span.offset(bytes);
+                def.generics = def.generics.offset(tokens);
+                def.variants = def.variants.offset(tokens);
+            }
+            Self::Variant(variant) => {
+                variant.span = variant.span.offset(bytes);
+                variant.fields = variant.fields.offset(tokens);
+            }
+            Self::Impl(def) => {
+                def.span = def.span.offset(bytes);
+                def.methods = def.methods.offset(tokens);
+            }
+            Self::Like(def) => {
+                def.span = def.span.offset(bytes);
+                def.sigs = def.sigs.offset(tokens);
+            }
+            Self::FnSig(sig) => {
+                sig.span = sig.span.offset(bytes);
+                sig.ty = sig.ty.map(|ty| ty.offset(tokens));
+                sig.params = sig.params.offset(tokens);
+            }
+            Self::Match(def) => {
+                def.span = def.span.offset(bytes);
+                def.arms = def.arms.offset(tokens);
+            }
+            Self::Arm(arm) => {
+                arm.span = arm.span.offset(bytes);
+                arm.pats = arm.pats.offset(tokens);
+                arm.body = arm.body.offset(tokens);
+            }
+            Self::Pat(pat) => {
+                pat.end = pat.end.wrapping_add_signed(tokens);
+                pat.span = pat.span.offset(bytes);
+            }
+            Self::Type(ty) => {
+                ty.end = ty.end.wrapping_add_signed(tokens);
+                ty.span = ty.span.offset(bytes);
+            }
+            Self::Tagged(tagged) => {
+                tagged.span = tagged.span.offset(bytes);
+                tagged.tokens = tagged.tokens.offset(tokens);
+            }
+            Self::Break(brk) => {
+                brk.span = brk.span.offset(bytes);
+                brk.values = brk.values.offset(tokens);
+            }
+            Self::Expr(expr) => {
+                expr.end = expr.end.wrapping_add_signed(tokens);
+                expr.span = expr.span.offset(bytes);
+                if let ExprKind::Closure(closure) = &mut expr.kind {
+                    closure.params = closure.params.offset(tokens);
+                    closure.tokens = closure.tokens.offset(tokens);
+                }
+            }
+            Self::Assign(assign) => {
+                assign.targets = assign.targets.offset(tokens);
+                assign.values = assign.values.offset(tokens);
+            }
+            Self::Block(block) => {
+                block.span = block.span.offset(bytes);
+                block.tokens = block.tokens.offset(tokens);
+            }
+            Self::Return(span) | Self::Tail(span) => *span = span.offset(tokens),
+            Self::Flow(Flow::Goto(_, span) | Flow::Label(_, span)) | Self::Error(span) => {
+                *span = span.offset(bytes);
+            }
+            Self::Import(_) | Self::Dummy => {}
+        }
+    }
+}
+
 /// A user defined function
 ///
-/// Acts as both as a module, datatype and function
+/// Acts as both as a module, datatype and function. Its return type
+/// follows it, then its generic params as [`TypeKind::Param`]s.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct FnDef {
     pub name: Symbol,
-    pub type_name: Option<Symbol>,
+    /// the span of the name
+    pub span: BSpan,
+    pub ty: Option<TSpan>,
+    /// true if the last param is `...`
+    pub variadic: bool,
+    /// `prefn`, callable at compile time
+    pub pre: bool,
     pub params: TSpan,
     pub tokens: TSpan,
 }
 
-/// <type > <name> ?(= <value>);
+impl FnDef {
+    /// The generic params of the fn at `pos`, between its type and params
+    #[must_use]
+    pub fn generics(self, pos: usize) -> TSpan {
+        TSpan {
+            from: self.ty.map_or(pos + 1, |ty| ty.to),
+            to: self.params.from,
+        }
+    }
+}
+
+/// `impl` <type> (?`like` <like>) { <fn>, .. }
+///
+/// Its methods follow it as [`Token::FnDef`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Impl {
+    pub ty: Symbol,
+    /// the span of the type
+    pub span: BSpan,
+    /// the like the methods claim to satisfy
+    pub like: Option<Symbol>,
+    pub methods: TSpan,
+}
+
+/// `like` <name> { <fn sig>, .. }
+///
+/// Its sigs follow it as [`Token::FnSig`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Like {
+    pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
+    pub sigs: TSpan,
+}
+
+/// `fn` (?<type>) <name>(?<<generic>, ..>)(<param>, ..), a fn without a body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FnSig {
+    pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
+    pub ty: Option<TSpan>,
+    /// true if the last param is `...`
+    pub variadic: bool,
+    pub params: TSpan,
+}
+
+impl FnSig {
+    /// The generic params of the sig at `pos`, between its type and params
+    #[must_use]
+    pub fn generics(self, pos: usize) -> TSpan {
+        TSpan {
+            from: self.ty.map_or(pos + 1, |ty| ty.to),
+            to: self.params.from,
+        }
+    }
+}
+
+/// ?<type> <name> ?(= <value>);
+///
+/// Its type follows it, then its default value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct FnDefParam {
-    pub type_name: Symbol,
+    pub ty: Option<TSpan>,
     pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
     pub value: bool,
 }
 
+impl FnDefParam {
+    /// The pos of the default value of the param at `pos`
+    #[must_use]
+    pub fn value_pos(self, pos: usize) -> usize {
+        self.ty.map_or(pos + 1, |ty| ty.to)
+    }
+}
+
 macro_rules! token_from {
     ($($name:ident),*) => {$(
         impl<'a> From<$name> for Token {
@@ -78,7 +536,10 @@ macro_rules! token_from {
     )*};
 }
 
-token_from!(FnDef, Decl, Expr, Import, FnDefParam, Flow);
+token_from!(
+    FnDef, Decl, Expr, Import, FnDefParam, Flow, Assign, Struct, Enum, Variant, Field, Impl, Like,
+    FnSig, Tagged, Break, Match, Arm, Pat, Type, Block
+);
 
 impl From<ExprKind> for Token {
     fn from(value: ExprKind) -> Self {
@@ -86,20 +547,228 @@ impl From<ExprKind> for Token {
     }
 }
 
+/// `struct` <name>(?<<generic>, ..>) { (?<type>) <name>, .. }
+///
+/// Its generic params follow it, then its fields as [`Token::Field`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Struct {
+    pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
+    pub generics: TSpan,
+    pub fields: TSpan,
+}
+
+/// `enum` <name>(?<<generic>, ..>) { <variant>, .. }
+///
+/// Its generic params follow it, then its variants as [`Token::Variant`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Enum {
+    pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
+    pub generics: TSpan,
+    pub variants: TSpan,
+}
+
+/// <name> | <name>(<type>, ..) | <name> { (?<type>) <name>, .. }
+///
+/// Its fields follow it as [`Token::Field`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Variant {
+    pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
+    pub kind: VariantKind,
+    pub fields: TSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VariantKind {
+    /// <name>
+    Unit,
+    /// <name>(<type>, ..)
+    Tuple,
+    /// <name> { <field>, .. }
+    Struct,
+}
+
+/// (?<type>) <name>, or the <type> of a tuple variant
+///
+/// Its type follows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Field {
+    pub ty: Option<TSpan>,
+    /// `None` in a tuple variant
+    pub name: Option<Symbol>,
+    /// the span of the name, or of the type in a tuple variant
+    pub span: BSpan,
+}
+
+/// { <token>, .. }
+///
+/// Its tokens follow it. It may also be the value of a [`Decl`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Block {
+    /// from the `{` to the `}`
+    pub span: BSpan,
+    pub tokens: TSpan,
+}
+
+/// '<label>: { <token>, .. }, a block that can be broken out of
+///
+/// Its tokens follow it. It may also be the value of a [`Decl`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tagged {
+    pub label: Symbol,
+    /// the span of the label, including the `'`
+    pub span: BSpan,
+    pub tokens: TSpan,
+}
+
+/// `break` '<label> (?<value>, ..)
+///
+/// Its values follow it as exprs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Break {
+    pub label: Symbol,
+    /// the span of the label, including the `'`
+    pub span: BSpan,
+    pub values: TSpan,
+}
+
+/// `match` <value>, .. { <arm>, .. }
+///
+/// Its values follow it as exprs, then its arms as [`Token::Arm`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Match {
+    /// the span of the `match` & its values
+    pub span: BSpan,
+    pub arms: TSpan,
+}
+
+/// <pat> | .. (?`if` <guard>) => <expr> | { <token>, .. }
+///
+/// Its pats follow it, then its guard expr, if any, then its body. The
+/// value of the body is a [`Token::Tail`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Arm {
+    /// the span of the pats
+    pub span: BSpan,
+    pub pats: TSpan,
+    pub body: TSpan,
+}
+
+/// A pattern of an [`Arm`]
+///
+/// The sub pats of a pattern are stored directly after it, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pat {
+    /// The total end of the pattern, exclusive
+    pub end: usize,
+    pub kind: PatKind,
+    pub span: BSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PatKind {
+    /// `_`
+    Wild,
+    /// <name>, binds the value
+    Bind(Symbol),
+    /// a literal, `true`, `false` or `nil`
+    Const(Const),
+    /// (<pat>, ..), each value of the match, only allowed at the top
+    Tuple,
+    /// <struct> { <name> (?= <pat>), .. } | <enum>.<variant>(?(<pat>, ..))
+    ///
+    /// The sub pats are [`Self::Field`]s, or the plain pats of a tuple
+    /// variant. Fields left out match anything.
+    Data(Construct),
+    /// <name> (?= <pat>) in a [`Self::Data`], the pat is its sub pat, a
+    /// binding of <name> if left out
+    Field(Symbol),
+}
+
+/// A type, written where a binding, param, field or fn is declared
+///
+/// The sub types of a type are stored directly after it, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Type {
+    /// The total end of the type, exclusive
+    pub end: usize,
+    pub kind: TypeKind,
+    pub span: BSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeKind {
+    /// <name>(?<<type>, ..>), the sub types are its generic args
+    Name(Symbol),
+    /// <module>.<type>, the rest of the path is its sub type
+    Path(Symbol),
+    /// (<type>, ..)
+    Tuple,
+    /// <type>?, the value may also be `nil`
+    Optional,
+    /// `fn` (?<type>)(<type>, ..), the return type is the first sub type if
+    /// `ret`, then the params
+    Fn { ret: bool },
+    /// a generic param of a fn, struct or enum, `<T>`
+    Param(Symbol),
+}
+
 /// Control flow
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Flow {
     If(TSpan, Option<TSpan>),
-    // While(TSpan),
+    /// `while` <cond> `do` <token>, .. `end` in lua, the cond follows it,
+    /// then its body
+    While(TSpan),
+    /// `repeat` <token>, .. `until` <cond> in lua, its body follows it,
+    /// then the cond, which sees the locals of the body
+    Repeat(TSpan),
+    For(For),
+    /// `goto` <label> in lua, with the span of the label
+    Goto(Symbol, BSpan),
+    /// `::`<label>`::` in lua, with the span of the label
+    Label(Symbol, BSpan),
 }
 
-/// [`DeclKind`] <name> ?(= <value>);
+/// `for` <name> `=` <start>, <limit> (?, <step>) `do` <token>, .. `end` |
+/// `for` <name>, .. `in` <value>, .. `do` <token>, .. `end` in lua
+///
+/// Its values follow it, then its vars as [`Token::Decl`]s, then its body.
+/// Each run of the body has vars of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct For {
+    /// counts from <start> to <limit>, rather than calling an iterator
+    pub numeric: bool,
+    pub vars: TSpan,
+    pub body: TSpan,
+}
+
+/// [`DeclKind`] (?<type>) <name> ?(= <value>);
+///
+/// Its type follows it, then its value.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Decl {
     pub kind: DeclKind,
-    pub type_name: Option<Symbol>,
+    pub ty: Option<TSpan>,
     pub name: Symbol,
+    /// the span of the name
+    pub span: BSpan,
     pub value: bool,
+    /// `prelet` | `preconst` | `predef`, the value is a [`ExprKind::Comp`]
+    pub pre: bool,
+}
+
+impl Decl {
+    /// The pos of the value of the decl at `pos`
+    #[must_use]
+    pub fn value_pos(self, pos: usize) -> usize {
+        self.ty.map_or(pos + 1, |ty| ty.to)
+    }
 }
 
 /// <type> | `let` | `const` | `const` <type>
@@ -109,6 +778,20 @@ pub enum DeclKind {
     Let,
     /// const <name>
     Const,
+    /// `local` <name> `<close>` in lua, a const whose value is closed when
+    /// its scope ends
+    Close,
+}
+
+/// <target>, .. (?<op>)= <value>, ..
+///
+/// Compound assignments have exactly one target and one value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Assign {
+    /// `Some` for compound assignments such as `+=`
+    pub op: Option<BinOp>,
+    pub targets: TSpan,
+    pub values: TSpan,
 }
 
 /// ?(<defer>) `use` <name>;
@@ -121,11 +804,17 @@ pub struct Import {
 // NOTE: consider turnin span to just a usize denoting the end
 
 /// <name>(<params>) | <var> | <value>
+///
+/// The operands of an expression are stored directly after it, in order.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Expr {
-    /// The total end of the expression
+    /// The total end of the expression, exclusive
+    ///
+    /// A unit expr at `pos` ends at `pos + 1`.
     pub end: usize,
     pub kind: ExprKind,
+    /// The source of the expression, including its operands
+    pub span: BSpan,
 }
 
 impl From<ExprKind> for Expr {
@@ -133,28 +822,263 @@ impl From<ExprKind> for Expr {
         Self {
             end: 0,
             kind: value,
+            span: BSpan::default(),
         }
     }
 }
 
+impl Expr {
+    #[must_use]
+    pub const fn end(mut self, end: usize) -> Self {
+        self.end = end;
+        self
+    }
+
+    #[must_use]
+    pub const fn span(mut self, span: BSpan) -> Self {
+        self.span = span;
+        self
+    }
+}
+
 /// <name>(<params>) | <var> | <value>
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ExprKind {
     /// <name>(<params>)
     FnCall(FnCall),
+    /// <expr>(<params>)
+    ///
+    /// The callee is the first operand.
+    Call,
+    /// <expr>.<name>
+    Field(Symbol),
+    /// <expr>.<name>(<params>)
+    ///
+    /// The receiver is the first operand.
+    MethodCall(Symbol),
+    /// <expr>[<expr>]
+    Index,
+    /// <expr> <op> <expr>
+    Binary(BinOp),
+    /// <op> <expr>
+    Unary(UnOp),
+    /// `fn`(<params>) { <tokens> } | `|`<params>`|` <expr>
+    Closure(Closure),
+    /// `...`, the extra arguments of a variadic fn
+    Varargs,
+    /// (<expr>), a call or `...` truncated to its first value
+    Paren,
+    /// <struct> { <name> = <expr>, .. } | <enum>.<variant>(?(<expr>, ..))
+    ///
+    /// The operands are [`Self::FieldInit`]s, or the plain exprs of a tuple
+    /// variant.
+    Construct(Construct),
+    /// <name> = <expr> in a [`Self::Construct`] or [`Self::Table`], the
+    /// value is its operand
+    FieldInit(Symbol),
+    /// `{` <field>, .. `}` in lua, a table
+    ///
+    /// The operands are [`Self::FieldInit`]s, [`Self::Entry`]s & the plain
+    /// exprs of its list, the last of which gives all of its values.
+    Table,
+    /// `[`<key>`] =` <value> in a [`Self::Table`], the key & value are its
+    /// operands
+    Entry,
+    /// `comp` <expr>, evaluated at compile time
+    ///
+    /// The value is filled in by [`check::comp`](crate::check::comp), `None`
+    /// if it couldn't be evaluated.
+    Comp(Option<Const>),
     /// <name>
     Var(Symbol),
     /// constant value
     Value(Value),
 }
 
+impl ExprKind {
+    /// true for [`Self::FnCall`], [`Self::Call`] & [`Self::MethodCall`]
+    #[must_use]
+    pub const fn is_call(self) -> bool {
+        matches!(self, Self::FnCall(_) | Self::Call | Self::MethodCall(_))
+    }
+
+    /// true for calls & [`Self::Varargs`], which may produce any number of values
+    #[must_use]
+    pub const fn is_multi(self) -> bool {
+        self.is_call() || matches!(self, Self::Varargs)
+    }
+}
+
+/// An anonymous function
+///
+/// An expr body is stored as a [`Token::Return`] of the expr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Closure {
+    /// true if the last param is `...`
+    pub variadic: bool,
+    pub params: TSpan,
+    pub tokens: TSpan,
+}
+
+/// A binary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BinOp {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Rem,
+    /// `//`, floor division
+    IDiv,
+    /// `/` in lua, division that always gives a float
+    FDiv,
+    /// `%` in lua, the remainder of floor division
+    Mod,
+    /// `^` in lua, exponentiation
+    Pow,
+    /// `..` in lua, string concatenation
+    Concat,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `&`
+    BitAnd,
+    /// `|`
+    BitOr,
+    /// `^`
+    BitXor,
+    /// `<<`
+    Shl,
+    /// `>>`
+    Shr,
+    /// `==`
+    Eq,
+    /// `!=`
+    Ne,
+    /// `<`
+    Lt,
+    /// `<=`
+    Le,
+    /// `>`
+    Gt,
+    /// `>=`
+    Ge,
+}
+
+impl BinOp {
+    /// Binding power, higher binds tighter
+    #[must_use]
+    pub const fn precedence(self) -> u8 {
+        use BinOp::*;
+        match self {
+            Or => 1,
+            And => 2,
+            Eq | Ne | Lt | Le | Gt | Ge => 3,
+            BitOr => 4,
+            BitXor => 5,
+            BitAnd => 6,
+            Shl | Shr => 7,
+            Concat => 8,
+            Add | Sub => 9,
+            Mul | Div | Rem | IDiv | FDiv | Mod => 10,
+            Pow => 11,
+        }
+    }
+
+    /// true if the operator may be used in a compound assignment, such as `+=`
+    #[must_use]
+    pub const fn is_compound(self) -> bool {
+        use BinOp::*;
+        !matches!(
+            self,
+            And | Or | Eq | Ne | Lt | Le | Gt | Ge | IDiv | FDiv | Mod | Pow | Concat
+        )
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        use BinOp::*;
+        match self {
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div | FDiv => "/",
+            Rem | Mod => "%",
+            IDiv => "//",
+            Pow => "**",
+            Concat => "..",
+            And => "&&",
+            Or => "||",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "^",
+            Shl => "<<",
+            Shr => ">>",
+            Eq => "==",
+            Ne => "!=",
+            Lt => "<",
+            Le => "<=",
+            Gt => ">",
+            Ge => ">=",
+        }
+    }
+}
+
+/// A unary operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnOp {
+    /// `-`
+    Neg,
+    /// `!`
+    Not,
+    /// `#` in lua, the length of a string
+    Len,
+    /// `~` in lua, bitwise not
+    BitNot,
+}
+
+impl UnOp {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Neg => "-",
+            Self::Not => "!",
+            Self::Len => "#",
+            Self::BitNot => "~",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct FnCall {
     pub name: Symbol,
-    /// true if ended with comma
-    pub comma: bool,
-    // /// true if call has been closed
-    // pub closed: bool,
+}
+
+/// A value known at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Const {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    /// the bits of the float
+    Float(u64),
+    Str(Symbol),
+    AStr(Symbol),
+}
+
+/// The struct or the enum & variant being constructed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Construct {
+    pub ty: Symbol,
+    pub variant: Option<Symbol>,
+    /// [`VariantKind::Struct`] for a struct
+    pub kind: VariantKind,
 }
 
 macro_rules! impl_from {
@@ -166,10 +1090,7 @@ macro_rules! impl_from {
         }
         impl From<$ty> for Expr {
             fn from(value: $ty) -> Self {
-                Self {
-                    end: 0,
-                    kind: ExprKind::from(value)
-                }
+                Self::from(ExprKind::from(value))
             }
         }
         // impl From<$ty> for Token {
@@ -180,7 +1101,7 @@ macro_rules! impl_from {
     )*};
 }
 
-impl_from!(Value, FnCall);
+impl_from!(Value, FnCall, Closure, Construct);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Value {
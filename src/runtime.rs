@@ -0,0 +1,1080 @@
+//! a tree walking interpreter over a parsed [`Module`]
+//!
+//! Bindings declared at the top level of a module are globals, every other
+//! binding lives in a [`Cell`] so that closures can share it.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::check::{binding_name, captures};
+use crate::error::{HostError, RuntimeError};
+use crate::parse::token::*;
+use crate::span::{BSpan, TSpan};
+use crate::util::Symbol;
+
+pub use host::{FromValue, HostFn, HostFunction, IntoValue, IntoValues};
+pub use table::Table;
+pub use userdata::{HostMethod, TypeBuilder, UserData, UserRef, UserType};
+pub use value::{Cell, Data, Function, Value};
+
+use value::Chunk;
+
+pub mod builtin;
+mod flow;
+pub mod host;
+pub mod lua;
+mod meta;
+pub mod table;
+#[cfg(test)]
+mod test;
+pub mod userdata;
+pub mod value;
+
+/// The max number of nested calls
+pub const MAX_DEPTH: usize = 200;
+
+/// The max number of tables an `__index` or `__newindex` goes through, like
+/// lua
+const MAX_META: usize = 2000;
+
+pub type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Runs modules & holds their globals
+#[derive(Debug, Default)]
+pub struct Engine {
+    globals: HashMap<Symbol, Value>,
+    /// registered userdata types
+    types: HashMap<TypeId, Rc<UserType>>,
+    /// the methods of `impl` blocks, by type name & method name
+    methods: HashMap<(Symbol, Symbol), Value>,
+    depth: usize,
+    /// the calls left to make, unlimited if `None`
+    fuel: Option<usize>,
+    /// the control of a [`RuntimeError::Unwind`] on its way out of an expr
+    unwind: Option<Control>,
+    /// the `<close>` locals in scope, in the order they're declared
+    closing: Vec<flow::Closing>,
+}
+
+/// The variables of a running fn
+#[derive(Debug)]
+struct Frame {
+    chunk: Rc<Chunk>,
+    upvalues: Vec<(Symbol, Cell)>,
+    /// empty at the top level of a module
+    scopes: Vec<Vec<(Symbol, Cell)>>,
+    /// the extra arguments of a variadic fn
+    varargs: Vec<Value>,
+}
+
+#[derive(Debug)]
+enum Control {
+    Next,
+    Return(Vec<Value>),
+    /// `break` out of every block up to the tagged block with the label
+    Break(Symbol, Vec<Value>),
+    /// the tail expr of a block used as a value
+    Value(Value),
+    /// `goto` the label in the innermost block around it that has it
+    Goto(Symbol),
+}
+
+impl Engine {
+    /// An engine with the conversions of [`builtin`] as globals
+    #[must_use]
+    pub fn new() -> Self {
+        let mut engine = Self::default();
+        builtin::register(&mut engine);
+        engine
+    }
+
+    /// An engine with the standard library of [`lua`] as globals
+    #[must_use]
+    pub fn lua() -> Self {
+        let mut engine = Self::new();
+        lua::register(&mut engine);
+        engine
+    }
+
+    /// Limit the calls made & loop iterations run from now on to `fuel`,
+    /// after which a [`RuntimeError::OutOfFuel`] is raised, or lift the
+    /// limit with `None`
+    pub const fn set_fuel(&mut self, fuel: Option<usize>) {
+        self.fuel = fuel;
+    }
+
+    /// Run the top level of a module
+    ///
+    /// Returns the values of a top level `return`.
+    ///
+    /// # Errors
+    ///
+    /// The first [`RuntimeError`] raised
+    pub fn run(&mut self, module: Module) -> Result<Vec<Value>> {
+        self.run_with_args(module, Vec::new())
+    }
+
+    /// Run the top level of a module, with `args` as its `...`
+    ///
+    /// # Errors
+    ///
+    /// The first [`RuntimeError`] raised
+    pub fn run_with_args(&mut self, module: Module, args: Vec<Value>) -> Result<Vec<Value>> {
+        let captures = captures(&module);
+        let mut frame = Frame {
+            chunk: Rc::new(Chunk { module, captures }),
+            upvalues: Vec::new(),
+            scopes: Vec::new(),
+            varargs: args,
+        };
+        let len = frame.chunk.module.len();
+        let out = self.block(&mut frame, 0, len);
+        match self.close_vars((self.depth, 0, 0), out)? {
+            Control::Return(values) => Ok(values),
+            // an unknown label is reported by `check::labels` & the lua
+            // reader
+            Control::Next | Control::Break(..) | Control::Value(_) | Control::Goto(_) => {
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Call a fn value, returning every value it returns
+    ///
+    /// Missing arguments are `nil`, extra arguments are ignored unless the fn
+    /// is variadic.
+    ///
+    /// # Errors
+    ///
+    /// If `func` isn't a fn or the call raises a [`RuntimeError`]
+    pub fn call(&mut self, func: &Value, args: &[Value]) -> Result<Vec<Value>> {
+        if self.depth >= MAX_DEPTH || self.fuel == Some(0) {
+            return Err(self.limit());
+        }
+        if let Some(fuel) = &mut self.fuel {
+            *fuel -= 1;
+        }
+        let func = match func {
+            Value::Fn(func) => func,
+            Value::Host(func) => {
+                self.depth += 1;
+                let out = (func.func)(self, args.to_vec());
+                self.depth -= 1;
+                return out.map_err(|err| err.into_runtime(None, func.name));
+            }
+            Value::Table(table) => return self.call_table(table, args),
+            other => return Err(RuntimeError::NotCallable(other.type_name())),
+        };
+
+        let (params, tokens, variadic) = match func.chunk.module.get(func.pos) {
+            Some(Token::FnDef(def)) => (def.params, def.tokens, def.variadic),
+            Some(Token::Expr(Expr {
+                kind: ExprKind::Closure(closure),
+                ..
+            })) => (closure.params, closure.tokens, closure.variadic),
+            other => unreachable!("fn at {} is not a fn: {other:?}", func.pos),
+        };
+        let mut frame = Frame {
+            chunk: func.chunk.clone(),
+            upvalues: func.upvalues.clone(),
+            scopes: vec![Vec::new()],
+            varargs: Vec::new(),
+        };
+
+        self.depth += 1;
+        let out = self.params(&mut frame, params, args).and_then(|rest| {
+            if variadic {
+                frame.varargs = rest.to_vec();
+            }
+            self.block(&mut frame, tokens.from, tokens.to)
+        });
+        self.depth -= 1;
+        self.returned(out)
+    }
+
+    /// the values a fn call gives once its body ran, closing its `<close>`
+    /// locals
+    fn returned(&mut self, out: Result<Control>) -> Result<Vec<Value>> {
+        let out = match self.closing.is_empty() {
+            true => out,
+            false => self.close_vars((self.depth + 1, 0, 0), out),
+        };
+
+        // a `return` in the default value of a param
+        if matches!(out, Err(RuntimeError::Unwind)) {
+            if let Some(Control::Return(values)) = self.unwind.take() {
+                return Ok(values);
+            }
+        }
+        match out? {
+            Control::Return(values) => Ok(values),
+            // an unknown label is reported by `check::labels` & the lua
+            // reader
+            Control::Next | Control::Break(..) | Control::Value(_) | Control::Goto(_) => {
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Call the global fn `name`
+    ///
+    /// # Errors
+    ///
+    /// If there is no such global or the call raises a [`RuntimeError`]
+    pub fn call_global(&mut self, name: &str, args: &[Value]) -> Result<Vec<Value>> {
+        let func = self
+            .global(name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::Undefined(name.into()))?;
+        self.call(&func, args)
+    }
+
+    /// Register a rust fn with typed args as the global `name`
+    ///
+    /// Each arg is converted with [`FromValue`], & the result with
+    /// [`IntoValues`]. Missing args are `nil` & extra args are an error.
+    pub fn register_fn<Args>(&mut self, name: &str, func: impl HostFn<Args>) {
+        self.register_raw_fn(name, move |_, args| func.call(args));
+    }
+
+    /// Register a rust fn taking its args as they are as the global `name`
+    ///
+    /// The fn may call back into the engine.
+    pub fn register_raw_fn(
+        &mut self,
+        name: &str,
+        func: impl Fn(&mut Self, Vec<Value>) -> std::result::Result<Vec<Value>, HostError> + 'static,
+    ) {
+        let func = HostFunction {
+            name: name.into(),
+            func: Box::new(func),
+        };
+        self.globals.insert(name.into(), Value::Host(Rc::new(func)));
+    }
+
+    /// Register a rust fn with typed args as the method `name` of the type
+    /// `ty`, the value it's called on is its first arg
+    ///
+    /// Like the methods of an `impl`, it comes after the ones of a userdata.
+    pub fn register_method<Args>(&mut self, ty: &str, name: &str, func: impl HostFn<Args>) {
+        let func = HostFunction {
+            name: name.into(),
+            func: Box::new(move |_, args| func.call(args)),
+        };
+        let key = (Symbol::from(ty), Symbol::from(name));
+        self.methods.insert(key, Value::Host(Rc::new(func)));
+    }
+
+    #[must_use]
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.globals.get(&Symbol::from(name))
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        self.globals.insert(name.into(), value.into());
+    }
+
+    /// The names of every global, in no particular order
+    pub fn globals(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.globals.keys().copied()
+    }
+
+    /// the error of a call past the depth or fuel limit
+    const fn limit(&self) -> RuntimeError {
+        if self.depth >= MAX_DEPTH {
+            RuntimeError::StackOverflow
+        } else {
+            RuntimeError::OutOfFuel
+        }
+    }
+
+    /// declare the params, returning the args left over
+    fn params<'v>(
+        &mut self,
+        frame: &mut Frame,
+        params: TSpan,
+        mut args: &'v [Value],
+    ) -> Result<&'v [Value]> {
+        let chunk = frame.chunk.clone();
+        let mut pos = params.from;
+        while pos < params.to {
+            if let Some(Token::FnDefParam(param)) = chunk.module.get(pos) {
+                let value = match args.split_first() {
+                    Some((arg, rest)) => {
+                        args = rest;
+                        arg.clone()
+                    }
+                    None if param.value => self.eval(frame, param.value_pos(pos))?,
+                    None => Value::Nil,
+                };
+                declare_local(frame, param.name, value);
+            }
+            pos = chunk.module.end(pos);
+        }
+        Ok(args)
+    }
+
+    fn block(&mut self, frame: &mut Frame, from: usize, to: usize) -> Result<Control> {
+        let chunk = frame.chunk.clone();
+        let mut pos = from;
+        while pos < to {
+            let control = match self.stmt(frame, pos) {
+                Err(RuntimeError::Unwind) => self.unwind.take().unwrap_or(Control::Next),
+                out => out?,
+            };
+            pos = chunk.module.end(pos);
+            match control {
+                Control::Next => (),
+                // only the tail of the last statement is the block's value
+                Control::Value(_) if pos < to => (),
+                Control::Goto(label) => pos = self.goto(frame, from, to, pos, label)?,
+                control => return Ok(control),
+            }
+        }
+        Ok(Control::Next)
+    }
+
+    fn scoped(&mut self, frame: &mut Frame, span: TSpan) -> Result<Control> {
+        frame.scopes.push(Vec::new());
+        let out = self.block(frame, span.from, span.to);
+        frame.scopes.pop();
+        match self.closing.is_empty() {
+            true => out,
+            false => self.close_vars((self.depth, frame.scopes.len() + 1, 0), out),
+        }
+    }
+
+    fn stmt(&mut self, frame: &mut Frame, pos: usize) -> Result<Control> {
+        let chunk = frame.chunk.clone();
+        match chunk.module.items()[pos] {
+            Token::Decl(decl) => return self.decl(frame, pos, decl),
+            Token::FnDef(FnDef { name, .. }) => self.fn_def(frame, pos, name),
+            Token::Impl(def) => self.impl_methods(frame, def),
+            Token::Block(block) => return self.scoped(frame, block.tokens),
+            Token::Tagged(tagged) => match self.scoped(frame, tagged.tokens)? {
+                Control::Break(label, values) if label == tagged.label => {
+                    return Ok(values
+                        .into_iter()
+                        .next()
+                        .map_or(Control::Next, Control::Value));
+                }
+                control => return Ok(control),
+            },
+            Token::Break(brk) => {
+                let values = self.operands(frame, brk.values.from, brk.values.to)?;
+                return Ok(Control::Break(brk.label, values));
+            }
+            Token::Flow(Flow::If(span, el)) => return self.if_else(frame, pos, span, el),
+            Token::Flow(flow) => return self.flow(frame, pos, flow),
+            Token::Match(def) => return self.match_arms(frame, pos, def),
+            Token::Expr(_) => {
+                self.eval(frame, pos)?;
+            }
+            Token::Return(values) => {
+                let values = self.operands(frame, values.from, values.to)?;
+                return Ok(Control::Return(values));
+            }
+            Token::Tail(values) => return self.eval(frame, values.from).map(Control::Value),
+            Token::Assign(assign) => self.assign(frame, assign)?,
+            Token::FnDefParam(_)
+            | Token::Import(_)
+            | Token::Struct(_)
+            | Token::Enum(_)
+            | Token::Variant(_)
+            | Token::Field(_)
+            | Token::Like(_)
+            | Token::FnSig(_)
+            | Token::Arm(_)
+            | Token::Pat(_)
+            | Token::Type(_)
+            | Token::Error(_)
+            | Token::Dummy => (),
+        }
+        Ok(Control::Next)
+    }
+
+    // `fn_def`, `if_else` & the operators are kept out of `stmt` & `eval`,
+    // every nested call repeats their stack frames
+    fn fn_def(&mut self, frame: &mut Frame, pos: usize, name: Symbol) {
+        if frame.scopes.is_empty() {
+            let func = make_fn(frame, pos);
+            self.globals.insert(name, func);
+        } else {
+            // declared first so the fn can capture itself
+            declare_local(frame, name, Value::Nil);
+            let func = make_fn(frame, pos);
+            self.set_var(frame, name, func);
+        }
+    }
+
+    fn if_else(
+        &mut self,
+        frame: &mut Frame,
+        pos: usize,
+        span: TSpan,
+        el: Option<TSpan>,
+    ) -> Result<Control> {
+        if self.eval(frame, pos + 1)?.is_truthy() {
+            self.scoped(frame, span)
+        } else if let Some(el) = el {
+            self.scoped(frame, el)
+        } else {
+            Ok(Control::Next)
+        }
+    }
+
+    /// A tagged block as the value takes the first value it's broken out
+    /// with, or the value of its tail like a block
+    fn decl(&mut self, frame: &mut Frame, pos: usize, decl: Decl) -> Result<Control> {
+        let chunk = frame.chunk.clone();
+        let at = decl.value_pos(pos);
+        let value = match chunk.module.get(at) {
+            Some(Token::Tagged(_)) if decl.value => match self.stmt(frame, at)? {
+                Control::Value(value) => value,
+                Control::Next => Value::Nil,
+                control => return Ok(control),
+            },
+            _ if decl.value => self.eval(frame, at)?,
+            _ => Value::Nil,
+        };
+        match decl.kind {
+            DeclKind::Close => self.declare_close(frame, decl.name, value)?,
+            DeclKind::Let | DeclKind::Const => self.declare(frame, decl.name, value),
+        }
+        Ok(Control::Next)
+    }
+
+    /// Run the body of the first arm with a pat matching the values & a
+    /// guard that passes, nothing if there's none
+    fn match_arms(&mut self, frame: &mut Frame, pos: usize, def: Match) -> Result<Control> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let values = self.operands(frame, pos + 1, def.arms.from)?;
+        let mut arm = def.arms.from;
+        while arm < def.arms.to {
+            if let Token::Arm(token) = module.items()[arm] {
+                frame.scopes.push(Vec::new());
+                let out = self.arm(frame, token, &values);
+                frame.scopes.pop();
+                if let Some(control) = out? {
+                    return Ok(control);
+                }
+            }
+            arm = module.end(arm);
+        }
+        Ok(Control::Next)
+    }
+
+    /// `None` if the arm doesn't match, its bindings are declared in the
+    /// innermost scope
+    fn arm(&mut self, frame: &mut Frame, arm: Arm, values: &[Value]) -> Result<Option<Control>> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let mut pat = arm.pats.from;
+        let bindings = loop {
+            if pat >= arm.pats.to {
+                return Ok(None);
+            }
+            let mut bindings = Vec::new();
+            if top_pattern(module, pat, values, &mut bindings) {
+                break bindings;
+            }
+            pat = module.end(pat);
+        };
+        for (name, value) in bindings {
+            declare_local(frame, name, value);
+        }
+        if arm.pats.to < arm.body.from && !self.eval(frame, arm.pats.to)?.is_truthy() {
+            return Ok(None);
+        }
+        self.block(frame, arm.body.from, arm.body.to).map(Some)
+    }
+
+    /// every value is evaluated before any target is assigned
+    fn assign(&mut self, frame: &mut Frame, assign: Assign) -> Result<()> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+
+        let values = self.operands(frame, assign.values.from, assign.values.to)?;
+        let mut values = values.into_iter();
+        let mut pos = assign.targets.from;
+        while pos < assign.targets.to {
+            let mut value = values.next().unwrap_or_default();
+            if let Some(op) = assign.op {
+                let current = self.eval(frame, pos)?;
+                value = binary(op, &current, &value)?;
+            }
+            match module.get(pos) {
+                Some(Token::Expr(Expr {
+                    kind: ExprKind::Var(name),
+                    ..
+                })) => self.set_var(frame, name, value),
+                Some(Token::Expr(Expr {
+                    kind: ExprKind::Field(name),
+                    span,
+                    ..
+                })) => {
+                    let target = self.eval(frame, pos + 1)?;
+                    match target {
+                        Value::Table(_) => self.set_index(target, name.as_str().into(), value)?,
+                        _ => set_field(&target, name, value).map_err(|err| err.at(span))?,
+                    }
+                }
+                _ => {
+                    let target = self.eval(frame, pos + 1)?;
+                    let key = self.eval(frame, module.end(pos + 1))?;
+                    self.set_index(target, key, value)?;
+                }
+            }
+            pos = module.end(pos);
+        }
+        Ok(())
+    }
+
+    /// evaluate an expr that may produce any number of values
+    fn eval_multi(&mut self, frame: &mut Frame, pos: usize) -> Result<Vec<Value>> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let Some(Token::Expr(expr)) = module.get(pos) else {
+            return self.eval(frame, pos).map(|value| vec![value]);
+        };
+
+        let (func, args) = match expr.kind {
+            ExprKind::FnCall(FnCall { name }) => {
+                let func = self.lookup(frame, name)?;
+                (func, self.operands(frame, pos + 1, expr.end)?)
+            }
+            ExprKind::Call => {
+                let func = self.eval(frame, pos + 1)?;
+                (func, self.operands(frame, module.end(pos + 1), expr.end)?)
+            }
+            ExprKind::MethodCall(name) => {
+                let args = self.operands(frame, pos + 1, expr.end)?;
+                return self
+                    .call_method(name, &args)
+                    .map_err(|err| err.at(expr.span));
+            }
+            ExprKind::Varargs => return Ok(frame.varargs.clone()),
+            _ => return self.eval(frame, pos).map(|value| vec![value]),
+        };
+        self.call(&func, &args).map_err(|err| err.at(expr.span))
+    }
+
+    /// register the methods of an `impl`
+    fn impl_methods(&mut self, frame: &Frame, def: Impl) {
+        let module = &frame.chunk.module;
+        let mut pos = def.methods.from;
+        while pos < def.methods.to {
+            if let Token::FnDef(method) = module.items()[pos] {
+                self.methods
+                    .insert((def.ty, method.name), make_fn(frame, pos));
+            }
+            pos = module.end(pos);
+        }
+    }
+
+    /// Call the method `name` of `args[0]`
+    ///
+    /// The methods of a userdata's type come first, then the ones of the
+    /// `impl`s of the type itself, there's no inheritance.
+    fn call_method(&mut self, name: Symbol, args: &[Value]) -> Result<Vec<Value>> {
+        let target = &args[0];
+        if let Value::Table(_) = target {
+            let method = self.index(target.clone(), name.as_str().into())?;
+            return self.call(&method, args);
+        }
+        let host = match target {
+            Value::UserData(data) => data.ty.method(name).cloned().map(Value::Host),
+            _ => None,
+        };
+        let ty = Symbol::from(target.type_name());
+        let method = host
+            .or_else(|| self.methods.get(&(ty, name)).cloned())
+            .ok_or_else(|| match target {
+                Value::UserData(_) | Value::Data(_) => {
+                    RuntimeError::NoField(target.type_name(), name)
+                }
+                _ => RuntimeError::Index(target.type_name()),
+            })?;
+        self.call(&method, args)
+    }
+
+    /// evaluate an expr, truncated to its first value
+    fn eval(&mut self, frame: &mut Frame, pos: usize) -> Result<Value> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let Some(Token::Expr(expr)) = module.get(pos) else {
+            return self.value(frame, pos);
+        };
+
+        match expr.kind {
+            ExprKind::Value(lit) => Value::from_literal(lit),
+            ExprKind::Var(name) => self.lookup(frame, name),
+            ExprKind::FnCall(_) | ExprKind::Call | ExprKind::MethodCall(_) | ExprKind::Varargs => {
+                let values = self.eval_multi(frame, pos)?;
+                Ok(values.into_iter().next().unwrap_or_default())
+            }
+            ExprKind::Paren | ExprKind::FieldInit(_) | ExprKind::Comp(None) => {
+                self.eval(frame, pos + 1)
+            }
+            ExprKind::Comp(Some(value)) => Ok(value.into()),
+            ExprKind::Field(name) => self.field(frame, pos, name, expr.span),
+            ExprKind::Index => self.index_expr(frame, pos),
+            ExprKind::Binary(op @ (BinOp::And | BinOp::Or)) => {
+                let lhs = self.eval(frame, pos + 1)?;
+                match (op, lhs.is_truthy()) {
+                    (BinOp::And, true) | (BinOp::Or, false) => {
+                        self.eval(frame, module.end(pos + 1))
+                    }
+                    _ => Ok(lhs),
+                }
+            }
+            ExprKind::Binary(op) => self.binary(frame, pos, op, expr.span),
+            ExprKind::Unary(op) => self.unary(frame, pos, op, expr.span),
+            ExprKind::Closure(_) => Ok(make_fn(frame, pos)),
+            ExprKind::Construct(construct) => self.construct(frame, pos, expr.end, construct),
+            ExprKind::Table => self.table(frame, pos, expr.end),
+            // only in a table
+            ExprKind::Entry => Ok(Value::Nil),
+        }
+    }
+
+    /// The value of a block, `if` or `match` used as an operand, the tail
+    /// of the body that ran or `nil`
+    ///
+    /// A `return` or `break` in it unwinds to the statement around it.
+    fn value(&mut self, frame: &mut Frame, pos: usize) -> Result<Value> {
+        match self.stmt(frame, pos)? {
+            Control::Value(value) => Ok(value),
+            Control::Next => Ok(Value::Nil),
+            control => {
+                self.unwind = Some(control);
+                Err(RuntimeError::Unwind)
+            }
+        }
+    }
+
+    /// A binary operator, overloaded by either operand's userdata type
+    fn binary(&mut self, frame: &mut Frame, pos: usize, op: BinOp, span: BSpan) -> Result<Value> {
+        let lhs = self.eval(frame, pos + 1)?;
+        let rhs = self.eval(frame, frame.chunk.module.end(pos + 1))?;
+        let overload = [&lhs, &rhs].into_iter().find_map(|value| match value {
+            Value::UserData(data) => data.ty.binary(op).cloned(),
+            _ => None,
+        });
+        match overload {
+            Some(func) => self.call_operator(&func, &[lhs, rhs], span),
+            None if matches!(lhs, Value::Table(_)) || matches!(rhs, Value::Table(_)) => {
+                self.meta_binary(op, lhs, rhs)
+            }
+            None => binary(op, &lhs, &rhs),
+        }
+    }
+
+    /// A unary operator, overloaded by the operand's userdata type
+    fn unary(&mut self, frame: &mut Frame, pos: usize, op: UnOp, span: BSpan) -> Result<Value> {
+        let value = self.eval(frame, pos + 1)?;
+        let overload = match &value {
+            Value::UserData(data) => data.ty.unary(op).cloned(),
+            _ => None,
+        };
+        match overload {
+            Some(func) => self.call_operator(&func, &[value], span),
+            None if matches!(value, Value::Table(_)) => self.meta_unary(op, value),
+            None => unary(op, &value),
+        }
+    }
+
+    /// fields are stored in declaration order, whatever order they're set in
+    fn construct(
+        &mut self,
+        frame: &mut Frame,
+        pos: usize,
+        end: usize,
+        construct: Construct,
+    ) -> Result<Value> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let mut fields = Vec::new();
+        let mut at = pos + 1;
+        while at < end {
+            let field = match module.get(at) {
+                Some(Token::Expr(Expr {
+                    kind: ExprKind::FieldInit(name),
+                    ..
+                })) => (name, self.eval(frame, at + 1)?),
+                _ => (
+                    fields.len().to_string().as_str().into(),
+                    self.eval(frame, at)?,
+                ),
+            };
+            fields.push(field);
+            at = module.end(at);
+        }
+
+        let def = module.data(construct.ty).and_then(|def| {
+            construct
+                .variant
+                .map_or(Some(def), |variant| module.variant(def, variant))
+        });
+        if let (Some(def), VariantKind::Struct) = (def, construct.kind) {
+            let order = module.fields(def);
+            fields
+                .sort_by_key(|(name, _)| order.iter().position(|field| field.name == Some(*name)));
+        }
+
+        Ok(Value::Data(Rc::new(Data {
+            ty: construct.ty,
+            variant: construct.variant,
+            kind: construct.kind,
+            fields: RefCell::new(fields),
+        })))
+    }
+
+    fn call_operator(
+        &mut self,
+        func: &Rc<HostFunction>,
+        args: &[Value],
+        span: BSpan,
+    ) -> Result<Value> {
+        let values = self
+            .call(&Value::Host(func.clone()), args)
+            .map_err(|err| err.at(span))?;
+        Ok(values.into_iter().next().unwrap_or_default())
+    }
+
+    /// evaluate every expr in `from..to`
+    ///
+    /// Only the last expr is expanded to all of its values.
+    fn operands(&mut self, frame: &mut Frame, from: usize, to: usize) -> Result<Vec<Value>> {
+        let chunk = frame.chunk.clone();
+        let mut values = Vec::new();
+        let mut pos = from;
+        while pos < to {
+            let end = chunk.module.end(pos);
+            if end < to {
+                values.push(self.eval(frame, pos)?);
+            } else {
+                values.extend(self.eval_multi(frame, pos)?);
+            }
+            pos = end;
+        }
+        Ok(values)
+    }
+
+    fn declare(&mut self, frame: &mut Frame, name: Symbol, value: Value) {
+        if frame.scopes.is_empty() {
+            self.globals.insert(name, value);
+        } else {
+            declare_local(frame, name, value);
+        }
+    }
+
+    fn lookup(&self, frame: &Frame, name: Symbol) -> Result<Value> {
+        if let Some(cell) = find_cell(frame, name) {
+            return Ok(cell.borrow().clone());
+        }
+        if let Some(value) = self.globals.get(&name) {
+            return Ok(value.clone());
+        }
+        match name.as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            "nil" => Ok(Value::Nil),
+            _ => Err(RuntimeError::Undefined(name)),
+        }
+    }
+
+    /// assigning an undeclared variable creates a global
+    fn set_var(&mut self, frame: &Frame, name: Symbol, value: Value) {
+        match find_cell(frame, name) {
+            Some(cell) => *cell.borrow_mut() = value,
+            None => {
+                self.globals.insert(name, value);
+            }
+        }
+    }
+}
+
+fn declare_local(frame: &mut Frame, name: Symbol, value: Value) {
+    if let Some(scope) = frame.scopes.last_mut() {
+        scope.push((name, Rc::new(RefCell::new(value))));
+    }
+}
+
+fn find_cell(frame: &Frame, name: Symbol) -> Option<&Cell> {
+    frame
+        .scopes
+        .iter()
+        .rev()
+        .chain(std::iter::once(&frame.upvalues))
+        .find_map(|vars| vars.iter().rev().find(|(other, _)| *other == name))
+        .map(|(_, cell)| cell)
+}
+
+/// create the fn at `pos`, capturing its upvalues from `frame`
+fn make_fn(frame: &Frame, pos: usize) -> Value {
+    let module = &frame.chunk.module;
+    let upvalues = frame
+        .chunk
+        .captures
+        .get(pos)
+        .iter()
+        .filter_map(|&binding| binding_name(module, binding))
+        .map(|name| {
+            let cell = find_cell(frame, name).cloned().unwrap_or_default();
+            (name, cell)
+        })
+        .collect();
+    Value::Fn(Rc::new(Function {
+        chunk: frame.chunk.clone(),
+        pos,
+        upvalues,
+    }))
+}
+
+/// true if the values of a match fit the pat at `pos`, a tuple is matched
+/// against each value, any other pat against the first
+fn top_pattern(
+    module: &Module,
+    pos: usize,
+    values: &[Value],
+    bindings: &mut Vec<(Symbol, Value)>,
+) -> bool {
+    match module.get(pos) {
+        Some(Token::Pat(Pat {
+            kind: PatKind::Tuple,
+            end,
+            ..
+        })) => {
+            let mut values = values.iter();
+            let mut sub = pos + 1;
+            while sub < end {
+                let value = values.next().cloned().unwrap_or_default();
+                if !pattern(module, sub, &value, bindings) {
+                    return false;
+                }
+                sub = module.end(sub);
+            }
+            true
+        }
+        _ => pattern(
+            module,
+            pos,
+            &values.first().cloned().unwrap_or_default(),
+            bindings,
+        ),
+    }
+}
+
+/// true if `value` fits the pat at `pos`, pushing the values of its bindings
+fn pattern(
+    module: &Module,
+    pos: usize,
+    value: &Value,
+    bindings: &mut Vec<(Symbol, Value)>,
+) -> bool {
+    let Some(Token::Pat(pat)) = module.get(pos) else {
+        return false;
+    };
+    match pat.kind {
+        PatKind::Wild => true,
+        PatKind::Bind(name) => {
+            bindings.push((name, value.clone()));
+            true
+        }
+        PatKind::Const(expected) => *value == Value::from(expected),
+        PatKind::Data(construct) => {
+            let Value::Data(data) = value else {
+                return false;
+            };
+            if data.ty != construct.ty || data.variant != construct.variant {
+                return false;
+            }
+            let mut sub = pos + 1;
+            let mut index = 0;
+            while sub < pat.end {
+                let field = match module.get(sub) {
+                    Some(Token::Pat(Pat {
+                        kind: PatKind::Field(name),
+                        ..
+                    })) => data.get(name).map(|value| (sub + 1, value)),
+                    _ => data
+                        .fields
+                        .borrow()
+                        .get(index)
+                        .map(|(_, value)| (sub, value.clone())),
+                };
+                let Some((at, field)) = field else {
+                    return false;
+                };
+                if !pattern(module, at, &field, bindings) {
+                    return false;
+                }
+                index += 1;
+                sub = module.end(sub);
+            }
+            true
+        }
+        // only at the top
+        PatKind::Tuple | PatKind::Field(_) => false,
+    }
+}
+
+fn get_field(target: &Value, name: Symbol) -> Result<Value> {
+    if let Value::Data(data) = target {
+        return data
+            .get(name)
+            .ok_or_else(|| RuntimeError::NoField(target.type_name(), name));
+    }
+    let Value::UserData(data) = target else {
+        return Err(RuntimeError::Index(target.type_name()));
+    };
+    let Some(value) = data.ty.get(data, name) else {
+        return Err(RuntimeError::NoField(target.type_name(), name));
+    };
+    value.map_err(|err| err.into_runtime(None, name))
+}
+
+fn set_field(target: &Value, name: Symbol, value: Value) -> Result<()> {
+    if let Value::Data(data) = target {
+        return match data.set(name, value) {
+            true => Ok(()),
+            false => Err(RuntimeError::NoField(target.type_name(), name)),
+        };
+    }
+    let Value::UserData(data) = target else {
+        return Err(RuntimeError::Index(target.type_name()));
+    };
+    let Some(out) = data.ty.set(data, name, value) else {
+        return Err(RuntimeError::NoField(target.type_name(), name));
+    };
+    out.map_err(|err| err.into_runtime(None, name))
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub(crate) const fn float(i: i64) -> f64 {
+    i as f64
+}
+
+fn binary(op: BinOp, lhs: &Value, rhs: &Value) -> Result<Value> {
+    use BinOp::*;
+    use Value::{AStr, BStr, Float, Int, Str};
+    let err = || RuntimeError::BinOp(op, lhs.type_name(), rhs.type_name());
+    let as_float = |v: &Value| match *v {
+        Int(i) => float(i),
+        Float(f) => f,
+        _ => unreachable!(),
+    };
+    let value = match (op, lhs, rhs) {
+        (Eq, _, _) => Value::Bool(lhs == rhs),
+        (Ne, _, _) => Value::Bool(lhs != rhs),
+        (Div | Rem | IDiv | Mod, Int(_), Int(0)) => return Err(RuntimeError::DivZero),
+        (Concat, Str(_) | Int(_) | Float(_), Str(_) | Int(_) | Float(_)) => {
+            Str(format!("{lhs}{rhs}").into())
+        }
+        (Pow, Int(_) | Float(_), Int(_) | Float(_)) => Float(as_float(lhs).powf(as_float(rhs))),
+        (FDiv, Int(_) | Float(_), Int(_) | Float(_)) => Float(as_float(lhs) / as_float(rhs)),
+        (_, &Int(l), &Int(r)) => match op {
+            Add => Int(l.wrapping_add(r)),
+            Sub => Int(l.wrapping_sub(r)),
+            Mul => Int(l.wrapping_mul(r)),
+            Div => Int(l.wrapping_div(r)),
+            Rem => Int(l.wrapping_rem(r)),
+            Mod => {
+                let rem = l.wrapping_rem(r);
+                // takes the sign of the divisor
+                Int(if rem != 0 && (rem < 0) != (r < 0) {
+                    rem + r
+                } else {
+                    rem
+                })
+            }
+            IDiv => {
+                let div = l.wrapping_div(r);
+                // round towards negative infinity
+                Int(if l.wrapping_rem(r) != 0 && (l < 0) != (r < 0) {
+                    div - 1
+                } else {
+                    div
+                })
+            }
+            BitAnd => Int(l & r),
+            BitOr => Int(l | r),
+            BitXor => Int(l ^ r),
+            Shl => Int(u32::try_from(r)
+                .ok()
+                .and_then(|r| l.checked_shl(r))
+                .unwrap_or(0)),
+            Shr => Int(u32::try_from(r)
+                .ok()
+                .and_then(|r| l.checked_shr(r))
+                .unwrap_or(0)),
+            Lt => Value::Bool(l < r),
+            Le => Value::Bool(l <= r),
+            Gt => Value::Bool(l > r),
+            Ge => Value::Bool(l >= r),
+            And | Or | Eq | Ne | Pow | Concat | FDiv => unreachable!(),
+        },
+        (_, Int(_) | Float(_), Int(_) | Float(_)) => {
+            let (l, r) = (as_float(lhs), as_float(rhs));
+            match op {
+                Add => Float(l + r),
+                Sub => Float(l - r),
+                Mul => Float(l * r),
+                Div => Float(l / r),
+                Rem => Float(l % r),
+                IDiv => Float((l / r).floor()),
+                Mod => {
+                    let rem = l % r;
+                    Float(if rem != 0.0 && (rem < 0.0) != (r < 0.0) {
+                        rem + r
+                    } else {
+                        rem
+                    })
+                }
+                Lt => Value::Bool(l < r),
+                Le => Value::Bool(l <= r),
+                Gt => Value::Bool(l > r),
+                Ge => Value::Bool(l >= r),
+                _ => return Err(err()),
+            }
+        }
+        (Add, Str(l), Str(r)) => Str(format!("{l}{r}").into()),
+        (Add, AStr(l), AStr(r)) => AStr(format!("{l}{r}").into()),
+        (Add, BStr(l), BStr(r)) => BStr([&l[..], &r[..]].concat().into()),
+        (Lt | Le | Gt | Ge, Str(l), Str(r)) | (Lt | Le | Gt | Ge, AStr(l), AStr(r)) => {
+            compare(op, l.cmp(r))
+        }
+        (Lt | Le | Gt | Ge, BStr(l), BStr(r)) => compare(op, l.cmp(r)),
+        _ => return Err(err()),
+    };
+    Ok(value)
+}
+
+/// the result of `<`, `<=`, `>` or `>=` from an ordering
+const fn compare(op: BinOp, ordering: Ordering) -> Value {
+    Value::Bool(match op {
+        BinOp::Lt => ordering.is_lt(),
+        BinOp::Le => ordering.is_le(),
+        BinOp::Gt => ordering.is_gt(),
+        _ => ordering.is_ge(),
+    })
+}
+
+fn unary(op: UnOp, value: &Value) -> Result<Value> {
+    match (op, value) {
+        (UnOp::Neg, Value::Int(i)) => Ok(Value::Int(i.wrapping_neg())),
+        (UnOp::Neg, Value::Float(f)) => Ok(Value::Float(-f)),
+        (UnOp::Not, _) => Ok(Value::Bool(!value.is_truthy())),
+        #[allow(clippy::cast_possible_wrap)]
+        (UnOp::Len, Value::Str(s) | Value::AStr(s)) => Ok(Value::Int(s.len() as i64)),
+        #[allow(clippy::cast_possible_wrap)]
+        (UnOp::Len, Value::BStr(b)) => Ok(Value::Int(b.len() as i64)),
+        (UnOp::BitNot, Value::Int(i)) => Ok(Value::Int(!i)),
+        _ => Err(RuntimeError::UnOp(op, value.type_name())),
+    }
+}
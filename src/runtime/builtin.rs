@@ -0,0 +1,140 @@
+//! the conversions every [`Engine::new`] has as globals
+//!
+//! Numbers & strings never convert implicitly, these do it explicitly:
+//! - `signed(x)`, `unsigned(x)` & `decimal(x)` between numbers, fails if
+//!   the value is out of range
+//! - `astr(s)`, `bstr(s)` & `ustr(s)` between strings, fails if the bytes
+//!   aren't ascii or utf8
+//!
+//! `ustr`s & `astr`s also get the methods of lua's strings that don't need
+//! tables or patterns: `len`, `upper`, `lower`, `rep` & `sub`.
+
+use std::rc::Rc;
+
+use crate::error::HostError;
+use crate::runtime::{float, Engine, Value};
+
+type Result<T> = std::result::Result<T, HostError>;
+
+/// The longest string `rep` builds
+const MAX_LEN: usize = 1 << 30;
+
+pub(crate) fn register(engine: &mut Engine) {
+    engine.register_fn("signed", |x: Value| to_int(&x, i64::MIN));
+    engine.register_fn("unsigned", |x: Value| to_int(&x, 0));
+    engine.register_fn("decimal", |x: Value| match x {
+        Value::Int(i) => Ok(float(i)),
+        Value::Float(x) => Ok(x),
+        other => Err(HostError::type_error("number", &other)),
+    });
+    engine.register_fn("astr", |s: Value| astr(&s));
+    engine.register_fn("bstr", |s: Value| match s {
+        Value::Str(s) | Value::AStr(s) => Ok(Value::BStr(s.as_bytes().into())),
+        Value::BStr(_) => Ok(s),
+        other => Err(HostError::type_error("string", &other)),
+    });
+    engine.register_fn("ustr", |s: Value| match s {
+        Value::Str(s) | Value::AStr(s) => Ok(Value::Str(s)),
+        Value::BStr(b) => std::str::from_utf8(&b)
+            .map(|s| Value::Str(s.into()))
+            .map_err(|err| format!("invalid utf8: {err}").into()),
+        other => Err(HostError::type_error("string", &other)),
+    });
+    for ty in ["ustr", "astr"] {
+        engine.register_method(ty, "len", |s: Rc<str>| int(s.len()));
+        engine.register_method(ty, "upper", |s: Value| map_str(&s, str::to_ascii_uppercase));
+        engine.register_method(ty, "lower", |s: Value| map_str(&s, str::to_ascii_lowercase));
+        engine.register_method(ty, "rep", |s: Value, n: i64, sep: Option<Rc<str>>| {
+            rep(&s, n, sep.as_deref().unwrap_or(""))
+        });
+        engine.register_method(ty, "sub", |s: Value, i: i64, j: Option<i64>| {
+            sub(&s, i, j.unwrap_or(-1))
+        });
+    }
+}
+
+/// `x` as an int of at least `min`, decimals are truncated
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn to_int(x: &Value, min: i64) -> Result<i64> {
+    // `i64::MAX as f64` rounds up to 2^63
+    let range = i64::MIN as f64..i64::MAX as f64;
+    let i = match *x {
+        Value::Int(i) => i,
+        Value::Float(f) if range.contains(&f.trunc()) => f.trunc() as i64,
+        Value::Float(f) => return Err(format!("{f} is out of range").into()),
+        _ => return Err(HostError::type_error("number", x)),
+    };
+    match i >= min {
+        true => Ok(i),
+        false => Err(format!("{i} is out of range").into()),
+    }
+}
+
+fn astr(s: &Value) -> Result<Value> {
+    let text: Rc<str> = match s {
+        Value::AStr(_) => return Ok(s.clone()),
+        Value::Str(s) => s.clone(),
+        Value::BStr(b) => match std::str::from_utf8(b) {
+            Ok(s) => s.into(),
+            Err(_) => return Err("not ascii".into()),
+        },
+        other => return Err(HostError::type_error("string", other)),
+    };
+    match text.is_ascii() {
+        true => Ok(Value::AStr(text)),
+        false => Err(format!("`{text}` is not ascii").into()),
+    }
+}
+
+#[allow(clippy::cast_possible_wrap)]
+const fn int(len: usize) -> i64 {
+    len as i64
+}
+
+/// a string of the same kind as `s` from its text
+fn map_str(s: &Value, f: impl FnOnce(&str) -> String) -> Value {
+    match s {
+        Value::AStr(text) => Value::AStr(f(text).into()),
+        Value::Str(text) => Value::Str(f(text).into()),
+        other => unreachable!("string method of {}", other.type_name()),
+    }
+}
+
+/// `s` `n` times with `sep` between
+fn rep(s: &Value, n: i64, sep: &str) -> Result<Value> {
+    let (Value::Str(text) | Value::AStr(text)) = s else {
+        unreachable!("string method of {}", s.type_name());
+    };
+    let Some(n) = usize::try_from(n).ok().filter(|&n| n > 0) else {
+        return Ok(map_str(s, |_| String::new()));
+    };
+    let len = (text.len() + sep.len())
+        .checked_mul(n)
+        .map(|len| len - sep.len());
+    if len.is_none_or(|len| len > MAX_LEN) {
+        return Err("resulting string too large".into());
+    }
+    Ok(map_str(s, |text| [text, sep].concat().repeat(n - 1) + text))
+}
+
+/// the bytes `i..=j` of `s`, counting from 1, negative from the end, like lua
+fn sub(s: &Value, i: i64, j: i64) -> Result<Value> {
+    let (Value::Str(text) | Value::AStr(text)) = s else {
+        unreachable!("string method of {}", s.type_name());
+    };
+    let len = int(text.len());
+    let pos = |i: i64| match i {
+        i if i < 0 => (len + i + 1).max(0),
+        i => i.min(len),
+    };
+    // both are in 0..=len
+    let (from, to) = (pos(i).max(1), pos(j));
+    let range = usize::try_from(from - 1).unwrap_or(0)..usize::try_from(to).unwrap_or(0);
+    if range.is_empty() {
+        return Ok(map_str(s, |_| String::new()));
+    }
+    let slice = text
+        .get(range)
+        .ok_or_else(|| format!("{from}..{to} splits a char"))?;
+    Ok(map_str(s, |_| slice.to_owned()))
+}
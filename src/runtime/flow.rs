@@ -0,0 +1,388 @@
+//! the loops, `goto`s & `<close>` locals of lua
+
+use crate::check::binding_name;
+use crate::error::RuntimeError;
+use crate::parse::token::*;
+use crate::span::TSpan;
+use crate::util::Symbol;
+
+use super::{declare_local, float, Cell, Control, Engine, Frame, Result, Value};
+
+/// The values a numeric `for` counts through
+#[derive(Debug, Clone, Copy)]
+enum Range {
+    /// runs `count + 1` times
+    Int {
+        start: i64,
+        step: i64,
+        count: u64,
+    },
+    Float {
+        start: f64,
+        limit: f64,
+        step: f64,
+    },
+}
+
+impl Engine {
+    /// a loop, `goto` or label
+    pub(super) fn flow(&mut self, frame: &mut Frame, pos: usize, flow: Flow) -> Result<Control> {
+        match flow {
+            Flow::While(body) => loop {
+                if !self.eval(frame, pos + 1)?.is_truthy() {
+                    return Ok(Control::Next);
+                }
+                let vars = TSpan {
+                    from: body.from,
+                    to: body.from,
+                };
+                if let Some(control) = self.iterate(frame, vars, Vec::new(), body)? {
+                    return Ok(control);
+                }
+            },
+            Flow::Repeat(body) => loop {
+                if let Some(control) = self.repeat(frame, body)? {
+                    return Ok(control);
+                }
+            },
+            Flow::For(def) if def.numeric => self.numeric_for(frame, pos, def),
+            Flow::For(def) => self.generic_for(frame, pos, def),
+            Flow::Goto(label, _) => Ok(Control::Goto(label)),
+            Flow::If(..) | Flow::Label(..) => Ok(Control::Next),
+        }
+    }
+
+    /// Run the body of a loop in a scope of its own, with the vars in
+    /// `vars` bound to `values`, `Some` if the loop ends
+    fn iterate(
+        &mut self,
+        frame: &mut Frame,
+        vars: TSpan,
+        values: Vec<Value>,
+        body: TSpan,
+    ) -> Result<Option<Control>> {
+        self.tick()?;
+        let chunk = frame.chunk.clone();
+        frame.scopes.push(Vec::new());
+        let mut values = values.into_iter();
+        for var in vars.from..vars.to {
+            if let Some(name) = binding_name(&chunk.module, var) {
+                declare_local(frame, name, values.next().unwrap_or_default());
+            }
+        }
+        let out = self.block(frame, body.from, body.to);
+        frame.scopes.pop();
+        let out = self.close_vars((self.depth, frame.scopes.len() + 1, 0), out)?;
+        Ok(end_loop(out))
+    }
+
+    /// run the body of a `repeat` once, then its cond, which sees the
+    /// locals of the body
+    fn repeat(&mut self, frame: &mut Frame, body: TSpan) -> Result<Option<Control>> {
+        self.tick()?;
+        frame.scopes.push(Vec::new());
+        let out = self
+            .block(frame, body.from, body.to)
+            .and_then(|control| match control {
+                Control::Next | Control::Value(_) if self.eval(frame, body.to)?.is_truthy() => {
+                    Ok(Control::Break("".into(), Vec::new()))
+                }
+                control => Ok(control),
+            });
+        frame.scopes.pop();
+        let out = self.close_vars((self.depth, frame.scopes.len() + 1, 0), out)?;
+        Ok(end_loop(out))
+    }
+
+    /// `for` <var> `=` <start>, <limit>, <step>
+    ///
+    /// Like lua 5.4, the loop counts in ints if the start & step are ints,
+    /// running a number of times worked out before it starts, & in floats
+    /// otherwise.
+    fn numeric_for(&mut self, frame: &mut Frame, pos: usize, def: For) -> Result<Control> {
+        let chunk = frame.chunk.clone();
+        let mut values = Vec::new();
+        let mut at = pos + 1;
+        while at < def.vars.from {
+            values.push(self.eval(frame, at)?);
+            at = chunk.module.end(at);
+        }
+        let step = values.get(2).cloned().unwrap_or(Value::Int(1));
+        match range(&values[0], &values[1], &step)? {
+            Some(Range::Int { start, step, count }) => {
+                let mut i = start;
+                for _ in 0..=count {
+                    let vars = vec![Value::Int(i)];
+                    if let Some(control) = self.iterate(frame, def.vars, vars, def.body)? {
+                        return Ok(control);
+                    }
+                    i = i.wrapping_add(step);
+                }
+            }
+            Some(Range::Float { start, limit, step }) => {
+                let mut x = start;
+                loop {
+                    let vars = vec![Value::Float(x)];
+                    if let Some(control) = self.iterate(frame, def.vars, vars, def.body)? {
+                        return Ok(control);
+                    }
+                    x += step;
+                    let more = match step > 0.0 {
+                        true => x <= limit,
+                        false => limit <= x,
+                    };
+                    if !more {
+                        break;
+                    }
+                }
+            }
+            None => (),
+        }
+        Ok(Control::Next)
+    }
+
+    /// `for` <var>, .. `in` <f>, <state>, <ctrl>, <closing>
+    ///
+    /// Calls `f(state, ctrl)` until its first value, the next `ctrl`, is
+    /// `nil`. The closing value is closed when the loop ends, like a
+    /// `<close>` local.
+    fn generic_for(&mut self, frame: &mut Frame, pos: usize, def: For) -> Result<Control> {
+        let mut values = self.operands(frame, pos + 1, def.vars.from)?.into_iter();
+        let mut next = || values.next().unwrap_or_default();
+        let (func, state, mut ctrl, closing) = (next(), next(), next(), next());
+        let closing = match closing.is_truthy() {
+            true => {
+                check_closable("(for state)".into(), &closing)?;
+                Some(closing)
+            }
+            false => None,
+        };
+        let out = loop {
+            let vars = match self.call(&func, &[state.clone(), ctrl]) {
+                Ok(vars) => vars,
+                Err(err) => break Err(err),
+            };
+            ctrl = vars.first().cloned().unwrap_or_default();
+            if matches!(ctrl, Value::Nil) {
+                break Ok(Control::Next);
+            }
+            match self.iterate(frame, def.vars, vars, def.body) {
+                Ok(None) => (),
+                Ok(Some(control)) => break Ok(control),
+                Err(err) => break Err(err),
+            }
+        };
+        match closing {
+            Some(value) => self.close_value(value, out),
+            None => out,
+        }
+    }
+
+    /// The pos after the label a `goto` in the block `from..to`, which has
+    /// run up to `pos`, jumps to
+    ///
+    /// A jump back drops the locals declared after the label, closing the
+    /// ones that are `<close>`. If the label isn't in the block the `goto`
+    /// unwinds to the blocks around it.
+    pub(super) fn goto(
+        &mut self,
+        frame: &mut Frame,
+        from: usize,
+        to: usize,
+        pos: usize,
+        label: Symbol,
+    ) -> Result<usize> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let mut at = from;
+        while at < to {
+            if let Token::Flow(Flow::Label(name, _)) = module.items()[at] {
+                if name == label {
+                    break;
+                }
+            }
+            at = module.end(at);
+        }
+        if at >= to {
+            self.unwind = Some(Control::Goto(label));
+            return Err(RuntimeError::Unwind);
+        }
+        let mut declared = 0;
+        let mut stmt = at;
+        while stmt < pos {
+            if let Token::Decl(_) | Token::FnDef(_) = module.items()[stmt] {
+                declared += 1;
+            }
+            stmt = module.end(stmt);
+        }
+        let level = frame.scopes.len();
+        if let Some(scope) = frame.scopes.last_mut() {
+            let len = scope.len().saturating_sub(declared);
+            scope.truncate(len);
+            self.close_vars((self.depth, level, len), Ok(Control::Next))?;
+        }
+        Ok(module.end(at))
+    }
+
+    /// Declare a `<close>` local, its value must have a `__close` unless
+    /// it's `nil` or `false`
+    ///
+    /// A local of a `local` with more than one name is declared before its
+    /// value is assigned, & checked when it's closed.
+    pub(super) fn declare_close(
+        &mut self,
+        frame: &mut Frame,
+        name: Symbol,
+        value: Value,
+    ) -> Result<()> {
+        if value.is_truthy() {
+            check_closable(name, &value)?;
+        }
+        let index = frame.scopes.last().map_or(0, Vec::len);
+        self.declare(frame, name, value);
+        let cell = frame
+            .scopes
+            .last()
+            .and_then(|scope| scope.last())
+            .map(|(_, cell)| cell.clone());
+        self.closing
+            .push(((self.depth, frame.scopes.len(), index), name, cell));
+        Ok(())
+    }
+
+    /// Close the `<close>` locals declared at or after `from`, the last
+    /// first, with the error leaving their scope
+    pub(super) fn close_vars(
+        &mut self,
+        from: (usize, usize, usize),
+        mut out: Result<Control>,
+    ) -> Result<Control> {
+        while let Some((at, name, cell)) = self.closing.pop() {
+            if at < from {
+                self.closing.push((at, name, cell));
+                break;
+            }
+            // a global at the top level
+            let value = match cell {
+                Some(cell) => cell.borrow().clone(),
+                None => self.globals.get(&name).cloned().unwrap_or_default(),
+            };
+            if !value.is_truthy() {
+                continue;
+            }
+            out = match check_closable(name, &value) {
+                Ok(()) => self.close_value(value, out),
+                Err(err) => Err(err),
+            };
+        }
+        out
+    }
+
+    /// call the `__close` of `value` with the error of `out`, which a new
+    /// error replaces
+    fn close_value(&mut self, value: Value, out: Result<Control>) -> Result<Control> {
+        let err = match &out {
+            Ok(_) | Err(RuntimeError::Unwind) => Value::Nil,
+            Err(err) => Value::from(err.to_string()),
+        };
+        let close = match &value {
+            Value::Table(table) => table.meta_field("__close"),
+            _ => Value::Nil,
+        };
+        self.call(&close, &[value, err])?;
+        out
+    }
+
+    /// use up the fuel of a loop iteration
+    const fn tick(&mut self) -> Result<()> {
+        match &mut self.fuel {
+            Some(0) => Err(RuntimeError::OutOfFuel),
+            Some(fuel) => {
+                *fuel -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// `None` to run a loop again after its body gave `control`
+fn end_loop(control: Control) -> Option<Control> {
+    match control {
+        Control::Next | Control::Value(_) => None,
+        Control::Break(label, _) if label.as_str().is_empty() => Some(Control::Next),
+        control => Some(control),
+    }
+}
+
+/// the values of a numeric `for`, `None` if it runs no times
+fn range(start: &Value, limit: &Value, step: &Value) -> Result<Option<Range>> {
+    for (value, what) in [(start, "initial value"), (limit, "limit"), (step, "step")] {
+        if !matches!(value, Value::Int(_) | Value::Float(_)) {
+            return Err(RuntimeError::ForValue(what, value.type_name()));
+        }
+    }
+    if let (&Value::Int(start), &Value::Int(step)) = (start, step) {
+        if step == 0 {
+            return Err(RuntimeError::ForStep);
+        }
+        let limit = match *limit {
+            Value::Int(limit) => limit,
+            Value::Float(limit) => match int_limit(limit, step) {
+                Some(limit) => limit,
+                None => return Ok(None),
+            },
+            _ => unreachable!(),
+        };
+        if (step > 0 && start > limit) || (step < 0 && start < limit) {
+            return Ok(None);
+        }
+        let count = start.abs_diff(limit) / step.unsigned_abs();
+        return Ok(Some(Range::Int { start, step, count }));
+    }
+    let as_float = |value: &Value| match *value {
+        Value::Int(i) => float(i),
+        Value::Float(f) => f,
+        _ => unreachable!(),
+    };
+    let (start, limit, step) = (as_float(start), as_float(limit), as_float(step));
+    if step == 0.0 {
+        return Err(RuntimeError::ForStep);
+    }
+    // a NaN limit runs the loop once, like lua
+    if (step > 0.0 && limit < start) || (step < 0.0 && start < limit) {
+        return Ok(None);
+    }
+    Ok(Some(Range::Float { start, limit, step }))
+}
+
+/// a float limit of an int loop, rounded towards the start & clipped to
+/// the ints, `None` if the loop runs no times
+#[allow(clippy::cast_possible_truncation)]
+fn int_limit(limit: f64, step: i64) -> Option<i64> {
+    let limit = match step < 0 {
+        true => limit.ceil(),
+        false => limit.floor(),
+    };
+    // -2^63..2^63
+    if (float(i64::MIN)..-float(i64::MIN)).contains(&limit) {
+        return Some(limit as i64);
+    }
+    // NaN counts as too small, like lua
+    match limit > 0.0 {
+        true => (step > 0).then_some(i64::MAX),
+        false => (step < 0).then_some(i64::MIN),
+    }
+}
+
+/// A `<close>` local, by the depth, scope & index in the scope it's
+/// declared at, with its cell, `None` for a global
+pub(super) type Closing = ((usize, usize, usize), Symbol, Option<Cell>);
+
+/// a `<close>` value must have a `__close` metamethod
+fn check_closable(name: Symbol, value: &Value) -> Result<()> {
+    match value {
+        Value::Table(table) if !matches!(table.meta_field("__close"), Value::Nil) => Ok(()),
+        _ => Err(RuntimeError::NotClosable(name)),
+    }
+}
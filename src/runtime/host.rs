@@ -0,0 +1,233 @@
+//! functions provided by the host program
+
+use std::fmt::{self, Debug};
+use std::rc::Rc;
+
+use crate::error::HostError;
+use crate::runtime::{Engine, Table, Value};
+use crate::util::Symbol;
+
+/// The signature of every host fn once its args are no longer typed
+pub type RawFn = dyn Fn(&mut Engine, Vec<Value>) -> Result<Vec<Value>, HostError>;
+
+/// A rust fn callable from scripts
+pub struct HostFunction {
+    pub(crate) name: Symbol,
+    pub(crate) func: Box<RawFn>,
+}
+
+impl HostFunction {
+    #[must_use]
+    pub const fn name(&self) -> Symbol {
+        self.name
+    }
+}
+
+impl Debug for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostFunction")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Convert a script value into a rust value
+pub trait FromValue: Sized {
+    /// # Errors
+    ///
+    /// A [`HostError::Type`] if the value has the wrong type
+    fn from_value(value: Value) -> Result<Self, HostError>;
+}
+
+/// Convert a rust value into a script value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+impl<T: Into<Value>> IntoValue for T {
+    fn into_value(self) -> Value {
+        self.into()
+    }
+}
+
+/// The return type of a host fn, any number of values
+pub trait IntoValues {
+    /// # Errors
+    ///
+    /// If the host fn failed
+    fn into_values(self) -> Result<Vec<Value>, HostError>;
+}
+
+impl<T: IntoValue> IntoValues for T {
+    fn into_values(self) -> Result<Vec<Value>, HostError> {
+        Ok(vec![self.into_value()])
+    }
+}
+
+impl IntoValues for () {
+    fn into_values(self) -> Result<Vec<Value>, HostError> {
+        Ok(Vec::new())
+    }
+}
+
+impl IntoValues for Vec<Value> {
+    fn into_values(self) -> Result<Vec<Value>, HostError> {
+        Ok(self)
+    }
+}
+
+impl<T: IntoValues, E: Into<HostError>> IntoValues for Result<T, E> {
+    fn into_values(self) -> Result<Vec<Value>, HostError> {
+        self.map_err(Into::into)?.into_values()
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($ty:ident),*) => {
+        impl<$($ty: IntoValue),*> IntoValues for ($($ty,)*) {
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Result<Vec<Value>, HostError> {
+                let ($($ty,)*) = self;
+                Ok(vec![$($ty.into_value()),*])
+            }
+        }
+    };
+}
+
+impl_tuple!(A, B);
+impl_tuple!(A, B, C);
+impl_tuple!(A, B, C, D);
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, HostError> {
+        Ok(value)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, HostError> {
+        match value {
+            Value::Nil => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+macro_rules! impl_from_value {
+    ($($ty:ty, $expected:literal, $pat:pat => $out:expr),* $(,)?) => { $(
+        impl FromValue for $ty {
+            fn from_value(value: Value) -> Result<Self, HostError> {
+                match value {
+                    $pat => Ok($out),
+                    other => Err(HostError::type_error($expected, &other)),
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_value!(
+    bool, "bool", Value::Bool(b) => b,
+    i64, "int", Value::Int(i) => i,
+    f64, "float", Value::Float(f) => f,
+    Rc<str>, "ustr", Value::Str(s) | Value::AStr(s) => s,
+    String, "ustr", Value::Str(s) | Value::AStr(s) => s.to_string(),
+    Rc<[u8]>, "bstr", Value::BStr(b) => b,
+    Vec<u8>, "bstr", Value::BStr(b) => b.to_vec(),
+    Rc<Table>, "table", Value::Table(t) => t,
+);
+
+macro_rules! impl_from_int {
+    ($($ty:ty),*) => { $(
+        impl FromValue for $ty {
+            fn from_value(value: Value) -> Result<Self, HostError> {
+                match value {
+                    Value::Int(i) => {
+                        <$ty>::try_from(i).map_err(|_| HostError::type_error(stringify!($ty), &value))
+                    }
+                    other => Err(HostError::type_error(stringify!($ty), &other)),
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_int!(i32, u32, usize);
+
+/// A rust fn with typed args, see [`Engine::register_fn`]
+///
+/// `Args` is a tuple of the arg types, only used to tell impls apart.
+pub trait HostFn<Args>: 'static {
+    /// # Errors
+    ///
+    /// A wrong number of args, an arg of the wrong type or a failed call
+    fn call(&self, args: Vec<Value>) -> Result<Vec<Value>, HostError>;
+}
+
+/// The args of a host fn, converted one at a time
+pub(crate) struct Args {
+    values: std::iter::Enumerate<std::vec::IntoIter<Value>>,
+    expected: usize,
+    found: usize,
+}
+
+impl Args {
+    /// # Errors
+    ///
+    /// A [`HostError::Arity`] if there are more than `expected` args
+    pub(crate) fn new(args: Vec<Value>, expected: usize) -> Result<Self, HostError> {
+        let found = args.len();
+        if found > expected {
+            return Err(HostError::Arity { expected, found });
+        }
+        Ok(Self {
+            values: args.into_iter().enumerate(),
+            expected,
+            found,
+        })
+    }
+
+    /// Convert the next arg
+    ///
+    /// Missing args are nil, which only some types accept.
+    ///
+    /// # Errors
+    ///
+    /// If the arg has the wrong type, or is missing & not optional
+    pub(crate) fn next<T: FromValue>(&mut self) -> Result<T, HostError> {
+        let (index, value) = self.values.next().unwrap_or((self.found, Value::Nil));
+        T::from_value(value).map_err(|err| match index < self.found {
+            true => err.at_arg(index),
+            false => HostError::Arity {
+                expected: self.expected,
+                found: self.found,
+            },
+        })
+    }
+}
+
+macro_rules! impl_host_fn {
+    ($($arg:ident),*) => {
+        impl<Func, Out, $($arg),*> HostFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Out + 'static,
+            Out: IntoValues,
+            $($arg: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, args: Vec<Value>) -> Result<Vec<Value>, HostError> {
+                let mut args = Args::new(args, 0 $(+ { stringify!($arg); 1 })*)?;
+                $(let $arg = args.next()?;)*
+                (self)($($arg),*).into_values()
+            }
+        }
+    };
+}
+
+impl_host_fn!();
+impl_host_fn!(A);
+impl_host_fn!(A, B);
+impl_host_fn!(A, B, C);
+impl_host_fn!(A, B, C, D);
+impl_host_fn!(A, B, C, D, E);
+impl_host_fn!(A, B, C, D, E, F);
@@ -0,0 +1,612 @@
+//! the standard library of lua every [`Engine::lua`] has as globals
+//!
+//! - the basic fns: `assert`, `error`, `pcall`, `select`, `type`,
+//!   `tostring`, `tonumber`, `ipairs`, `pairs`, `next`, `rawget`, `rawset`,
+//!   `rawequal`, `rawlen`, `setmetatable` & `getmetatable`
+//! - `table`: `insert`, `remove`, `concat`, `unpack`, `pack` & `sort`
+//! - `string`: the string methods of [`builtin`](super::builtin), with
+//!   `byte`, `char` & `reverse`
+//! - `math`: `abs`, `ceil`, `floor`, `max`, `min`, `sqrt`, `fmod`,
+//!   `tointeger`, `type`, `huge`, `pi`, `maxinteger` & `mininteger`
+//!
+//! Errors are strings: `error` raises the string of its value & `pcall`
+//! gives the message of the error it caught.
+
+use std::rc::Rc;
+
+use crate::error::{HostError, RuntimeError};
+use crate::runtime::meta::len;
+use crate::runtime::{float, Engine, HostFunction, Table, Value};
+
+type Result<T> = std::result::Result<T, HostError>;
+
+pub(crate) fn register(engine: &mut Engine) {
+    engine.register_raw_fn("assert", |_, args| match args.first() {
+        Some(value) if value.is_truthy() => Ok(args),
+        _ => Err(args
+            .get(1)
+            .map_or_else(|| "assertion failed!".to_owned(), ToString::to_string)
+            .into()),
+    });
+    engine.register_fn("error", |message: Value| -> Result<()> {
+        Err(message.to_string().into())
+    });
+    engine.register_raw_fn("pcall", pcall);
+    engine.register_raw_fn("select", select);
+    engine.register_fn("type", |value: Value| type_name(&value));
+    engine.register_raw_fn("tostring", |engine, args| {
+        let value = args.into_iter().next().unwrap_or_default();
+        tostring(engine, value).map(|s| vec![s])
+    });
+    engine.register_fn("tonumber", tonumber);
+    engine.register_raw_fn("ipairs", |_, args| {
+        let table = args.into_iter().next().unwrap_or_default();
+        let iter = host("ipairs_iter", |engine: &mut Engine, args: Vec<Value>| {
+            let mut args = args.into_iter();
+            let (table, i) = (args.next().unwrap_or_default(), args.next());
+            let i = match i {
+                Some(Value::Int(i)) => i.wrapping_add(1),
+                _ => return Err(HostError::type_error("int", &Value::Nil)),
+            };
+            let value = engine.index(table, Value::Int(i))?;
+            Ok(match value {
+                Value::Nil => vec![Value::Nil],
+                value => vec![Value::Int(i), value],
+            })
+        });
+        Ok(vec![iter, table, Value::Int(0)])
+    });
+    engine.register_raw_fn("pairs", |engine, args| {
+        let value = args.into_iter().next().unwrap_or_default();
+        let table: Rc<Table> = host_arg(value.clone())?;
+        let handler = table.meta_field("__pairs");
+        if let Value::Nil = handler {
+            let next = engine.global("next").cloned().unwrap_or_default();
+            return Ok(vec![next, value, Value::Nil]);
+        }
+        let mut values = engine.call(&handler, &[value])?;
+        values.resize(3, Value::Nil);
+        Ok(values)
+    });
+    engine.register_fn("next", |table: Rc<Table>, key: Value| {
+        match table.next(&key) {
+            Ok(Some((key, value))) => Ok(vec![key, value]),
+            Ok(None) => Ok(vec![Value::Nil]),
+            Err(()) => Err(HostError::from("invalid key to `next`")),
+        }
+    });
+    engine.register_fn("rawget", |table: Rc<Table>, key: Value| table.get(&key));
+    engine.register_fn("rawset", |table: Rc<Table>, key: Value, value: Value| {
+        table.set(key, value)?;
+        Ok::<_, HostError>(Value::Table(table))
+    });
+    engine.register_fn("rawequal", |a: Value, b: Value| a == b);
+    engine.register_fn("rawlen", |value: Value| match value {
+        Value::Table(table) => Ok(len(&table)),
+        Value::Str(s) | Value::AStr(s) => Ok(int(s.len())),
+        Value::BStr(b) => Ok(int(b.len())),
+        other => Err(HostError::type_error("table or string", &other)),
+    });
+    engine.register_fn(
+        "setmetatable",
+        |table: Rc<Table>, meta: Option<Rc<Table>>| {
+            let protected = table.meta_field("__metatable");
+            if !matches!(protected, Value::Nil) {
+                return Err(HostError::from("cannot change a protected metatable"));
+            }
+            table.set_meta(meta);
+            Ok(Value::Table(table))
+        },
+    );
+    engine.register_fn("getmetatable", |value: Value| {
+        let Value::Table(table) = value else {
+            return Value::Nil;
+        };
+        match table.meta_field("__metatable") {
+            Value::Nil => table.meta().map_or(Value::Nil, Value::Table),
+            protected => protected,
+        }
+    });
+    let lib = table_lib();
+    engine.set_global("table", lib);
+    let lib = string_lib(engine);
+    engine.set_global("string", lib);
+    engine.set_global("math", math_lib());
+}
+
+fn table_lib() -> Table {
+    let lib = Table::default();
+    set(
+        &lib,
+        "insert",
+        host("insert", |e: &mut Engine, a: Vec<Value>| insert(e, &a)),
+    );
+    set(
+        &lib,
+        "remove",
+        host("remove", |e: &mut Engine, a: Vec<Value>| remove(e, &a)),
+    );
+    set(
+        &lib,
+        "concat",
+        host("concat", |engine: &mut Engine, args: Vec<Value>| {
+            concat(engine, args)
+        }),
+    );
+    set(&lib, "unpack", host("unpack", unpack));
+    set(
+        &lib,
+        "pack",
+        host("pack", |_: &mut Engine, args: Vec<Value>| {
+            let n = int(args.len());
+            let table = Table::from_list(args);
+            set(&table, "n", Value::Int(n));
+            Ok(vec![table.into()])
+        }),
+    );
+    set(&lib, "sort", host("sort", sort));
+    lib
+}
+
+/// the string methods, which are the fields of `string` as well
+fn string_lib(engine: &mut Engine) -> Table {
+    for ty in ["ustr", "astr"] {
+        engine.register_method(ty, "byte", |s: Rc<str>, i: Option<i64>, j: Option<i64>| {
+            let i = i.unwrap_or(1);
+            let (from, to) = byte_range(s.len(), i, j.unwrap_or(i));
+            s.as_bytes()[from..to]
+                .iter()
+                .map(|&b| Value::Int(b.into()))
+                .collect::<Vec<_>>()
+        });
+        engine.register_method(ty, "reverse", |s: Rc<str>| -> Result<String> {
+            match s.is_ascii() {
+                true => Ok(s.chars().rev().collect()),
+                false => Err("`reverse` of a string that is not ascii".into()),
+            }
+        });
+    }
+    let lib = Table::default();
+    let methods: Vec<_> = engine
+        .methods
+        .iter()
+        .filter(|((ty, _), _)| ty.as_str() == "ustr")
+        .map(|((_, name), method)| (*name, method.clone()))
+        .collect();
+    for (name, method) in methods {
+        set(&lib, &name, method);
+    }
+    set(
+        &lib,
+        "char",
+        host("char", |_: &mut Engine, args: Vec<Value>| {
+            let mut out = String::new();
+            for (i, arg) in args.into_iter().enumerate() {
+                let c = match arg {
+                    Value::Int(c) => u8::try_from(c).ok().filter(u8::is_ascii),
+                    _ => None,
+                };
+                let Some(c) = c else {
+                    return Err(HostError::type_error("ascii code", &arg).at_arg(i));
+                };
+                out.push(char::from(c));
+            }
+            Ok(vec![out.into()])
+        }),
+    );
+    lib
+}
+
+fn math_lib() -> Table {
+    let lib = Table::default();
+    let math = |name: &'static str, func: fn(f64) -> f64| {
+        host(name, move |_: &mut Engine, args: Vec<Value>| {
+            let x = number(args.first(), 0)?;
+            Ok(vec![Value::Float(func(x))])
+        })
+    };
+    set(&lib, "sqrt", math("sqrt", f64::sqrt));
+    set(
+        &lib,
+        "floor",
+        host("floor", |_: &mut Engine, args: Vec<Value>| {
+            round(args.first(), f64::floor)
+        }),
+    );
+    set(
+        &lib,
+        "ceil",
+        host("ceil", |_: &mut Engine, args: Vec<Value>| {
+            round(args.first(), f64::ceil)
+        }),
+    );
+    set(
+        &lib,
+        "abs",
+        host("abs", |_: &mut Engine, args: Vec<Value>| {
+            Ok(vec![match args.first() {
+                Some(&Value::Int(i)) => Value::Int(i.wrapping_abs()),
+                x => Value::Float(number(x, 0)?.abs()),
+            }])
+        }),
+    );
+    set(
+        &lib,
+        "max",
+        host("max", |e: &mut Engine, a: Vec<Value>| extreme(e, a, "max")),
+    );
+    set(
+        &lib,
+        "min",
+        host("min", |e: &mut Engine, a: Vec<Value>| extreme(e, a, "min")),
+    );
+    set(
+        &lib,
+        "fmod",
+        host("fmod", |_: &mut Engine, args: Vec<Value>| {
+            Ok(vec![match (args.first(), args.get(1)) {
+                (_, Some(Value::Int(0))) => return Err("bad argument #2 to `fmod` (zero)".into()),
+                (Some(&Value::Int(a)), Some(&Value::Int(b))) => Value::Int(a.wrapping_rem(b)),
+                (a, b) => Value::Float(number(a, 0)? % number(b, 1)?),
+            }])
+        }),
+    );
+    set(
+        &lib,
+        "tointeger",
+        host("tointeger", |_: &mut Engine, args: Vec<Value>| {
+            Ok(vec![match args.first() {
+                Some(&Value::Int(i)) => Value::Int(i),
+                Some(&Value::Float(f)) => float_to_int(f).map_or(Value::Nil, Value::Int),
+                _ => Value::Nil,
+            }])
+        }),
+    );
+    set(
+        &lib,
+        "type",
+        host("type", |_: &mut Engine, args: Vec<Value>| {
+            Ok(vec![match args.first() {
+                Some(Value::Int(_)) => "integer".into(),
+                Some(Value::Float(_)) => "float".into(),
+                _ => Value::Nil,
+            }])
+        }),
+    );
+    set(&lib, "huge", Value::Float(f64::INFINITY));
+    set(&lib, "pi", Value::Float(std::f64::consts::PI));
+    set(&lib, "maxinteger", Value::Int(i64::MAX));
+    set(&lib, "mininteger", Value::Int(i64::MIN));
+    lib
+}
+
+/// `f(...)`, `true` & the values it returns, or `false` & the message of
+/// the error it raised
+///
+/// Running out of fuel can't be caught.
+fn pcall(engine: &mut Engine, args: Vec<Value>) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let func = args.next().unwrap_or_default();
+    let args: Vec<_> = args.collect();
+    match engine.call(&func, &args) {
+        Ok(values) => Ok(std::iter::once(Value::Bool(true)).chain(values).collect()),
+        Err(err @ (RuntimeError::OutOfFuel | RuntimeError::Unwind)) => Err(err.into()),
+        Err(err) => {
+            let message = match err {
+                RuntimeError::Host(_, _, HostError::Custom(message)) => message,
+                err => err.to_string(),
+            };
+            Ok(vec![Value::Bool(false), message.into()])
+        }
+    }
+}
+
+/// `select(n, ...)`, the args from the `n`th, from the end if negative, or
+/// their number for `'#'`
+fn select(_: &mut Engine, args: Vec<Value>) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let n = args.next().unwrap_or_default();
+    let rest: Vec<_> = args.collect();
+    let count = int(rest.len());
+    let n = match n {
+        Value::Str(s) if &*s == "#" => return Ok(vec![Value::Int(count)]),
+        Value::Int(n) if n < 0 && -n <= count => count + n,
+        Value::Int(n) if n > 0 => n - 1,
+        other => return Err(HostError::type_error("index in range", &other)),
+    };
+    Ok(rest
+        .into_iter()
+        .skip(usize::try_from(n).unwrap_or(usize::MAX))
+        .collect())
+}
+
+/// the name lua gives the type of a value
+const fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Nil => "nil",
+        Value::Bool(_) => "boolean",
+        Value::Int(_) | Value::Float(_) => "number",
+        Value::Str(_) | Value::AStr(_) | Value::BStr(_) => "string",
+        Value::Fn(_) | Value::Host(_) => "function",
+        Value::Table(_) => "table",
+        Value::UserData(_) | Value::Data(_) => "userdata",
+    }
+}
+
+/// a value as a string, through the `__tostring` of a table's metatable
+fn tostring(engine: &mut Engine, value: Value) -> Result<Value> {
+    let handler = match &value {
+        Value::Table(table) => table.meta_field("__tostring"),
+        _ => Value::Nil,
+    };
+    if let Value::Nil = handler {
+        return Ok(value.to_string().into());
+    }
+    let out = engine.call(&handler, &[value])?;
+    match out.into_iter().next().unwrap_or_default() {
+        out @ (Value::Str(_) | Value::AStr(_)) => Ok(out),
+        _ => Err("`__tostring` must return a string".into()),
+    }
+}
+
+/// a number, or the number a string reads as in `base`, `nil` if it doesn't
+fn tonumber(value: Value, base: Option<u32>) -> Result<Value> {
+    let text = match (&value, base) {
+        (Value::Int(_) | Value::Float(_), None) => return Ok(value),
+        (Value::Str(s) | Value::AStr(s), _) => s.trim().to_owned(),
+        (other, _) => return Err(HostError::type_error("string", other)),
+    };
+    let (negative, digits) = text
+        .strip_prefix('-')
+        .map_or((false, text.as_str()), |digits| (true, digits));
+    let number = match base {
+        Some(base) if !(2..=36).contains(&base) => return Err("base out of range".into()),
+        Some(base) => u64::from_str_radix(digits, base)
+            .ok()
+            .map(|n| Value::Int(n.cast_signed())),
+        None => crate::parse::lua::number(digits).and_then(|lit| Value::from_literal(lit).ok()),
+    };
+    Ok(match (number, negative) {
+        (Some(Value::Int(i)), true) => Value::Int(i.wrapping_neg()),
+        (Some(Value::Float(f)), true) => Value::Float(-f),
+        (Some(number), false) => number,
+        _ => Value::Nil,
+    })
+}
+
+/// `table.insert(t, (?pos), value)`, shifting the values from `pos` up
+fn insert(engine: &mut Engine, args: &[Value]) -> Result<Vec<Value>> {
+    let table: Rc<Table> = host_arg(args.first().cloned().unwrap_or_default())?;
+    let end = len(&table) + 1;
+    let (pos, value) = match &args[1..] {
+        [value] => (end, value.clone()),
+        [Value::Int(pos), value] if (1..=end).contains(pos) => (*pos, value.clone()),
+        [_, _] => return Err("bad argument #2 to `insert` (position out of bounds)".into()),
+        _ => return Err("wrong number of arguments to `insert`".into()),
+    };
+    for i in (pos + 1..=end).rev() {
+        let moved = engine.index(Value::Table(table.clone()), Value::Int(i - 1))?;
+        engine.set_index(Value::Table(table.clone()), Value::Int(i), moved)?;
+    }
+    engine.set_index(Value::Table(table), Value::Int(pos), value)?;
+    Ok(Vec::new())
+}
+
+/// `table.remove(t, (?pos))`, the value at `pos`, shifting the values after
+/// it down
+fn remove(engine: &mut Engine, args: &[Value]) -> Result<Vec<Value>> {
+    let table: Rc<Table> = host_arg(args.first().cloned().unwrap_or_default())?;
+    let size = len(&table);
+    let pos = match args.get(1) {
+        None | Some(Value::Nil) => size,
+        Some(&Value::Int(pos)) if size + 1 == pos || (size == 0 && pos == 0) => pos,
+        Some(&Value::Int(pos)) if (1..=size + 1).contains(&pos) => pos,
+        Some(_) => return Err("bad argument #2 to `remove` (position out of bounds)".into()),
+    };
+    let t = || Value::Table(table.clone());
+    let value = engine.index(t(), Value::Int(pos))?;
+    for i in pos..size {
+        let moved = engine.index(t(), Value::Int(i + 1))?;
+        engine.set_index(t(), Value::Int(i), moved)?;
+    }
+    if pos <= size {
+        engine.set_index(t(), Value::Int(size), Value::Nil)?;
+    }
+    Ok(vec![value])
+}
+
+/// `table.concat(t, (?sep), (?i), (?j))`, the strings & numbers of
+/// `t[i..=j]` joined by `sep`
+fn concat(engine: &mut Engine, args: Vec<Value>) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let table: Rc<Table> = host_arg(args.next().unwrap_or_default())?;
+    let sep: Option<Rc<str>> = host_arg(args.next().unwrap_or_default())?;
+    let i: Option<i64> = host_arg(args.next().unwrap_or_default())?;
+    let j: Option<i64> = host_arg(args.next().unwrap_or_default())?;
+    let mut out = String::new();
+    for k in i.unwrap_or(1)..=j.unwrap_or_else(|| len(&table)) {
+        if k > i.unwrap_or(1) {
+            out.push_str(sep.as_deref().unwrap_or(""));
+        }
+        match engine.index(Value::Table(table.clone()), Value::Int(k))? {
+            value @ (Value::Str(_) | Value::AStr(_) | Value::Int(_) | Value::Float(_)) => {
+                out.push_str(&value.to_string());
+            }
+            other => {
+                let ty = type_name(&other);
+                return Err(
+                    format!("invalid value (at index {k}) in table for `concat`, a {ty}").into(),
+                );
+            }
+        }
+    }
+    Ok(vec![out.into()])
+}
+
+/// `table.unpack(t, (?i), (?j))`, the values of `t[i..=j]`
+fn unpack(engine: &mut Engine, args: Vec<Value>) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let table = args.next().unwrap_or_default();
+    let i: Option<i64> = host_arg(args.next().unwrap_or_default())?;
+    let j: Option<i64> = host_arg(args.next().unwrap_or_default())?;
+    let j = match j {
+        Some(j) => j,
+        None => len(&*host_arg::<Rc<Table>>(table.clone())?),
+    };
+    let i = i.unwrap_or(1);
+    if j >= i && j.abs_diff(i) >= 1 << 20 {
+        return Err("too many results to unpack".into());
+    }
+    let mut values = Vec::new();
+    for k in i..=j {
+        values.push(engine.index(table.clone(), Value::Int(k))?);
+    }
+    Ok(values)
+}
+
+/// `table.sort(t, (?lt))`, a stable merge sort of `t[1..=#t]` by `lt`, or
+/// by `<`
+fn sort(engine: &mut Engine, args: Vec<Value>) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let table: Rc<Table> = host_arg(args.next().unwrap_or_default())?;
+    let lt = args.next().unwrap_or_default();
+    let t = || Value::Table(table.clone());
+    let mut values = Vec::new();
+    for i in 1..=len(&table) {
+        values.push(engine.index(t(), Value::Int(i))?);
+    }
+    let mut less = |engine: &mut Engine, a: &Value, b: &Value| -> Result<bool> {
+        let out = match &lt {
+            Value::Nil => {
+                engine.meta_binary(crate::parse::token::BinOp::Lt, a.clone(), b.clone())?
+            }
+            lt => engine
+                .call(lt, &[a.clone(), b.clone()])?
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+        };
+        Ok(out.is_truthy())
+    };
+    let values = merge_sort(engine, values, &mut less)?;
+    for (i, value) in (1..).zip(values) {
+        engine.set_index(t(), Value::Int(i), value)?;
+    }
+    Ok(Vec::new())
+}
+
+type Less<'a> = dyn FnMut(&mut Engine, &Value, &Value) -> Result<bool> + 'a;
+
+fn merge_sort(engine: &mut Engine, mut values: Vec<Value>, less: &mut Less) -> Result<Vec<Value>> {
+    if values.len() < 2 {
+        return Ok(values);
+    }
+    let right = values.split_off(values.len() / 2);
+    let left = merge_sort(engine, values, less)?;
+    let right = merge_sort(engine, right, less)?;
+    let mut out = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // equal values keep their order
+        let next = match less(engine, b, a)? {
+            true => right.next(),
+            false => left.next(),
+        };
+        out.extend(next);
+    }
+    out.extend(left);
+    out.extend(right);
+    Ok(out)
+}
+
+/// the largest or smallest of the args
+fn extreme(engine: &mut Engine, args: Vec<Value>, which: &str) -> Result<Vec<Value>> {
+    let mut args = args.into_iter();
+    let Some(mut best) = args.next() else {
+        return Err(format!("bad argument #1 to `{which}` (number expected)").into());
+    };
+    number(Some(&best), 0)?;
+    for (i, arg) in args.enumerate() {
+        number(Some(&arg), i + 1)?;
+        let (a, b) = match which {
+            "max" => (best.clone(), arg.clone()),
+            _ => (arg.clone(), best.clone()),
+        };
+        if engine
+            .meta_binary(crate::parse::token::BinOp::Lt, a, b)?
+            .is_truthy()
+        {
+            best = arg;
+        }
+    }
+    Ok(vec![best])
+}
+
+/// `floor` or `ceil`, an int if it fits
+fn round(x: Option<&Value>, f: fn(f64) -> f64) -> Result<Vec<Value>> {
+    Ok(vec![match x {
+        Some(&Value::Int(i)) => Value::Int(i),
+        x => {
+            let x = f(number(x, 0)?);
+            float_to_int(x).map_or(Value::Float(x), Value::Int)
+        }
+    }])
+}
+
+/// the int equal to a float, if any
+#[allow(clippy::cast_possible_truncation)]
+fn float_to_int(f: f64) -> Option<i64> {
+    // -2^63..2^63
+    let range = float(i64::MIN)..-float(i64::MIN);
+    (f.fract() == 0.0 && range.contains(&f)).then_some(f as i64)
+}
+
+/// the arg at `index` as a float
+fn number(value: Option<&Value>, index: usize) -> Result<f64> {
+    match value {
+        Some(&Value::Int(i)) => Ok(float(i)),
+        Some(&Value::Float(f)) => Ok(f),
+        other => {
+            let other = other.cloned().unwrap_or_default();
+            Err(HostError::type_error("number", &other).at_arg(index))
+        }
+    }
+}
+
+/// the byte range of `s[i..=j]`, counting from 1, negative from the end
+fn byte_range(len: usize, i: i64, j: i64) -> (usize, usize) {
+    let len = int(len);
+    let pos = |i: i64| match i {
+        i if i < 0 => (len + i + 1).max(0),
+        i => i.min(len),
+    };
+    let (from, to) = (pos(i).max(1), pos(j));
+    let from = usize::try_from(from - 1).unwrap_or(0);
+    let to = usize::try_from(to).unwrap_or(0);
+    (from, to.max(from))
+}
+
+fn host_arg<T: crate::runtime::FromValue>(value: Value) -> Result<T> {
+    T::from_value(value)
+}
+
+fn set(table: &Table, name: &str, value: impl Into<Value>) {
+    // a string key is never `nil` or NaN
+    let _ = table.set(name.into(), value.into());
+}
+
+/// a host fn value, taking its args as they are
+fn host(
+    name: &str,
+    func: impl Fn(&mut Engine, Vec<Value>) -> Result<Vec<Value>> + 'static,
+) -> Value {
+    Value::Host(Rc::new(HostFunction {
+        name: name.into(),
+        func: Box::new(func),
+    }))
+}
+
+#[allow(clippy::cast_possible_wrap)]
+const fn int(len: usize) -> i64 {
+    len as i64
+}
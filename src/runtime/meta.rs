@@ -0,0 +1,227 @@
+//! lua tables & the metamethods of their metatables
+//!
+//! Indexing goes through `__index` & `__newindex`, calls through `__call`
+//! & the operators through the metamethod lua gives each, of the left
+//! operand's metatable first. `==` only calls `__eq` for two different
+//! tables.
+
+use std::rc::Rc;
+
+use crate::error::RuntimeError;
+use crate::parse::token::*;
+
+use crate::span::BSpan;
+use crate::util::Symbol;
+
+use super::{binary, get_field, unary, Engine, Frame, Result, Table, Value, MAX_META};
+
+impl Engine {
+    /// `target[key]`, through the `__index` of its metatable while it has
+    /// no such key
+    pub(super) fn index(&mut self, target: Value, key: Value) -> Result<Value> {
+        let mut target = target;
+        for _ in 0..MAX_META {
+            let Value::Table(table) = &target else {
+                return Err(RuntimeError::Index(target.type_name()));
+            };
+            let value = table.get(&key);
+            if !matches!(value, Value::Nil) {
+                return Ok(value);
+            }
+            match table.meta_field("__index") {
+                Value::Nil => return Ok(Value::Nil),
+                handler @ (Value::Fn(_) | Value::Host(_)) => {
+                    return first(self.call(&handler, &[target, key]));
+                }
+                handler => target = handler,
+            }
+        }
+        Err(RuntimeError::MetaLoop("__index"))
+    }
+
+    /// `target.name`, a field of a table or of a data value
+    pub(super) fn field(
+        &mut self,
+        frame: &mut Frame,
+        pos: usize,
+        name: Symbol,
+        span: BSpan,
+    ) -> Result<Value> {
+        match self.eval(frame, pos + 1)? {
+            target @ Value::Table(_) => self.index(target, name.as_str().into()),
+            target => get_field(&target, name).map_err(|err| err.at(span)),
+        }
+    }
+
+    /// `target[key]`, the target & key are the operands of the expr at `pos`
+    pub(super) fn index_expr(&mut self, frame: &mut Frame, pos: usize) -> Result<Value> {
+        let target = self.eval(frame, pos + 1)?;
+        let key = self.eval(frame, frame.chunk.module.end(pos + 1))?;
+        self.index(target, key)
+    }
+
+    /// `target[key] = value`, through the `__newindex` of its metatable if
+    /// it has no such key
+    pub(super) fn set_index(&mut self, target: Value, key: Value, value: Value) -> Result<()> {
+        let mut target = target;
+        for _ in 0..MAX_META {
+            let Value::Table(table) = &target else {
+                return Err(RuntimeError::Index(target.type_name()));
+            };
+            let handler = match table.get(&key) {
+                Value::Nil => table.meta_field("__newindex"),
+                _ => Value::Nil,
+            };
+            match handler {
+                Value::Nil => return table.set(key, value),
+                Value::Fn(_) | Value::Host(_) => {
+                    self.call(&handler, &[target, key, value])?;
+                    return Ok(());
+                }
+                handler => target = handler,
+            }
+        }
+        Err(RuntimeError::MetaLoop("__newindex"))
+    }
+
+    /// call a table through the `__call` of its metatable, with the table
+    /// as the first arg
+    pub(super) fn call_table(&mut self, table: &Rc<Table>, args: &[Value]) -> Result<Vec<Value>> {
+        let handler = table.meta_field("__call");
+        if let Value::Nil = handler {
+            return Err(RuntimeError::NotCallable("table"));
+        }
+        let args: Vec<_> = std::iter::once(Value::Table(table.clone()))
+            .chain(args.iter().cloned())
+            .collect();
+        self.call(&handler, &args)
+    }
+
+    /// a table constructor, the last plain expr gives all of its values
+    pub(super) fn table(&mut self, frame: &mut Frame, pos: usize, end: usize) -> Result<Value> {
+        let chunk = frame.chunk.clone();
+        let module = &chunk.module;
+        let table = Table::default();
+        let mut index = 0;
+        let mut at = pos + 1;
+        while at < end {
+            let next = module.end(at);
+            match module.items()[at] {
+                Token::Expr(Expr {
+                    kind: ExprKind::FieldInit(name),
+                    ..
+                }) => table.set(name.as_str().into(), self.eval(frame, at + 1)?)?,
+                Token::Expr(Expr {
+                    kind: ExprKind::Entry,
+                    ..
+                }) => {
+                    let key = self.eval(frame, at + 1)?;
+                    table.set(key, self.eval(frame, module.end(at + 1))?)?;
+                }
+                _ => {
+                    let values = match next < end {
+                        true => vec![self.eval(frame, at)?],
+                        false => self.eval_multi(frame, at)?,
+                    };
+                    for value in values {
+                        index += 1;
+                        table.set(Value::Int(index), value)?;
+                    }
+                }
+            }
+            at = next;
+        }
+        Ok(table.into())
+    }
+
+    /// A binary operator with a table operand, through the metamethod of
+    /// either operand, or the operator itself if neither has one
+    pub(super) fn meta_binary(&mut self, op: BinOp, lhs: Value, rhs: Value) -> Result<Value> {
+        use BinOp::*;
+        // `~=`, `>` & `>=` negate or swap `==`, `<` & `<=`
+        let (name, swap) = match op {
+            Eq | Ne => ("__eq", false),
+            Gt => ("__lt", true),
+            Ge => ("__le", true),
+            op => match metamethod(op) {
+                Some(name) => (name, false),
+                None => return binary(op, &lhs, &rhs),
+            },
+        };
+        let tables = matches!((&lhs, &rhs), (Value::Table(_), Value::Table(_)));
+        if matches!(op, Eq | Ne) && (!tables || lhs == rhs) {
+            return binary(op, &lhs, &rhs);
+        }
+        let handler = [&lhs, &rhs].into_iter().find_map(|value| match value {
+            Value::Table(table) => {
+                Some(table.meta_field(name)).filter(|h| !matches!(h, Value::Nil))
+            }
+            _ => None,
+        });
+        let Some(handler) = handler else {
+            return binary(op, &lhs, &rhs);
+        };
+        let args = match swap {
+            true => [rhs, lhs],
+            false => [lhs, rhs],
+        };
+        let value = first(self.call(&handler, &args))?;
+        Ok(match op {
+            Eq | Lt | Le | Gt | Ge => Value::Bool(value.is_truthy()),
+            Ne => Value::Bool(!value.is_truthy()),
+            _ => value,
+        })
+    }
+
+    /// A unary operator of a table, through its metamethod, `#` is the
+    /// length of the table without one
+    pub(super) fn meta_unary(&mut self, op: UnOp, value: Value) -> Result<Value> {
+        let Value::Table(table) = &value else {
+            return unary(op, &value);
+        };
+        let name = match op {
+            UnOp::Neg => "__unm",
+            UnOp::BitNot => "__bnot",
+            UnOp::Len => "__len",
+            UnOp::Not => return unary(op, &value),
+        };
+        match table.meta_field(name) {
+            Value::Nil if op == UnOp::Len => Ok(Value::Int(len(table))),
+            Value::Nil => unary(op, &value),
+            // like lua, the operand is passed twice
+            handler => first(self.call(&handler, &[value.clone(), value])),
+        }
+    }
+}
+
+/// the name of the metamethod of a binary operator
+pub const fn metamethod(op: BinOp) -> Option<&'static str> {
+    Some(match op {
+        BinOp::Add => "__add",
+        BinOp::Sub => "__sub",
+        BinOp::Mul => "__mul",
+        BinOp::FDiv => "__div",
+        BinOp::Mod => "__mod",
+        BinOp::Pow => "__pow",
+        BinOp::IDiv => "__idiv",
+        BinOp::BitAnd => "__band",
+        BinOp::BitOr => "__bor",
+        BinOp::BitXor => "__bxor",
+        BinOp::Shl => "__shl",
+        BinOp::Shr => "__shr",
+        BinOp::Concat => "__concat",
+        BinOp::Lt => "__lt",
+        BinOp::Le => "__le",
+        _ => return None,
+    })
+}
+
+/// the length of a table as a lua int
+pub fn len(table: &Table) -> i64 {
+    i64::try_from(table.len()).unwrap_or(i64::MAX)
+}
+
+/// the first of the values of a call, `nil` if there are none
+fn first(values: Result<Vec<Value>>) -> Result<Value> {
+    Ok(values?.into_iter().next().unwrap_or_default())
+}
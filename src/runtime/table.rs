@@ -0,0 +1,295 @@
+//! lua tables
+//!
+//! The keys `1..=n` with values live in an array part, any other key in a
+//! hash part kept in insertion order, so that [`Table::next`] can walk it.
+//! A key set to `nil` stays in the hash part as a hole until a new key is
+//! added, which lets a traversal clear the fields it has seen.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::error::RuntimeError;
+use crate::runtime::{float, Value};
+
+/// A lua table, with its metatable
+#[derive(Debug, Default)]
+pub struct Table {
+    /// the values of the keys `1..=n`, never ending in `nil`
+    array: RefCell<Vec<Value>>,
+    hash: RefCell<HashPart>,
+    meta: RefCell<Option<Rc<Self>>>,
+}
+
+#[derive(Debug, Default)]
+struct HashPart {
+    /// the index of each key in `entries`
+    slots: HashMap<Key, usize>,
+    entries: Vec<(Key, Value)>,
+    /// the entries set to `nil`
+    holes: usize,
+}
+
+/// A key of the hash part, a float with an int value is stored as the int
+///
+/// Numbers, bools & strings are compared by value, any other value by
+/// identity.
+#[derive(Debug, Clone)]
+struct Key(Value);
+
+impl Table {
+    /// A table with `values` at the keys `1..`
+    #[must_use]
+    pub fn from_list(values: Vec<Value>) -> Self {
+        let table = Self::default();
+        for (i, value) in values.into_iter().enumerate() {
+            // nils leave holes, like a table constructor
+            let _ = table.set(index(i + 1), value);
+        }
+        table
+    }
+
+    /// The value at `key`, without metamethods
+    #[must_use]
+    pub fn get(&self, key: &Value) -> Value {
+        let key = normalize(key.clone());
+        if let Some(i) = self.array_index(&key) {
+            return self.array.borrow()[i].clone();
+        }
+        let hash = self.hash.borrow();
+        hash.slots
+            .get(&Key(key))
+            .map(|&slot| hash.entries[slot].1.clone())
+            .unwrap_or_default()
+    }
+
+    /// The value of the string key `name`, without metamethods
+    #[must_use]
+    pub fn field(&self, name: &str) -> Value {
+        self.get(&Value::from(name))
+    }
+
+    /// Set the value at `key`, without metamethods
+    ///
+    /// # Errors
+    ///
+    /// A [`RuntimeError::TableKey`] if `key` is `nil` or NaN
+    pub fn set(&self, key: Value, value: Value) -> Result<(), RuntimeError> {
+        let key = match normalize(key) {
+            Value::Nil => return Err(RuntimeError::TableKey("nil")),
+            Value::Float(f) if f.is_nan() => return Err(RuntimeError::TableKey("NaN")),
+            key => key,
+        };
+        if let Some(i) = self.array_index(&key) {
+            let mut array = self.array.borrow_mut();
+            array[i] = value;
+            while array
+                .last()
+                .is_some_and(|value| matches!(value, Value::Nil))
+            {
+                array.pop();
+            }
+            return Ok(());
+        }
+        let next = self.len() + 1;
+        if matches!(key, Value::Int(i) if usize::try_from(i) == Ok(next)) && !is_nil(&value) {
+            self.remove(&key);
+            self.push(value);
+            return Ok(());
+        }
+        self.hash.borrow_mut().set(Key(key), value);
+        Ok(())
+    }
+
+    /// The length of the array part, a border of the table like lua's `#`
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.array.borrow().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0 && self.hash.borrow().entries.len() == self.hash.borrow().holes
+    }
+
+    /// The key & value after `key` in a traversal, which starts at `nil`
+    ///
+    /// `Ok(None)` at the end.
+    ///
+    /// # Errors
+    ///
+    /// If `key` is not in the table
+    #[allow(clippy::result_unit_err)]
+    pub fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, ()> {
+        let key = normalize(key.clone());
+        let len = self.len();
+        let from = match &key {
+            Value::Nil => 0,
+            _ if self.array_index(&key).is_some() => match key {
+                Value::Int(i) => usize::try_from(i).unwrap_or(0),
+                _ => 0,
+            },
+            _ => {
+                let hash = self.hash.borrow();
+                match hash.slots.get(&Key(key.clone())) {
+                    Some(&slot) => len + slot + 1,
+                    // an array key cleared during the traversal, with every
+                    // key after it
+                    None if matches!(key, Value::Int(i) if i > 0) => len,
+                    None => return Err(()),
+                }
+            }
+        };
+        let array = self.array.borrow();
+        for i in from..len {
+            if !is_nil(&array[i]) {
+                return Ok(Some((index(i + 1), array[i].clone())));
+            }
+        }
+        let hash = self.hash.borrow();
+        let entries = hash.entries.iter().skip(from.saturating_sub(len));
+        Ok(entries
+            .filter(|(_, value)| !is_nil(value))
+            .map(|(Key(key), value)| (key.clone(), value.clone()))
+            .next())
+    }
+
+    /// Append `value` at `#t + 1`
+    pub fn push(&self, value: Value) {
+        let mut array = self.array.borrow_mut();
+        array.push(value);
+        // the keys that now follow the array part move into it
+        let mut hash = self.hash.borrow_mut();
+        while let Some(value) = hash.take(&index(array.len() + 1)) {
+            array.push(value);
+        }
+    }
+
+    /// The metatable
+    #[must_use]
+    pub fn meta(&self) -> Option<Rc<Self>> {
+        self.meta.borrow().clone()
+    }
+
+    pub fn set_meta(&self, meta: Option<Rc<Self>>) {
+        *self.meta.borrow_mut() = meta;
+    }
+
+    /// The field `name` of the metatable, `nil` without one
+    #[must_use]
+    pub fn meta_field(&self, name: &str) -> Value {
+        self.meta().map_or(Value::Nil, |meta| meta.field(name))
+    }
+
+    /// the index into the array part of an int key
+    fn array_index(&self, key: &Value) -> Option<usize> {
+        let Value::Int(i) = *key else {
+            return None;
+        };
+        let i = usize::try_from(i).ok()?.checked_sub(1)?;
+        (i < self.len()).then_some(i)
+    }
+
+    fn remove(&self, key: &Value) {
+        self.hash.borrow_mut().take(key);
+    }
+}
+
+impl HashPart {
+    fn set(&mut self, key: Key, value: Value) {
+        if let Some(&slot) = self.slots.get(&key) {
+            let entry = &mut self.entries[slot].1;
+            match (is_nil(entry), is_nil(&value)) {
+                (false, true) => self.holes += 1,
+                (true, false) => self.holes -= 1,
+                _ => (),
+            }
+            *entry = value;
+            return;
+        }
+        if is_nil(&value) {
+            return;
+        }
+        if self.holes > self.entries.len() / 2 {
+            self.compact();
+        }
+        self.slots.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+    }
+
+    /// clear the value at `key`, returning it if it wasn't `nil`
+    fn take(&mut self, key: &Value) -> Option<Value> {
+        let &slot = self.slots.get(&Key(key.clone()))?;
+        let value = std::mem::take(&mut self.entries[slot].1);
+        if is_nil(&value) {
+            return None;
+        }
+        self.holes += 1;
+        Some(value)
+    }
+
+    /// drop the holes
+    fn compact(&mut self) {
+        self.entries.retain(|(_, value)| !is_nil(value));
+        self.slots = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(slot, (key, _))| (key.clone(), slot))
+            .collect();
+        self.holes = 0;
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Value::Float(a), Value::Float(b)) => a.to_bits() == b.to_bits(),
+            (Value::Data(a), Value::Data(b)) => Rc::ptr_eq(a, b),
+            (a, b) => a == b,
+        }
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(&self.0).hash(state);
+        match &self.0 {
+            Value::Nil => (),
+            Value::Bool(b) => b.hash(state),
+            Value::Int(i) => i.hash(state),
+            Value::Float(f) => f.to_bits().hash(state),
+            Value::Str(s) | Value::AStr(s) => s.hash(state),
+            Value::BStr(b) => b.hash(state),
+            Value::Fn(func) => Rc::as_ptr(func).hash(state),
+            Value::Host(func) => Rc::as_ptr(func).hash(state),
+            Value::UserData(data) => Rc::as_ptr(data).hash(state),
+            Value::Data(data) => Rc::as_ptr(data).hash(state),
+            Value::Table(table) => Rc::as_ptr(table).hash(state),
+        }
+    }
+}
+
+/// a float key with an int value as the int, like lua
+#[allow(clippy::cast_possible_truncation)]
+fn normalize(key: Value) -> Value {
+    // -2^63..2^63
+    let range = float(i64::MIN)..-float(i64::MIN);
+    match key {
+        Value::Float(f) if f.fract() == 0.0 && range.contains(&f) => Value::Int(f as i64),
+        key => key,
+    }
+}
+
+const fn is_nil(value: &Value) -> bool {
+    matches!(value, Value::Nil)
+}
+
+/// the key of a 1 based index
+#[allow(clippy::cast_possible_wrap)]
+const fn index(i: usize) -> Value {
+    Value::Int(i as i64)
+}
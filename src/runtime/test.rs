@@ -0,0 +1,732 @@
+use std::rc::Rc;
+
+use crate::error::{ErrorMulti, HostError, RuntimeError};
+use crate::parse::token::{BinOp, UnOp};
+use crate::parse::Reader;
+use crate::runtime::*;
+use crate::span::BSpan;
+
+fn run(src: &str) -> Result<Vec<Value>> {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    Engine::new().run(module)
+}
+
+macro_rules! assert_run {
+    ($src:expr $(, $expected:expr)* $(,)?) => {
+        assert_eq!(run($src), Ok(vec![$(Value::from($expected)),*]), "{}", $src);
+    };
+}
+
+#[test]
+fn values() {
+    assert_run!("");
+    assert_run!("return 1", 1);
+    assert_run!("return 0x1_f", 31);
+    assert_run!("return 1.5", 1.5);
+    assert_run!(r#"return "a\tb""#, "a\tb");
+    assert_run!(r##"return r#"a\b"#"##, "a\\b");
+    assert_run!("return 'c'", "c");
+    assert_run!("return true", true);
+}
+
+#[test]
+fn operators() {
+    assert_run!("return 1 + 2 * 3", 7);
+    assert_run!("return 7 / 2", 3);
+    assert_run!("return 7 % 2 + 1.5", 2.5);
+    assert_run!("return -1 << 2", -4);
+    assert_run!("return 1 < 2 && 2 <= 2", true);
+    assert_run!("return !1 || 3", 3);
+    assert_run!(r#"return "a" + "b" == "ab""#, true);
+    assert_eq!(run("return 1 / 0"), Err(RuntimeError::DivZero));
+    assert_eq!(
+        run(r#"return 1 + "a""#),
+        Err(RuntimeError::BinOp(BinOp::Add, "int", "ustr"))
+    );
+}
+
+#[test]
+fn variables() {
+    assert_run!("let x = 1; x += 2; return x", 3);
+    assert_run!("let a = 1; let b = 2; a, b = b, a; return a - b", 1);
+    assert_run!("let x = 1; { let x = 2 } return x", 1);
+    assert_run!("let x = 1; { x = 2 } return x", 2);
+    assert_eq!(run("return y"), Err(RuntimeError::Undefined("y".into())));
+}
+
+#[test]
+fn functions() {
+    assert_run!("fn add(int a, int b) { return a + b } return add(1, 2)", 3);
+    assert_run!("fn f(a, b = 2) { return a * b } return f(3)", 6);
+    assert_run!(
+        "fn fib(n) { if n < 2 { return n } return fib(n - 1) + fib(n - 2) } return fib(10)",
+        55
+    );
+    assert_run!("fn f() { } return f()");
+    assert_eq!(run("return 1()"), Err(RuntimeError::NotCallable("int")));
+    assert_eq!(
+        run("fn f() { return f() } return f()"),
+        Err(RuntimeError::StackOverflow)
+    );
+}
+
+#[test]
+fn closures() {
+    assert_run!("let f = |x| x + 1; return f(1)", 2);
+    assert_run!("fn f(x) { x * 2 } let g = || { f(2) } return g()", 4);
+    assert_run!("let f = fn(x) { return x * 2 } return f(4)", 8);
+    assert_run!("return (|x, y| x - y)(5, 3)", 2);
+    assert_run!("return fn() { return 9 }()", 9);
+    assert_run!(
+        "fn apply(f, x) { return f(x) } return apply(|x| x * x, 7)",
+        49
+    );
+}
+
+#[test]
+fn upvalues() {
+    // a counter shares its variable between calls
+    let src = "
+        fn counter() {
+            let n = 0
+            return || { n += 1; return n }
+        }
+        let c = counter()
+        c()
+        c()
+        return c()
+    ";
+    assert_run!(src, 3);
+
+    // two closures share the same variable
+    let src = "
+        fn pair() {
+            let n = 0
+            let inc = || { n += 1 }
+            let get = || n
+            inc()
+            inc()
+            return get()
+        }
+        return pair()
+    ";
+    assert_run!(src, 2);
+
+    // captured through a closure in between
+    assert_run!("fn f(x) { return || || x } return f(5)()()", 5);
+    // a local fn may call itself
+    assert_run!(
+        "fn f() { fn g(n) { if n > 0 { return g(n - 1) } return 7 } return g(3) } return f()",
+        7
+    );
+}
+
+#[test]
+fn engine_api() {
+    let (module, _) = Reader::new("fn double(x) { return x * 2 } let y = 4").module("test");
+    let mut engine = Engine::new();
+    engine.run(module).unwrap();
+    assert_eq!(engine.global("y"), Some(&Value::Int(4)));
+    let double = engine.global("double").unwrap().clone();
+    assert_eq!(
+        engine.call(&double, &[Value::Int(21)]),
+        Ok(vec![Value::Int(42)])
+    );
+    engine.set_global("z", 1);
+    assert_eq!(engine.global("z"), Some(&Value::Int(1)));
+}
+
+#[test]
+fn multiple_returns() {
+    assert_run!("return 1, 2", 1, 2);
+    assert_run!("fn f() { return 1, 2 } return f()", 1, 2);
+    assert_run!("fn f() { return } return f()");
+    // only the last expr of a list is expanded
+    assert_run!("fn f() { return 1, 2 } return f(), f()", 1, 1, 2);
+    assert_run!("fn f() { return 1, 2 } return (f())", 1);
+    assert_run!("fn f() { return 1, 2 } let x = f(); return x", 1);
+    assert_run!("fn f() { return 1, 2 } return f() + 10", 11);
+    assert_run!("fn f() { } let x = 1; x = f(); return x", Value::Nil);
+}
+
+#[test]
+fn multiple_assign() {
+    let src = "fn f() { return 1, 2 } let a = 0; let b = 0; a, b = f(); return a, b";
+    assert_run!(src, 1, 2);
+    let src = "fn f() { return 1, 2 } let a = 0; let b = 0; a, b = f(), 5; return a, b";
+    assert_run!(src, 1, 5);
+    let src = "let a = 0; let b = 0; let c = 0; a, b, c = 1, 2; return a, b, c";
+    assert_run!(src, 1, 2, Value::Nil);
+    assert_run!("let a = 0; a = 1, 2; return a", 1);
+}
+
+#[test]
+fn varargs() {
+    assert_run!("fn f(...) { return ... } return f(1, 2, 3)", 1, 2, 3);
+    assert_run!("fn f(a, ...) { return ... } return f(1, 2, 3)", 2, 3);
+    assert_run!("fn f(a, ...) { return a } return f(1, 2, 3)", 1);
+    assert_run!("fn f(...) { return ... } return f()");
+    assert_run!("fn f(...) { let x = ...; return x } return f(4, 5)", 4);
+    assert_run!("fn f(...) { return (...) } return f(4, 5)", 4);
+    // forwarded to another call
+    let src =
+        "fn sum(a, b, c) { return a + b + c } fn f(...) { return sum(...) } return f(1, 2, 3)";
+    assert_run!(src, 6);
+    assert_run!("fn f(...) { return ..., 9 } return f(1, 2)", 1, 9);
+    assert_run!(
+        "let f = |x, ...| fn(...) { return ... }(...); return f(1, 2, 3)",
+        2,
+        3
+    );
+    assert_run!("let f = |...| ...; return f(7, 8)", 7, 8);
+}
+
+#[test]
+fn main_varargs() {
+    let (module, errors) = Reader::new("return ...").module("test");
+    assert_eq!(errors, ErrorMulti::default());
+    let out = Engine::new().run_with_args(module, vec![Value::Int(1), "a".into()]);
+    assert_eq!(out, Ok(vec![Value::Int(1), "a".into()]));
+}
+
+fn run_with(engine: &mut Engine, src: &str) -> Result<Vec<Value>> {
+    let (module, errors) = Reader::new(src).module("test");
+    assert_eq!(errors, ErrorMulti::default(), "{src}");
+    engine.run(module)
+}
+
+#[test]
+fn host_fns() {
+    let mut engine = Engine::new();
+    engine.register_fn("add", |a: i64, b: i64| a + b);
+    engine.register_fn("greet", |name: String| format!("hi {name}"));
+    engine.register_fn("swap", |a: Value, b: Value| (b, a));
+    engine.register_fn("opt", |a: Option<i64>| a.unwrap_or(-1));
+    engine.register_fn("nothing", || ());
+    let out = run_with(
+        &mut engine,
+        r#"let a = 0; let b = 0; a, b = swap(1, 2); return add(a, b), greet("bob"), opt(), opt(3), nothing()"#,
+    );
+    let expected = vec![3.into(), "hi bob".into(), (-1).into(), 3.into()];
+    assert_eq!(out, Ok(expected));
+    assert_eq!(
+        engine.call_global("add", &[1.into(), 2.into()]),
+        Ok(vec![3.into()])
+    );
+}
+
+#[test]
+fn host_errors() {
+    let mut engine = Engine::new();
+    engine.register_fn("add", |a: i64, b: i64| a + b);
+    engine.register_fn("fail", |msg: String| -> std::result::Result<(), String> {
+        Err(msg)
+    });
+    let err = |span, err| RuntimeError::Host(Some(span), "add".into(), err);
+    assert_eq!(
+        run_with(&mut engine, "return add(1, 2, 3)"),
+        Err(err(
+            BSpan::new(7, 19),
+            HostError::Arity {
+                expected: 2,
+                found: 3
+            }
+        ))
+    );
+    assert_eq!(
+        run_with(&mut engine, "return add(1)"),
+        Err(err(
+            BSpan::new(7, 13),
+            HostError::Arity {
+                expected: 2,
+                found: 1
+            }
+        ))
+    );
+    let type_err = HostError::Type {
+        index: 1,
+        expected: "int",
+        found: "ustr",
+    };
+    assert_eq!(
+        run_with(&mut engine, r#"let x = 1 + add(1, "a")"#),
+        Err(err(BSpan::new(12, 23), type_err))
+    );
+    assert_eq!(
+        run_with(&mut engine, r#"fail("oops")"#),
+        Err(RuntimeError::Host(
+            Some(BSpan::new(0, 12)),
+            "fail".into(),
+            HostError::Custom("oops".into())
+        ))
+    );
+    assert_eq!(
+        engine.call_global("add", &[]),
+        Err(RuntimeError::Host(
+            None,
+            "add".into(),
+            HostError::Arity {
+                expected: 2,
+                found: 0
+            }
+        ))
+    );
+}
+
+#[test]
+fn host_callbacks() {
+    let mut engine = Engine::new();
+    engine.register_raw_fn("apply", |engine, mut args| {
+        let func = args.remove(0);
+        Ok(engine.call(&func, &args)?)
+    });
+    assert_run_with(&mut engine, "return apply(|x, y| x * y, 6, 7)", 42);
+    assert_run_with(&mut engine, "return apply(apply, |x| x + 1, 1)", 2);
+    // script errors pass through unchanged
+    assert_eq!(
+        run_with(&mut engine, "return apply(|| 1 / 0)"),
+        Err(RuntimeError::DivZero)
+    );
+    assert_eq!(
+        run_with(&mut engine, "return apply(1)"),
+        Err(RuntimeError::NotCallable("int"))
+    );
+}
+
+fn assert_run_with(engine: &mut Engine, src: &str, expected: impl Into<Value>) {
+    assert_eq!(run_with(engine, src), Ok(vec![expected.into()]), "{src}");
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vec2 {
+    x: f64,
+    y: f64,
+}
+
+fn vec2_type() -> TypeBuilder<Vec2> {
+    TypeBuilder::<Vec2>::new("Vec2")
+        .field("x", |v| v.x, |v, x| v.x = x)
+        .getter("len", |v| v.x.hypot(v.y))
+        .method("scale", |v: &mut Vec2, by: f64| {
+            v.x *= by;
+            v.y *= by;
+        })
+        .method("parts", |v: &mut Vec2| (v.x, v.y))
+        .operator(BinOp::Add, |engine, lhs, rhs| {
+            let (l, r) = (
+                UserRef::<Vec2>::from_value(lhs)?,
+                UserRef::<Vec2>::from_value(rhs).map_err(|err| err.at_arg(1))?,
+            );
+            let sum = {
+                let (l, r) = (l.borrow()?, r.borrow()?);
+                Vec2 {
+                    x: l.x + r.x,
+                    y: l.y + r.y,
+                }
+            };
+            Ok(engine.userdata(sum))
+        })
+        .unary_operator(UnOp::Neg, |engine, value| {
+            let v = *UserRef::<Vec2>::from_value(value)?.borrow()?;
+            Ok(engine.userdata(Vec2 { x: -v.x, y: -v.y }))
+        })
+}
+
+fn vec2_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.register_type(vec2_type());
+    engine.register_raw_fn("vec2", |engine, args| {
+        let mut args = args.into_iter().map(f64::from_value);
+        let x = args.next().unwrap_or(Ok(0.0))?;
+        let y = args.next().unwrap_or(Ok(0.0))?;
+        Ok(vec![engine.userdata(Vec2 { x, y })])
+    });
+    engine
+}
+
+#[test]
+fn userdata() {
+    let mut engine = vec2_engine();
+    assert_run_with(&mut engine, "return vec2(3.0, 4.0).len", 5.0);
+    assert_run_with(
+        &mut engine,
+        "let v = vec2(1.0, 2.0); v.x = 5.0; return v.x",
+        5.0,
+    );
+    assert_run_with(
+        &mut engine,
+        "let v = vec2(1.0, 2.0); v.x += 1.0; return v.x",
+        2.0,
+    );
+    assert_run_with(
+        &mut engine,
+        "let v = vec2(3.0, 4.0); v.scale(2.0); return v.len",
+        10.0,
+    );
+    let out = run_with(&mut engine, "let v = vec2(1.0, 2.0); return v.parts()");
+    assert_eq!(out, Ok(vec![1.0.into(), 2.0.into()]));
+    let out = run_with(
+        &mut engine,
+        "let v = vec2(1.0, 2.0) + -vec2(0.5, 0.5); return v.parts()",
+    );
+    assert_eq!(out, Ok(vec![0.5.into(), 1.5.into()]));
+    let out = run_with(&mut engine, "let v = vec2(); return v == v, v == vec2()");
+    assert_eq!(out, Ok(vec![true.into(), false.into()]));
+
+    // downcasting back on the rust side
+    let v = run_with(&mut engine, "return vec2(1.0, 2.0)")
+        .unwrap()
+        .remove(0);
+    let Value::UserData(data) = &v else {
+        panic!("{v:?}")
+    };
+    assert_eq!(*data.borrow::<Vec2>().unwrap(), Vec2 { x: 1.0, y: 2.0 });
+    assert!(data.borrow::<String>().is_err());
+    assert_eq!(v.type_name(), "Vec2");
+    engine.register_fn("is_vec2", |v: Option<UserRef<Vec2>>| v.is_some());
+    let out = run_with(&mut engine, "return is_vec2(vec2()), is_vec2(nil)");
+    assert_eq!(out, Ok(vec![true.into(), false.into()]));
+}
+
+#[test]
+fn userdata_errors() {
+    let mut engine = vec2_engine();
+    assert_eq!(
+        run_with(&mut engine, "return vec2().z"),
+        Err(RuntimeError::NoField("Vec2", "z".into()))
+    );
+    assert_eq!(
+        run_with(&mut engine, "let v = vec2(); v.len = 1.0"),
+        Err(RuntimeError::NoField("Vec2", "len".into()))
+    );
+    assert_eq!(
+        run_with(&mut engine, "return vec2().nope()"),
+        Err(RuntimeError::NoField("Vec2", "nope".into()))
+    );
+    assert_eq!(
+        run_with(&mut engine, "return 1 .x"),
+        Err(RuntimeError::Index("int"))
+    );
+    assert_eq!(
+        run_with(&mut engine, r#"vec2().scale("a")"#),
+        Err(RuntimeError::Host(
+            Some(BSpan::new(0, 17)),
+            "Vec2.scale".into(),
+            HostError::Type {
+                index: 0,
+                expected: "float",
+                found: "ustr"
+            }
+        ))
+    );
+    assert_eq!(
+        run_with(&mut engine, "return vec2() + 1"),
+        Err(RuntimeError::Host(
+            Some(BSpan::new(7, 17)),
+            "Vec2.+".into(),
+            HostError::Type {
+                index: 1,
+                expected: std::any::type_name::<Vec2>(),
+                found: "int"
+            }
+        ))
+    );
+    assert_eq!(
+        run_with(&mut engine, "return vec2() - vec2()"),
+        Err(RuntimeError::BinOp(BinOp::Sub, "Vec2", "Vec2"))
+    );
+}
+
+#[test]
+fn data() {
+    assert_run!(
+        "struct P { int x, int y } let p = P { y = 2, x = 1 } p.x += 10; return p.x, p.y",
+        11,
+        2
+    );
+    assert_run!(
+        "enum E { A, B(int, ustr), C { x } } return E.A == E.A, E.B(1, \"a\") == E.B(1, \"a\"), E.C { x = 1 } == E.C { x = 2 }",
+        true,
+        true,
+        false
+    );
+    assert_run!(
+        "fn f() { return P { x = 1 } } fn g() { return E.B(2) } struct P { x } enum E { B(int) } return f().x, g().0",
+        1,
+        2
+    );
+    let values = run(
+        "struct P { x, y } enum E { A, B(int, float), C { y } } return P { y = 2, x = 1 }, E.A, E.B(1, 2.0), E.C { y = nil }",
+    )
+    .unwrap();
+    assert_eq!(
+        values.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        ["P { x: 1, y: 2 }", "E.A", "E.B(1, 2.0)", "E.C { y: nil }"]
+    );
+    assert_eq!(
+        run("struct P { x } let p = P { x = 1 } return p.y"),
+        Err(RuntimeError::NoField("P", "y".into()))
+    );
+    assert_run!("enum E { A(int) } return E.A(7).0", 7);
+    assert_eq!(
+        run("enum E { A(int) } return E.A(1).1"),
+        Err(RuntimeError::NoField("E", "1".into()))
+    );
+}
+
+#[test]
+fn generics() {
+    assert_run!(
+        "fn T id<T>(T x) { return x } return id<int>(3), id(4)",
+        3,
+        4
+    );
+    assert_run!(
+        "struct Pair<A, B> { A a, B b } let p = Pair<int, str> { b = \"b\", a = 1 } return p.a, p.b",
+        1,
+        "b"
+    );
+    assert_run!("enum Opt<T> { Some(T), None } return Opt<int>.Some(2).0", 2);
+    // a glued `<` without args after it is still a comparison
+    assert_run!("let a = 1; let b = 2; return a<b", true);
+}
+
+#[test]
+fn methods() {
+    assert_run!(
+        "struct P { x, y } impl P { fn sum(self) { return self.x + self.y } fn scale(self, n) { self.x *= n; self.y *= n } } let p = P { x = 1, y = 2 } p.scale(3); return p.sum()",
+        9
+    );
+    assert_run!(
+        "like Double { fn double(self) } impl int like Double { fn double(self) { return self * 2 } } return 4.double()",
+        8
+    );
+    assert_run!(
+        "enum E { A, B(int) } impl E { fn get(self) { if self == E.A { return 0 } return self.0 } } return E.A.get(), E.B(5).get()",
+        0,
+        5
+    );
+    assert_eq!(
+        run("struct P { x } impl P { } return P { x = 1 }.get()"),
+        Err(RuntimeError::NoField("P", "get".into()))
+    );
+    assert_eq!(
+        run(r#"impl int { fn f(self) { } } return "a".f()"#),
+        Err(RuntimeError::Index("ustr"))
+    );
+}
+
+#[test]
+fn comp() {
+    assert_run!(
+        "prefn sq(x) { return x * x } preconst a = sq(3); let b = comp sq(a) + 1; return a, b, sq(2)",
+        9,
+        82,
+        4
+    );
+    assert_run!("prelet a = 1; a += 1; return a", 2);
+}
+
+#[test]
+fn value_blocks() {
+    assert_run!("let y = { let t = 2; t * 3 }; return y", 6);
+    assert_run!("let y = { let t = 2; }; return y", Value::Nil);
+    let src = "fn f(n) { let x = if n < 0 { -1 } else if n == 0 { 0 } else { 1 }; return x } \
+               return f(-5), f(0), f(5)";
+    assert_run!(src, -1, 0, 1);
+    // a return in a value block returns from the fn
+    assert_run!(
+        "fn f(c) { let x = if c { return 1 } else { 2 }; return x } return f(true), f(false)",
+        1,
+        2
+    );
+    // an `if` or block is a value anywhere an expr is
+    assert_run!(
+        "fn f(c) { return if c { 1 } else { 2 } } return f(true), f(false)",
+        1,
+        2
+    );
+    assert_run!("let x = 0; x = if x == 0 { 3 } else { 4 }; return x", 3);
+    assert_run!("let x = 0; x = { 5 }; return x", 5);
+    assert_run!(
+        "let c = false; let y = if c { 1 } else { 2 } + 1; return y",
+        3
+    );
+    assert_run!("let x = { { 5 } }; return x", 5);
+    assert_run!(
+        "fn id(v) { return v } fn f() { id({ return 1 }); return 2 } return f()",
+        1
+    );
+    // a fn body ending in an `if`, block or match returns its value
+    assert_run!(
+        "fn f(c) { if c { 1 } else { 2 } } return f(true), f(false)",
+        1,
+        2
+    );
+    assert_run!("fn f() { { let t = 2; t * 3 } } return f()", 6);
+    assert_run!(
+        "fn f(n) { match n { 0 => 1, _ => { 2 } } } return f(0), f(5)",
+        1,
+        2
+    );
+}
+
+#[test]
+fn matches() {
+    let src = "enum Shape { Dot, Circle(int), Rect { w, h } } \
+               fn area(s) { let a = match s { Shape.Dot => 0, Shape.Circle(r) => r * r * 3, \
+               Shape.Rect { w, h = 1 } => w, Shape.Rect { w, h } => w * h }; return a } \
+               return area(Shape.Dot), area(Shape.Circle(2)), \
+               area(Shape.Rect { w = 5, h = 1 }), area(Shape.Rect { w = 2, h = 3 })";
+    assert_run!(src, 0, 12, 5, 6);
+    let src = "fn f(n) { let x = match n { 0 | 1 => \"small\", -1 => \"neg\", n if n > 9 => \"big\", _ => \"other\" } \
+               return x } return f(1), f(-1), f(10), f(5)";
+    assert_run!(src, "small", "neg", "big", "other");
+    // a tuple is matched against each value, missing values are `nil`
+    let src = "fn pair() { return 1, true } \
+               let x = match pair() { (1, false) => 1, (n, true) => n + 1, (_, _) => 0 } \
+               let y = match 2, 3 { (a, b, nil) => a * b, _ => 0 } return x, y";
+    assert_run!(src, 2, 6);
+    // an arm body may be a block, a match stmt runs the first matching arm
+    let src = "let n = 0; match true { true => { n = 1 } false => { n = 2 } } \
+               let f = match n { 1 => { let g = |x| x + n; g } _ => |x| x } return f(1)";
+    assert_run!(src, 2);
+    assert_run!(
+        "fn f(s) { match s { _ => { return 1 } } return 2 } return f(0)",
+        1
+    );
+    assert_run!("return match 1 { 1 => { 5 }, _ => 0 }", 5);
+    // a match is a value anywhere an expr is
+    assert_run!(
+        "fn f(n) { return match n { 0 => 1, _ => 2 } } return f(0), f(5)",
+        1,
+        2
+    );
+    assert_run!("let x = 0; x = match x { 0 => 7, _ => 8 }; return x", 7);
+    assert_run!(
+        "fn id(v) { return v } return id(match 2 { 2 => \"two\", _ => \"other\" })",
+        "two"
+    );
+}
+
+#[test]
+fn tagged() {
+    assert_run!("let x = 'a: { 'b: { break 'a 1 } return 2 } return x", 1);
+    assert_run!("let x = 'a: { let y = 2 } return x", Value::Nil);
+    assert_run!(
+        "fn f(n) { let x = 'a: { if n > 1 { return n } break 'a n * 10 } return x } return f(1), f(2)",
+        10,
+        2
+    );
+    assert_run!(
+        "let n = 0; 'a: { n = 1; 'b: { break 'b } n = 2; if n == 2 { break 'a } n = 3 } return n",
+        2
+    );
+    // the tail is the value when the block isn't broken out of
+    assert_run!("let v = 'a: { 1 } return v", 1);
+    assert_run!(
+        "fn f(n) { let v = 'a: { if n > 1 { break 'a n } 0 } return v } return f(1), f(5)",
+        0,
+        5
+    );
+    assert_run!("let v = { 'a: { break 'a 3 } } return v", 3);
+    assert_run!("let v = 'a: { 'b: { break 'b 1 } 2 } return v", 2);
+}
+
+#[test]
+fn conversions() {
+    assert_run!(
+        "return signed(2.7), unsigned(3), decimal(2), signed(-1.5)",
+        2,
+        3,
+        2.0,
+        -1
+    );
+    assert!(run("return unsigned(-1)").is_err());
+    assert!(run("return signed(1e300)").is_err());
+    assert!(run(r#"return decimal("1")"#).is_err());
+}
+
+#[test]
+fn strings() {
+    assert_run!(
+        r#"return c"ab" + c"\x41", b"a\xff" + b'c', br"\n", ustr(b"\xc3\xa9")"#,
+        Value::AStr("abA".into()),
+        &b"a\xffc"[..],
+        &b"\\n"[..],
+        "é"
+    );
+    assert_run!(
+        r#"return astr("ab") == c"ab", bstr(c"a") == b"a", c"a" < c"b""#,
+        true,
+        true,
+        true
+    );
+    assert_eq!(
+        run(r#"return c"a" + "b""#),
+        Err(RuntimeError::BinOp(BinOp::Add, "astr", "ustr"))
+    );
+    assert_eq!(
+        run(r#"return b"a" + "b""#),
+        Err(RuntimeError::BinOp(BinOp::Add, "bstr", "ustr"))
+    );
+    assert!(run(r#"return astr("é")"#).is_err());
+    assert!(run(r#"return ustr(b"\xff")"#).is_err());
+    assert!(run("return astr(1)").is_err());
+}
+
+#[test]
+fn string_methods() {
+    assert_run!(
+        r#"return "ab".len(), "é".len(), "aB".upper(), c"aB".lower(), "ab".rep(3, "-"), "a".rep(0)"#,
+        2,
+        2,
+        "AB",
+        Value::AStr("ab".into()),
+        "ab-ab-ab",
+        ""
+    );
+    assert_run!(
+        r#"let s = "hello"; return s.sub(2), s.sub(2, 3), s.sub(-3), s.sub(-100, 2), s.sub(4, 2)"#,
+        "ello",
+        "el",
+        "llo",
+        "he",
+        ""
+    );
+    assert!(run(r#"return "é".sub(1, 1)"#).is_err());
+    assert_run!(
+        r#"return "".rep(2000000000000000000), "ab".rep(1, "-")"#,
+        "",
+        "ab"
+    );
+    let err = run(r#"let x = "a"; return x.rep(2000000000000000000)"#).unwrap_err();
+    assert!(
+        err.to_string().contains("resulting string too large"),
+        "{err}"
+    );
+    assert_eq!(
+        run(r#"return b"a".len()"#),
+        Err(RuntimeError::Index("bstr"))
+    );
+}
+
+#[test]
+fn finalizers() {
+    use std::cell::Cell;
+
+    let dropped = Rc::new(Cell::new(0));
+    let mut engine = Engine::new();
+    let counter = dropped.clone();
+    engine.register_type(
+        TypeBuilder::<i64>::new("Handle").finalizer(move |_| counter.set(counter.get() + 1)),
+    );
+    engine.register_raw_fn("handle", |engine, _| Ok(vec![engine.userdata(1_i64)]));
+    run_with(&mut engine, "fn f() { let h = handle() } f(); f()").unwrap();
+    assert_eq!(dropped.get(), 2);
+    run_with(&mut engine, "let h = handle()").unwrap();
+    assert_eq!(dropped.get(), 2);
+    drop(engine);
+    assert_eq!(dropped.get(), 3);
+}
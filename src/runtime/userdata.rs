@@ -0,0 +1,422 @@
+//! rust values exposed to scripts as opaque userdata
+//!
+//! A type is registered once with a [`TypeBuilder`], listing the fields,
+//! methods & operators scripts may use. Values of the type are then wrapped
+//! with [`Engine::userdata`] & downcast back with [`UserData::borrow`] or
+//! [`UserRef`].
+
+use std::any::{type_name, Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::error::HostError;
+use crate::parse::token::{BinOp, UnOp};
+use crate::runtime::host::{Args, FromValue, HostFunction, IntoValue, IntoValues};
+use crate::runtime::{Engine, Value};
+use crate::util::Symbol;
+
+type Getter = dyn Fn(&dyn Any) -> Value;
+type Setter = dyn Fn(&mut dyn Any, Value) -> Result<(), HostError>;
+type Finalizer = dyn Fn(&mut dyn Any);
+
+/// The fields, methods & operators of a registered rust type
+pub struct UserType {
+    pub(crate) name: Symbol,
+    type_id: TypeId,
+    getters: HashMap<Symbol, Box<Getter>>,
+    setters: HashMap<Symbol, Box<Setter>>,
+    methods: HashMap<Symbol, Rc<HostFunction>>,
+    binary: BTreeMap<BinOp, Rc<HostFunction>>,
+    unary: BTreeMap<UnOp, Rc<HostFunction>>,
+    finalizer: Option<Box<Finalizer>>,
+}
+
+impl UserType {
+    #[must_use]
+    pub const fn name(&self) -> Symbol {
+        self.name
+    }
+
+    /// The value of the field `name` of `data`
+    pub(crate) fn get(&self, data: &UserData, name: Symbol) -> Option<Result<Value, HostError>> {
+        if let Some(get) = self.getters.get(&name) {
+            return Some(
+                data.data
+                    .try_borrow()
+                    .map_err(|_| HostError::Borrowed)
+                    .map(|data| get(&**data)),
+            );
+        }
+        let method = self.methods.get(&name)?;
+        Some(Ok(Value::Host(method.clone())))
+    }
+
+    /// Set the field `name` of `data`, `None` if it has no setter
+    pub(crate) fn set(
+        &self,
+        data: &UserData,
+        name: Symbol,
+        value: Value,
+    ) -> Option<Result<(), HostError>> {
+        let set = self.setters.get(&name)?;
+        let data = data.data.try_borrow_mut();
+        Some(data.map_or(Err(HostError::Borrowed), |mut data| set(&mut **data, value)))
+    }
+
+    pub(crate) fn method(&self, name: Symbol) -> Option<&Rc<HostFunction>> {
+        self.methods.get(&name)
+    }
+
+    pub(crate) fn binary(&self, op: BinOp) -> Option<&Rc<HostFunction>> {
+        self.binary.get(&op)
+    }
+
+    pub(crate) fn unary(&self, op: UnOp) -> Option<&Rc<HostFunction>> {
+        self.unary.get(&op)
+    }
+}
+
+impl Debug for UserType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserType")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A rust value owned by the engine
+pub struct UserData {
+    pub(crate) ty: Rc<UserType>,
+    data: RefCell<Box<dyn Any>>,
+}
+
+impl UserData {
+    #[must_use]
+    pub fn ty(&self) -> &UserType {
+        &self.ty
+    }
+
+    #[must_use]
+    pub fn is<T: 'static>(&self) -> bool {
+        self.ty.type_id == TypeId::of::<T>()
+    }
+
+    /// # Errors
+    ///
+    /// If the data isn't a `T` or is mutably borrowed
+    pub fn borrow<T: 'static>(&self) -> Result<Ref<'_, T>, HostError> {
+        let data = self.data.try_borrow().map_err(|_| HostError::Borrowed)?;
+        Ref::filter_map(data, |data| data.downcast_ref()).map_err(|_| self.type_error::<T>())
+    }
+
+    /// # Errors
+    ///
+    /// If the data isn't a `T` or is already borrowed
+    pub fn borrow_mut<T: 'static>(&self) -> Result<RefMut<'_, T>, HostError> {
+        let data = self
+            .data
+            .try_borrow_mut()
+            .map_err(|_| HostError::Borrowed)?;
+        RefMut::filter_map(data, |data| data.downcast_mut()).map_err(|_| self.type_error::<T>())
+    }
+
+    fn type_error<T: 'static>(&self) -> HostError {
+        HostError::Type {
+            index: 0,
+            expected: type_name::<T>(),
+            found: self.ty.name.as_str(),
+        }
+    }
+}
+
+impl Drop for UserData {
+    fn drop(&mut self) {
+        if let Some(finalize) = &self.ty.finalizer {
+            finalize(&mut **self.data.get_mut());
+        }
+    }
+}
+
+impl Debug for UserData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserData")
+            .field("type", &self.ty.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A userdata arg of a host fn, checked to hold a `T`
+#[derive(Debug)]
+pub struct UserRef<T> {
+    data: Rc<UserData>,
+    ty: PhantomData<T>,
+}
+
+impl<T: 'static> UserRef<T> {
+    /// # Errors
+    ///
+    /// If the data is mutably borrowed
+    pub fn borrow(&self) -> Result<Ref<'_, T>, HostError> {
+        self.data.borrow()
+    }
+
+    /// # Errors
+    ///
+    /// If the data is already borrowed
+    pub fn borrow_mut(&self) -> Result<RefMut<'_, T>, HostError> {
+        self.data.borrow_mut()
+    }
+
+    #[must_use]
+    pub fn value(&self) -> Value {
+        Value::UserData(self.data.clone())
+    }
+}
+
+impl<T> Clone for UserRef<T> {
+    fn clone(&self) -> Self {
+        Self {
+            data: self.data.clone(),
+            ty: PhantomData,
+        }
+    }
+}
+
+impl<T: 'static> FromValue for UserRef<T> {
+    fn from_value(value: Value) -> Result<Self, HostError> {
+        match value {
+            Value::UserData(data) if data.is::<T>() => Ok(Self {
+                data,
+                ty: PhantomData,
+            }),
+            other => Err(HostError::type_error(type_name::<T>(), &other)),
+        }
+    }
+}
+
+impl FromValue for Rc<UserData> {
+    fn from_value(value: Value) -> Result<Self, HostError> {
+        match value {
+            Value::UserData(data) => Ok(data),
+            other => Err(HostError::type_error("userdata", &other)),
+        }
+    }
+}
+
+/// A rust method with typed args, see [`TypeBuilder::method`]
+pub trait HostMethod<T, Args>: 'static {
+    /// # Errors
+    ///
+    /// A wrong number of args, an arg of the wrong type or a failed call
+    fn call(&self, this: &mut T, args: Vec<Value>) -> Result<Vec<Value>, HostError>;
+}
+
+macro_rules! impl_host_method {
+    ($($arg:ident),*) => {
+        impl<T, Func, Out, $($arg),*> HostMethod<T, ($($arg,)*)> for Func
+        where
+            Func: Fn(&mut T, $($arg),*) -> Out + 'static,
+            Out: IntoValues,
+            $($arg: FromValue,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, this: &mut T, args: Vec<Value>) -> Result<Vec<Value>, HostError> {
+                let mut args = Args::new(args, 0 $(+ { stringify!($arg); 1 })*)?;
+                $(let $arg = args.next()?;)*
+                (self)(this, $($arg),*).into_values()
+            }
+        }
+    };
+}
+
+impl_host_method!();
+impl_host_method!(A);
+impl_host_method!(A, B);
+impl_host_method!(A, B, C);
+impl_host_method!(A, B, C, D);
+impl_host_method!(A, B, C, D, E);
+
+/// Describes how scripts may use a rust type, see [`Engine::register_type`]
+#[derive(Debug)]
+pub struct TypeBuilder<T> {
+    ty: UserType,
+    data: PhantomData<T>,
+}
+
+impl<T: 'static> TypeBuilder<T> {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            ty: UserType {
+                name: name.into(),
+                type_id: TypeId::of::<T>(),
+                getters: HashMap::new(),
+                setters: HashMap::new(),
+                methods: HashMap::new(),
+                binary: BTreeMap::new(),
+                unary: BTreeMap::new(),
+                finalizer: None,
+            },
+            data: PhantomData,
+        }
+    }
+
+    /// A field that scripts may read & assign
+    #[must_use]
+    pub fn field<V: IntoValue + FromValue>(
+        self,
+        name: &str,
+        get: impl Fn(&T) -> V + 'static,
+        set: impl Fn(&mut T, V) + 'static,
+    ) -> Self {
+        self.getter(name, get).setter(name, set)
+    }
+
+    /// A field that scripts may read
+    #[must_use]
+    pub fn getter<V: IntoValue>(mut self, name: &str, get: impl Fn(&T) -> V + 'static) -> Self {
+        let get = move |data: &dyn Any| get(downcast_ref(data)).into_value();
+        self.ty.getters.insert(name.into(), Box::new(get));
+        self
+    }
+
+    /// A field that scripts may assign
+    #[must_use]
+    pub fn setter<V: FromValue>(mut self, name: &str, set: impl Fn(&mut T, V) + 'static) -> Self {
+        let set = move |data: &mut dyn Any, value| {
+            set(downcast_mut(data), V::from_value(value)?);
+            Ok(())
+        };
+        self.ty.setters.insert(name.into(), Box::new(set));
+        self
+    }
+
+    /// A method with typed args, called as `value.name(args)`
+    ///
+    /// The data is mutably borrowed for the whole call.
+    #[must_use]
+    pub fn method<Args>(self, name: &str, func: impl HostMethod<T, Args>) -> Self {
+        self.raw_method(name, move |_, mut args| {
+            let this = receiver::<T>(&mut args)?;
+            let mut this = this.borrow_mut()?;
+            func.call(&mut this, args)
+        })
+    }
+
+    /// A method taking the receiver & its args as they are
+    ///
+    /// The receiver is the first arg. The method may call back into the engine.
+    #[must_use]
+    pub fn raw_method(
+        mut self,
+        name: &str,
+        func: impl Fn(&mut Engine, Vec<Value>) -> Result<Vec<Value>, HostError> + 'static,
+    ) -> Self {
+        let func = self.host_fn(name, func);
+        self.ty.methods.insert(name.into(), func);
+        self
+    }
+
+    /// Overload a binary operator
+    ///
+    /// It is used when either operand is of this type, the left one first.
+    #[must_use]
+    pub fn operator(
+        mut self,
+        op: BinOp,
+        func: impl Fn(&mut Engine, Value, Value) -> Result<Value, HostError> + 'static,
+    ) -> Self {
+        let func = self.host_fn(op.as_str(), move |engine, args| {
+            let mut args = Args::new(args, 2)?;
+            let (lhs, rhs) = (args.next()?, args.next()?);
+            func(engine, lhs, rhs).into_values()
+        });
+        self.ty.binary.insert(op, func);
+        self
+    }
+
+    /// Overload a unary operator
+    #[must_use]
+    pub fn unary_operator(
+        mut self,
+        op: UnOp,
+        func: impl Fn(&mut Engine, Value) -> Result<Value, HostError> + 'static,
+    ) -> Self {
+        let func = self.host_fn(op.as_str(), move |engine, args| {
+            let mut args = Args::new(args, 1)?;
+            func(engine, args.next()?).into_values()
+        });
+        self.ty.unary.insert(op, func);
+        self
+    }
+
+    /// Called with the data once the last script reference is dropped
+    #[must_use]
+    pub fn finalizer(mut self, finalize: impl Fn(&mut T) + 'static) -> Self {
+        let finalize = move |data: &mut dyn Any| finalize(downcast_mut(data));
+        self.ty.finalizer = Some(Box::new(finalize));
+        self
+    }
+
+    /// name the fn `<type>.<name>` in errors
+    fn host_fn(
+        &self,
+        name: &str,
+        func: impl Fn(&mut Engine, Vec<Value>) -> Result<Vec<Value>, HostError> + 'static,
+    ) -> Rc<HostFunction> {
+        Rc::new(HostFunction {
+            name: format!("{}.{name}", self.ty.name).into(),
+            func: Box::new(func),
+        })
+    }
+
+    pub(crate) fn build(self) -> UserType {
+        self.ty
+    }
+}
+
+/// take the receiver of a method call, checked to be a `T`
+fn receiver<T: 'static>(args: &mut Vec<Value>) -> Result<UserRef<T>, HostError> {
+    let this = match args.is_empty() {
+        true => Value::Nil,
+        false => args.remove(0),
+    };
+    UserRef::from_value(this)
+}
+
+fn downcast_ref<T: 'static>(data: &dyn Any) -> &T {
+    data.downcast_ref()
+        .expect("userdata of the registered type")
+}
+
+fn downcast_mut<T: 'static>(data: &mut dyn Any) -> &mut T {
+    data.downcast_mut()
+        .expect("userdata of the registered type")
+}
+
+impl Engine {
+    /// Register a rust type so that its values can be passed to scripts
+    ///
+    /// Registering a type again replaces it for values created afterwards.
+    pub fn register_type<T: 'static>(&mut self, ty: TypeBuilder<T>) {
+        self.types.insert(TypeId::of::<T>(), Rc::new(ty.build()));
+    }
+
+    /// Wrap a rust value as userdata
+    ///
+    /// A type that was never registered has no fields, methods or operators.
+    pub fn userdata<T: 'static>(&mut self, data: T) -> Value {
+        let ty = self
+            .types
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(TypeBuilder::<T>::new(type_name::<T>()).build()))
+            .clone();
+        Value::UserData(Rc::new(UserData {
+            ty,
+            data: RefCell::new(Box::new(data)),
+        }))
+    }
+}
@@ -0,0 +1,410 @@
+//! runtime values
+
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Display};
+use std::rc::Rc;
+
+use crate::check::Captures;
+use crate::error::RuntimeError;
+use crate::lex::unescape::{
+    byte_from_char, unescape_byte, unescape_char, unescape_mixed, unescape_unicode, MixedUnit, Mode,
+};
+use crate::lex::{Base, LiteralKind};
+use crate::parse::token::{self, Const, Module, VariantKind};
+use crate::runtime::host::HostFunction;
+use crate::runtime::table::Table;
+use crate::runtime::userdata::UserData;
+use crate::util::Symbol;
+
+/// A shared variable, captured by every closure that uses it
+pub type Cell = Rc<RefCell<Value>>;
+
+/// A value produced while running a module
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    #[default]
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// A `ustr`, utf8
+    Str(Rc<str>),
+    /// An `astr`, only ever ascii
+    AStr(Rc<str>),
+    /// A `bstr`, any bytes
+    BStr(Rc<[u8]>),
+    Fn(Rc<Function>),
+    /// A fn provided by the host program
+    Host(Rc<HostFunction>),
+    /// A rust value, see [`Engine::userdata`](crate::runtime::Engine::userdata)
+    UserData(Rc<UserData>),
+    /// An instance of a struct or enum declared in a module
+    Data(Rc<Data>),
+    /// A lua table
+    Table(Rc<Table>),
+}
+
+impl Value {
+    /// false for `nil` & `false`
+    #[must_use]
+    pub const fn is_truthy(&self) -> bool {
+        !matches!(self, Self::Nil | Self::Bool(false))
+    }
+
+    /// The names [`Self::type_name`] gives the builtin types
+    pub const TYPE_NAMES: &[&str] = &["nil", "bool", "int", "float", "ustr", "astr", "bstr", "fn"];
+
+    /// The name of a userdata's registered type, or of the builtin type
+    #[must_use]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Nil => "nil",
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Str(_) => "ustr",
+            Self::AStr(_) => "astr",
+            Self::BStr(_) => "bstr",
+            Self::Fn(_) | Self::Host(_) => "fn",
+            Self::UserData(data) => data.ty.name.as_str(),
+            Self::Data(data) => data.ty.as_str(),
+            Self::Table(_) => "table",
+        }
+    }
+
+    /// The value as a [`Const`], `None` for fns, data, tables & `bstr`s
+    #[must_use]
+    pub fn to_const(&self) -> Option<Const> {
+        Some(match self {
+            Self::Nil => Const::Nil,
+            Self::Bool(b) => Const::Bool(*b),
+            Self::Int(i) => Const::Int(*i),
+            Self::Float(x) => Const::Float(x.to_bits()),
+            Self::Str(s) => Const::Str(s.as_ref().into()),
+            Self::AStr(s) => Const::AStr(s.as_ref().into()),
+            Self::BStr(_)
+            | Self::Fn(_)
+            | Self::Host(_)
+            | Self::UserData(_)
+            | Self::Data(_)
+            | Self::Table(_) => return None,
+        })
+    }
+
+    /// Convert a literal to a value
+    ///
+    /// # Errors
+    ///
+    /// Unterminated or malformed literals & literal kinds without a runtime
+    /// representation yet.
+    pub fn from_literal(lit: token::Value) -> Result<Self, RuntimeError> {
+        let err = || RuntimeError::Literal(lit.value);
+        let text = &lit.value.as_str()[..lit.suffix_start];
+        match lit.kind {
+            LiteralKind::Int {
+                empty_int: false,
+                base,
+            } => {
+                let digits = match base {
+                    Base::Decimal => text,
+                    _ => &text[2..],
+                };
+                let digits = digits.replace('_', "");
+                i64::from_str_radix(&digits, base as u32)
+                    .map(Self::Int)
+                    .map_err(|_| err())
+            }
+            LiteralKind::Float {
+                empty_exponent: false,
+                base: Base::Decimal,
+            } => text
+                .replace('_', "")
+                .parse()
+                .map(Self::Float)
+                .map_err(|_| err()),
+            LiteralKind::Str { terminated: true } => {
+                let mut out = String::new();
+                let mut ok = true;
+                unescape_unicode(&text[1..text.len() - 1], Mode::Str, &mut |_, c| match c {
+                    Ok(c) => out.push(c),
+                    Err(_) => ok = false,
+                });
+                ok.then(|| Self::Str(out.into())).ok_or_else(err)
+            }
+            LiteralKind::RawStr {
+                n_hashes: Some(hashes),
+            } => {
+                let hashes = usize::from(hashes);
+                Ok(Self::Str(text[2 + hashes..text.len() - 1 - hashes].into()))
+            }
+            LiteralKind::Char { terminated: true } => unescape_char(&text[1..text.len() - 1])
+                .map(|c| Self::Str(c.to_string().into()))
+                .map_err(|_| err()),
+            LiteralKind::ByteStr { terminated: true } => {
+                bytes(&text[2..text.len() - 1], Mode::ByteStr).ok_or_else(err)
+            }
+            LiteralKind::RawByteStr {
+                n_hashes: Some(hashes),
+            } => {
+                let hashes = usize::from(hashes);
+                bytes(&text[3 + hashes..text.len() - 1 - hashes], Mode::RawByteStr).ok_or_else(err)
+            }
+            LiteralKind::Byte { terminated: true } => unescape_byte(&text[2..text.len() - 1])
+                .map(|b| Self::BStr(Rc::new([b])))
+                .map_err(|_| err()),
+            LiteralKind::CStr { terminated: true } => {
+                let mut out = String::new();
+                let mut ok = true;
+                unescape_mixed(&text[2..text.len() - 1], Mode::CStr, &mut |_, c| match c {
+                    Ok(MixedUnit::Char(c)) if c.is_ascii() => out.push(c),
+                    _ => ok = false,
+                });
+                ok.then(|| Self::AStr(out.into())).ok_or_else(err)
+            }
+            LiteralKind::RawCStr {
+                n_hashes: Some(hashes),
+            } => {
+                let hashes = usize::from(hashes);
+                let text = &text[3 + hashes..text.len() - 1 - hashes];
+                let ok = text.is_ascii() && !text.contains('\0');
+                ok.then(|| Self::AStr(text.into())).ok_or_else(err)
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+/// the bytes of a `bstr` literal without its quotes
+fn bytes(text: &str, mode: Mode) -> Option<Value> {
+    let mut out = Vec::new();
+    let mut ok = true;
+    unescape_unicode(text, mode, &mut |_, c| match c {
+        Ok(c) => out.push(byte_from_char(c)),
+        Err(_) => ok = false,
+    });
+    ok.then(|| Value::BStr(out.into()))
+}
+
+impl PartialEq for Value {
+    #[allow(clippy::cast_precision_loss)]
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a == b,
+            (&Self::Int(a), &Self::Float(b)) | (&Self::Float(b), &Self::Int(a)) => a as f64 == b,
+            (Self::Str(a), Self::Str(b)) | (Self::AStr(a), Self::AStr(b)) => a == b,
+            (Self::BStr(a), Self::BStr(b)) => a == b,
+            (Self::Fn(a), Self::Fn(b)) => Rc::ptr_eq(a, b),
+            (Self::Host(a), Self::Host(b)) => Rc::ptr_eq(a, b),
+            (Self::UserData(a), Self::UserData(b)) => Rc::ptr_eq(a, b),
+            (Self::Table(a), Self::Table(b)) => Rc::ptr_eq(a, b),
+            (Self::Data(a), Self::Data(b)) => {
+                Rc::ptr_eq(a, b)
+                    || (a.ty == b.ty
+                        && a.variant == b.variant
+                        && *a.fields.borrow() == *b.fields.borrow())
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nil => f.write_str("nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "{i}"),
+            Self::Float(x) => write!(f, "{x:?}"),
+            Self::Str(s) | Self::AStr(s) => f.write_str(s),
+            Self::BStr(b) => write!(f, "{}", b.escape_ascii()),
+            Self::Fn(func) => write!(f, "fn@{}", func.pos),
+            Self::Host(func) => write!(f, "fn@{}", func.name),
+            Self::UserData(data) => write!(f, "{}@{:p}", data.ty.name, Rc::as_ptr(data)),
+            Self::Data(data) => write!(f, "{data}"),
+            Self::Table(table) => write!(f, "table: {:p}", Rc::as_ptr(table)),
+        }
+    }
+}
+
+/// An instance of a struct, or of an enum variant
+#[derive(Debug)]
+pub struct Data {
+    pub ty: Symbol,
+    pub variant: Option<Symbol>,
+    pub kind: VariantKind,
+    /// in declaration order, tuple fields are named `0`, `1`, ..
+    pub fields: RefCell<Vec<(Symbol, Value)>>,
+}
+
+impl Data {
+    #[must_use]
+    pub fn get(&self, name: Symbol) -> Option<Value> {
+        let fields = self.fields.borrow();
+        fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// false if there's no field `name`
+    pub fn set(&self, name: Symbol, value: Value) -> bool {
+        let mut fields = self.fields.borrow_mut();
+        let Some((_, slot)) = fields.iter_mut().find(|(field, _)| *field == name) else {
+            return false;
+        };
+        *slot = value;
+        true
+    }
+}
+
+impl Display for Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.ty)?;
+        if let Some(variant) = self.variant {
+            write!(f, ".{variant}")?;
+        }
+        let fields = self.fields.borrow();
+        match self.kind {
+            VariantKind::Unit => Ok(()),
+            VariantKind::Tuple => {
+                f.write_str("(")?;
+                for (i, (_, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_str(")")
+            }
+            VariantKind::Struct if fields.is_empty() => f.write_str(" {}"),
+            VariantKind::Struct => {
+                f.write_str(" {")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    let sep = if i > 0 { "," } else { "" };
+                    write!(f, "{sep} {name}: {value}")?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+impl From<Const> for Value {
+    fn from(value: Const) -> Self {
+        match value {
+            Const::Nil => Self::Nil,
+            Const::Bool(b) => Self::Bool(b),
+            Const::Int(i) => Self::Int(i),
+            Const::Float(bits) => Self::Float(f64::from_bits(bits)),
+            Const::Str(s) => Self::Str(s.as_str().into()),
+            Const::AStr(s) => Self::AStr(s.as_str().into()),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<Rc<str>> for Value {
+    fn from(value: Rc<str>) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Self::BStr(value.into())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Self::BStr(value.into())
+    }
+}
+
+impl From<Table> for Value {
+    fn from(value: Table) -> Self {
+        Self::Table(Rc::new(value))
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Self::Int(value.into())
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Self::Float(value.into())
+    }
+}
+
+/// `None` is `nil`
+impl<T: Into<Self>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Nil, Into::into)
+    }
+}
+
+/// A parsed module, shared by every fn defined in it
+#[derive(Debug)]
+pub(crate) struct Chunk {
+    pub module: Module,
+    pub captures: Captures,
+}
+
+/// A fn or closure together with the variables it captured
+pub struct Function {
+    pub(crate) chunk: Rc<Chunk>,
+    /// the pos of the [`token::FnDef`] or [`token::Closure`]
+    pub(crate) pos: usize,
+    pub(crate) upvalues: Vec<(Symbol, Cell)>,
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("module", &self.chunk.module.name())
+            .field("pos", &self.pos)
+            .field("upvalues", &self.upvalues)
+            .finish()
+    }
+}
@@ -16,6 +16,30 @@ impl BSpan {
     pub const fn from_len(from: usize, len: usize) -> Self {
         Self::new(from, from + len)
     }
+
+    /// Move both ends by `by` bytes
+    #[must_use]
+    pub const fn offset(self, by: isize) -> Self {
+        Self::new(
+            self.from.wrapping_add_signed(by),
+            self.to.wrapping_add_signed(by),
+        )
+    }
+}
+
+/// The 1-based line & column of the byte `pos` of `src`
+///
+/// Columns count chars. A `pos` past the end is clamped to the end.
+#[must_use]
+pub fn line_col(src: &str, pos: usize) -> (usize, usize) {
+    let mut pos = pos.min(src.len());
+    while !src.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    let before = &src[..pos];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
 }
 
 /// A token span
@@ -49,4 +73,18 @@ impl TSpan {
         self.to = to;
         self
     }
+
+    #[must_use]
+    pub const fn contains(self, pos: usize) -> bool {
+        self.from <= pos && pos < self.to
+    }
+
+    /// Move both ends by `by` tokens
+    #[must_use]
+    pub const fn offset(self, by: isize) -> Self {
+        Self {
+            from: self.from.wrapping_add_signed(by),
+            to: self.to.wrapping_add_signed(by),
+        }
+    }
 }
@@ -0,0 +1,237 @@
+//! drives `allua::lsp::serve` over framed json-rpc, like an editor would
+
+use allua::lsp::{read_body, serve, write_message, Json};
+
+const URI: &str = "file:///main.allua";
+
+/// a scripted client, its messages are sent in one go
+#[derive(Default)]
+struct Client {
+    input: Vec<u8>,
+    next_id: i32,
+}
+
+impl Client {
+    fn send(&mut self, method: &str, id: Option<i32>, params: Json) {
+        let mut message = vec![("jsonrpc", "2.0".into()), ("method", method.into())];
+        if let Some(id) = id {
+            message.push(("id", id.into()));
+        }
+        message.push(("params", params));
+        write_message(&mut self.input, &Json::object(message)).unwrap();
+    }
+
+    /// send a request, returning its id
+    fn request(&mut self, method: &str, params: Json) -> i32 {
+        self.next_id += 1;
+        self.send(method, Some(self.next_id), params);
+        self.next_id
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(method, None, params);
+    }
+
+    fn open(&mut self, src: &str) {
+        let doc = Json::object([("uri", URI.into()), ("text", src.into())]);
+        self.notify(
+            "textDocument/didOpen",
+            Json::object([("textDocument", doc)]),
+        );
+    }
+
+    /// initialize, run the script, then shut down & exit
+    fn run(mut self, script: impl FnOnce(&mut Self)) -> Replies {
+        self.request("initialize", Json::object([]));
+        self.notify("initialized", Json::object([]));
+        script(&mut self);
+        self.request("shutdown", Json::Null);
+        self.notify("exit", Json::Null);
+        let mut output = Vec::new();
+        serve(self.input.as_slice(), &mut output).unwrap();
+        let mut output = output.as_slice();
+        let mut replies = Vec::new();
+        while let Some(body) = read_body(&mut output).unwrap() {
+            replies.push(Json::parse(&body).unwrap());
+        }
+        Replies(replies)
+    }
+}
+
+struct Replies(Vec<Json>);
+
+impl Replies {
+    fn result(&self, id: i32) -> &Json {
+        let reply = self
+            .0
+            .iter()
+            .find(|reply| reply.get("id").as_usize() == usize::try_from(id).ok());
+        reply.expect("a reply").get("result")
+    }
+
+    fn notifications(&self, method: &str) -> impl Iterator<Item = &Json> {
+        let method = method.to_owned();
+        self.0
+            .iter()
+            .filter(move |reply| reply.get("method").as_str() == Some(&*method))
+    }
+}
+
+fn document() -> Json {
+    Json::object([("textDocument", Json::object([("uri", URI.into())]))])
+}
+
+fn at(line: usize, character: usize) -> Json {
+    Json::object([
+        ("textDocument", Json::object([("uri", URI.into())])),
+        (
+            "position",
+            Json::object([("line", line.into()), ("character", character.into())]),
+        ),
+    ])
+}
+
+fn folds(json: &Json) -> Vec<(usize, usize, Option<&str>)> {
+    let mut folds: Vec<_> = json
+        .as_array()
+        .iter()
+        .map(|range| {
+            let line = |key| range.get(key).as_usize().unwrap();
+            (
+                line("startLine"),
+                line("endLine"),
+                range.get("kind").as_str(),
+            )
+        })
+        .collect();
+    folds.sort_unstable();
+    folds
+}
+
+#[test]
+fn lifecycle() {
+    let mut ids = (0, 0);
+    let replies = Client::default().run(|client| {
+        client.open("let a = 1");
+        ids.0 = client.request("textDocument/documentSymbol", document());
+        ids.1 = client.request("no/such/method", Json::Null);
+    });
+    let capabilities = replies.result(1).get("capabilities");
+    assert_eq!(capabilities.get("foldingRangeProvider"), &Json::Bool(true));
+    assert_eq!(replies.result(ids.0).as_array().len(), 1);
+    let unknown = replies
+        .0
+        .iter()
+        .find(|reply| reply.get("id").as_usize() == Some(3));
+    assert!(!unknown.unwrap().get("error").is_null());
+    // the reply to shutdown, nothing after exit
+    assert!(replies.0.last().unwrap().get("result").is_null());
+}
+
+#[test]
+fn diagnostics() {
+    let replies = Client::default().run(|client| client.open("let = 1\nlet b = 2"));
+    let published: Vec<_> = replies
+        .notifications("textDocument/publishDiagnostics")
+        .collect();
+    assert_eq!(published.len(), 1);
+    let diagnostics = published[0].at(&["params", "diagnostics"]).as_array();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].at(&["range", "start", "line"]).as_usize(),
+        Some(0)
+    );
+}
+
+#[test]
+fn navigation() {
+    let src = "let a = 1\nfn f(int x) {\n  return x + a\n}\nf(a)";
+    let mut ids = (0, 0);
+    let replies = Client::default().run(|client| {
+        client.open(src);
+        ids.0 = client.request("textDocument/definition", at(2, 13));
+        ids.1 = client.request("textDocument/hover", at(4, 0));
+    });
+    let start = replies.result(ids.0).at(&["range", "start"]);
+    assert_eq!(start.get("line").as_usize(), Some(0));
+    assert_eq!(start.get("character").as_usize(), Some(4));
+    assert_eq!(
+        replies.result(ids.1).at(&["contents", "value"]).as_str(),
+        Some("```allua\nfn f(int x)\n```")
+    );
+}
+
+#[test]
+fn folding() {
+    let src = "\
+fn f(c) {
+  /* a
+  b */
+  if c {
+    return 1
+  } else if c {
+    return 2
+  } else {
+    return 3
+  }
+}
+struct P {
+  x
+}
+let v = match 1 {
+  1 if 1 == 1 => 2,
+  _ => {
+    3
+  }
+}
+let g = fn(x) {
+  return x
+}
+{
+  let t = 1
+}
+'out: {
+  break 'out
+}
+fn h() { }";
+    let mut id = 0;
+    let replies = Client::default().run(|client| {
+        client.open(src);
+        id = client.request("textDocument/foldingRange", document());
+    });
+    assert_eq!(
+        folds(replies.result(id)),
+        [
+            (0, 10, None),
+            (1, 2, Some("comment")),
+            (3, 5, None),
+            (5, 7, None),
+            (7, 9, None),
+            (11, 13, None),
+            (14, 19, None),
+            (16, 18, None),
+            (20, 22, None),
+            (23, 25, None),
+            (26, 28, None),
+        ]
+    );
+}
+
+#[test]
+fn edits() {
+    let mut id = 0;
+    let replies = Client::default().run(|client| {
+        client.open("fn f() { return 1 }");
+        let doc = Json::object([("uri", URI.into()), ("version", 2_usize.into())]);
+        let change = Json::object([("text", "fn f() {\n  return 1\n}".into())]);
+        client.notify(
+            "textDocument/didChange",
+            Json::object([
+                ("textDocument", doc),
+                ("contentChanges", Json::Array(vec![change])),
+            ]),
+        );
+        id = client.request("textDocument/foldingRange", document());
+    });
+    assert_eq!(folds(replies.result(id)), [(0, 2, None)]);
+}
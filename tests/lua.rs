@@ -0,0 +1,144 @@
+//! runs the lua samples in `tests/lua`, which are plain lua 5.4
+
+use allua::parse::LuaReader;
+use allua::runtime::{Engine, Value};
+
+fn run(name: &str, src: &str) -> Vec<Value> {
+    let (module, errors) = LuaReader::new(src).module(name);
+    assert!(errors.is_empty(), "{name}: {errors:#?}");
+    Engine::lua().run(module).unwrap()
+}
+
+macro_rules! assert_sample {
+    ($name:literal $(, $expected:expr)* $(,)?) => {
+        let src = include_str!(concat!("lua/", $name, ".lua"));
+        assert_eq!(run($name, src), [$(Value::from($expected)),*], $name);
+    };
+}
+
+#[test]
+fn recursion() {
+    assert_sample!("recursion", 6765, 21, true, true);
+}
+
+#[test]
+fn closures() {
+    assert_sample!("closures", 12, 10, 3, 10, 3, 256.0);
+}
+
+#[test]
+fn strings() {
+    assert_sample!(
+        "strings",
+        "HELLO!",
+        "-----",
+        "ab, ab, ab",
+        "Lua",
+        5,
+        5,
+        "llo",
+        "long!"
+    );
+}
+
+#[test]
+fn arith() {
+    assert_sample!(
+        "arith",
+        3.5,
+        2,
+        -2,
+        -4,
+        -4,
+        0.5,
+        -4.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::INFINITY,
+        2.0
+    );
+    for src in ["return 1 // 0", "return 1 % 0"] {
+        let (module, _) = LuaReader::new(src).module("zero");
+        let err = Engine::new().run(module).unwrap_err();
+        assert_eq!(err.to_string(), "integer division by zero", "{src}");
+    }
+}
+
+#[test]
+fn loops() {
+    assert_sample!("loops", 55, "3,2,1", 5, 111, 5, 8, 4);
+}
+
+#[test]
+fn tables() {
+    assert_sample!(
+        "tables",
+        5,
+        2,
+        "one",
+        3,
+        3,
+        "ABC",
+        "kiwi apple fig",
+        "pear",
+        "5,4,3,2,1",
+        3,
+        "table",
+        2
+    );
+}
+
+#[test]
+fn goto() {
+    assert_sample!("goto", "1,3,5", "3x4", 5);
+}
+
+#[test]
+fn metatables() {
+    assert_sample!(
+        "metatables",
+        "(4, 6)",
+        true,
+        true,
+        true,
+        2,
+        "hi!",
+        42,
+        5,
+        true,
+        false,
+        "boom"
+    );
+}
+
+#[test]
+fn close() {
+    assert_sample!("close", "b a loop1 loop2 d e:err", "f");
+}
+
+#[test]
+fn errors() {
+    let cases = [
+        ("for i = 1, 2, 0 do end", "`for` step is zero"),
+        (
+            "for i = 'a', 2 do end",
+            "`for` initial value must be a number, not a ustr",
+        ),
+        ("local t = {} t[nil] = 1", "table index is nil"),
+        (
+            "local x <close> = 1",
+            "variable `x` got a non-closable value",
+        ),
+        (
+            "local t = {} setmetatable(t, {__index = t}) return t.x",
+            "`__index` chain too long, possible loop",
+        ),
+        ("error('raised')", "in `error` at 0..15: raised"),
+    ];
+    for (src, expected) in cases {
+        let (module, errors) = LuaReader::new(src).module("errors");
+        assert!(errors.is_empty(), "{src}: {errors:#?}");
+        let err = Engine::lua().run(module).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{src}");
+    }
+}
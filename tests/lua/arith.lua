@@ -0,0 +1,5 @@
+-- `/` always divides as floats, `%` & `//` round towards negative infinity
+
+local a, b = 7, 2
+return a / b, -7 % 3, 7 % -3, -7 // 2, 7 // -2, -7.5 % 2, -7.5 // 2,
+  1 / 0, -1 / 0, 1.0 // 0, 6 / 3
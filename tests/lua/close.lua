@@ -0,0 +1,31 @@
+-- `<close>` variables, closed in reverse order when their scope ends
+local log = {}
+local function closer(name)
+  return setmetatable({}, {__close = function(_, err)
+    log[#log + 1] = name .. (err and ":err" or "")
+  end})
+end
+
+do
+  local a <close> = closer("a")
+  local b <close> = closer("b")
+  local skipped <close> = nil
+end
+
+for i = 1, 2 do
+  local c <close> = closer("loop" .. i)
+  if i == 2 then break end
+end
+
+local function f()
+  local d <close> = closer("d")
+  return "f"
+end
+local r = f()
+
+pcall(function()
+  local e <close> = closer("e")
+  error("oops")
+end)
+
+return table.concat(log, " "), r
@@ -0,0 +1,31 @@
+-- counters sharing upvalues, & varargs passed along
+
+local function counter(start, step)
+  local n = start
+  local function inc()
+    n = n + (step or 1)
+    return n
+  end
+  local function get() return n end
+  return inc, get
+end
+
+local inc, get = counter(10)
+inc()
+inc()
+local inc2 = counter(0, 5)
+inc2()
+
+local function pack(...)
+  return select_last(...)
+end
+
+function select_last(first, ...)
+  if ... == nil then return first end
+  return select_last(...)
+end
+
+local x <const> = 2
+local f = function(a, b) return a * x + b end
+
+return get(), inc2(), pack(1, 2, 3), f(3, 4), 7 // 2, 2 ^ 8
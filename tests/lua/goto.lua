@@ -0,0 +1,25 @@
+-- `goto` as `continue`, out of nested loops & backwards
+local odd = {}
+for i = 1, 6 do
+  if i % 2 == 0 then goto continue end
+  odd[#odd + 1] = i
+  ::continue::
+end
+
+local pair
+for a = 1, 5 do
+  for b = 1, 5 do
+    if a * b == 12 then
+      pair = a .. "x" .. b
+      goto done
+    end
+  end
+end
+::done::
+
+local n = 0
+::again::
+n = n + 1
+if n < 5 then goto again end
+
+return table.concat(odd, ","), pair, n
@@ -0,0 +1,41 @@
+-- numeric, generic, while & repeat loops
+local sum = 0
+for i = 1, 10 do
+  sum = sum + i
+end
+local down = {}
+for i = 3, 1, -1 do
+  down[#down + 1] = i
+end
+local halves = 0
+for x = 0, 1, 0.25 do
+  halves = halves + 1
+end
+
+local n, steps = 27, 0
+while n ~= 1 do
+  if n % 2 == 0 then n = n // 2 else n = 3 * n + 1 end
+  steps = steps + 1
+end
+
+local i = 0
+repeat
+  local done = i >= 4
+  i = i + 1
+until done
+
+local found
+for k = 1, math.maxinteger do
+  if k * k > 50 then
+    found = k
+    break
+  end
+end
+
+-- the loop var is a fresh local each time
+local fns = {}
+for k = 1, 3 do
+  fns[k] = function() return k end
+end
+
+return sum, table.concat(down, ","), halves, steps, i, found, fns[1]() + fns[3]()
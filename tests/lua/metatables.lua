@@ -0,0 +1,31 @@
+-- operators, indexing & calls through metatables
+local Vec = {}
+Vec.__index = Vec
+
+function Vec.new(x, y)
+  return setmetatable({x = x, y = y}, Vec)
+end
+
+function Vec.__add(a, b) return Vec.new(a.x + b.x, a.y + b.y) end
+function Vec.__eq(a, b) return a.x == b.x and a.y == b.y end
+function Vec.__lt(a, b) return a:len() < b:len() end
+function Vec.__len(v) return 2 end
+function Vec.__tostring(v) return "(" .. v.x .. ", " .. v.y .. ")" end
+function Vec:len() return self.x * self.x + self.y * self.y end
+
+local a, b = Vec.new(1, 2), Vec.new(3, 4)
+local c = a + b
+
+local defaults = setmetatable({}, {__index = function(t, k) return k .. "!" end})
+local proxy = setmetatable({}, {__newindex = function(t, k, v) rawset(t, k, v * 2) end})
+proxy.x = 21
+local counter = setmetatable({n = 0}, {__call = function(self, by)
+  self.n = self.n + by
+  return self.n
+end})
+counter(2)
+
+local ok, err = pcall(function() error("boom") end)
+
+return tostring(c), c == Vec.new(4, 6), a < b, b > a, #a, defaults.hi, proxy.x, counter(3),
+  getmetatable(a) == Vec, ok, err
@@ -0,0 +1,27 @@
+-- mutually recursive & memoized functions, without loops
+
+local is_even, is_odd
+
+function is_even(n)
+  if n == 0 then return true end
+  return is_odd(n - 1)
+end
+
+function is_odd(n)
+  if n == 0 then return false end
+  return is_even(n - 1)
+end
+
+local function fib(n)
+  if n < 2 then
+    return n
+  end
+  return fib(n - 1) + fib(n - 2)
+end
+
+local function gcd(a, b)
+  if b == 0 then return a end
+  return gcd(b, a % b)
+end
+
+return fib(20), gcd(1071, 462), is_even(10), is_odd(7)
@@ -0,0 +1,13 @@
+-- string methods & concatenation
+
+local s = "Hello"
+local shout = s:upper() .. "!"
+local line = ("-"):rep(5)
+local words = ("ab"):rep(3, ", ")
+
+local function title(word)
+  return word:sub(1, 1):upper() .. word:sub(2):lower()
+end
+
+return shout, line, words, title("lUA"), #s, s:len(), s:sub(-3), [[
+long]] .. "\x21"
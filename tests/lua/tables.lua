@@ -0,0 +1,25 @@
+-- constructors, fields, length & the table library
+local function three() return 3, 4, 5 end
+local t = {1, 2, three()}
+local u = {three(), 10}
+local point = {x = 1, ["y"] = 2, [1.0] = "one"}
+point.z = point.x + point.y
+
+local keys = 0
+for k, v in pairs({a = 1, b = 2, 3}) do
+  keys = keys + 1
+end
+
+local list = {}
+for i, v in ipairs({"a", "b", "c", nil, "e"}) do
+  list[i] = v:upper()
+end
+
+local words = {"pear", "apple", "fig"}
+table.sort(words)
+table.insert(words, 1, "kiwi")
+local last = table.remove(words)
+table.sort(t, function(a, b) return a > b end)
+
+return #t, #u, point[1], point.z, keys, table.concat(list), table.concat(words, " "), last,
+  table.concat(t, ","), select("#", table.unpack({1, 2, 3})), type(t), rawlen(u)